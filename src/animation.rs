@@ -0,0 +1,235 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use three_d::{InnerSpace, Mat3, Mat4, Quat, SquareMatrix, Vec3, VectorSpace};
+
+/// Drives the animation time handed to `ModelPart::animate`.
+///
+/// Time is kept in milliseconds, matching what the model animation closures expect.
+pub struct AnimationClock {
+    time: f32,
+    playing: bool,
+}
+
+impl Default for AnimationClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnimationClock {
+    /// Creates a clock at time zero that is playing.
+    pub fn new() -> Self {
+        Self {
+            time: 0.0,
+            playing: true,
+        }
+    }
+
+    /// Current animation time in milliseconds.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    /// Jumps to an absolute time in milliseconds, regardless of whether the clock is playing.
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time;
+    }
+
+    /// Advances the clock by `dt` if it is playing. Returns whether the time changed.
+    pub fn advance(&mut self, dt: Duration) -> bool {
+        if !self.playing || dt.is_zero() {
+            return false;
+        }
+        self.time += dt.as_secs_f32() * 1000.0;
+        true
+    }
+}
+
+/// Interpolates between the transforms `from` and `to`, `t = 0.0` giving `from` and `t = 1.0`
+/// giving `to`.
+///
+/// Both are decomposed into translation, rotation and scale; translation and scale are
+/// interpolated linearly and rotation along the shortest arc, so a halfway pose is rotated
+/// halfway instead of collapsing like a plain matrix lerp. Mirrored transforms keep their
+/// mirroring in the scale, and any shear is dropped by the decomposition.
+pub fn blend_transforms(from: Mat4, to: Mat4, t: f32) -> Mat4 {
+    let t = t.clamp(0.0, 1.0);
+    let (from_translation, from_rotation, from_scale) = decompose(from);
+    let (to_translation, mut to_rotation, to_scale) = decompose(to);
+    if from_rotation.dot(to_rotation) < 0.0 {
+        to_rotation = -to_rotation;
+    }
+    let translation = from_translation.lerp(to_translation, t);
+    let rotation = from_rotation.nlerp(to_rotation, t);
+    let scale = from_scale.lerp(to_scale, t);
+    Mat4::from_translation(translation)
+        * Mat4::from(rotation)
+        * Mat4::from_nonuniform_scale(scale.x, scale.y, scale.z)
+}
+
+/// Splits an affine `transform` into translation, unit rotation and per-axis scale.
+fn decompose(transform: Mat4) -> (Vec3, Quat, Vec3) {
+    let translation = transform.w.truncate();
+    let axes = [
+        transform.x.truncate(),
+        transform.y.truncate(),
+        transform.z.truncate(),
+    ];
+    let mut scale = Vec3::new(
+        axes[0].magnitude(),
+        axes[1].magnitude(),
+        axes[2].magnitude(),
+    );
+    // A mirrored transform would leave an improper rotation, move the mirroring into the
+    // scale instead.
+    if Mat3::from_cols(axes[0], axes[1], axes[2]).determinant() < 0.0 {
+        scale.x = -scale.x;
+    }
+    if [scale.x, scale.y, scale.z]
+        .iter()
+        .any(|s| s.abs() <= f32::EPSILON)
+    {
+        // Collapsed axes carry no orientation.
+        return (translation, Quat::new(1.0, 0.0, 0.0, 0.0), scale);
+    }
+    let rotation = Mat3::from_cols(axes[0] / scale.x, axes[1] / scale.y, axes[2] / scale.z);
+    (translation, Quat::from(rotation).normalize(), scale)
+}
+
+/// A blend position shared between the UI and an animation closure, see [pose_blend].
+///
+/// Cheap to clone and `Send`, the value is always within `0.0..=1.0`.
+#[derive(Clone, Debug, Default)]
+pub struct BlendFactor(Arc<AtomicU32>);
+
+impl BlendFactor {
+    pub fn new(value: f32) -> Self {
+        let factor = Self::default();
+        factor.set(value);
+        factor
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, value: f32) {
+        let value = if value.is_nan() {
+            0.0
+        } else {
+            value.clamp(0.0, 1.0)
+        };
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// An animation for `ModelPart::set_animation` that holds the pose `factor` selects between
+/// `from` and `to`, ignoring the time.
+///
+/// The pose is evaluated when the scene animates, so after changing `factor` while the clock
+/// is paused call [crate::scene::Scene::reanimate].
+pub fn pose_blend(from: Mat4, to: Mat4, factor: BlendFactor) -> impl Fn(f32) -> Mat4 + Send + Sync {
+    move |_time| blend_transforms(from, to, factor.get())
+}
+
+/// An animation for `ModelPart::set_animation` that crossfades from `from` to `to` over
+/// `duration` milliseconds starting at `start`, holding either pose outside that interval.
+pub fn crossfade(
+    from: Mat4,
+    to: Mat4,
+    start: f32,
+    duration: f32,
+) -> impl Fn(f32) -> Mat4 + Send + Sync {
+    move |time| {
+        let t = if duration > 0.0 {
+            (time - start) / duration
+        } else if time >= start {
+            1.0
+        } else {
+            0.0
+        };
+        blend_transforms(from, to, t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paused_clock_stands_still() {
+        let mut clock = AnimationClock::new();
+        assert!(clock.advance(Duration::from_millis(10)));
+        assert_eq!(clock.time(), 10.0);
+        clock.pause();
+        assert!(!clock.advance(Duration::from_millis(10)));
+        assert_eq!(clock.time(), 10.0);
+        clock.play();
+        assert!(!clock.advance(Duration::ZERO));
+    }
+
+    fn assert_near(actual: Mat4, expected: Mat4) {
+        let difference: [[f32; 4]; 4] = (actual - expected).into();
+        assert!(
+            difference.iter().flatten().all(|d| d.abs() < 1e-5),
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn blend_interpolates_translation_and_rotation() {
+        use three_d::{vec3, Deg};
+
+        let from = Mat4::from_translation(vec3(0.0, 0.0, 0.0));
+        let to = Mat4::from_translation(vec3(2.0, 4.0, 0.0)) * Mat4::from_angle_y(Deg(90.0));
+        assert_near(blend_transforms(from, to, 0.0), from);
+        assert_near(blend_transforms(from, to, 1.0), to);
+        let half = Mat4::from_translation(vec3(1.0, 2.0, 0.0)) * Mat4::from_angle_y(Deg(45.0));
+        assert_near(blend_transforms(from, to, 0.5), half);
+        // Clamped outside the range.
+        assert_near(blend_transforms(from, to, 2.0), to);
+    }
+
+    #[test]
+    fn mirrored_and_collapsed_scales_blend_without_nans() {
+        let mirrored = Mat4::from_nonuniform_scale(-1.0, 2.0, 3.0);
+        let collapsed = Mat4::from_nonuniform_scale(1.0, 0.0, 1.0);
+        assert_near(blend_transforms(mirrored, mirrored, 0.5), mirrored);
+        for t in [0.0, 0.5, 1.0] {
+            let blended: [[f32; 4]; 4] = blend_transforms(mirrored, collapsed, t).into();
+            assert!(
+                blended.iter().flatten().all(|x| x.is_finite()),
+                "{blended:?}"
+            );
+        }
+        assert_near(blend_transforms(mirrored, collapsed, 1.0), collapsed);
+    }
+
+    #[test]
+    fn crossfade_holds_the_poses_outside_its_interval() {
+        let from = Mat4::identity();
+        let to = Mat4::from_scale(3.0);
+        let fade = crossfade(from, to, 100.0, 200.0);
+        assert_near(fade(0.0), from);
+        assert_near(fade(200.0), Mat4::from_scale(2.0));
+        assert_near(fade(500.0), to);
+        let factor = BlendFactor::new(f32::NAN);
+        assert_eq!(factor.get(), 0.0);
+        factor.set(0.5);
+        assert_near(pose_blend(from, to, factor)(0.0), Mat4::from_scale(2.0));
+    }
+}
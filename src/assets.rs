@@ -0,0 +1,1166 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use three_d::{
+    AxisAlignedBoundingBox, CpuMaterial, CpuMesh, CpuModel, Deg, InnerSpace, Mat4, Positions,
+    SquareMatrix, Srgba, Vec3,
+};
+use three_d_asset::io::RawAssets;
+use three_d_asset::{Geometry, Indices, Primitive};
+
+use crate::error::LoadError;
+
+static ASSET_ROOT: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// The directory set with [set_asset_root], if any.
+pub fn asset_root() -> Option<PathBuf> {
+    ASSET_ROOT.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Makes `root` the only directory relative asset paths are looked up in, for the whole
+/// process, so loading doesn't depend on the working directory that `cargo run`, tests and
+/// packaged apps each start in. `None` goes back to searching the default locations, see
+/// [asset_search_roots]. Absolute paths to existing files are used as they are either way.
+pub fn set_asset_root(root: Option<PathBuf>) {
+    *ASSET_ROOT.write().unwrap_or_else(|e| e.into_inner()) = root;
+}
+
+/// The directories relative asset paths are looked up in, most specific first.
+///
+/// Only the one set with [set_asset_root], if there is one. Otherwise that is the working
+/// directory, which is the package root under `cargo run`, then the directory of the
+/// executable and, inside a macOS app bundle, its `Resources` directory, which is where
+/// packaged apps keep their assets.
+pub fn asset_search_roots() -> Vec<PathBuf> {
+    if let Some(root) = asset_root() {
+        return vec![root];
+    }
+    let mut roots = Vec::new();
+    if let Ok(current_dir) = std::env::current_dir() {
+        roots.push(current_dir);
+    }
+    if let Some(exe_dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        roots.push(exe_dir.join("assets"));
+        #[cfg(target_os = "macos")]
+        roots.push(exe_dir.join("../Resources"));
+        roots.push(exe_dir);
+    }
+    roots
+}
+
+/// Finds the file an asset path refers to.
+///
+/// Existing absolute paths are used as they are. Anything else is treated as relative to the
+/// bundle and looked up in [asset_search_roots]; a leading `/`, as in the paths `asset!()`
+/// produces, is stripped first.
+pub fn resolve_asset_path(path: impl AsRef<Path>) -> Result<PathBuf, LoadError> {
+    resolve_in(path.as_ref(), asset_search_roots())
+}
+
+/// [resolve_asset_path] with the given search roots.
+fn resolve_in(path: &Path, roots: Vec<PathBuf>) -> Result<PathBuf, LoadError> {
+    if path.is_absolute() && path.is_file() {
+        return Ok(path.to_path_buf());
+    }
+    let relative = path.strip_prefix("/").unwrap_or(path);
+    let searched: Vec<PathBuf> = roots.into_iter().map(|root| root.join(relative)).collect();
+    match searched.iter().find(|candidate| candidate.is_file()) {
+        Some(found) => Ok(found.clone()),
+        None => Err(LoadError::NotFound {
+            path: path.to_path_buf(),
+            searched,
+        }),
+    }
+}
+
+/// Resolves `path` with [resolve_asset_path] and parses the model there.
+///
+/// The format follows from the extension, see [ModelFormat]. glTF and OBJ files load the
+/// buffers, material libraries and textures they refer to relative to their own directory.
+/// Parts without a material, all of an STL and OBJ without `mtllib`, get a default one.
+pub fn load_model(path: impl AsRef<Path>) -> Result<CpuModel, LoadError> {
+    let path = resolve_asset_path(path)?;
+    log_debug!(path = %path.display(), "loading model");
+    let mut cpu_model = if ModelFormat::from_path(&path) == Some(ModelFormat::Stl) {
+        let mut raw_assets = three_d_asset::io::load(&[&path])?;
+        parse_stl(&raw_assets.remove(&path)?)?
+    } else {
+        three_d_asset::io::load_and_deserialize(&path)?
+    };
+    assign_default_material(&mut cpu_model);
+    fit_indices(&mut cpu_model);
+    Ok(cpu_model)
+}
+
+/// Like [load_model], with `options` applied to the result, see [apply_import_options].
+pub fn load_model_with(
+    path: impl AsRef<Path>,
+    options: &ImportOptions,
+) -> Result<CpuModel, LoadError> {
+    let mut cpu_model = load_model(path)?;
+    apply_import_options(&mut cpu_model, options);
+    Ok(cpu_model)
+}
+
+/// The axis a model file treats as up. glTF is Y-up, but models exported from Z-up tools,
+/// as STL and OBJ often are, otherwise lie on their back.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UpAxis {
+    #[default]
+    Y,
+    Z,
+}
+
+impl UpAxis {
+    /// The rotation that turns this axis into Y, keeping the model right-handed.
+    pub fn correction(self) -> Mat4 {
+        match self {
+            UpAxis::Y => Mat4::identity(),
+            UpAxis::Z => Mat4::from_angle_x(Deg(-90.0)),
+        }
+    }
+}
+
+/// How to place a model in the scene when importing it, and how to fix up its normals, see
+/// [load_model_with].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImportOptions {
+    pub up_axis: UpAxis,
+    /// Moves the center of the model's bounding box to the origin.
+    pub center: bool,
+    /// Scales the model uniformly so the largest side of its bounding box is this long.
+    pub normalize_scale: Option<f32>,
+    /// Applied last, after the up axis correction, centering and scaling, in Y-up space.
+    pub root_transform: Mat4,
+    /// Which parts get normals computed from their geometry, see [compute_normals].
+    pub normals: NormalOptions,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            up_axis: UpAxis::default(),
+            center: false,
+            normalize_scale: None,
+            root_transform: Mat4::identity(),
+            normals: NormalOptions::default(),
+        }
+    }
+}
+
+/// Puts `options` on top of the transformations of every primitive in `cpu_model`, like a
+/// parent node of the whole model.
+///
+/// Only the primitive transformations change, not the vertices, apart from the normals
+/// [ImportOptions::normals] asks for, which are computed first. Animations, both key frames
+/// from the file and [three_d::ModelPart::set_animation], stay relative to the primitive, the
+/// way a parent node's transformation applies to its animated children. So centering and
+/// scaling fit the model as posed by its primitive transformations, already turned Y-up; an
+/// animation moving parts further out can still leave the fitted box.
+pub fn apply_import_options(cpu_model: &mut CpuModel, options: &ImportOptions) {
+    compute_normals(cpu_model, &options.normals);
+    let correction = options.up_axis.correction();
+    let fit = if options.center || options.normalize_scale.is_some() {
+        fit_transform(&model_bounds(cpu_model, correction), options)
+    } else {
+        Mat4::identity()
+    };
+    let root = options.root_transform * fit * correction;
+    if root == Mat4::identity() {
+        return;
+    }
+    for primitive in &mut cpu_model.geometries {
+        primitive.transformation = root * primitive.transformation;
+    }
+}
+
+/// The bounds of all primitives of `cpu_model` in their placement, with `transformation` on
+/// top.
+fn model_bounds(cpu_model: &CpuModel, transformation: Mat4) -> AxisAlignedBoundingBox {
+    let mut bounds = AxisAlignedBoundingBox::EMPTY;
+    for primitive in &cpu_model.geometries {
+        let positions = match &primitive.geometry {
+            Geometry::Triangles(mesh) => mesh.positions.to_f32(),
+            Geometry::Points(points) => points.positions.to_f32(),
+        };
+        bounds.expand_with_aabb(&AxisAlignedBoundingBox::new_with_transformed_positions(
+            &positions,
+            &(transformation * primitive.transformation),
+        ));
+    }
+    bounds
+}
+
+/// Centers and scales `bounds` as `options` ask. Empty and flat-to-a-point bounds can't be
+/// scaled up, they are only centered.
+fn fit_transform(bounds: &AxisAlignedBoundingBox, options: &ImportOptions) -> Mat4 {
+    if bounds.is_empty() {
+        return Mat4::identity();
+    }
+    let center = if options.center {
+        Mat4::from_translation(-bounds.center())
+    } else {
+        Mat4::identity()
+    };
+    let size = bounds.size();
+    let largest = size.x.max(size.y).max(size.z);
+    let scale = match options.normalize_scale {
+        Some(target) if largest > f32::EPSILON && target > 0.0 => {
+            Mat4::from_scale(target / largest)
+        }
+        _ => Mat4::identity(),
+    };
+    scale * center
+}
+
+/// The file format of a model in memory, see [load_model_from_bytes].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModelFormat {
+    /// Binary glTF, the one format that is reliably self-contained.
+    Glb,
+    /// glTF JSON, with buffers and images embedded as `data:` URIs.
+    Gltf,
+    /// Wavefront OBJ; from memory only without a material library.
+    Obj,
+    /// STL, binary or ASCII. It has no materials, the parts get a default one.
+    Stl,
+}
+
+impl ModelFormat {
+    /// The format a file name extension stands for.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "glb" => Some(ModelFormat::Glb),
+            "gltf" => Some(ModelFormat::Gltf),
+            "obj" => Some(ModelFormat::Obj),
+            "stl" => Some(ModelFormat::Stl),
+            _ => None,
+        }
+    }
+
+    /// The format of a model in memory, judging by its first bytes. Binary glTF has a magic
+    /// number and glTF JSON opens an object; STL and OBJ are told apart by their keywords.
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"glTF") {
+            return Some(ModelFormat::Glb);
+        }
+        if is_binary_stl(bytes) {
+            return Some(ModelFormat::Stl);
+        }
+        let text = std::str::from_utf8(&bytes[..bytes.len().min(1024)])
+            .ok()?
+            .trim_start();
+        if text.starts_with('{') {
+            Some(ModelFormat::Gltf)
+        } else if text.starts_with("solid") {
+            Some(ModelFormat::Stl)
+        } else if text
+            .lines()
+            .any(|line| line.starts_with("v ") || line.starts_with("mtllib"))
+        {
+            Some(ModelFormat::Obj)
+        } else {
+            None
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ModelFormat::Glb => "glb",
+            ModelFormat::Gltf => "gltf",
+            ModelFormat::Obj => "obj",
+            ModelFormat::Stl => "stl",
+        }
+    }
+}
+
+/// Parses a model that was fetched over the network or embedded with `include_bytes!`.
+///
+/// Everything the model uses has to be inside `bytes`. References to other files, a glTF's
+/// external `.bin` or textures or an OBJ's `mtllib`, fail with
+/// [LoadError::ExternalReference] before parsing.
+pub fn load_model_from_bytes(bytes: &[u8], format: ModelFormat) -> Result<CpuModel, LoadError> {
+    let external = match format {
+        ModelFormat::Glb => glb_json(bytes).and_then(external_gltf_uri),
+        ModelFormat::Gltf => std::str::from_utf8(bytes).ok().and_then(external_gltf_uri),
+        ModelFormat::Obj => std::str::from_utf8(bytes).ok().and_then(|obj| {
+            obj.lines()
+                .map(str::trim_start)
+                .find(|line| line.starts_with("mtllib"))
+                .map(|line| line["mtllib".len()..].trim().to_string())
+        }),
+        ModelFormat::Stl => None,
+    };
+    if let Some(uri) = external {
+        return Err(LoadError::ExternalReference { uri });
+    }
+    let mut cpu_model = if format == ModelFormat::Stl {
+        parse_stl(bytes)?
+    } else {
+        let path = format!("model.{}", format.extension());
+        let mut raw_assets = RawAssets::new();
+        raw_assets.insert(&path, bytes.to_vec());
+        raw_assets.deserialize(&path)?
+    };
+    assign_default_material(&mut cpu_model);
+    fit_indices(&mut cpu_model);
+    Ok(cpu_model)
+}
+
+/// When [compute_tangents] generates tangents.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TangentMode {
+    /// For parts whose material has a normal map, the only thing tangents are used for.
+    #[default]
+    Auto,
+    /// For every part that can have them.
+    Always,
+    Never,
+}
+
+/// Generates tangents for the triangle parts of `cpu_model` according to `mode`, keeping
+/// those the file already has. Returns the number of parts that got tangents.
+///
+/// Tangents follow the texture coordinates and are smoothed along the normals, so parts
+/// missing either are skipped in every mode.
+pub fn compute_tangents(cpu_model: &mut CpuModel, mode: TangentMode) -> usize {
+    if mode == TangentMode::Never {
+        return 0;
+    }
+    let materials = &cpu_model.materials;
+    let mut computed = 0;
+    for primitive in &mut cpu_model.geometries {
+        let normal_mapped = primitive
+            .material_index
+            .and_then(|index| materials.get(index))
+            .is_some_and(|material| material.normal_texture.is_some());
+        let Geometry::Triangles(mesh) = &mut primitive.geometry else {
+            continue;
+        };
+        if mesh.tangents.is_some() || mesh.normals.is_none() || mesh.uvs.is_none() {
+            continue;
+        }
+        if mode == TangentMode::Always || normal_mapped {
+            mesh.compute_tangents();
+            computed += 1;
+        }
+    }
+    computed
+}
+
+/// Which parts [compute_normals] computes normals for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NormalMode {
+    /// Only parts without normals, which otherwise shade flat or black.
+    #[default]
+    Missing,
+    /// Every triangle part, replacing the normals of the file, for models whose normals are
+    /// broken or inconsistent.
+    Always,
+    Never,
+}
+
+/// How [compute_normals] computes normals.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NormalOptions {
+    pub mode: NormalMode,
+    /// Triangles meeting at a vertex are smoothed together if their faces differ by at most
+    /// this angle, and keep a hard edge otherwise: `0` shades every triangle flat, `180`
+    /// smooths everything. Vertices on a hard edge are split, one per side.
+    pub crease_angle: Deg<f32>,
+}
+
+impl Default for NormalOptions {
+    fn default() -> Self {
+        Self {
+            mode: NormalMode::default(),
+            crease_angle: Deg(60.0),
+        }
+    }
+}
+
+/// Computes vertex normals from the triangles of the parts of `cpu_model` `options` selects.
+/// Returns the number of parts that got normals.
+///
+/// Normals point to the side the triangles wind counterclockwise around, the front face, and
+/// average the faces around each vertex weighted by their area. Corners sharing a position
+/// are smoothed together, so meshes without indices or with seams split in the file come out
+/// smooth too. Tangents are dropped, as they followed the old normals; generate new ones with
+/// [compute_tangents]. Point clouds are skipped.
+pub fn compute_normals(cpu_model: &mut CpuModel, options: &NormalOptions) -> usize {
+    if options.mode == NormalMode::Never {
+        return 0;
+    }
+    let min_cos = options.crease_angle.0.clamp(0.0, 180.0).to_radians().cos();
+    let mut computed = 0;
+    for primitive in &mut cpu_model.geometries {
+        let Geometry::Triangles(mesh) = &mut primitive.geometry else {
+            continue;
+        };
+        if mesh.normals.is_some() && options.mode == NormalMode::Missing {
+            continue;
+        }
+        smooth_normals(mesh, min_cos);
+        computed += 1;
+    }
+    computed
+}
+
+/// Gives `mesh` normals smoothed between faces whose normals' cosine is at least `min_cos`,
+/// splitting vertices whose corners end up with different normals.
+fn smooth_normals(mesh: &mut CpuMesh, min_cos: f32) {
+    let positions = mesh.positions.to_f32();
+    let corners: Vec<u32> = mesh
+        .indices
+        .to_u32()
+        .unwrap_or_else(|| (0..positions.len() as u32).collect());
+    let triangles: Vec<[u32; 3]> = corners
+        .chunks_exact(3)
+        .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+        .collect();
+    // Unnormalized, so larger triangles weigh more.
+    let faces: Vec<Vec3> = triangles
+        .iter()
+        .map(|&[a, b, c]| {
+            let [a, b, c] = [a, b, c].map(|index| positions[index as usize]);
+            (b - a).cross(c - a)
+        })
+        .collect();
+    let unit = |face: Vec3| {
+        if face.magnitude2() > 0.0 {
+            face.normalize()
+        } else {
+            face
+        }
+    };
+    // `+ 0.0` turns -0.0 into 0.0, so both are the same position.
+    let key = |position: Vec3| [position.x, position.y, position.z].map(|x| (x + 0.0).to_bits());
+    let mut sharing: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+    for (triangle, vertices) in triangles.iter().enumerate() {
+        for &vertex in vertices {
+            let triangles = sharing.entry(key(positions[vertex as usize])).or_default();
+            // Degenerate triangles can touch a position twice.
+            if triangles.last() != Some(&triangle) {
+                triangles.push(triangle);
+            }
+        }
+    }
+    let mut normals = Vec::with_capacity(triangles.len() * 3);
+    for (triangle, vertices) in triangles.iter().enumerate() {
+        let face = unit(faces[triangle]);
+        for &vertex in vertices {
+            let normal: Vec3 = sharing[&key(positions[vertex as usize])]
+                .iter()
+                .copied()
+                .filter(|&other| unit(faces[other]).dot(face) >= min_cos)
+                .map(|other| faces[other])
+                .sum();
+            normals.push(if normal.magnitude2() > 0.0 {
+                normal.normalize()
+            } else if face.magnitude2() > 0.0 {
+                face
+            } else {
+                Vec3::unit_y()
+            });
+        }
+    }
+    // One vertex for each distinct pair of original vertex and normal.
+    let mut vertices = HashMap::new();
+    let mut sources = Vec::new();
+    let mut vertex_normals = Vec::new();
+    let indices: Vec<u32> = triangles
+        .iter()
+        .flatten()
+        .zip(&normals)
+        .map(|(&source, normal)| {
+            let vertex = (source, [normal.x, normal.y, normal.z].map(f32::to_bits));
+            *vertices.entry(vertex).or_insert_with(|| {
+                sources.push(source);
+                vertex_normals.push(*normal);
+                sources.len() as u32 - 1
+            })
+        })
+        .collect();
+    let unsplit = sources.len() == positions.len()
+        && sources
+            .iter()
+            .enumerate()
+            .all(|(index, &source)| index as u32 == source);
+    if !unsplit {
+        mesh.positions = match &mesh.positions {
+            Positions::F32(positions) => Positions::F32(gather(positions, &sources)),
+            Positions::F64(positions) => Positions::F64(gather(positions, &sources)),
+        };
+        mesh.uvs = mesh.uvs.as_deref().map(|uvs| gather(uvs, &sources));
+        mesh.colors = mesh
+            .colors
+            .as_deref()
+            .map(|colors| gather(colors, &sources));
+    }
+    if !unsplit || mesh.indices.to_u32().is_some() {
+        mesh.indices = if sources.len() > MAX_U16_INDEXED_VERTICES {
+            Indices::U32(indices)
+        } else {
+            Indices::U16(indices.into_iter().map(|index| index as u16).collect())
+        };
+    }
+    mesh.normals = Some(vertex_normals);
+    mesh.tangents = None;
+}
+
+fn gather<T: Copy>(values: &[T], sources: &[u32]) -> Vec<T> {
+    sources
+        .iter()
+        .map(|&source| values[source as usize])
+        .collect()
+}
+
+/// The most vertices 16-bit indices can address.
+pub const MAX_U16_INDEXED_VERTICES: usize = u16::MAX as usize + 1;
+
+/// Stores the indices of every triangle part in the narrowest type that addresses all of its
+/// vertices, 32 bits only past [MAX_U16_INDEXED_VERTICES], so small meshes keep the smaller
+/// index buffer and large ones don't wrap around. The loaders call this on every model.
+///
+/// Returns the number of parts whose indices look broken, each with a warning: indices past
+/// the last vertex, or 8 or 16-bit indices for more vertices than they can address, which
+/// some importers produce by truncating. Those are left as they are.
+pub fn fit_indices(cpu_model: &mut CpuModel) -> usize {
+    let mut broken = 0;
+    for primitive in &mut cpu_model.geometries {
+        let Geometry::Triangles(mesh) = &mut primitive.geometry else {
+            continue;
+        };
+        let Some(indices) = mesh.indices.to_u32() else {
+            continue;
+        };
+        let vertices = mesh.positions.len();
+        if let Some(&_index) = indices.iter().find(|&&index| index as usize >= vertices) {
+            log_warn!(
+                part = %primitive.name,
+                index = _index,
+                vertices,
+                "index past the last vertex"
+            );
+            broken += 1;
+            continue;
+        }
+        let wide = vertices > MAX_U16_INDEXED_VERTICES;
+        match &mesh.indices {
+            Indices::U32(_) if !wide => {
+                let indices = indices.into_iter().map(|index| index as u16).collect();
+                mesh.indices = Indices::U16(indices);
+            }
+            Indices::U8(_) | Indices::U16(_) if wide => {
+                log_warn!(
+                    part = %primitive.name,
+                    vertices,
+                    "narrow indices for too many vertices, likely truncated"
+                );
+                broken += 1;
+            }
+            _ => {}
+        }
+    }
+    broken
+}
+
+/// Points every primitive without a material at a plain light gray one, so all formats
+/// render the same way.
+fn assign_default_material(cpu_model: &mut CpuModel) {
+    if cpu_model
+        .geometries
+        .iter()
+        .all(|primitive| primitive.material_index.is_some())
+    {
+        return;
+    }
+    let index = cpu_model.materials.len();
+    cpu_model.materials.push(CpuMaterial {
+        name: "default".to_string(),
+        albedo: Srgba::new_opaque(200, 200, 200),
+        roughness: 0.6,
+        metallic: 0.0,
+        ..Default::default()
+    });
+    for primitive in &mut cpu_model.geometries {
+        primitive.material_index.get_or_insert(index);
+    }
+}
+
+/// Whether `bytes` has exactly the size a binary STL with its triangle count would. ASCII
+/// files start with `solid`, but so do the headers of some binary exporters.
+fn is_binary_stl(bytes: &[u8]) -> bool {
+    let Some(count) = bytes.get(80..84) else {
+        return false;
+    };
+    let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+    bytes.len() == 84 + count * 50
+}
+
+/// Parses a binary or ASCII STL into a single-part model with flat normals.
+fn parse_stl(bytes: &[u8]) -> Result<CpuModel, LoadError> {
+    let malformed = |reason: &str| LoadError::Malformed(format!("STL: {reason}"));
+    let mut positions = Vec::new();
+    if is_binary_stl(bytes) {
+        for triangle in bytes[84..].chunks_exact(50) {
+            // The 12 bytes of the stored normal are skipped, they are often zero.
+            for vertex in triangle[12..48].chunks_exact(12) {
+                let component =
+                    |i: usize| f32::from_le_bytes(vertex[i * 4..i * 4 + 4].try_into().unwrap());
+                positions.push(Vec3::new(component(0), component(1), component(2)));
+            }
+        }
+    } else {
+        let text = std::str::from_utf8(bytes).map_err(|_| malformed("not UTF-8 text"))?;
+        for line in text.lines() {
+            let mut words = line.split_whitespace();
+            if words.next() != Some("vertex") {
+                continue;
+            }
+            let mut component = || -> Result<f32, LoadError> {
+                words
+                    .next()
+                    .and_then(|word| word.parse().ok())
+                    .ok_or_else(|| malformed("invalid vertex"))
+            };
+            positions.push(Vec3::new(component()?, component()?, component()?));
+        }
+        if positions.len() % 3 != 0 {
+            return Err(malformed("vertex count is not a multiple of 3"));
+        }
+    }
+    if positions.is_empty() {
+        return Err(malformed("no triangles"));
+    }
+    let normals = positions
+        .chunks_exact(3)
+        .flat_map(|triangle| {
+            let normal = (triangle[1] - triangle[0]).cross(triangle[2] - triangle[0]);
+            let normal = if normal.magnitude2() > 0.0 {
+                normal.normalize()
+            } else {
+                Vec3::unit_z()
+            };
+            [normal; 3]
+        })
+        .collect();
+    let mesh = CpuMesh {
+        positions: Positions::F32(positions),
+        normals: Some(normals),
+        ..Default::default()
+    };
+    Ok(CpuModel {
+        name: "stl".to_string(),
+        geometries: vec![Primitive {
+            name: String::new(),
+            transformation: Mat4::from_scale(1.0),
+            animations: Vec::new(),
+            geometry: Geometry::Triangles(mesh),
+            material_index: None,
+        }],
+        materials: Vec::new(),
+    })
+}
+
+/// The JSON chunk of a binary glTF, which always comes first.
+fn glb_json(bytes: &[u8]) -> Option<&str> {
+    let length = u32::from_le_bytes(bytes.get(12..16)?.try_into().ok()?) as usize;
+    if bytes.get(16..20)? != b"JSON" {
+        return None;
+    }
+    std::str::from_utf8(bytes.get(20..20 + length)?).ok()
+}
+
+/// The first `uri` in a glTF document that isn't an embedded `data:` URI.
+fn external_gltf_uri(json: &str) -> Option<String> {
+    let mut rest = json;
+    while let Some(start) = rest.find("\"uri\"") {
+        rest = &rest[start + "\"uri\"".len()..];
+        let value = rest
+            .trim_start()
+            .strip_prefix(':')?
+            .trim_start()
+            .strip_prefix('"')?;
+        let uri = &value[..value.find('"')?];
+        if !uri.starts_with("data:") {
+            return Some(uri.to_string());
+        }
+        rest = value;
+    }
+    None
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// The directory of the model files tests load.
+    pub(crate) fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+    }
+
+    #[test]
+    fn relative_names_resolve_under_the_search_roots() {
+        let roots = vec![fixtures().join("missing"), fixtures()];
+        let expected = fixtures().join("triangle.stl");
+        let resolved = resolve_in(Path::new("triangle.stl"), roots.clone()).unwrap();
+        assert_eq!(resolved, expected);
+        // As `asset!()` gives them, rooted at the bundle.
+        let resolved = resolve_in(Path::new("/triangle.stl"), roots).unwrap();
+        assert_eq!(resolved, expected);
+    }
+
+    #[test]
+    fn absolute_paths_are_used_as_they_are() {
+        let path = fixtures().join("triangle.stl");
+        assert_eq!(resolve_in(&path, Vec::new()).unwrap(), path);
+    }
+
+    #[test]
+    fn relative_names_load_from_the_configured_root() {
+        // The other tests load by absolute path, which the root doesn't affect.
+        set_asset_root(Some(fixtures()));
+        assert_eq!(asset_search_roots(), [fixtures()]);
+        let resolved = resolve_asset_path("quad/quad.obj").unwrap();
+        assert_eq!(resolved, fixtures().join("quad/quad.obj"));
+        let cpu_model = load_model("quad/quad.obj").unwrap();
+        assert_eq!(cpu_model.geometries.len(), 1);
+        let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        assert_eq!(resolve_asset_path(&manifest).unwrap(), manifest);
+        assert!(matches!(
+            resolve_asset_path("Cargo.toml"),
+            Err(LoadError::NotFound { .. })
+        ));
+        set_asset_root(None);
+        assert_eq!(asset_root(), None);
+    }
+
+    #[test]
+    fn missing_files_list_where_they_were_looked_for() {
+        let roots = vec![fixtures()];
+        match resolve_in(Path::new("missing.glb"), roots) {
+            Err(LoadError::NotFound { path, searched }) => {
+                assert_eq!(path, Path::new("missing.glb"));
+                assert_eq!(searched, vec![fixtures().join("missing.glb")]);
+            }
+            other => panic!("expected NotFound, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn load_model_reads_a_resolved_file() {
+        let cpu_model = load_model(fixtures().join("triangle.stl")).unwrap();
+        assert_eq!(cpu_model.geometries.len(), 1);
+        assert!(cpu_model.geometries[0].material_index.is_some());
+    }
+
+    #[test]
+    fn obj_finds_its_material_library_next_to_it() {
+        let cpu_model = load_model(fixtures().join("quad/quad.obj")).unwrap();
+        assert_eq!(cpu_model.geometries.len(), 1);
+        let Geometry::Triangles(mesh) = &cpu_model.geometries[0].geometry else {
+            panic!("expected triangles");
+        };
+        assert_eq!(mesh.triangle_count(), 2);
+        let index = cpu_model.geometries[0].material_index.unwrap();
+        let material = &cpu_model.materials[index];
+        assert_eq!(material.name, "red");
+        assert_eq!(material.albedo, Srgba::new_opaque(255, 0, 0));
+    }
+
+    #[test]
+    fn stl_parts_get_the_default_material() {
+        let cpu_model = load_model(fixtures().join("triangle.stl")).unwrap();
+        let Geometry::Triangles(mesh) = &cpu_model.geometries[0].geometry else {
+            panic!("expected triangles");
+        };
+        assert_eq!(mesh.triangle_count(), 1);
+        assert!(mesh.normals.is_some());
+        let index = cpu_model.geometries[0].material_index.unwrap();
+        assert_eq!(cpu_model.materials[index].name, "default");
+    }
+
+    #[test]
+    fn binary_stl_is_told_from_ascii() {
+        let mut bytes = b"solid but binary".to_vec();
+        bytes.resize(80, 0);
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        for value in [
+            0.0f32, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0,
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&[0, 0]);
+        assert_eq!(ModelFormat::detect(&bytes), Some(ModelFormat::Stl));
+        let cpu_model = load_model_from_bytes(&bytes, ModelFormat::Stl).unwrap();
+        let Geometry::Triangles(mesh) = &cpu_model.geometries[0].geometry else {
+            panic!("expected triangles");
+        };
+        assert_eq!(mesh.positions.len(), 3);
+        assert!(cpu_model.geometries[0].material_index.is_some());
+    }
+
+    fn part(mesh: CpuMesh, material_index: usize) -> Primitive {
+        Primitive {
+            name: String::new(),
+            transformation: Mat4::identity(),
+            animations: Vec::new(),
+            geometry: Geometry::Triangles(mesh),
+            material_index: Some(material_index),
+        }
+    }
+
+    fn has_tangents(primitive: &Primitive) -> bool {
+        matches!(&primitive.geometry, Geometry::Triangles(mesh) if mesh.tangents.is_some())
+    }
+
+    #[test]
+    fn tangents_only_for_normal_mapped_parts_with_uvs() {
+        let normal_mapped = CpuMaterial {
+            normal_texture: Some(Default::default()),
+            ..Default::default()
+        };
+        let square = CpuMesh {
+            tangents: None,
+            ..CpuMesh::square()
+        };
+        let without_uvs = CpuMesh {
+            uvs: None,
+            ..square.clone()
+        };
+        let mut cpu_model = CpuModel {
+            name: String::new(),
+            geometries: vec![
+                part(square.clone(), 0),
+                part(without_uvs, 0),
+                part(square, 1),
+            ],
+            materials: vec![normal_mapped, CpuMaterial::default()],
+        };
+        assert_eq!(
+            compute_tangents(&mut cpu_model.clone(), TangentMode::Never),
+            0
+        );
+        assert_eq!(
+            compute_tangents(&mut cpu_model.clone(), TangentMode::Always),
+            2
+        );
+        assert_eq!(compute_tangents(&mut cpu_model, TangentMode::Auto), 1);
+        let computed: Vec<bool> = cpu_model.geometries.iter().map(has_tangents).collect();
+        assert_eq!(computed, [true, false, false]);
+    }
+
+    /// A flat square from -1 to 1 in X and Y of `n` by `n` vertices, with 32-bit indices.
+    fn grid(n: u32) -> CpuMesh {
+        let step = 2.0 / (n - 1) as f32;
+        let positions = (0..n * n)
+            .map(|i| {
+                Vec3::new(
+                    (i % n) as f32 * step - 1.0,
+                    (i / n) as f32 * step - 1.0,
+                    0.0,
+                )
+            })
+            .collect();
+        let mut indices = Vec::new();
+        for y in 0..n - 1 {
+            for x in 0..n - 1 {
+                let i = y * n + x;
+                indices.extend([i, i + 1, i + n, i + 1, i + n + 1, i + n]);
+            }
+        }
+        CpuMesh {
+            positions: Positions::F32(positions),
+            indices: Indices::U32(indices),
+            ..Default::default()
+        }
+    }
+
+    fn indices(primitive: &Primitive) -> &Indices {
+        let Geometry::Triangles(mesh) = &primitive.geometry else {
+            panic!("expected triangles");
+        };
+        &mesh.indices
+    }
+
+    #[test]
+    fn index_width_follows_the_vertex_count() {
+        let large = grid(300);
+        assert!(large.positions.len() > MAX_U16_INDEXED_VERTICES);
+        // What an importer truncating to 16 bits would produce.
+        let wrapped = large
+            .indices
+            .to_u32()
+            .unwrap()
+            .iter()
+            .map(|&i| i as u16)
+            .collect();
+        let truncated = CpuMesh {
+            indices: Indices::U16(wrapped),
+            ..large.clone()
+        };
+        let past_the_end = CpuMesh {
+            indices: Indices::U32(vec![0, 1, 4]),
+            ..grid(2)
+        };
+        let mut cpu_model = CpuModel {
+            name: String::new(),
+            geometries: vec![
+                part(grid(10), 0),
+                part(large, 0),
+                part(truncated, 0),
+                part(past_the_end, 0),
+            ],
+            materials: vec![CpuMaterial::default()],
+        };
+        assert_eq!(fit_indices(&mut cpu_model), 2);
+        assert!(matches!(indices(&cpu_model.geometries[0]), Indices::U16(_)));
+        assert!(matches!(indices(&cpu_model.geometries[1]), Indices::U32(_)));
+        assert!(matches!(indices(&cpu_model.geometries[2]), Indices::U16(_)));
+        assert!(matches!(indices(&cpu_model.geometries[3]), Indices::U32(_)));
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn meshes_past_the_16_bit_limit_render_whole() {
+        use crate::background::Background;
+        use crate::part::ScenePart;
+        use crate::scene::tests::empty_scene;
+        use crate::testing::TestHarness;
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let mut cpu_model = CpuModel {
+            name: String::new(),
+            geometries: vec![part(grid(300), 0)],
+            materials: vec![CpuMaterial::default()],
+        };
+        assert_eq!(fit_indices(&mut cpu_model), 0);
+        let mut scene = empty_scene();
+        scene.set_parts(ScenePart::from_cpu_model(harness.context(), &cpu_model).unwrap());
+        scene.set_background(Background::Solid(Srgba::WHITE));
+        let image = harness.render_scene(&mut scene, 0.0);
+        // Unlit, the square is black; from 4 units away it spans pixels 13 to 51.
+        for (x, y) in [(32, 32), (16, 16), (48, 16), (16, 48), (48, 48)] {
+            assert_eq!(image.pixel(x, y)[..3], [0, 0, 0], "{x}, {y}");
+        }
+        for (x, y) in [(4, 4), (60, 32), (32, 60)] {
+            assert_eq!(image.pixel(x, y)[..3], [255, 255, 255], "{x}, {y}");
+        }
+    }
+
+    /// Whether every normal of the mesh of `primitive` is of unit length and points away from
+    /// the origin, out of a convex shape around it, by at least `min_cos`.
+    fn normals_point_outward(primitive: &Primitive, min_cos: f32) -> bool {
+        let Geometry::Triangles(mesh) = &primitive.geometry else {
+            return false;
+        };
+        let Some(normals) = &mesh.normals else {
+            return false;
+        };
+        let positions = mesh.positions.to_f32();
+        positions.len() == normals.len()
+            && positions.iter().zip(normals).all(|(position, normal)| {
+                (normal.magnitude() - 1.0).abs() < 1e-4
+                    && normal.dot(position.normalize()) >= min_cos
+            })
+    }
+
+    #[test]
+    fn missing_normals_are_computed_pointing_outward() {
+        let without_normals = |mesh: CpuMesh| CpuMesh {
+            normals: None,
+            tangents: None,
+            ..mesh
+        };
+        let sphere = without_normals(CpuMesh::sphere(16));
+        // The same triangles without indices, each corner its own vertex.
+        let corners = sphere.indices.to_u32().unwrap();
+        let positions = sphere.positions.to_f32();
+        let unindexed = CpuMesh {
+            positions: Positions::F32(corners.iter().map(|&i| positions[i as usize]).collect()),
+            indices: Indices::None,
+            ..Default::default()
+        };
+        let mut cpu_model = CpuModel {
+            name: String::new(),
+            geometries: vec![
+                part(without_normals(CpuMesh::cube()), 0),
+                part(sphere, 0),
+                part(unindexed, 0),
+                part(CpuMesh::square(), 0),
+            ],
+            materials: vec![CpuMaterial::default()],
+        };
+        let square_normals = |cpu_model: &CpuModel| match &cpu_model.geometries[3].geometry {
+            Geometry::Triangles(mesh) => mesh.normals.clone(),
+            Geometry::Points(_) => None,
+        };
+        let before = square_normals(&cpu_model);
+        let options = NormalOptions::default();
+        assert_eq!(compute_normals(&mut cpu_model, &options), 3);
+        // The cube keeps its hard edges, its normals are those of its faces.
+        assert!(normals_point_outward(&cpu_model.geometries[0], 0.5));
+        // The sphere is smooth, its normals point almost straight out.
+        assert!(normals_point_outward(&cpu_model.geometries[1], 0.95));
+        assert!(normals_point_outward(&cpu_model.geometries[2], 0.95));
+        // Existing normals stay unless forced.
+        assert_eq!(square_normals(&cpu_model), before);
+    }
+
+    #[test]
+    fn z_up_models_stand_upright() {
+        // A marker pointing up along Z, as Z-up tools save it.
+        let stl = b"solid marker
+facet normal 0 -1 0
+outer loop
+vertex 0 0 0
+vertex 1 0 0
+vertex 0 0 2
+endloop
+endfacet
+endsolid marker
+";
+        let mut cpu_model = load_model_from_bytes(stl, ModelFormat::Stl).unwrap();
+        let options = ImportOptions {
+            up_axis: UpAxis::Z,
+            ..Default::default()
+        };
+        apply_import_options(&mut cpu_model, &options);
+        let bounds = model_bounds(&cpu_model, Mat4::identity());
+        assert!((bounds.min() - Vec3::new(0.0, 0.0, 0.0)).magnitude() < 1e-5);
+        assert!((bounds.max() - Vec3::new(1.0, 2.0, 0.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn import_transform_goes_on_top_of_the_primitive_transformation() {
+        let mut cpu_model = load_model(fixtures().join("triangle.stl")).unwrap();
+        let offset = Mat4::from_translation(Vec3::new(5.0, 0.0, 0.0));
+        cpu_model.geometries[0].transformation = offset;
+        let root = Mat4::from_scale(2.0);
+        let options = ImportOptions {
+            up_axis: UpAxis::Z,
+            root_transform: root,
+            ..Default::default()
+        };
+        apply_import_options(&mut cpu_model, &options);
+        let expected = root * UpAxis::Z.correction() * offset;
+        assert_eq!(cpu_model.geometries[0].transformation, expected);
+    }
+
+    #[test]
+    fn off_center_models_are_centered_and_scaled_to_the_target_size() {
+        // 40 by 20 by 10 units, far from the origin.
+        let stl = b"solid far
+facet normal 0 0 1
+outer loop
+vertex 100 100 100
+vertex 140 100 100
+vertex 100 120 110
+endloop
+endfacet
+endsolid far
+";
+        for up_axis in [UpAxis::Y, UpAxis::Z] {
+            let mut cpu_model = load_model_from_bytes(stl, ModelFormat::Stl).unwrap();
+            let options = ImportOptions {
+                up_axis,
+                center: true,
+                normalize_scale: Some(2.0),
+                ..Default::default()
+            };
+            apply_import_options(&mut cpu_model, &options);
+            let bounds = model_bounds(&cpu_model, Mat4::identity());
+            assert!(bounds.center().magnitude() < 1e-4, "{up_axis:?}");
+            let size = bounds.size();
+            assert!(
+                (size.x.max(size.y).max(size.z) - 2.0).abs() < 1e-4,
+                "{up_axis:?}"
+            );
+        }
+
+        // The root transform moves the fitted model.
+        let mut cpu_model = load_model_from_bytes(stl, ModelFormat::Stl).unwrap();
+        let options = ImportOptions {
+            center: true,
+            normalize_scale: Some(2.0),
+            root_transform: Mat4::from_translation(Vec3::new(0.0, 5.0, 0.0)),
+            ..Default::default()
+        };
+        apply_import_options(&mut cpu_model, &options);
+        let bounds = model_bounds(&cpu_model, Mat4::identity());
+        assert!((bounds.center() - Vec3::new(0.0, 5.0, 0.0)).magnitude() < 1e-4);
+        assert!((bounds.size() - Vec3::new(2.0, 1.0, 0.5)).magnitude() < 1e-4);
+    }
+
+    /// A binary glTF of one triangle, its buffer in the BIN chunk or, with `uri`, in that
+    /// external file.
+    fn triangle_glb(uri: Option<&str>) -> Vec<u8> {
+        let uri = uri.map_or(String::new(), |uri| format!(r#","uri":"{uri}""#));
+        let mut json = format!(
+            r#"{{"asset":{{"version":"2.0"}},"scene":0,"scenes":[{{"nodes":[0]}}],
+"nodes":[{{"mesh":0,"name":"triangle"}}],
+"meshes":[{{"primitives":[{{"attributes":{{"POSITION":0}}}}]}}],
+"accessors":[{{"bufferView":0,"componentType":5126,"count":3,"type":"VEC3",
+"min":[0,0,0],"max":[1,1,0]}}],
+"bufferViews":[{{"buffer":0,"byteLength":36}}],
+"buffers":[{{"byteLength":36{uri}}}]}}"#
+        )
+        .into_bytes();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let bin: Vec<u8> = [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect();
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+        let mut glb = Vec::new();
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(length as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin);
+        glb
+    }
+
+    #[test]
+    fn self_contained_glb_loads_from_memory() {
+        let bytes = triangle_glb(None);
+        assert_eq!(ModelFormat::detect(&bytes), Some(ModelFormat::Glb));
+        let cpu_model = load_model_from_bytes(&bytes, ModelFormat::Glb).unwrap();
+        assert_eq!(cpu_model.geometries.len(), 1);
+        let Geometry::Triangles(mesh) = &cpu_model.geometries[0].geometry else {
+            panic!("expected triangles");
+        };
+        assert_eq!(mesh.positions.len(), 3);
+        assert!(cpu_model.geometries[0].material_index.is_some());
+    }
+
+    #[test]
+    fn external_references_fail_before_parsing() {
+        let bytes = triangle_glb(Some("triangle.bin"));
+        match load_model_from_bytes(&bytes, ModelFormat::Glb) {
+            Err(LoadError::ExternalReference { uri }) => assert_eq!(uri, "triangle.bin"),
+            other => panic!("expected ExternalReference, got {other:?}"),
+        }
+        let embedded = triangle_glb(Some("data:application/octet-stream;base64,AAAA"));
+        assert!(!matches!(
+            load_model_from_bytes(&embedded, ModelFormat::Glb),
+            Err(LoadError::ExternalReference { .. })
+        ));
+        let obj = b"mtllib cube.mtl\nv 0 0 0\n";
+        assert_eq!(ModelFormat::detect(obj), Some(ModelFormat::Obj));
+        assert!(matches!(
+            load_model_from_bytes(obj, ModelFormat::Obj),
+            Err(LoadError::ExternalReference { uri }) if uri == "cube.mtl"
+        ));
+    }
+}
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use three_d::{vec4, Context, Srgba, Texture2D, Vec4, Viewport};
+
+use crate::post;
+
+const GRADIENT_SHADER: &str = "
+uniform vec4 topColor;
+uniform vec4 bottomColor;
+in vec2 uvs;
+layout (location = 0) out vec4 outColor;
+
+void main() {
+    outColor = mix(bottomColor, topColor, uvs.y);
+}
+";
+
+const IMAGE_SHADER: &str = "
+uniform sampler2D image;
+in vec2 uvs;
+layout (location = 0) out vec4 outColor;
+
+void main() {
+    outColor = texture(image, uvs);
+}
+";
+
+/// What is drawn behind the scene.
+///
+/// Alpha is kept as given, so with a transparent window a translucent background lets the
+/// webview show through like a translucent clear color does.
+#[derive(Clone)]
+pub enum Background {
+    /// A plain clear to this color.
+    Solid(Srgba),
+    /// A vertical gradient from the top to the bottom color.
+    Gradient { top: Srgba, bottom: Srgba },
+    /// An image stretched over the viewport, drawn as stored, that is sRGB encoded like the
+    /// rest of the frame.
+    Image(Arc<Texture2D>),
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Solid(Srgba::BLACK)
+    }
+}
+
+impl Background {
+    /// The color to clear to before [Background::render]. Gradients and images cover the
+    /// whole viewport, so for them it only matters for the alpha outside of it.
+    pub(crate) fn clear_color(&self) -> Srgba {
+        match self {
+            Background::Solid(color) => *color,
+            Background::Gradient { .. } | Background::Image(_) => Srgba::new(0, 0, 0, 0),
+        }
+    }
+
+    /// Draws the gradient or image into `viewport` of the bound target, after the clear and
+    /// before the scene. Depth is neither tested nor written, so it never hides the model.
+    pub(crate) fn render(&self, context: &Context, viewport: Viewport) {
+        match self {
+            Background::Solid(_) => {}
+            Background::Gradient { top, bottom } => {
+                let (top, bottom) = (to_vec4(*top), to_vec4(*bottom));
+                post::apply_effect(
+                    context,
+                    GRADIENT_SHADER,
+                    post::fullscreen_render_states(),
+                    viewport,
+                    |program| {
+                        program.use_uniform("topColor", top);
+                        program.use_uniform("bottomColor", bottom);
+                    },
+                );
+            }
+            Background::Image(image) => {
+                post::apply_effect(
+                    context,
+                    IMAGE_SHADER,
+                    post::fullscreen_render_states(),
+                    viewport,
+                    |program| program.use_texture("image", image),
+                );
+            }
+        }
+    }
+}
+
+fn to_vec4(color: Srgba) -> Vec4 {
+    vec4(
+        color.r as f32 / 255.0,
+        color.g as f32 / 255.0,
+        color.b as f32 / 255.0,
+        color.a as f32 / 255.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_solid_backgrounds_clear_to_their_color() {
+        let red = Srgba::new(255, 0, 0, 128);
+        assert_eq!(Background::Solid(red).clear_color(), red);
+        let gradient = Background::Gradient {
+            top: red,
+            bottom: Srgba::BLUE,
+        };
+        assert_eq!(gradient.clear_color(), Srgba::new(0, 0, 0, 0));
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+
+use three_d::{
+    Context, CpuMaterial, CpuMesh, CpuModel, Gm, Indices, InnerSpace, Mat4, Matrix, Mesh,
+    PhysicalMaterial, Positions, SquareMatrix,
+};
+use three_d_asset::{Geometry, Primitive};
+
+/// Static parts of a model that share a material, merged into a single mesh so they are drawn
+/// with one draw call.
+///
+/// The part transformations are baked into the vertices when the batch is built, so a batch
+/// only stays correct for parts that never move on their own. Parts with animations stay
+/// separate [crate::part::ScenePart]s.
+pub struct MaterialBatch {
+    object: Gm<Mesh, PhysicalMaterial>,
+    parts: Vec<usize>,
+    triangles: u64,
+}
+
+impl MaterialBatch {
+    /// The indices of the merged parts, into `cpu_model.geometries` and equally into the parts
+    /// created by [crate::part::ScenePart::from_cpu_model].
+    pub fn parts(&self) -> &[usize] {
+        &self.parts
+    }
+
+    pub fn triangles(&self) -> u64 {
+        self.triangles
+    }
+
+    pub fn object(&self) -> &Gm<Mesh, PhysicalMaterial> {
+        &self.object
+    }
+
+    pub fn object_mut(&mut self) -> &mut Gm<Mesh, PhysicalMaterial> {
+        &mut self.object
+    }
+}
+
+/// Merges the triangle primitives of `cpu_model` that share a material into one batch per
+/// material.
+///
+/// Only primitives without key-frame animations for which `is_static` returns `true` are
+/// considered, pass `|_| false` for the index of any part that gets an animation later. A
+/// material used by a single static primitive does not produce a batch: there is no draw to
+/// save, and it would only duplicate the part's GPU buffers.
+pub fn batch_by_material(
+    context: &Context,
+    cpu_model: &CpuModel,
+    is_static: impl Fn(usize) -> bool,
+) -> Vec<MaterialBatch> {
+    // Primitives with the same material index get the same material and so the same render
+    // states, which is what makes merging them invisible.
+    let mut groups: BTreeMap<Option<usize>, Vec<usize>> = BTreeMap::new();
+    for (index, primitive) in cpu_model.geometries.iter().enumerate() {
+        if matches!(primitive.geometry, Geometry::Triangles(_))
+            && primitive.animations.is_empty()
+            && is_static(index)
+        {
+            groups
+                .entry(primitive.material_index)
+                .or_default()
+                .push(index);
+        }
+    }
+    groups
+        .into_iter()
+        .filter(|(_, parts)| parts.len() > 1)
+        .map(|(material_index, parts)| {
+            let primitives: Vec<&Primitive> = parts
+                .iter()
+                .map(|&index| &cpu_model.geometries[index])
+                .collect();
+            let mesh = merge(&primitives);
+            let material = material_index
+                .and_then(|index| cpu_model.materials.get(index))
+                .map(|material| PhysicalMaterial::new(context, material))
+                .unwrap_or_else(|| PhysicalMaterial::new(context, &CpuMaterial::default()));
+            MaterialBatch {
+                triangles: mesh.triangle_count() as u64,
+                object: Gm::new(Mesh::new(context, &mesh), material),
+                parts,
+            }
+        })
+        .collect()
+}
+
+/// Concatenates the meshes of `primitives` in world space. Vertex attributes missing from any
+/// of them are dropped from the result.
+fn merge(primitives: &[&Primitive]) -> CpuMesh {
+    let meshes: Vec<(&CpuMesh, Mat4)> = primitives
+        .iter()
+        .filter_map(|primitive| match &primitive.geometry {
+            Geometry::Triangles(mesh) => Some((mesh, primitive.transformation)),
+            Geometry::Points(_) => None,
+        })
+        .collect();
+    let all = |has: fn(&CpuMesh) -> bool| meshes.iter().all(|(mesh, _)| has(mesh));
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+    let mut normals = all(|mesh| mesh.normals.is_some()).then(Vec::new);
+    let mut tangents = all(|mesh| mesh.tangents.is_some()).then(Vec::new);
+    let mut uvs = all(|mesh| mesh.uvs.is_some()).then(Vec::new);
+    let mut colors = all(|mesh| mesh.colors.is_some()).then(Vec::new);
+    for (mesh, transformation) in meshes {
+        let offset = positions.len() as u32;
+        let normal_transformation = transformation
+            .invert()
+            .unwrap_or_else(Mat4::identity)
+            .transpose();
+        positions.extend(
+            mesh.positions
+                .to_f32()
+                .into_iter()
+                .map(|position| (transformation * position.extend(1.0)).truncate()),
+        );
+        match mesh.indices.to_u32() {
+            Some(mesh_indices) => indices.extend(mesh_indices.into_iter().map(|i| i + offset)),
+            None => indices.extend(offset..positions.len() as u32),
+        }
+        if let (Some(normals), Some(mesh_normals)) = (&mut normals, &mesh.normals) {
+            normals.extend(mesh_normals.iter().map(|normal| {
+                (normal_transformation * normal.extend(0.0))
+                    .truncate()
+                    .normalize()
+            }));
+        }
+        if let (Some(tangents), Some(mesh_tangents)) = (&mut tangents, &mesh.tangents) {
+            tangents.extend(mesh_tangents.iter().map(|tangent| {
+                let direction = (transformation * tangent.truncate().extend(0.0))
+                    .truncate()
+                    .normalize();
+                direction.extend(tangent.w)
+            }));
+        }
+        if let (Some(uvs), Some(mesh_uvs)) = (&mut uvs, &mesh.uvs) {
+            uvs.extend_from_slice(mesh_uvs);
+        }
+        if let (Some(colors), Some(mesh_colors)) = (&mut colors, &mesh.colors) {
+            colors.extend_from_slice(mesh_colors);
+        }
+    }
+    CpuMesh {
+        positions: Positions::F32(positions),
+        indices: Indices::U32(indices),
+        normals,
+        tangents,
+        uvs,
+        colors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::vec3;
+
+    use super::*;
+
+    fn cube_at(x: f32) -> Primitive {
+        Primitive {
+            name: String::new(),
+            transformation: Mat4::from_translation(vec3(x, 0.0, 0.0)),
+            animations: Vec::new(),
+            geometry: Geometry::Triangles(CpuMesh::cube()),
+            material_index: None,
+        }
+    }
+
+    #[test]
+    fn merged_mesh_holds_every_part_in_world_space() {
+        let (left, right) = (cube_at(-5.0), cube_at(5.0));
+        let mesh = merge(&[&left, &right]);
+        assert_eq!(mesh.triangle_count(), 24);
+        let positions = mesh.positions.to_f32();
+        assert_eq!(positions.len(), 2 * CpuMesh::cube().positions.len());
+        let (first, second) = positions.split_at(positions.len() / 2);
+        assert!(first.iter().all(|p| (-6.0..=-4.0).contains(&p.x)));
+        assert!(second.iter().all(|p| (4.0..=6.0).contains(&p.x)));
+        let indices = mesh.indices.to_u32().unwrap();
+        assert!(indices.iter().all(|&i| (i as usize) < positions.len()));
+        assert!(mesh.normals.is_some());
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn only_static_parts_sharing_a_material_are_batched() {
+        let harness = crate::testing::TestHarness::new(8, 8).unwrap();
+        let context = &harness.context().context;
+        let mut model = crate::scene::tests::cubes(4);
+        model.geometries[3].material_index = Some(0);
+        model.materials.push(CpuMaterial::default());
+        let batches = batch_by_material(context, &model, |index| index != 2);
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].parts(), [0, 1]);
+        assert_eq!(batches[0].triangles(), 24);
+    }
+}
@@ -0,0 +1,248 @@
+use three_d::{
+    f16, vec2, Blend, BlendEquationType, BlendMultiplierType, Context, Interpolation, RenderStates,
+    Texture2D, Vec2, Viewport, Wrapping,
+};
+
+use crate::post::{self, SRGB_FUNCTIONS};
+use crate::render_texture::Draw;
+
+/// The most halvings [BloomChain] blurs over, at [Bloom::radius] `1.0`.
+pub const MAX_BLOOM_LEVELS: usize = 6;
+
+/// A glow around the bright parts of the picture, like emissive materials and highlights,
+/// see [crate::scene::Scene::set_bloom].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bloom {
+    /// How bright, in linear color after the exposure, a pixel has to be to glow. Raising the
+    /// exposure makes more of the picture glow.
+    pub threshold: f32,
+    /// How strongly the glow is added back.
+    pub intensity: f32,
+    /// How far the glow spreads, from `0.0` to `1.0` for the widest, relative to the size of
+    /// the viewport.
+    pub radius: f32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            intensity: 0.5,
+            radius: 0.75,
+        }
+    }
+}
+
+impl Bloom {
+    /// Negative thresholds and intensities are raised to zero and the radius is clamped to
+    /// `0.0..=1.0`.
+    pub fn clamped(self) -> Self {
+        Self {
+            threshold: self.threshold.max(0.0),
+            intensity: self.intensity.max(0.0),
+            radius: self.radius.clamp(0.0, 1.0),
+        }
+    }
+
+    /// How many halvings the blur goes through, at least one.
+    fn levels(&self) -> usize {
+        ((self.radius * MAX_BLOOM_LEVELS as f32).round() as usize).clamp(1, MAX_BLOOM_LEVELS)
+    }
+}
+
+const EXTRACT_SHADER: &str = "
+uniform sampler2D colorMap;
+uniform float exposure;
+uniform float threshold;
+uniform vec2 texelSize;
+in vec2 uvs;
+layout (location = 0) out vec4 outColor;
+
+vec3 bright(vec2 uv) {
+    vec3 c = srgb_to_linear(max(texture(colorMap, uv).rgb, vec3(0.0))) * exposure;
+    float brightness = max(c.r, max(c.g, c.b));
+    // Scaled rather than cut, so the glow fades in instead of starting at a hard edge.
+    return c * max(brightness - threshold, 0.0) / max(brightness, 1e-4);
+}
+
+void main() {
+    // Four bilinear taps average the 4x4 source texels under this half size texel.
+    vec3 sum = bright(uvs + vec2(-0.5, -0.5) * texelSize)
+        + bright(uvs + vec2(0.5, -0.5) * texelSize)
+        + bright(uvs + vec2(-0.5, 0.5) * texelSize)
+        + bright(uvs + vec2(0.5, 0.5) * texelSize);
+    outColor = vec4(sum * 0.25, 1.0);
+}
+";
+
+const DOWNSAMPLE_SHADER: &str = "
+uniform sampler2D sourceMap;
+uniform vec2 texelSize;
+in vec2 uvs;
+layout (location = 0) out vec4 outColor;
+
+void main() {
+    vec3 sum = texture(sourceMap, uvs + vec2(-1.0, -1.0) * texelSize).rgb
+        + texture(sourceMap, uvs + vec2(1.0, -1.0) * texelSize).rgb
+        + texture(sourceMap, uvs + vec2(-1.0, 1.0) * texelSize).rgb
+        + texture(sourceMap, uvs + vec2(1.0, 1.0) * texelSize).rgb;
+    outColor = vec4(sum * 0.25, 1.0);
+}
+";
+
+const UPSAMPLE_SHADER: &str = "
+uniform sampler2D sourceMap;
+uniform vec2 texelSize;
+in vec2 uvs;
+layout (location = 0) out vec4 outColor;
+
+void main() {
+    // A 3x3 tent, blurring further while blending up.
+    vec3 sum = vec3(0.0);
+    for (int y = -1; y <= 1; y++) {
+        for (int x = -1; x <= 1; x++) {
+            float weight = (2.0 - abs(float(x))) * (2.0 - abs(float(y)));
+            sum += weight * texture(sourceMap, uvs + vec2(float(x), float(y)) * texelSize).rgb;
+        }
+    }
+    outColor = vec4(sum / 16.0, 1.0);
+}
+";
+
+/// The blurred bright pixels of a frame, in linear color, for [post::tone_map_bloomed] to add
+/// back before tone mapping.
+///
+/// Bright pixels are extracted into half the size of the frame, halved further for each
+/// level and blended back up, each step blurring a little more, which spreads wide at little
+/// cost. The textures are kept between frames and reallocated when the size changes.
+pub struct BloomChain {
+    context: Context,
+    levels: Vec<Texture2D>,
+}
+
+impl BloomChain {
+    pub fn new(context: &Context) -> Self {
+        Self {
+            context: context.clone(),
+            levels: Vec::new(),
+        }
+    }
+
+    /// Renders the bloom of `source`, sRGB encoded as `three_d` materials write it, seen at
+    /// `exposure`, and returns it. Binds its own targets, so rebind the one rendered to
+    /// before.
+    pub fn render(&mut self, source: &Texture2D, exposure: f32, bloom: Bloom) -> &Texture2D {
+        let bloom = bloom.clamped();
+        self.resize(source.width(), source.height(), bloom.levels());
+        let (context, levels) = (&self.context, &mut self.levels);
+        let extract = format!("{}{}", SRGB_FUNCTIONS, EXTRACT_SHADER);
+        let source_texel = texel_size(source);
+        render_into(context, &mut levels[0], &extract, false, |program| {
+            program.use_texture("colorMap", source);
+            program.use_uniform("exposure", exposure);
+            program.use_uniform("threshold", bloom.threshold);
+            program.use_uniform("texelSize", source_texel);
+        });
+        for level in 1..levels.len() {
+            let (larger, smaller) = levels.split_at_mut(level);
+            let larger = &larger[level - 1];
+            render_into(
+                context,
+                &mut smaller[0],
+                DOWNSAMPLE_SHADER,
+                false,
+                |program| {
+                    program.use_texture("sourceMap", larger);
+                    program.use_uniform("texelSize", texel_size(larger));
+                },
+            );
+        }
+        for level in (1..levels.len()).rev() {
+            let (larger, smaller) = levels.split_at_mut(level);
+            let smaller = &smaller[0];
+            render_into(
+                context,
+                &mut larger[level - 1],
+                UPSAMPLE_SHADER,
+                true,
+                |program| {
+                    program.use_texture("sourceMap", smaller);
+                    program.use_uniform("texelSize", texel_size(smaller));
+                },
+            );
+        }
+        &self.levels[0]
+    }
+
+    /// Keeps `levels` textures, each half the size of the one before, starting at half of
+    /// `width` by `height`. Fewer if the frame is too small to halve that often.
+    fn resize(&mut self, width: u32, height: u32, levels: usize) {
+        let mut sizes = Vec::new();
+        let (mut width, mut height) = ((width / 2).max(1), (height / 2).max(1));
+        for _ in 0..levels {
+            sizes.push((width, height));
+            if width == 1 && height == 1 {
+                break;
+            }
+            (width, height) = ((width / 2).max(1), (height / 2).max(1));
+        }
+        let current: Vec<(u32, u32)> = self
+            .levels
+            .iter()
+            .map(|level| (level.width(), level.height()))
+            .collect();
+        if current != sizes {
+            self.levels = sizes
+                .into_iter()
+                .map(|(width, height)| new_level(&self.context, width, height))
+                .collect();
+        }
+    }
+}
+
+fn texel_size(texture: &Texture2D) -> Vec2 {
+    vec2(1.0 / texture.width() as f32, 1.0 / texture.height() as f32)
+}
+
+/// Runs the fullscreen `shader` over all of `target`, overwriting it or, with `add`, adding
+/// to what it holds.
+fn render_into(
+    context: &Context,
+    target: &mut Texture2D,
+    shader: &str,
+    add: bool,
+    use_uniforms: impl FnOnce(&three_d::Program),
+) {
+    let viewport = Viewport::new_at_origo(target.width(), target.height());
+    let render_states = if add {
+        RenderStates {
+            blend: Blend::Enabled {
+                source_rgb_multiplier: BlendMultiplierType::One,
+                source_alpha_multiplier: BlendMultiplierType::Zero,
+                destination_rgb_multiplier: BlendMultiplierType::One,
+                destination_alpha_multiplier: BlendMultiplierType::One,
+                rgb_equation: BlendEquationType::Add,
+                alpha_equation: BlendEquationType::Add,
+            },
+            ..post::fullscreen_render_states()
+        }
+    } else {
+        post::fullscreen_render_states()
+    };
+    target
+        .as_color_target(None)
+        .draw(|| post::apply_effect(context, shader, render_states, viewport, use_uniforms));
+}
+
+fn new_level(context: &Context, width: u32, height: u32) -> Texture2D {
+    Texture2D::new_empty::<[f16; 4]>(
+        context,
+        width,
+        height,
+        Interpolation::Linear,
+        Interpolation::Linear,
+        None,
+        Wrapping::ClampToEdge,
+        Wrapping::ClampToEdge,
+    )
+}
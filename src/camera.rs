@@ -0,0 +1,592 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use three_d::{
+    vec3, AxisAlignedBoundingBox, Camera, Deg, InnerSpace, Rad, Radians, Vec3, VectorSpace,
+    Viewport,
+};
+use three_d_asset::ProjectionType;
+
+use crate::controls::Controls;
+
+/// The projections a camera can switch between, see [set_projection_mode].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProjectionMode {
+    Perspective {
+        field_of_view_y: Radians,
+    },
+    /// `height` is the world-space height the viewport shows.
+    Orthographic {
+        height: f32,
+    },
+}
+
+impl ProjectionMode {
+    /// The mode `camera` is in.
+    pub fn of(camera: &Camera) -> Self {
+        match *camera.projection_type() {
+            ProjectionType::Perspective { field_of_view_y } => {
+                ProjectionMode::Perspective { field_of_view_y }
+            }
+            ProjectionType::Orthographic { height } => ProjectionMode::Orthographic { height },
+        }
+    }
+}
+
+/// Switches the projection of `camera`, keeping its position, target and clip planes.
+pub fn set_projection_mode(camera: &mut Camera, mode: ProjectionMode) {
+    let (z_near, z_far) = (camera.z_near(), camera.z_far());
+    match mode {
+        ProjectionMode::Perspective { field_of_view_y } => {
+            camera.set_perspective_projection(field_of_view_y, z_near, z_far)
+        }
+        ProjectionMode::Orthographic { height } => {
+            camera.set_orthographic_projection(height, z_near, z_far)
+        }
+    }
+}
+
+/// Which axis keeps its field of view as the aspect ratio changes, see [field_of_view_y].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FovAxis {
+    /// A wider viewport shows more at the sides, `three_d`'s behavior.
+    #[default]
+    Vertical,
+    /// A wider viewport shows less at the top and bottom, for consistent horizontal framing.
+    Horizontal,
+    /// Locks the shorter axis, so landscape viewports behave as [FovAxis::Vertical] and
+    /// portrait ones as [FovAxis::Horizontal] and the framed content always fits.
+    Adaptive,
+}
+
+/// The widest vertical field of view [field_of_view_y] gives; beyond it the perspective
+/// stretches the edges of the view beyond recognition.
+pub const MAX_FIELD_OF_VIEW_Y: Deg<f32> = Deg(150.0);
+
+/// The vertical field of view that gives `field_of_view` on `axis` at `aspect`, width over
+/// height.
+///
+/// Keeping the horizontal field of view of a very tall viewport would need a vertical one
+/// near 180°, so the result is clamped to [MAX_FIELD_OF_VIEW_Y], and crops the sides instead.
+pub fn field_of_view_y(field_of_view: Radians, axis: FovAxis, aspect: f32) -> Radians {
+    let aspect = if aspect.is_finite() && aspect > 0.0 {
+        aspect
+    } else {
+        1.0
+    };
+    let horizontal = match axis {
+        FovAxis::Vertical => false,
+        FovAxis::Horizontal => true,
+        FovAxis::Adaptive => aspect < 1.0,
+    };
+    if !horizontal {
+        return field_of_view;
+    }
+    let y = 2.0 * ((field_of_view.0 * 0.5).tan() / aspect).atan();
+    Rad(y.min(Rad::from(MAX_FIELD_OF_VIEW_Y).0))
+}
+
+/// Sets the perspective of `camera` to `field_of_view` on `axis` for the aspect ratio of
+/// `viewport`. Orthographic cameras are left alone.
+pub fn apply_field_of_view(
+    camera: &mut Camera,
+    field_of_view: Radians,
+    axis: FovAxis,
+    viewport: Viewport,
+) {
+    if let ProjectionType::Perspective { .. } = camera.projection_type() {
+        let field_of_view_y = field_of_view_y(field_of_view, axis, viewport.aspect());
+        let (z_near, z_far) = (camera.z_near(), camera.z_far());
+        camera.set_perspective_projection(field_of_view_y, z_near, z_far);
+    }
+}
+
+/// Toggles between perspective and orthographic projection so the plane through the target
+/// keeps its size on screen.
+///
+/// Going orthographic, the height is what the perspective frustum covers at the target
+/// distance. Going back to `field_of_view_y`, the camera moves along its view direction to
+/// the distance at which the frustum covers the orthographic height again, and the far plane
+/// is pushed out if that distance needs it.
+pub fn toggle_projection(camera: &mut Camera, field_of_view_y: Radians) {
+    let target = *camera.target();
+    let offset = *camera.position() - target;
+    let distance = offset.magnitude();
+    let half_tan = (field_of_view_y.0 * 0.5).tan();
+    match ProjectionMode::of(camera) {
+        ProjectionMode::Perspective { field_of_view_y } => {
+            let height = 2.0 * distance * (field_of_view_y.0 * 0.5).tan();
+            set_projection_mode(camera, ProjectionMode::Orthographic { height });
+        }
+        ProjectionMode::Orthographic { height } => {
+            let new_distance = height * 0.5 / half_tan;
+            if distance > f32::EPSILON {
+                let position = target + offset * (new_distance / distance);
+                let up = *camera.up();
+                camera.set_view(position, target, up);
+            }
+            let (z_near, z_far) = (camera.z_near(), camera.z_far().max(new_distance * 2.0));
+            camera.set_perspective_projection(field_of_view_y, z_near, z_far);
+        }
+    }
+}
+
+/// Moves `camera` so all of `aabb` is in view and makes its center the orbit target.
+///
+/// The view direction is kept, only the distance changes. `padding` scales the framed size:
+/// `1.0` fits the box's bounding sphere exactly, `1.2` leaves a 20% margin. The tighter of the
+/// horizontal and vertical field of view decides, so very wide or very tall viewports still
+/// show everything. Working with the bounding sphere also keeps flat boxes, like a single
+/// quad, framed sensibly from any angle. The near and far planes are moved to enclose the box,
+/// and the controls' distance range is widened if needed so the first zoom doesn't snap back.
+///
+/// Returns `false`, leaving everything untouched, if `aabb` is empty.
+pub fn frame_to_aabb(
+    camera: &mut Camera,
+    controls: &mut Controls,
+    aabb: AxisAlignedBoundingBox,
+    padding: f32,
+) -> bool {
+    if aabb.is_empty() {
+        return false;
+    }
+    let center = aabb.center();
+    // A single point still needs some extent to put the camera at a distance.
+    let radius = (aabb.size().magnitude() * 0.5).max(1e-3) * padding.max(f32::EPSILON);
+    let aspect = camera.viewport().aspect();
+    let direction = {
+        let direction = *camera.position() - *camera.target();
+        if direction.magnitude2() > f32::EPSILON {
+            direction.normalize()
+        } else {
+            vec3(0.0, 0.0, 1.0)
+        }
+    };
+    let up = *camera.up();
+    let projection_type = camera.projection_type().clone();
+    match projection_type {
+        ProjectionType::Perspective { field_of_view_y } => {
+            let half_vertical = field_of_view_y.0 * 0.5;
+            let half_horizontal = (half_vertical.tan() * aspect).atan();
+            let half_fov = half_vertical.min(half_horizontal);
+            let distance = radius / half_fov.sin();
+            camera.set_view(center + direction * distance, center, up);
+            camera.set_perspective_projection(
+                field_of_view_y,
+                (distance - radius).max(distance * 1e-3),
+                distance + radius,
+            );
+            widen_distance_range(controls, distance);
+        }
+        ProjectionType::Orthographic { .. } => {
+            let height = 2.0 * radius * (1.0 / aspect).max(1.0);
+            let distance = 2.0 * radius;
+            camera.set_view(center + direction * distance, center, up);
+            camera.set_orthographic_projection(height, distance - radius, distance + radius);
+            widen_distance_range(controls, distance);
+        }
+    }
+    controls.set_target(center);
+    true
+}
+
+fn widen_distance_range(controls: &mut Controls, distance: f32) {
+    let (min_distance, max_distance) = controls.distance_range();
+    if distance < min_distance || distance > max_distance {
+        controls.set_distance_range(min_distance.min(distance), max_distance.max(distance));
+    }
+}
+
+/// Where a camera is and how it projects, without its viewport, see [CameraPresets].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraState {
+    pub position: Vec3,
+    pub target: Vec3,
+    pub up: Vec3,
+    pub projection: ProjectionMode,
+}
+
+impl CameraState {
+    /// The state `camera` is in.
+    pub fn of(camera: &Camera) -> Self {
+        Self {
+            position: *camera.position(),
+            target: *camera.target(),
+            up: *camera.up(),
+            projection: ProjectionMode::of(camera),
+        }
+    }
+
+    /// Moves `camera` into this state, keeping its viewport and clip planes.
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.set_view(self.position, self.target, self.up);
+        set_projection_mode(camera, self.projection);
+    }
+
+    /// The state `t` of the way from `self` to `to`.
+    ///
+    /// Field of view and orthographic height are interpolated between projections of the same
+    /// kind. Between a perspective and an orthographic one there is nothing sensible in
+    /// between, the projection switches halfway.
+    pub fn lerp(&self, to: &CameraState, t: f32) -> Self {
+        let projection = match (self.projection, to.projection) {
+            (
+                ProjectionMode::Perspective {
+                    field_of_view_y: from,
+                },
+                ProjectionMode::Perspective {
+                    field_of_view_y: to,
+                },
+            ) => ProjectionMode::Perspective {
+                field_of_view_y: Rad(from.0 + (to.0 - from.0) * t),
+            },
+            (
+                ProjectionMode::Orthographic { height: from },
+                ProjectionMode::Orthographic { height: to },
+            ) => ProjectionMode::Orthographic {
+                height: from + (to - from) * t,
+            },
+            (from, to) => {
+                if t < 0.5 {
+                    from
+                } else {
+                    to
+                }
+            }
+        };
+        let up = self.up.lerp(to.up, t);
+        Self {
+            position: self.position.lerp(to.position, t),
+            target: self.target.lerp(to.target, t),
+            up: if up.magnitude2() > f32::EPSILON {
+                up.normalize()
+            } else {
+                to.up
+            },
+            projection,
+        }
+    }
+}
+
+/// A flight to a preset in progress. `from` is taken on the first update after
+/// [CameraPresets::goto], from wherever the camera is then.
+#[derive(Clone, Copy, Debug)]
+struct CameraFlight {
+    from: Option<CameraState>,
+    to: CameraState,
+    elapsed: Duration,
+    duration: Duration,
+}
+
+/// Named viewpoints, such as "front" or "top", that the camera can fly between, see
+/// [crate::scene::Scene::camera_presets_mut].
+#[derive(Clone, Debug, Default)]
+pub struct CameraPresets {
+    presets: HashMap<String, CameraState>,
+    flight: Option<CameraFlight>,
+}
+
+impl CameraPresets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores where `camera` is as `name`, replacing a preset of that name.
+    pub fn save(&mut self, name: impl Into<String>, camera: &Camera) {
+        self.insert(name, CameraState::of(camera));
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, state: CameraState) {
+        self.presets.insert(name.into(), state);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CameraState> {
+        self.presets.get(name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<CameraState> {
+        self.presets.remove(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.presets.keys().map(String::as_str)
+    }
+
+    /// Starts flying the camera to the preset `name` over `duration`, from wherever it is,
+    /// replacing a flight in progress. A zero `duration` jumps there on the next update.
+    /// Returns `false` if there is no such preset.
+    pub fn goto(&mut self, name: &str, duration: Duration) -> bool {
+        let Some(to) = self.presets.get(name) else {
+            return false;
+        };
+        self.flight = Some(CameraFlight {
+            from: None,
+            to: *to,
+            elapsed: Duration::ZERO,
+            duration,
+        });
+        true
+    }
+
+    /// Whether a [CameraPresets::goto] has yet to arrive.
+    pub fn is_flying(&self) -> bool {
+        self.flight.is_some()
+    }
+
+    /// Stops a flight in progress where it is.
+    pub fn cancel(&mut self) {
+        self.flight = None;
+    }
+
+    /// Advances a flight in progress by `dt`, moving `camera` and the orbit target of
+    /// `controls` along. Returns whether the camera moved.
+    pub fn update(&mut self, camera: &mut Camera, controls: &mut Controls, dt: Duration) -> bool {
+        let Some(flight) = &mut self.flight else {
+            return false;
+        };
+        let from = *flight.from.get_or_insert_with(|| CameraState::of(camera));
+        flight.elapsed += dt;
+        let t = if flight.duration.is_zero() {
+            1.0
+        } else {
+            (flight.elapsed.as_secs_f32() / flight.duration.as_secs_f32()).min(1.0)
+        };
+        // Smoothstep, so the camera neither starts nor stops abruptly.
+        let state = from.lerp(&flight.to, t * t * (3.0 - 2.0 * t));
+        if t >= 1.0 {
+            self.flight = None;
+        }
+        state.apply(camera);
+        controls.set_target(state.target);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::{degrees, Rad, Vec3, Vec4, Viewport};
+
+    use super::*;
+
+    fn camera(width: u32, height: u32) -> Camera {
+        Camera::new_perspective(
+            Viewport::new_at_origo(width, height),
+            vec3(3.0, 2.0, 5.0),
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            degrees(45.0),
+            0.1,
+            10.0,
+        )
+    }
+
+    fn controls() -> Controls {
+        Controls::new(vec3(0.0, 0.0, 0.0), 1.0, 10.0)
+    }
+
+    /// Whether every corner of `aabb` lands inside the view frustum of `camera`.
+    fn contains(camera: &Camera, aabb: &AxisAlignedBoundingBox) -> bool {
+        let (min, max) = (aabb.min(), aabb.max());
+        (0..8).all(|corner| {
+            let pick = |bit, min: f32, max: f32| if corner & bit == 0 { min } else { max };
+            let point = vec3(
+                pick(1, min.x, max.x),
+                pick(2, min.y, max.y),
+                pick(4, min.z, max.z),
+            );
+            let clip: Vec4 = camera.projection() * camera.view() * point.extend(1.0);
+            let ndc = clip.truncate() / clip.w;
+            clip.w > 0.0
+                && [ndc.x, ndc.y, ndc.z]
+                    .iter()
+                    .all(|c| (-1.0..=1.0).contains(c))
+        })
+    }
+
+    fn aabb(min: Vec3, max: Vec3) -> AxisAlignedBoundingBox {
+        AxisAlignedBoundingBox::new_with_positions(&[min, max])
+    }
+
+    #[test]
+    fn framed_box_is_inside_the_frustum() {
+        let mut camera = camera(800, 600);
+        let mut controls = controls();
+        let model = aabb(vec3(9.0, -1.0, -2.0), vec3(13.0, 5.0, 2.0));
+        assert!(!contains(&camera, &model));
+        assert!(frame_to_aabb(&mut camera, &mut controls, model, 1.1));
+        assert!(contains(&camera, &model));
+        assert_eq!(*camera.target(), model.center());
+        assert_eq!(controls.target(), model.center());
+        let distance = (*camera.position() - model.center()).magnitude();
+        let radius = model.size().magnitude() * 0.5 * 1.1;
+        assert!((distance - radius / 22.5_f32.to_radians().sin()).abs() < 1e-3);
+        let (_, max_distance) = controls.distance_range();
+        assert!(max_distance >= distance);
+    }
+
+    #[test]
+    fn flat_boxes_and_extreme_aspects_still_fit() {
+        let flat = aabb(vec3(-2.0, 0.0, -2.0), vec3(2.0, 0.0, 2.0));
+        for (width, height) in [(2000, 100), (100, 2000), (600, 600)] {
+            let mut camera = camera(width, height);
+            assert!(frame_to_aabb(&mut camera, &mut controls(), flat, 1.0));
+            assert!(contains(&camera, &flat), "{width}x{height}");
+        }
+    }
+
+    #[test]
+    fn empty_box_leaves_the_camera_alone() {
+        let mut camera = camera(800, 600);
+        let position = *camera.position();
+        let empty = AxisAlignedBoundingBox::EMPTY;
+        assert!(!frame_to_aabb(&mut camera, &mut controls(), empty, 1.0));
+        assert_eq!(*camera.position(), position);
+    }
+
+    #[test]
+    fn orthographic_mode_projects_the_expected_bounds() {
+        let mut camera = camera(800, 400);
+        let (position, target) = (*camera.position(), *camera.target());
+        set_projection_mode(&mut camera, ProjectionMode::Orthographic { height: 4.0 });
+        assert_eq!(
+            ProjectionMode::of(&camera),
+            ProjectionMode::Orthographic { height: 4.0 }
+        );
+        assert_eq!((*camera.position(), *camera.target()), (position, target));
+
+        // 4 units high at an aspect of 2 is 8 units wide, from -4 to 4.
+        let projection = camera.projection();
+        assert!((projection.x.x - 2.0 / 8.0).abs() < 1e-6);
+        assert!((projection.y.y - 2.0 / 4.0).abs() < 1e-6);
+        assert_eq!((projection.z.w, projection.w.w), (0.0, 1.0));
+        let corner: Vec4 = projection * vec3(4.0, 2.0, -1.0).extend(1.0);
+        assert!((corner.x - 1.0).abs() < 1e-6 && (corner.y - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn toggling_keeps_the_target_plane_the_same_size() {
+        let mut camera = camera(800, 600);
+        let distance = (*camera.position() - *camera.target()).magnitude();
+        let field_of_view_y = Rad::from(degrees(45.0));
+        toggle_projection(&mut camera, field_of_view_y);
+        let ProjectionMode::Orthographic { height } = ProjectionMode::of(&camera) else {
+            panic!("still perspective");
+        };
+        assert!((height - 2.0 * distance * (field_of_view_y.0 / 2.0).tan()).abs() < 1e-4);
+
+        toggle_projection(&mut camera, field_of_view_y);
+        assert_eq!(
+            ProjectionMode::of(&camera),
+            ProjectionMode::Perspective { field_of_view_y }
+        );
+        let back = (*camera.position() - *camera.target()).magnitude();
+        assert!((back - distance).abs() < 1e-3);
+    }
+
+    /// The horizontal field of view for `field_of_view_y` at `aspect`.
+    fn field_of_view_x(field_of_view_y: Radians, aspect: f32) -> f32 {
+        2.0 * ((field_of_view_y.0 * 0.5).tan() * aspect).atan()
+    }
+
+    #[test]
+    fn locked_axis_keeps_its_field_of_view_across_aspects() {
+        let field_of_view = Rad::from(degrees(60.0));
+        for aspect in [0.5, 1.0, 16.0 / 9.0, 3.0] {
+            let vertical = field_of_view_y(field_of_view, FovAxis::Vertical, aspect);
+            assert_eq!(vertical, field_of_view);
+            let horizontal = field_of_view_y(field_of_view, FovAxis::Horizontal, aspect);
+            assert!((field_of_view_x(horizontal, aspect) - field_of_view.0).abs() < 1e-5);
+        }
+        // Adaptive locks whichever axis is shorter.
+        let adaptive = |aspect| field_of_view_y(field_of_view, FovAxis::Adaptive, aspect);
+        assert_eq!(adaptive(2.0), field_of_view);
+        assert!((field_of_view_x(adaptive(0.5), 0.5) - field_of_view.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn extreme_aspects_stay_within_the_limit() {
+        let field_of_view = Rad::from(degrees(90.0));
+        let limit = Rad::from(MAX_FIELD_OF_VIEW_Y);
+        let tall = field_of_view_y(field_of_view, FovAxis::Horizontal, 0.01);
+        assert!((tall.0 - limit.0).abs() < 1e-6);
+        for aspect in [0.0, f32::NAN, f32::INFINITY] {
+            let y = field_of_view_y(field_of_view, FovAxis::Horizontal, aspect);
+            assert_eq!(y, field_of_view);
+        }
+
+        let mut camera = camera(1000, 10);
+        let viewport = Viewport::new_at_origo(1000, 10);
+        apply_field_of_view(&mut camera, field_of_view, FovAxis::Horizontal, viewport);
+        let projection = camera.projection();
+        assert!(projection.x.x.is_finite() && projection.y.y.is_finite());
+        assert!((projection.x.x - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn goto_flies_the_camera_to_a_preset_over_updates() {
+        let mut camera = camera(4, 4);
+        let mut controls = controls();
+        let mut presets = CameraPresets::new();
+        presets.save("start", &camera);
+        let top = CameraState {
+            position: vec3(1.0, 0.0, 8.0),
+            target: vec3(1.0, 0.0, 0.0),
+            up: vec3(0.0, 1.0, 0.0),
+            projection: ProjectionMode::Perspective {
+                field_of_view_y: degrees(60.0).into(),
+            },
+        };
+        presets.insert("top", top);
+        assert!(!presets.goto("missing", Duration::from_secs(1)));
+        assert!(!presets.is_flying());
+
+        assert!(presets.goto("top", Duration::from_secs(1)));
+        let mut distance = (*camera.position() - top.position).magnitude();
+        for _ in 0..3 {
+            assert!(presets.update(&mut camera, &mut controls, Duration::from_millis(250)));
+            let closer = (*camera.position() - top.position).magnitude();
+            assert!(closer < distance);
+            distance = closer;
+            assert!(presets.is_flying());
+        }
+        presets.update(&mut camera, &mut controls, Duration::from_millis(250));
+        assert!(!presets.is_flying());
+        assert!((*camera.position() - top.position).magnitude() < 1e-5);
+        assert!((controls.target() - top.target).magnitude() < 1e-5);
+        let ProjectionMode::Perspective { field_of_view_y } = ProjectionMode::of(&camera) else {
+            panic!("expected a perspective projection");
+        };
+        assert!((field_of_view_y.0 - Rad::from(degrees(60.0)).0).abs() < 1e-5);
+        assert!(!presets.update(&mut camera, &mut controls, Duration::from_millis(250)));
+
+        // Flying back and jumping with a zero duration.
+        assert!(presets.goto("start", Duration::ZERO));
+        presets.update(&mut camera, &mut controls, Duration::ZERO);
+        assert!((*camera.position() - vec3(3.0, 2.0, 5.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn projection_switches_halfway_between_perspective_and_orthographic() {
+        let perspective = CameraState::of(&camera(4, 4));
+        let orthographic = CameraState {
+            projection: ProjectionMode::Orthographic { height: 4.0 },
+            ..perspective
+        };
+        assert_eq!(
+            perspective.lerp(&orthographic, 0.4).projection,
+            perspective.projection
+        );
+        assert_eq!(
+            perspective.lerp(&orthographic, 0.6).projection,
+            orthographic.projection
+        );
+        let taller = CameraState {
+            projection: ProjectionMode::Orthographic { height: 8.0 },
+            ..perspective
+        };
+        assert_eq!(
+            orthographic.lerp(&taller, 0.5).projection,
+            ProjectionMode::Orthographic { height: 6.0 }
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use three_d::context::{self as gl, HasContext};
+use three_d::{Context, Viewport};
+
+/// Which buffers the scene clears before drawing, for multi-pass rendering that builds on
+/// what an earlier pass left behind.
+///
+/// Buffers the target doesn't have are skipped by GL, so e.g. clearing stencil without a
+/// stencil buffer, see [crate::windowed_context::WindowedContextBuilder::stencil_bits], is
+/// harmless.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClearConfig {
+    /// Clears to the background. Without it the background isn't drawn either, the previous
+    /// color shows through.
+    pub color: bool,
+    /// Clears depth to `depth_value`, or to the far plane of the scene's depth convention.
+    pub depth: bool,
+    pub depth_value: Option<f32>,
+    /// Clears stencil to the given value.
+    pub stencil: Option<i32>,
+}
+
+impl Default for ClearConfig {
+    fn default() -> Self {
+        Self {
+            color: true,
+            depth: true,
+            depth_value: None,
+            stencil: None,
+        }
+    }
+}
+
+impl ClearConfig {
+    /// Clears the stencil buffer inside `viewport` of the bound target, if configured.
+    /// `three_d`'s `ClearState` has no stencil, so this goes to GL directly.
+    pub(crate) fn clear_stencil(&self, context: &Context, viewport: Viewport) {
+        let Some(stencil) = self.stencil else {
+            return;
+        };
+        unsafe {
+            context.enable(gl::SCISSOR_TEST);
+            context.scissor(
+                viewport.x,
+                viewport.y,
+                viewport.width as i32,
+                viewport.height as i32,
+            );
+            context.stencil_mask(0xFF);
+            context.clear_stencil(stencil);
+            context.clear(gl::STENCIL_BUFFER_BIT);
+            context.disable(gl::SCISSOR_TEST);
+        }
+    }
+}
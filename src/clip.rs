@@ -0,0 +1,200 @@
+use three_d::{
+    Camera, FragmentAttributes, InnerSpace, Light, Material, MaterialType, Program, RenderStates,
+    Vec3, Vec4,
+};
+
+/// The most planes [crate::scene::Scene::set_clip_planes] applies at once.
+pub const MAX_CLIP_PLANES: usize = 4;
+
+/// A plane cutting the model, keeping what is on the side its normal points to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClipPlane {
+    /// Unit length, see [ClipPlane::new].
+    pub normal: Vec3,
+    /// The signed distance of the origin from the plane, along the normal.
+    pub distance: f32,
+}
+
+impl ClipPlane {
+    /// The plane of points `p` with `normal · p + distance = 0`. `normal` is normalized and
+    /// `distance` scaled along, a zero normal keeps everything.
+    pub fn new(normal: Vec3, distance: f32) -> Self {
+        let length = normal.magnitude();
+        if length <= f32::EPSILON {
+            return Self {
+                normal: Vec3::new(0.0, 0.0, 0.0),
+                distance: 0.0,
+            };
+        }
+        Self {
+            normal: normal / length,
+            distance: distance / length,
+        }
+    }
+
+    /// The plane through `point`, keeping the side `normal` points to.
+    pub fn through(point: Vec3, normal: Vec3) -> Self {
+        let plane = Self::new(normal, 0.0);
+        Self {
+            distance: -plane.normal.dot(point),
+            ..plane
+        }
+    }
+
+    /// The same plane keeping the other side.
+    pub fn flipped(self) -> Self {
+        Self {
+            normal: -self.normal,
+            distance: -self.distance,
+        }
+    }
+
+    /// The signed distance of `point` from the plane, negative on the clipped side.
+    pub fn signed_distance(&self, point: Vec3) -> f32 {
+        self.normal.dot(point) + self.distance
+    }
+
+    /// Whether `point` stays, points on the plane do.
+    pub fn keeps(&self, point: Vec3) -> bool {
+        self.signed_distance(point) >= 0.0
+    }
+
+    fn equation(&self) -> Vec4 {
+        self.normal.extend(self.distance)
+    }
+}
+
+/// `material` with the fragments outside of any of `planes` discarded, in world space.
+///
+/// Works for any material whose fragment shader has a `main` function, which it runs as is
+/// for the fragments that stay; the world position comes from the geometry's `pos` output.
+/// The cut isn't capped, so the inside of closed meshes shows through it. Program caching
+/// goes by [Material::id], which is that of `material` without the bit `three_d` reserves
+/// for its own materials, so don't mix this with custom materials having that same id.
+pub struct Clipped<'a> {
+    material: &'a dyn Material,
+    planes: &'a [ClipPlane],
+}
+
+impl<'a> Clipped<'a> {
+    /// Only the first [MAX_CLIP_PLANES] of `planes` clip.
+    pub fn new(material: &'a dyn Material, planes: &'a [ClipPlane]) -> Self {
+        let planes = &planes[..planes.len().min(MAX_CLIP_PLANES)];
+        Self { material, planes }
+    }
+}
+
+impl Material for Clipped<'_> {
+    fn fragment_shader_source(&self, lights: &[&dyn Light]) -> String {
+        clipped_shader(&self.material.fragment_shader_source(lights))
+    }
+
+    fn id(&self) -> u16 {
+        self.material.id() & !(1 << 15)
+    }
+
+    fn fragment_attributes(&self) -> FragmentAttributes {
+        FragmentAttributes {
+            position: true,
+            ..self.material.fragment_attributes()
+        }
+    }
+
+    fn use_uniforms(&self, program: &Program, camera: &Camera, lights: &[&dyn Light]) {
+        self.material.use_uniforms(program, camera, lights);
+        use_clip_uniforms(program, self.planes);
+    }
+
+    fn render_states(&self) -> RenderStates {
+        self.material.render_states()
+    }
+
+    fn material_type(&self) -> MaterialType {
+        self.material.material_type()
+    }
+}
+
+/// Sets the uniforms of the clip test [clipped_shader] adds, for the first [MAX_CLIP_PLANES]
+/// of `planes`.
+pub(crate) fn use_clip_uniforms(program: &Program, planes: &[ClipPlane]) {
+    let planes = &planes[..planes.len().min(MAX_CLIP_PLANES)];
+    let mut equations = [Vec4::new(0.0, 0.0, 0.0, 0.0); MAX_CLIP_PLANES];
+    for (equation, plane) in equations.iter_mut().zip(planes) {
+        *equation = plane.equation();
+    }
+    program.use_uniform_array("clipPlanes", &equations);
+    program.use_uniform("clipPlaneCount", planes.len() as i32);
+}
+
+/// `source` with the clip test run first thing in `main`, discarding the fragments outside
+/// the planes set with [use_clip_uniforms]. Needs the world position as `pos`.
+pub(crate) fn clipped_shader(source: &str) -> String {
+    let Some(main) = source.find("void main()") else {
+        return source.to_string();
+    };
+    let Some(body) = source[main..].find('{').map(|brace| main + brace + 1) else {
+        return source.to_string();
+    };
+    let position = if source.contains("in vec3 pos;") {
+        ""
+    } else {
+        "in vec3 pos;\n"
+    };
+    format!(
+        "{}{position}
+uniform vec4 clipPlanes[{MAX_CLIP_PLANES}];
+uniform int clipPlaneCount;
+
+void clip_fragment() {{
+    for (int i = 0; i < {MAX_CLIP_PLANES}; i++) {{
+        if (i < clipPlaneCount && dot(clipPlanes[i].xyz, pos) + clipPlanes[i].w < 0.0) {{
+            discard;
+        }}
+    }}
+}}
+
+{}
+    clip_fragment();{}",
+        &source[..main],
+        &source[main..body],
+        &source[body..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::vec3;
+
+    use super::*;
+
+    #[test]
+    fn planes_are_normalized_and_keep_the_side_their_normal_points_to() {
+        let plane = ClipPlane::new(vec3(0.0, 2.0, 0.0), 2.0);
+        assert_eq!(plane.normal, vec3(0.0, 1.0, 0.0));
+        assert_eq!(plane.signed_distance(vec3(5.0, -1.0, 3.0)), 0.0);
+        assert!(plane.keeps(vec3(0.0, 0.0, 0.0)));
+        assert!(!plane.keeps(vec3(0.0, -2.0, 0.0)));
+        assert!(plane.flipped().keeps(vec3(0.0, -2.0, 0.0)));
+
+        let through = ClipPlane::through(vec3(1.0, 0.0, 0.0), vec3(1.0, 0.0, 0.0));
+        assert!(through.keeps(vec3(2.0, 0.0, 0.0)));
+        assert!(!through.keeps(vec3(0.5, 0.0, 0.0)));
+
+        // A zero normal keeps everything.
+        let degenerate = ClipPlane::new(vec3(0.0, 0.0, 0.0), -1.0);
+        assert!(degenerate.keeps(vec3(-100.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn clip_test_runs_first_in_main() {
+        let source = "in vec3 pos;\nvoid main() {\n    outColor = vec4(1.0);\n}\n";
+        let clipped = clipped_shader(source);
+        assert_eq!(clipped.matches("in vec3 pos;").count(), 1);
+        let main = clipped.find("void main() {").unwrap();
+        let call = clipped.find("    clip_fragment();").unwrap();
+        assert!(main < call && call < clipped.find("outColor").unwrap());
+
+        assert!(clipped_shader("void main() {}").starts_with("in vec3 pos;"));
+        assert_eq!(clipped_shader("no entry point"), "no entry point");
+    }
+}
@@ -0,0 +1,143 @@
+use three_d::context::{self as gl, HasContext};
+use three_d::{
+    Blend, BlendEquationType, BlendMultiplierType, Context, Cull, DepthTest, RenderStates,
+    Viewport, WriteMask,
+};
+
+use crate::post;
+
+/// How the color in the framebuffer relates to its alpha when the window compositor blends
+/// the 3D view with the webview.
+///
+/// The compositors we run on all expect premultiplied alpha for transparent windows:
+/// DWM on Windows, Core Animation on macOS, Wayland (`ARGB8888` buffers are premultiplied by
+/// definition) and the X11 compositing managers for ARGB visuals. `Straight` is only correct
+/// when the rendered content is already premultiplied or the window is opaque.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompositeMode {
+    /// Leave the framebuffer as rendered.
+    Straight,
+    /// Keep the color multiplied by alpha, starting with the background.
+    #[default]
+    Premultiplied,
+}
+
+/// A bundle of clear and composite settings for drawing the 3D view as an overlay over the
+/// page, see [crate::scene::Scene::set_overlay_mode].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverlayMode {
+    /// Clears to fully transparent and premultiplies, so the page shows everywhere the model
+    /// doesn't cover it and blends correctly with the model's translucent parts.
+    Transparent,
+    /// Only clears depth and leaves the color as it is, for drawing on top of content already
+    /// in the framebuffer. That content has to be premultiplied already, so the composite pass
+    /// is skipped, and this frame's translucent parts blend over it keeping it premultiplied.
+    KeepColor,
+}
+
+/// Converts the currently bound framebuffer to the given composite mode, in place.
+///
+/// Runs right after the background is drawn. Parts then blend over the premultiplied color
+/// with [crate::part::BlendMode], which keeps it premultiplied, so nothing that was blended
+/// over a transparent background is multiplied by its alpha a second time.
+pub fn apply(context: &Context, mode: CompositeMode, viewport: Viewport) {
+    if mode == CompositeMode::Straight {
+        return;
+    }
+    // The fragment color is discarded by the blend factors: this computes
+    // `dst.rgb * dst.a` and leaves `dst.a` untouched.
+    let render_states = RenderStates {
+        write_mask: WriteMask::COLOR,
+        depth_test: DepthTest::Always,
+        cull: Cull::None,
+        blend: Blend::Enabled {
+            source_rgb_multiplier: BlendMultiplierType::Zero,
+            source_alpha_multiplier: BlendMultiplierType::Zero,
+            destination_rgb_multiplier: BlendMultiplierType::DstAlpha,
+            destination_alpha_multiplier: BlendMultiplierType::One,
+            rgb_equation: BlendEquationType::Add,
+            alpha_equation: BlendEquationType::Add,
+        },
+    };
+    post::apply_effect(
+        context,
+        "layout (location = 0) out vec4 outColor;\nvoid main() { outColor = vec4(0.0); }",
+        render_states,
+        viewport,
+        |_| {},
+    );
+}
+
+/// Turns fragment alpha into MSAA sample coverage for the draws in between this and
+/// [end_alpha_to_coverage].
+///
+/// Only meaningful on a multisampled framebuffer. Where available, `GL_SAMPLE_ALPHA_TO_ONE`
+/// is enabled as well, so the covered samples store alpha 1 and the resolved alpha is the
+/// coverage itself instead of coverage times alpha.
+pub(crate) fn begin_alpha_to_coverage(context: &Context) {
+    unsafe {
+        context.enable(gl::SAMPLE_ALPHA_TO_COVERAGE);
+        if !context.version().is_embedded {
+            context.enable(gl::SAMPLE_ALPHA_TO_ONE);
+        }
+    }
+}
+
+pub(crate) fn end_alpha_to_coverage(context: &Context) {
+    unsafe {
+        context.disable(gl::SAMPLE_ALPHA_TO_COVERAGE);
+        if !context.version().is_embedded {
+            context.disable(gl::SAMPLE_ALPHA_TO_ONE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::ClearState;
+
+    use super::*;
+    use crate::part::BlendMode;
+    use crate::render_texture::Draw;
+    use crate::testing::{assert_pixel_near, TestHarness};
+
+    const HALF_RED: &str = "
+layout (location = 0) out vec4 outColor;
+void main() { outColor = vec4(1.0, 0.0, 0.0, 0.5); }
+";
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn half_transparent_fragment_is_premultiplied_over_a_transparent_background() {
+        let harness = TestHarness::new(8, 8).unwrap();
+        let context = &harness.context().context;
+        let viewport = harness.viewport();
+        let image = harness.render(|screen| {
+            screen
+                .clear(ClearState::color(0.0, 0.0, 0.0, 0.0))
+                .draw(|| {
+                    apply(context, CompositeMode::Premultiplied, viewport);
+                    let render_states = RenderStates {
+                        blend: BlendMode::Normal.blend(),
+                        ..post::fullscreen_render_states()
+                    };
+                    post::apply_effect(context, HALF_RED, render_states, viewport, |_| {});
+                });
+        });
+        assert_pixel_near(&image, 4, 4, [128, 0, 0, 128], 1);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn translucent_background_is_premultiplied() {
+        let harness = TestHarness::new(8, 8).unwrap();
+        let context = &harness.context().context;
+        let viewport = harness.viewport();
+        let image = harness.render(|screen| {
+            screen
+                .clear(ClearState::color(1.0, 1.0, 1.0, 0.5))
+                .draw(|| apply(context, CompositeMode::Premultiplied, viewport));
+        });
+        assert_pixel_near(&image, 4, 4, [128, 128, 128, 128], 1);
+    }
+}
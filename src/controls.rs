@@ -0,0 +1,767 @@
+use std::time::{Duration, Instant};
+
+use three_d::{
+    vec2, Camera, Deg, Event, InnerSpace, Mat3, MouseButton, OrbitControl, PhysicalPoint, Vec2,
+    Vec3,
+};
+use three_d_asset::ProjectionType;
+
+/// How long [Controls::focus_on] takes to move the camera when animated.
+pub const FOCUS_DURATION: Duration = Duration::from_millis(300);
+/// The longest time between two presses of the left button that still counts as a double
+/// click.
+pub const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+/// How far apart, in physical pixels, the presses of a double click may be.
+const DOUBLE_CLICK_SLOP: f32 = 6.0;
+/// The drag speed, in pixels per second, below which [Inertia] comes to a stop.
+const MIN_INERTIA_SPEED: f32 = 20.0;
+
+/// Turntable mode: the camera slowly orbits the target while the user leaves it alone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutoRotate {
+    /// Degrees per second around the camera's up axis, negative to turn the other way.
+    pub speed_deg_per_sec: f32,
+    /// How long after the last interaction the rotation resumes.
+    pub idle_timeout: Duration,
+}
+
+impl Default for AutoRotate {
+    fn default() -> Self {
+        Self {
+            speed_deg_per_sec: 10.0,
+            idle_timeout: Duration::from_secs(3),
+        }
+    }
+}
+
+/// Lets the camera keep orbiting after a drag is released, slowing down until it stops.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Inertia {
+    /// How quickly the spin slows down: the speed falls by a factor of `e` every
+    /// `1 / damping` seconds. Higher values stop sooner, `0.0` never stops.
+    pub damping: f32,
+}
+
+impl Default for Inertia {
+    fn default() -> Self {
+        Self { damping: 4.0 }
+    }
+}
+
+/// A [Controls::focus_on] in progress: camera and target are shifted by `offset` in total,
+/// eased over `duration`.
+///
+/// Only the part of the shift not applied yet is applied on each update, on top of wherever
+/// the camera is then, so orbiting or zooming during the move composes with it instead of
+/// being overridden or snapping back.
+#[derive(Clone, Copy, Debug)]
+struct FocusTransition {
+    offset: Vec3,
+    elapsed: Duration,
+    duration: Duration,
+    applied: f32,
+}
+
+/// Wraps [OrbitControl] with the extra behaviours the viewer needs.
+///
+/// The orbit target is owned here rather than inside the [OrbitControl], so it can be moved
+/// (for example by zooming towards the cursor); the inner control is rebuilt whenever it does.
+pub struct Controls {
+    orbit: OrbitControl,
+    target: Vec3,
+    min_distance: f32,
+    max_distance: f32,
+    zoom_to_cursor: bool,
+    auto_rotate: Option<AutoRotate>,
+    /// Time since the user last moved the camera.
+    idle: Duration,
+    pressed_buttons: u32,
+    focus_on_double_click: bool,
+    last_click: Option<(Instant, PhysicalPoint)>,
+    focus: Option<FocusTransition>,
+    inertia: Option<Inertia>,
+    /// Pixels dragged with the left button since the last update.
+    drag: Vec2,
+    /// In pixels per second: measured while dragging, coasting at it after the release.
+    orbit_velocity: Vec2,
+}
+
+impl Controls {
+    pub fn new(target: Vec3, min_distance: f32, max_distance: f32) -> Self {
+        Self {
+            orbit: OrbitControl::new(target, min_distance, max_distance),
+            target,
+            min_distance,
+            max_distance,
+            zoom_to_cursor: false,
+            auto_rotate: None,
+            idle: Duration::ZERO,
+            pressed_buttons: 0,
+            focus_on_double_click: true,
+            last_click: None,
+            focus: None,
+            inertia: None,
+            drag: vec2(0.0, 0.0),
+            orbit_velocity: vec2(0.0, 0.0),
+        }
+    }
+
+    pub fn target(&self) -> Vec3 {
+        self.target
+    }
+
+    /// Moves the orbit target without moving the camera.
+    pub fn set_target(&mut self, target: Vec3) {
+        self.target = target;
+        self.orbit = OrbitControl::new(target, self.min_distance, self.max_distance);
+    }
+
+    /// The closest and farthest the camera may orbit from the target.
+    pub fn distance_range(&self) -> (f32, f32) {
+        (self.min_distance, self.max_distance)
+    }
+
+    pub fn set_distance_range(&mut self, min_distance: f32, max_distance: f32) {
+        self.min_distance = min_distance;
+        self.max_distance = max_distance;
+        self.orbit = OrbitControl::new(self.target, min_distance, max_distance);
+    }
+
+    pub fn zoom_to_cursor(&self) -> bool {
+        self.zoom_to_cursor
+    }
+
+    /// When enabled, scrolling zooms towards the point under the cursor instead of the target.
+    pub fn set_zoom_to_cursor(&mut self, enabled: bool) {
+        self.zoom_to_cursor = enabled;
+    }
+
+    pub fn focus_on_double_click(&self) -> bool {
+        self.focus_on_double_click
+    }
+
+    /// When enabled, the default, double-clicking a point on the model focuses on it, see
+    /// [Controls::focus_on].
+    pub fn set_focus_on_double_click(&mut self, enabled: bool) {
+        self.focus_on_double_click = enabled;
+        self.last_click = None;
+    }
+
+    /// Makes `point` the orbit target, shifting the camera along so the view direction and
+    /// distance stay the same.
+    ///
+    /// With `animate` the shift takes [FOCUS_DURATION], advanced by [Controls::update];
+    /// otherwise it happens right away. Either replaces a focus still in progress.
+    pub fn focus_on(&mut self, camera: &mut Camera, point: Vec3, animate: bool) {
+        let offset = point - self.target;
+        if animate {
+            self.focus = Some(FocusTransition {
+                offset,
+                elapsed: Duration::ZERO,
+                duration: FOCUS_DURATION,
+                applied: 0.0,
+            });
+        } else {
+            self.focus = None;
+            self.shift(camera, offset);
+        }
+    }
+
+    /// Whether a [Controls::focus_on] is still moving the camera.
+    pub fn is_focusing(&self) -> bool {
+        self.focus.is_some()
+    }
+
+    fn shift(&mut self, camera: &mut Camera, offset: Vec3) {
+        let target = self.target + offset;
+        let (position, up) = (*camera.position() + offset, *camera.up());
+        camera.set_view(position, target, up);
+        self.set_target(target);
+    }
+
+    /// Advances the focus in progress by `dt`. Returns whether the camera moved.
+    fn update_focus(&mut self, camera: &mut Camera, dt: Duration) -> bool {
+        let Some(mut focus) = self.focus else {
+            return false;
+        };
+        focus.elapsed += dt;
+        let t =
+            (focus.elapsed.as_secs_f32() / focus.duration.as_secs_f32().max(f32::EPSILON)).min(1.0);
+        // Smoothstep, so the camera neither starts nor stops abruptly.
+        let progress = t * t * (3.0 - 2.0 * t);
+        let step = progress - focus.applied;
+        focus.applied = progress;
+        self.focus = (t < 1.0).then_some(focus);
+        if step <= 0.0 {
+            return false;
+        }
+        self.shift(camera, focus.offset * step);
+        true
+    }
+
+    pub fn auto_rotate(&self) -> Option<AutoRotate> {
+        self.auto_rotate
+    }
+
+    /// Enables or, with `None`, disables turntable rotation, see [Controls::update].
+    pub fn set_auto_rotate(&mut self, auto_rotate: Option<AutoRotate>) {
+        self.auto_rotate = auto_rotate;
+    }
+
+    pub fn inertia(&self) -> Option<Inertia> {
+        self.inertia
+    }
+
+    /// Enables or, with `None`, the default, disables orbiting on after a drag, see
+    /// [Inertia]. Advanced by [Controls::update].
+    pub fn set_inertia(&mut self, inertia: Option<Inertia>) {
+        self.inertia = inertia;
+        if inertia.is_none() {
+            self.stop_inertia();
+        }
+    }
+
+    /// Whether the camera is still orbiting on after a drag.
+    pub fn is_coasting(&self) -> bool {
+        self.pressed_buttons == 0 && self.orbit_velocity != vec2(0.0, 0.0)
+    }
+
+    /// Stops the camera orbiting on after a drag, as pressing a button does.
+    pub fn stop_inertia(&mut self) {
+        self.orbit_velocity = vec2(0.0, 0.0);
+    }
+
+    /// Whether auto-rotation is enabled and not held off by a drag in progress. While idling
+    /// towards the timeout it counts as rotating too, so on-demand rendering keeps advancing.
+    pub fn is_auto_rotating(&self) -> bool {
+        self.auto_rotate.is_some() && self.pressed_buttons == 0
+    }
+
+    /// Advances a focus in progress, inertia and auto-rotation by `dt`. Returns whether the
+    /// camera moved.
+    ///
+    /// The camera turns from wherever the user left it, and only by the time that passed
+    /// since the timeout elapsed, so resuming never jumps.
+    pub fn update(&mut self, camera: &mut Camera, dt: Duration) -> bool {
+        let focused = self.update_focus(camera, dt);
+        let coasted = self.update_inertia(camera, dt);
+        self.update_auto_rotate(camera, dt) || coasted || focused
+    }
+
+    /// While dragging, measures the drag speed; after the release, keeps orbiting at it the
+    /// way [OrbitControl] would, damped.
+    fn update_inertia(&mut self, camera: &mut Camera, dt: Duration) -> bool {
+        let drag = std::mem::replace(&mut self.drag, vec2(0.0, 0.0));
+        let Some(inertia) = self.inertia else {
+            return false;
+        };
+        let seconds = dt.as_secs_f32();
+        if seconds <= 0.0 {
+            return false;
+        }
+        if self.pressed_buttons > 0 {
+            // Smoothed over the last few frames; pausing before the release slows it down,
+            // so letting go of a still drag doesn't spin.
+            self.orbit_velocity = self.orbit_velocity * 0.5 + drag / seconds * 0.5;
+            return false;
+        }
+        if self.orbit_velocity.magnitude() < MIN_INERTIA_SPEED {
+            self.stop_inertia();
+            return false;
+        }
+        let distance = (self.target - *camera.position()).magnitude();
+        let step = self.orbit_velocity * seconds * orbit_speed(distance);
+        camera.rotate_around_with_fixed_up(&self.target, step.x, step.y);
+        self.orbit_velocity *= (-inertia.damping.max(0.0) * seconds).exp();
+        self.idle = Duration::ZERO;
+        true
+    }
+
+    fn update_auto_rotate(&mut self, camera: &mut Camera, dt: Duration) -> bool {
+        let Some(auto_rotate) = self.auto_rotate else {
+            return false;
+        };
+        if self.pressed_buttons > 0 {
+            return false;
+        }
+        let idle = self.idle + dt;
+        let rotating = idle.saturating_sub(self.idle.max(auto_rotate.idle_timeout));
+        self.idle = idle;
+        if rotating.is_zero() {
+            return false;
+        }
+        let up = *camera.up();
+        let angle = Deg(auto_rotate.speed_deg_per_sec * rotating.as_secs_f32());
+        let offset = *camera.position() - self.target;
+        let rotated = Mat3::from_axis_angle(up.normalize(), angle) * offset;
+        camera.set_view(self.target + rotated, self.target, up);
+        true
+    }
+
+    /// Applies `events` to `camera`. Returns whether the camera changed.
+    ///
+    /// `pick` returns the world-space point under a pixel; it is only consulted for
+    /// cursor-anchored zoom, where zoom falls back to the target when it returns `None`, and
+    /// for double clicks, see [Controls::set_focus_on_double_click].
+    ///
+    /// With an orthographic camera moving closer doesn't change the scale, so zooming scales
+    /// the view height instead, limited to the same range as the distance.
+    pub fn handle_events(
+        &mut self,
+        camera: &mut Camera,
+        events: &mut [Event],
+        pick: impl Fn(&Camera, PhysicalPoint) -> Option<Vec3>,
+    ) -> bool {
+        let mut change = false;
+        if let ProjectionType::Orthographic { .. } = camera.projection_type() {
+            for event in events.iter_mut() {
+                if let Event::MouseWheel {
+                    delta,
+                    position,
+                    handled,
+                    ..
+                } = event
+                {
+                    if *handled {
+                        continue;
+                    }
+                    let anchor = if self.zoom_to_cursor {
+                        pick(camera, *position)
+                    } else {
+                        None
+                    };
+                    self.zoom_orthographic(camera, anchor, delta.1);
+                    *handled = true;
+                    change = true;
+                }
+            }
+        } else if self.zoom_to_cursor {
+            for event in events.iter_mut() {
+                if let Event::MouseWheel {
+                    delta,
+                    position,
+                    handled,
+                    ..
+                } = event
+                {
+                    if *handled {
+                        continue;
+                    }
+                    if let Some(point) = pick(camera, *position) {
+                        self.zoom_towards_point(camera, point, delta.1);
+                        *handled = true;
+                        change = true;
+                    }
+                }
+            }
+        }
+        if self.focus_on_double_click {
+            let now = Instant::now();
+            for event in events.iter() {
+                let Event::MousePress {
+                    button: MouseButton::Left,
+                    position,
+                    handled: false,
+                    ..
+                } = event
+                else {
+                    continue;
+                };
+                let double_click = self.last_click.is_some_and(|(time, last)| {
+                    now.duration_since(time) <= DOUBLE_CLICK_INTERVAL
+                        && (position.x - last.x).hypot(position.y - last.y) <= DOUBLE_CLICK_SLOP
+                });
+                if !double_click {
+                    self.last_click = Some((now, *position));
+                    continue;
+                }
+                self.last_click = None;
+                if let Some(point) = pick(camera, *position) {
+                    self.focus_on(camera, point, true);
+                    change = true;
+                }
+            }
+        }
+        for event in events.iter() {
+            match event {
+                Event::MousePress { handled: false, .. } => {
+                    // A new drag takes over from whatever spin is left.
+                    self.stop_inertia();
+                    self.pressed_buttons += 1;
+                }
+                Event::MouseMotion {
+                    button: Some(MouseButton::Left),
+                    delta,
+                    handled: false,
+                    ..
+                } if self.inertia.is_some() => self.drag += vec2(delta.0, delta.1),
+                Event::MouseRelease { .. } => {
+                    self.pressed_buttons = self.pressed_buttons.saturating_sub(1)
+                }
+                _ => {}
+            }
+        }
+        let change = self.orbit.handle_events(camera, events) || change;
+        if change || self.pressed_buttons > 0 {
+            self.idle = Duration::ZERO;
+        }
+        change
+    }
+
+    /// Zooms by scaling the camera and target about `point`, which keeps `point` at the same
+    /// pixel on screen.
+    fn zoom_towards_point(&mut self, camera: &mut Camera, point: Vec3, delta: f32) {
+        let position = *camera.position();
+        let distance = (self.target - position).magnitude();
+        if distance <= f32::EPSILON {
+            return;
+        }
+        // Same speed curve as `OrbitControl`, so both zoom modes feel alike.
+        let speed = 0.01 * distance + 0.001;
+        let new_distance = (distance - speed * delta).clamp(self.min_distance, self.max_distance);
+        let scale = new_distance / distance;
+
+        let position = point + (position - point) * scale;
+        let target = point + (self.target - point) * scale;
+        let up = *camera.up();
+        camera.set_view(position, target, up);
+        self.set_target(target);
+    }
+
+    /// Zooms an orthographic camera by scaling its height. With an `anchor`, camera and
+    /// target shift sideways so the anchor stays at the same pixel.
+    fn zoom_orthographic(&mut self, camera: &mut Camera, anchor: Option<Vec3>, delta: f32) {
+        let ProjectionType::Orthographic { height } = *camera.projection_type() else {
+            return;
+        };
+        let speed = 0.01 * height + 0.001;
+        let new_height = (height - speed * delta).clamp(self.min_distance, self.max_distance);
+        if let Some(point) = anchor {
+            let offset = (point - self.target) * (1.0 - new_height / height);
+            let target = self.target + offset;
+            let (position, up) = (*camera.position() + offset, *camera.up());
+            camera.set_view(position, target, up);
+            self.set_target(target);
+        }
+        let (z_near, z_far) = (camera.z_near(), camera.z_far());
+        camera.set_orthographic_projection(new_height, z_near, z_far);
+    }
+}
+
+/// What [OrbitControl] scales dragged pixels by to orbit a camera `distance` away from its
+/// target, so [Inertia] coasts at the speed the drag left off.
+fn orbit_speed(distance: f32) -> f32 {
+    0.01 * distance + 0.001
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::{degrees, vec3, Modifiers, MouseButton, Viewport};
+
+    use super::*;
+
+    fn camera() -> Camera {
+        Camera::new_perspective(
+            Viewport::new_at_origo(64, 64),
+            vec3(0.0, 0.0, 10.0),
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            degrees(45.0),
+            0.1,
+            100.0,
+        )
+    }
+
+    fn wheel(delta: f32) -> Event {
+        Event::MouseWheel {
+            delta: (0.0, delta),
+            position: PhysicalPoint { x: 40.0, y: 40.0 },
+            modifiers: Modifiers::default(),
+            handled: false,
+        }
+    }
+
+    #[test]
+    fn zoom_to_cursor_moves_the_target_towards_the_hit_point() {
+        let mut camera = camera();
+        let mut controls = Controls::new(vec3(0.0, 0.0, 0.0), 1.0, 100.0);
+        controls.set_zoom_to_cursor(true);
+        let hit = vec3(2.0, 1.0, 0.0);
+        assert!(controls.handle_events(&mut camera, &mut [wheel(10.0)], |_, _| Some(hit)));
+
+        let target = controls.target();
+        assert!(target.magnitude() > 0.0);
+        assert!((target - hit).magnitude() < hit.magnitude());
+        // Scaled about the hit point, so it stays on the line from the old target.
+        assert!(target.cross(hit).magnitude() < 1e-5);
+        assert!((*camera.position() - target).magnitude() < 10.0);
+        assert_eq!(*camera.target(), target);
+    }
+
+    #[test]
+    fn zoom_to_cursor_falls_back_to_the_target_without_a_hit() {
+        let mut camera = camera();
+        let mut controls = Controls::new(vec3(0.0, 0.0, 0.0), 1.0, 100.0);
+        controls.set_zoom_to_cursor(true);
+        assert!(controls.handle_events(&mut camera, &mut [wheel(10.0)], |_, _| None));
+        assert_eq!(controls.target(), vec3(0.0, 0.0, 0.0));
+        assert!(camera.position().z < 10.0);
+    }
+
+    #[test]
+    fn zoom_without_zoom_to_cursor_ignores_the_hit_point() {
+        let mut camera = camera();
+        let mut controls = Controls::new(vec3(0.0, 0.0, 0.0), 1.0, 100.0);
+        let hit = vec3(2.0, 1.0, 0.0);
+        controls.handle_events(&mut camera, &mut [wheel(10.0)], |_, _| Some(hit));
+        assert_eq!(controls.target(), vec3(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn orthographic_zoom_scales_the_height_instead_of_the_distance() {
+        let mut camera = camera();
+        camera.set_orthographic_projection(8.0, 0.1, 100.0);
+        let position = *camera.position();
+        let mut controls = Controls::new(vec3(0.0, 0.0, 0.0), 1.0, 100.0);
+        assert!(controls.handle_events(&mut camera, &mut [wheel(10.0)], |_, _| None));
+        assert_eq!(*camera.position(), position);
+        let ProjectionType::Orthographic { height } = *camera.projection_type() else {
+            panic!("no longer orthographic");
+        };
+        assert!(height < 8.0);
+    }
+
+    /// The camera's angle around the y axis, counterclockwise from `+z` seen from above.
+    fn azimuth(camera: &Camera) -> f32 {
+        let position = camera.position();
+        position.x.atan2(position.z).to_degrees()
+    }
+
+    fn button(pressed: bool) -> Event {
+        let position = PhysicalPoint { x: 32.0, y: 32.0 };
+        if pressed {
+            Event::MousePress {
+                button: MouseButton::Left,
+                position,
+                modifiers: Modifiers::default(),
+                handled: false,
+            }
+        } else {
+            Event::MouseRelease {
+                button: MouseButton::Left,
+                position,
+                modifiers: Modifiers::default(),
+                handled: false,
+            }
+        }
+    }
+
+    #[test]
+    fn auto_rotate_turns_by_speed_times_time_after_the_timeout() {
+        let mut camera = camera();
+        let mut controls = Controls::new(vec3(0.0, 0.0, 0.0), 1.0, 100.0);
+        controls.set_auto_rotate(Some(AutoRotate {
+            speed_deg_per_sec: 20.0,
+            idle_timeout: Duration::from_secs(1),
+        }));
+        let step = Duration::from_millis(250);
+        for _ in 0..4 {
+            assert!(!controls.update(&mut camera, step));
+        }
+        for _ in 0..8 {
+            assert!(controls.update(&mut camera, step));
+        }
+        assert!(
+            (azimuth(&camera) - 40.0).abs() < 0.01,
+            "{}",
+            azimuth(&camera)
+        );
+        assert!((camera.position().magnitude() - 10.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn auto_rotate_pauses_while_dragging_and_resumes_smoothly() {
+        let mut camera = camera();
+        let mut controls = Controls::new(vec3(0.0, 0.0, 0.0), 1.0, 100.0);
+        controls.set_auto_rotate(Some(AutoRotate {
+            speed_deg_per_sec: 20.0,
+            idle_timeout: Duration::from_secs(1),
+        }));
+        controls.update(&mut camera, Duration::from_secs(2));
+        let before = azimuth(&camera);
+
+        controls.handle_events(&mut camera, &mut [button(true)], |_, _| None);
+        assert!(!controls.is_auto_rotating());
+        assert!(!controls.update(&mut camera, Duration::from_secs(5)));
+        controls.handle_events(&mut camera, &mut [button(false)], |_, _| None);
+        assert!(!controls.update(&mut camera, Duration::from_millis(500)));
+        assert_eq!(azimuth(&camera), before);
+        // Half a second of idling is left before the timeout, then a quarter second turns.
+        assert!(controls.update(&mut camera, Duration::from_millis(750)));
+        assert!((azimuth(&camera) - before - 5.0).abs() < 0.01);
+    }
+
+    fn near(a: Vec3, b: Vec3) -> bool {
+        (a - b).magnitude() < 1e-4
+    }
+
+    #[test]
+    fn focus_moves_the_target_and_keeps_the_view() {
+        let mut camera = camera();
+        let mut controls = Controls::new(vec3(0.0, 0.0, 0.0), 1.0, 100.0);
+        let point = vec3(2.0, 1.0, -1.0);
+        controls.focus_on(&mut camera, point, false);
+        assert!(!controls.is_focusing());
+        assert_eq!(controls.target(), point);
+        assert_eq!(*camera.target(), point);
+        assert!(near(*camera.position(), point + vec3(0.0, 0.0, 10.0)));
+    }
+
+    #[test]
+    fn animated_focus_eases_over_successive_updates() {
+        let mut camera = camera();
+        let mut controls = Controls::new(vec3(0.0, 0.0, 0.0), 1.0, 100.0);
+        let point = vec3(4.0, 0.0, 0.0);
+        controls.focus_on(&mut camera, point, true);
+        assert!(controls.is_focusing());
+        assert_eq!(controls.target(), vec3(0.0, 0.0, 0.0));
+
+        let step = FOCUS_DURATION / 6;
+        let mut progress = Vec::new();
+        while controls.is_focusing() {
+            assert!(controls.update(&mut camera, step));
+            progress.push(controls.target().x);
+            assert!(near(
+                *camera.position() - controls.target(),
+                vec3(0.0, 0.0, 10.0)
+            ));
+        }
+        assert_eq!(progress.len(), 6);
+        assert!(progress.windows(2).all(|pair| pair[0] < pair[1]));
+        // Smoothstep is halfway at half the time, and slower at the ends than in between.
+        assert!((progress[2] - 2.0).abs() < 1e-4);
+        assert!(progress[0] < progress[3] - progress[2]);
+        assert!(near(controls.target(), point));
+        assert!(!controls.update(&mut camera, step));
+    }
+
+    #[test]
+    fn input_during_a_focus_does_not_throw_it_off() {
+        let mut camera = camera();
+        let mut controls = Controls::new(vec3(0.0, 0.0, 0.0), 1.0, 100.0);
+        let point = vec3(0.0, 3.0, 0.0);
+        controls.focus_on(&mut camera, point, true);
+        controls.update(&mut camera, FOCUS_DURATION / 3);
+        controls.handle_events(&mut camera, &mut [wheel(10.0)], |_, _| None);
+        controls.update(&mut camera, FOCUS_DURATION);
+        assert!(!controls.is_focusing());
+        assert!(near(controls.target(), point));
+        assert_eq!(*camera.target(), controls.target());
+        assert!(camera.position().z < 10.0);
+    }
+
+    fn motion(dx: f32) -> Event {
+        Event::MouseMotion {
+            button: Some(MouseButton::Left),
+            delta: (dx, 0.0),
+            position: PhysicalPoint { x: 32.0, y: 32.0 },
+            modifiers: Modifiers::default(),
+            handled: false,
+        }
+    }
+
+    /// Presses, drags `dx` pixels per `step` a few times and releases.
+    fn fling(controls: &mut Controls, camera: &mut Camera, dx: f32, step: Duration) {
+        controls.handle_events(camera, &mut [button(true)], |_, _| None);
+        for _ in 0..4 {
+            controls.handle_events(camera, &mut [motion(dx)], |_, _| None);
+            controls.update(camera, step);
+        }
+        controls.handle_events(camera, &mut [button(false)], |_, _| None);
+    }
+
+    #[test]
+    fn inertia_keeps_orbiting_after_the_release_and_settles() {
+        let mut camera = camera();
+        let mut controls = Controls::new(vec3(0.0, 0.0, 0.0), 1.0, 100.0);
+        controls.set_inertia(Some(Inertia::default()));
+        let step = Duration::from_millis(16);
+        fling(&mut controls, &mut camera, 20.0, step);
+        assert!(controls.is_coasting());
+
+        let mut turns = Vec::new();
+        let mut last = azimuth(&camera);
+        for _ in 0..1000 {
+            if !controls.update(&mut camera, step) {
+                break;
+            }
+            let now = azimuth(&camera);
+            // Wrapped, the azimuth jumps by 360 degrees passing behind the target.
+            turns.push(((now - last + 540.0).rem_euclid(360.0) - 180.0).abs());
+            last = now;
+        }
+        assert!(turns.len() > 10, "{}", turns.len());
+        assert!(turns.iter().all(|&turn| turn > 0.0));
+        // Damped: every step turns less than the one before.
+        assert!(turns.windows(2).all(|pair| pair[1] < pair[0]));
+        assert!(!controls.is_coasting());
+        assert!(!controls.update(&mut camera, step));
+        assert_eq!(azimuth(&camera), last);
+    }
+
+    #[test]
+    fn inertia_starts_at_the_speed_the_drag_turned() {
+        let mut camera = camera();
+        let mut controls = Controls::new(vec3(0.0, 0.0, 0.0), 1.0, 100.0);
+        controls.set_inertia(Some(Inertia::default()));
+        let step = Duration::from_millis(16);
+        controls.handle_events(&mut camera, &mut [button(true)], |_, _| None);
+        let mut last_turn = 0.0;
+        // Long enough for the measured speed to settle on the steady drag.
+        for _ in 0..30 {
+            let before = azimuth(&camera);
+            controls.handle_events(&mut camera, &mut [motion(20.0)], |_, _| None);
+            controls.update(&mut camera, step);
+            last_turn = azimuth(&camera) - before;
+        }
+        controls.handle_events(&mut camera, &mut [button(false)], |_, _| None);
+
+        let before = azimuth(&camera);
+        assert!(controls.update(&mut camera, step));
+        let first_turn = azimuth(&camera) - before;
+        assert!(last_turn.abs() > 0.1, "{last_turn}");
+        assert!(
+            (first_turn - last_turn).abs() < 1e-3,
+            "{first_turn} vs {last_turn}"
+        );
+    }
+
+    #[test]
+    fn pressing_again_stops_the_inertia() {
+        let mut camera = camera();
+        let mut controls = Controls::new(vec3(0.0, 0.0, 0.0), 1.0, 100.0);
+        controls.set_inertia(Some(Inertia::default()));
+        let step = Duration::from_millis(16);
+        fling(&mut controls, &mut camera, 20.0, step);
+        assert!(controls.update(&mut camera, step));
+
+        controls.handle_events(&mut camera, &mut [button(true)], |_, _| None);
+        assert!(!controls.is_coasting());
+        let before = azimuth(&camera);
+        assert!(!controls.update(&mut camera, step));
+        controls.handle_events(&mut camera, &mut [button(false)], |_, _| None);
+        assert!(!controls.is_coasting());
+        assert!(!controls.update(&mut camera, step));
+        assert_eq!(azimuth(&camera), before);
+    }
+
+    #[test]
+    fn without_inertia_the_camera_stops_on_release() {
+        let mut camera = camera();
+        let mut controls = Controls::new(vec3(0.0, 0.0, 0.0), 1.0, 100.0);
+        let step = Duration::from_millis(16);
+        fling(&mut controls, &mut camera, 20.0, step);
+        assert!(!controls.is_coasting());
+        assert!(!controls.update(&mut camera, step));
+    }
+}
@@ -0,0 +1,252 @@
+use three_d::{
+    AxisAlignedBoundingBox, Blend, Camera, FragmentAttributes, Light, Material, MaterialType,
+    PhysicalMaterial, Program, RenderStates, Vec3,
+};
+
+use crate::clip::{self, ClipPlane};
+use crate::post::SRGB_FUNCTIONS;
+
+/// What [crate::scene::Scene::set_debug_view] shows in place of the shaded picture, for
+/// finding out why a model looks wrong.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DebugView {
+    /// The shaded picture, rendered exactly as without a debug view.
+    #[default]
+    None,
+    /// World space normals of the geometry, without normal maps, as `normal * 0.5 + 0.5`:
+    /// +X red, +Y green, +Z blue. Back faces show the side facing the camera.
+    Normals,
+    /// The base color, with its texture, unlit.
+    Albedo,
+    /// Roughness, with its texture, from black for smooth to white for rough.
+    Roughness,
+    /// Metalness, with its texture, from black for dielectric to white for metal.
+    Metalness,
+    /// World position within the scene's bounding box, from black at its minimum corner to
+    /// white at its maximum.
+    WorldPosition,
+}
+
+impl DebugView {
+    /// Every view, in the order [DebugView::next] cycles through them.
+    pub const ALL: [DebugView; 6] = [
+        DebugView::None,
+        DebugView::Normals,
+        DebugView::Albedo,
+        DebugView::Roughness,
+        DebugView::Metalness,
+        DebugView::WorldPosition,
+    ];
+
+    /// The view after this one, wrapping around to [DebugView::None].
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&view| view == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// A short name for menus.
+    pub fn label(self) -> &'static str {
+        match self {
+            DebugView::None => "Shaded",
+            DebugView::Normals => "Normals",
+            DebugView::Albedo => "Albedo",
+            DebugView::Roughness => "Roughness",
+            DebugView::Metalness => "Metalness",
+            DebugView::WorldPosition => "World position",
+        }
+    }
+
+    fn id(self) -> i32 {
+        match self {
+            DebugView::None => 0,
+            DebugView::Normals => 1,
+            DebugView::Albedo => 2,
+            DebugView::Roughness => 3,
+            DebugView::Metalness => 4,
+            DebugView::WorldPosition => 5,
+        }
+    }
+}
+
+/// The first of the [Material::id]s of [DebugMaterial], one for each combination of
+/// textures and clipping.
+const DEBUG_MATERIAL_ID: u16 = 0x7e00;
+
+const DEBUG_SHADER: &str = "
+uniform int debugView;
+uniform vec4 albedo;
+uniform float metallic;
+uniform float roughness;
+uniform vec3 boundsMin;
+uniform vec3 boundsSize;
+#ifdef USE_ALBEDO_TEXTURE
+uniform sampler2D albedoTexture;
+uniform mat3 albedoTexTransform;
+#endif
+#ifdef USE_METALLIC_ROUGHNESS_TEXTURE
+uniform sampler2D metallicRoughnessTexture;
+uniform mat3 metallicRoughnessTexTransform;
+#endif
+in vec3 pos;
+in vec3 nor;
+#ifdef USE_UVS
+in vec2 uvs;
+#endif
+layout (location = 0) out vec4 outColor;
+
+void main() {
+    vec4 base = albedo;
+    float metal = metallic;
+    float rough = roughness;
+#ifdef USE_ALBEDO_TEXTURE
+    vec4 albedoTexel = texture(albedoTexture, (albedoTexTransform * vec3(uvs, 1.0)).xy);
+    base *= vec4(srgb_to_linear(albedoTexel.rgb), albedoTexel.a);
+#endif
+#ifdef USE_METALLIC_ROUGHNESS_TEXTURE
+    vec2 mrUvs = (metallicRoughnessTexTransform * vec3(uvs, 1.0)).xy;
+    vec4 metallicRoughness = texture(metallicRoughnessTexture, mrUvs);
+    metal *= metallicRoughness.b;
+    rough *= metallicRoughness.g;
+#endif
+    vec3 color = vec3(0.0);
+    if (debugView == 1) {
+        vec3 normal = normalize(nor);
+        color = (gl_FrontFacing ? normal : -normal) * 0.5 + 0.5;
+    } else if (debugView == 2) {
+        color = linear_to_srgb(base.rgb);
+    } else if (debugView == 3) {
+        color = vec3(rough);
+    } else if (debugView == 4) {
+        color = vec3(metal);
+    } else if (debugView == 5) {
+        color = clamp((pos - boundsMin) / max(boundsSize, vec3(1e-6)), 0.0, 1.0);
+    }
+    outColor = vec4(color, 1.0);
+}
+";
+
+/// Shows one [DebugView] channel of `material` instead of shading it, opaque and unlit, cut
+/// by `clip_planes` like [clip::Clipped].
+pub(crate) struct DebugMaterial<'a> {
+    material: &'a PhysicalMaterial,
+    view: DebugView,
+    bounds: AxisAlignedBoundingBox,
+    clip_planes: &'a [ClipPlane],
+}
+
+impl<'a> DebugMaterial<'a> {
+    pub(crate) fn new(
+        material: &'a PhysicalMaterial,
+        view: DebugView,
+        bounds: AxisAlignedBoundingBox,
+        clip_planes: &'a [ClipPlane],
+    ) -> Self {
+        Self {
+            material,
+            view,
+            bounds,
+            clip_planes,
+        }
+    }
+
+    fn uses_uvs(&self) -> bool {
+        self.material.albedo_texture.is_some() || self.material.metallic_roughness_texture.is_some()
+    }
+}
+
+impl Material for DebugMaterial<'_> {
+    fn fragment_shader_source(&self, _lights: &[&dyn Light]) -> String {
+        let mut source = String::new();
+        if self.material.albedo_texture.is_some() {
+            source.push_str("#define USE_ALBEDO_TEXTURE\n");
+        }
+        if self.material.metallic_roughness_texture.is_some() {
+            source.push_str("#define USE_METALLIC_ROUGHNESS_TEXTURE\n");
+        }
+        if self.uses_uvs() {
+            source.push_str("#define USE_UVS\n");
+        }
+        source.push_str(SRGB_FUNCTIONS);
+        source.push_str(DEBUG_SHADER);
+        if self.clip_planes.is_empty() {
+            source
+        } else {
+            clip::clipped_shader(&source)
+        }
+    }
+
+    fn id(&self) -> u16 {
+        DEBUG_MATERIAL_ID
+            | self.material.albedo_texture.is_some() as u16
+            | (self.material.metallic_roughness_texture.is_some() as u16) << 1
+            | (!self.clip_planes.is_empty() as u16) << 2
+    }
+
+    fn fragment_attributes(&self) -> FragmentAttributes {
+        FragmentAttributes {
+            position: true,
+            normal: true,
+            uv: self.uses_uvs(),
+            ..FragmentAttributes::NONE
+        }
+    }
+
+    fn use_uniforms(&self, program: &Program, _camera: &Camera, _lights: &[&dyn Light]) {
+        let material = self.material;
+        program.use_uniform("debugView", self.view.id());
+        program.use_uniform("albedo", material.albedo.to_linear_srgb());
+        program.use_uniform("metallic", material.metallic);
+        program.use_uniform("roughness", material.roughness);
+        let (min, size) = if self.bounds.is_empty() {
+            (Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0))
+        } else {
+            (self.bounds.min(), self.bounds.size())
+        };
+        program.use_uniform_if_required("boundsMin", min);
+        program.use_uniform_if_required("boundsSize", size);
+        if let Some(texture) = &material.albedo_texture {
+            program.use_texture("albedoTexture", texture);
+            program.use_uniform("albedoTexTransform", texture.transformation);
+        }
+        if let Some(texture) = &material.metallic_roughness_texture {
+            program.use_texture("metallicRoughnessTexture", texture);
+            program.use_uniform("metallicRoughnessTexTransform", texture.transformation);
+        }
+        if !self.clip_planes.is_empty() {
+            clip::use_clip_uniforms(program, self.clip_planes);
+        }
+    }
+
+    fn render_states(&self) -> RenderStates {
+        RenderStates {
+            blend: Blend::Disabled,
+            ..self.material.render_states
+        }
+    }
+
+    fn material_type(&self) -> MaterialType {
+        MaterialType::Opaque
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_cycles_through_every_view_and_back() {
+        let mut view = DebugView::default();
+        let mut seen = Vec::new();
+        for _ in 0..DebugView::ALL.len() {
+            seen.push(view);
+            view = view.next();
+        }
+        assert_eq!(seen, DebugView::ALL);
+        assert_eq!(view, DebugView::None);
+        let mut ids: Vec<i32> = DebugView::ALL.iter().map(|view| view.id()).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), DebugView::ALL.len());
+    }
+}
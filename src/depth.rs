@@ -0,0 +1,148 @@
+use three_d::context::HasContext;
+use three_d::{vec4, Camera, Context, DepthTest, Mat4};
+use three_d_asset::ProjectionType;
+
+/// Depth buffer conventions for the scene.
+///
+/// With `reverse_z` the near plane maps to depth 1 and the far plane to 0, tested with
+/// `GL_GREATER` and cleared to 0. Floating point depth has most of its precision close to 0, so
+/// this spreads it far more evenly over the view distance than the usual convention, but only
+/// with a float depth buffer: the default framebuffer's depth is fixed point on practically
+/// every driver, so the scene then renders through the offscreen target, whose depth attachment
+/// is 32-bit float.
+///
+/// `three_d` materials use the camera's OpenGL style projection, so the flip is done with
+/// `glDepthRange(1, 0)`. That gets the conventions right everywhere; the full precision gain
+/// additionally needs a `[0, 1]` clip space projection, [DepthConfig::projection], which only
+/// custom shaders can use together with `glClipControl`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DepthConfig {
+    pub reverse_z: bool,
+}
+
+impl DepthConfig {
+    /// The depth test materials should use.
+    pub fn depth_test(&self) -> DepthTest {
+        if self.reverse_z {
+            DepthTest::Greater
+        } else {
+            DepthTest::Less
+        }
+    }
+
+    /// The value the depth buffer is cleared to, the depth of the far plane.
+    pub fn clear_depth(&self) -> f32 {
+        if self.reverse_z {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    /// The projection matrix for `camera` under this configuration.
+    ///
+    /// Without reverse-Z this is the camera's own projection. With it, it is a `[0, 1]` clip
+    /// space projection mapping the near plane to 1 and the far plane to 0.
+    pub fn projection(&self, camera: &Camera) -> Mat4 {
+        if !self.reverse_z {
+            return *camera.projection();
+        }
+        let (near, far) = (camera.z_near(), camera.z_far());
+        let aspect = camera.viewport().aspect();
+        let depth = far - near;
+        match camera.projection_type() {
+            ProjectionType::Perspective { field_of_view_y } => {
+                let f = 1.0 / (field_of_view_y.0 / 2.0).tan();
+                Mat4::from_cols(
+                    vec4(f / aspect, 0.0, 0.0, 0.0),
+                    vec4(0.0, f, 0.0, 0.0),
+                    vec4(0.0, 0.0, near / depth, -1.0),
+                    vec4(0.0, 0.0, far * near / depth, 0.0),
+                )
+            }
+            ProjectionType::Orthographic { height } => {
+                let width = height * aspect;
+                Mat4::from_cols(
+                    vec4(2.0 / width, 0.0, 0.0, 0.0),
+                    vec4(0.0, 2.0 / height, 0.0, 0.0),
+                    vec4(0.0, 0.0, 1.0 / depth, 0.0),
+                    vec4(0.0, 0.0, far / depth, 1.0),
+                )
+            }
+        }
+    }
+
+    /// Sets up the depth range before rendering the scene.
+    pub(crate) fn begin(&self, context: &Context) {
+        if self.reverse_z {
+            unsafe { context.depth_range_f32(1.0, 0.0) };
+        }
+    }
+
+    /// Restores the default depth range after rendering the scene.
+    pub(crate) fn end(&self, context: &Context) {
+        if self.reverse_z {
+            unsafe { context.depth_range_f32(0.0, 1.0) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::{degrees, vec3, Vec4, Viewport};
+
+    use super::*;
+
+    fn camera(perspective: bool) -> Camera {
+        let viewport = Viewport::new_at_origo(200, 100);
+        let (position, target, up) = (
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 0.0, -1.0),
+            vec3(0.0, 1.0, 0.0),
+        );
+        if perspective {
+            Camera::new_perspective(viewport, position, target, up, degrees(60.0), 0.5, 50.0)
+        } else {
+            Camera::new_orthographic(viewport, position, target, up, 10.0, 0.5, 50.0)
+        }
+    }
+
+    /// The depth a point `distance` in front of the camera ends up at, in clip space after the
+    /// perspective divide.
+    fn depth(projection: Mat4, distance: f32) -> f32 {
+        let clip: Vec4 = projection * vec4(0.0, 0.0, -distance, 1.0);
+        clip.z / clip.w
+    }
+
+    #[test]
+    fn reverse_z_flips_the_depth_test_and_clear_value() {
+        let standard = DepthConfig::default();
+        assert_eq!(standard.depth_test(), DepthTest::Less);
+        assert_eq!(standard.clear_depth(), 1.0);
+        let reverse = DepthConfig { reverse_z: true };
+        assert_eq!(reverse.depth_test(), DepthTest::Greater);
+        assert_eq!(reverse.clear_depth(), 0.0);
+    }
+
+    #[test]
+    fn standard_projection_is_the_cameras_own() {
+        let camera = camera(true);
+        assert_eq!(
+            DepthConfig::default().projection(&camera),
+            *camera.projection()
+        );
+    }
+
+    #[test]
+    fn reverse_z_maps_near_to_one_and_far_to_zero() {
+        let reverse = DepthConfig { reverse_z: true };
+        for perspective in [true, false] {
+            let camera = camera(perspective);
+            let projection = reverse.projection(&camera);
+            assert_ne!(projection, *camera.projection());
+            assert!((depth(projection, 0.5) - 1.0).abs() < 1e-5);
+            assert!(depth(projection, 50.0).abs() < 1e-5);
+            assert!(depth(projection, 5.0) > depth(projection, 10.0));
+        }
+    }
+}
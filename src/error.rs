@@ -0,0 +1,295 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::windowed_context::GlVersion;
+
+/// Everything that can go wrong while creating or driving a
+/// [crate::windowed_context::WindowedContext].
+#[derive(Debug)]
+pub enum ContextError {
+    /// The requested MSAA sample count is not a power of two.
+    InvalidNumberOfMsaaSamples(u8),
+    /// No GL display could be opened for the window.
+    DisplayCreation(glutin::error::Error),
+    /// Querying the display for configs failed.
+    ConfigSelection(glutin::error::Error),
+    /// The display has no config matching the requested settings.
+    NoMatchingConfig,
+    ContextCreation(glutin::error::Error),
+    SurfaceCreation(glutin::error::Error),
+    MakeCurrent(glutin::error::Error),
+    SwapInterval(glutin::error::Error),
+    SwapBuffers(glutin::error::Error),
+    /// [three_d::HardwareAcceleration::Required] was requested but the driver only offers a
+    /// software rasterizer.
+    HardwareAccelerationUnavailable {
+        renderer: String,
+    },
+    /// The driver's GL version is older than `three_d` needs.
+    UnsupportedGlVersion {
+        found: GlVersion,
+        required: GlVersion,
+    },
+    /// Hardware acceleration was unavailable, and so was the software fallback, see
+    /// [crate::windowed_context::WindowedContextBuilder::software_fallback].
+    SoftwareFallbackFailed {
+        hardware: Box<ContextError>,
+        software: Box<ContextError>,
+    },
+    /// `three_d` rejected the created GL context.
+    Core(three_d::CoreError),
+}
+
+impl fmt::Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContextError::InvalidNumberOfMsaaSamples(samples) => {
+                write!(f, "invalid number of MSAA samples: {samples}")
+            }
+            ContextError::DisplayCreation(e) => write!(f, "failed to create GL display: {e}"),
+            ContextError::ConfigSelection(e) => write!(f, "failed to query GL configs: {e}"),
+            ContextError::NoMatchingConfig => {
+                write!(f, "no GL config matches the requested settings")
+            }
+            ContextError::ContextCreation(e) => write!(f, "failed to create GL context: {e}"),
+            ContextError::SurfaceCreation(e) => write!(f, "failed to create GL surface: {e}"),
+            ContextError::MakeCurrent(e) => write!(f, "failed to make GL context current: {e}"),
+            ContextError::SwapInterval(e) => write!(f, "failed to set swap interval: {e}"),
+            ContextError::SwapBuffers(e) => write!(f, "failed to swap buffers: {e}"),
+            ContextError::HardwareAccelerationUnavailable { renderer } => {
+                write!(
+                    f,
+                    "hardware acceleration required, but the renderer is {renderer}"
+                )
+            }
+            ContextError::UnsupportedGlVersion { found, required } => {
+                write!(
+                    f,
+                    "{found} is not supported, {required} or newer is required"
+                )
+            }
+            ContextError::SoftwareFallbackFailed { hardware, software } => {
+                write!(
+                    f,
+                    "{hardware}; the software fallback failed too: {software}"
+                )
+            }
+            ContextError::Core(e) => write!(f, "failed to initialize three_d: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ContextError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ContextError::DisplayCreation(e)
+            | ContextError::ConfigSelection(e)
+            | ContextError::ContextCreation(e)
+            | ContextError::SurfaceCreation(e)
+            | ContextError::MakeCurrent(e)
+            | ContextError::SwapInterval(e)
+            | ContextError::SwapBuffers(e) => Some(e),
+            ContextError::Core(e) => Some(e),
+            ContextError::SoftwareFallbackFailed { software, .. } => Some(software.as_ref()),
+            ContextError::InvalidNumberOfMsaaSamples(_)
+            | ContextError::NoMatchingConfig
+            | ContextError::HardwareAccelerationUnavailable { .. }
+            | ContextError::UnsupportedGlVersion { .. } => None,
+        }
+    }
+}
+
+impl From<three_d::CoreError> for ContextError {
+    fn from(e: three_d::CoreError) -> Self {
+        ContextError::Core(e)
+    }
+}
+
+/// Why a model could not be loaded, see [crate::assets::load_model].
+#[derive(Debug)]
+pub enum LoadError {
+    /// No file exists at `path` or in any of the `searched` locations.
+    NotFound {
+        path: PathBuf,
+        searched: Vec<PathBuf>,
+    },
+    /// The file exists but could not be read or parsed.
+    Asset(three_d_asset::Error),
+    /// A model loaded from memory refers to another file, like a glTF buffer or an OBJ
+    /// material library, which can't be resolved without a path.
+    ExternalReference { uri: String },
+    /// The file is not valid in its format, for formats this crate parses itself.
+    Malformed(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::NotFound { path, searched } => {
+                write!(f, "asset {} not found, looked in:", path.display())?;
+                for candidate in searched {
+                    write!(f, " {}", candidate.display())?;
+                }
+                Ok(())
+            }
+            LoadError::Asset(e) => write!(f, "failed to load asset: {e}"),
+            LoadError::Malformed(reason) => write!(f, "malformed model: {reason}"),
+            LoadError::ExternalReference { uri } => {
+                write!(
+                    f,
+                    "model refers to the external file {uri}, not loadable from memory"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Asset(e) => Some(e),
+            LoadError::NotFound { .. }
+            | LoadError::ExternalReference { .. }
+            | LoadError::Malformed(_) => None,
+        }
+    }
+}
+
+impl From<three_d_asset::Error> for LoadError {
+    fn from(e: three_d_asset::Error) -> Self {
+        LoadError::Asset(e)
+    }
+}
+
+/// A message for end users explaining what went wrong and what they can try, as opposed to
+/// the [fmt::Display] output which is meant for logs.
+pub fn describe_error(error: &ContextError) -> String {
+    match error {
+        ContextError::InvalidNumberOfMsaaSamples(samples) => format!(
+            "Anti-aliasing is set to {samples} samples, which is not supported. \
+             Choose 2, 4, 8 or 16 samples, or turn anti-aliasing off."
+        ),
+        ContextError::DisplayCreation(_) => "Could not connect to the graphics system. \
+             Make sure a graphics driver is installed and, on linux, that a display server is \
+             running."
+            .to_string(),
+        ContextError::ConfigSelection(_) => "Could not look up the graphics settings your GPU \
+             driver supports. Try updating it, or switching to a different graphics backend."
+            .to_string(),
+        ContextError::NoMatchingConfig => {
+            "Your GPU driver doesn't support the requested graphics settings. \
+             Try disabling anti-aliasing or transparency, or lowering the depth buffer precision."
+                .to_string()
+        }
+        ContextError::ContextCreation(_) => "Could not create an OpenGL context. \
+             Your GPU driver may be outdated or may not support OpenGL 3.3; try updating it."
+            .to_string(),
+        ContextError::SurfaceCreation(_) => "Could not attach 3D rendering to the window. \
+             Try restarting the application, or switching to a different graphics backend."
+            .to_string(),
+        ContextError::MakeCurrent(_) => "The graphics context could not be activated. \
+             Another application may be holding the GPU, try closing other 3D applications."
+            .to_string(),
+        ContextError::SwapInterval(_) => "Vertical sync could not be configured. \
+             Try turning vsync off in the settings."
+            .to_string(),
+        ContextError::SwapBuffers(_) => "The rendered frame could not be shown. \
+             The graphics driver may have been reset; try restarting the application."
+            .to_string(),
+        ContextError::HardwareAccelerationUnavailable { renderer } => format!(
+            "No GPU acceleration is available, only the software renderer \"{renderer}\". \
+             Install or update your GPU driver, or allow software rendering in the settings."
+        ),
+        ContextError::UnsupportedGlVersion { found, required } => format!(
+            "Your graphics driver only provides {found}, but {required} or newer is needed. \
+             Update your GPU driver; very old integrated GPUs may not support it at all."
+        ),
+        ContextError::SoftwareFallbackFailed { software, .. } => format!(
+            "Neither GPU acceleration nor software rendering is available. {}",
+            describe_error(software)
+        ),
+        ContextError::Core(e) => format!(
+            "The graphics driver reported an unexpected error ({e}). \
+             Try updating your GPU driver."
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glutin::error::ErrorKind;
+
+    use super::*;
+
+    fn glutin_error() -> glutin::error::Error {
+        ErrorKind::NotSupported("test").into()
+    }
+
+    fn every_context_error() -> Vec<ContextError> {
+        let version = |major, minor| GlVersion {
+            es: false,
+            major,
+            minor,
+        };
+        vec![
+            ContextError::InvalidNumberOfMsaaSamples(3),
+            ContextError::DisplayCreation(glutin_error()),
+            ContextError::ConfigSelection(glutin_error()),
+            ContextError::NoMatchingConfig,
+            ContextError::ContextCreation(glutin_error()),
+            ContextError::SurfaceCreation(glutin_error()),
+            ContextError::MakeCurrent(glutin_error()),
+            ContextError::SwapInterval(glutin_error()),
+            ContextError::SwapBuffers(glutin_error()),
+            ContextError::HardwareAccelerationUnavailable {
+                renderer: "llvmpipe".to_string(),
+            },
+            ContextError::UnsupportedGlVersion {
+                found: version(2, 1),
+                required: version(3, 3),
+            },
+            ContextError::SoftwareFallbackFailed {
+                hardware: Box::new(ContextError::NoMatchingConfig),
+                software: Box::new(ContextError::MakeCurrent(glutin_error())),
+            },
+            ContextError::Core(three_d::CoreError::ContextCreation("test".to_string())),
+        ]
+    }
+
+    #[test]
+    fn every_variant_has_its_own_message() {
+        let messages: Vec<String> = every_context_error().iter().map(describe_error).collect();
+        for (index, message) in messages.iter().enumerate() {
+            assert!(!message.trim().is_empty());
+            assert!(
+                !messages[..index].contains(message),
+                "duplicate message: {message}"
+            );
+        }
+    }
+
+    #[test]
+    fn user_messages_differ_from_the_log_output() {
+        for error in every_context_error() {
+            assert_ne!(describe_error(&error), error.to_string());
+        }
+    }
+
+    #[test]
+    fn wrapped_errors_get_a_generic_message() {
+        let message = describe_error(&ContextError::Core(three_d::CoreError::ContextError(
+            "lost".to_string(),
+        )));
+        assert!(message.contains("unexpected error"), "{message}");
+    }
+
+    #[test]
+    fn failed_fallback_explains_the_software_failure() {
+        let software = ContextError::MakeCurrent(glutin_error());
+        let message = describe_error(&ContextError::SoftwareFallbackFailed {
+            hardware: Box::new(ContextError::NoMatchingConfig),
+            software: Box::new(ContextError::MakeCurrent(glutin_error())),
+        });
+        assert!(message.ends_with(&describe_error(&software)), "{message}");
+    }
+}
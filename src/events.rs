@@ -0,0 +1,472 @@
+use dioxus::desktop::tao::dpi::{PhysicalPosition, PhysicalSize};
+use dioxus::desktop::tao::event::{
+    ElementState, MouseButton as TaoMouseButton, MouseScrollDelta, Touch, TouchPhase, WindowEvent,
+};
+use dioxus::desktop::tao::keyboard::{KeyCode, ModifiersState};
+use three_d::{Event, Key, Modifiers, MouseButton, PhysicalPoint};
+
+/// Pixels scrolled per wheel "line", matching what three_d's own window does.
+const LINE_HEIGHT: f32 = 24.0;
+/// Logical pixels a single touch has to travel before it counts as a drag rather than a tap.
+const TOUCH_SLOP: f64 = 8.0;
+/// Zoom (in scroll pixels) per unit of relative pinch magnification.
+pub const PINCH_ZOOM_SCALE: f32 = 200.0;
+
+/// Translates tao window events into [three_d::Event]s for the camera controls.
+///
+/// Positions are converted from tao's top-left origin to three_d's bottom-left origin, so the
+/// translator needs to know the window height; it tracks `Resized` events itself.
+///
+/// Touch input is mapped onto the mouse events the controls already understand: a one-finger
+/// drag orbits like a left-button drag, a two-finger pinch zooms like the scroll wheel and the
+/// two-finger centroid drags to orbit. A single touch that never leaves [TOUCH_SLOP] is a tap
+/// and produces nothing.
+pub struct EventTranslator {
+    size: PhysicalSize<u32>,
+    scale_factor: f64,
+    cursor: PhysicalPoint,
+    pressed: Option<MouseButton>,
+    modifiers: Modifiers,
+    touches: Vec<(u64, PhysicalPosition<f64>)>,
+    touch_start: Option<PhysicalPosition<f64>>,
+    touch_dragging: bool,
+    relative_motion: bool,
+}
+
+impl EventTranslator {
+    pub fn new(size: PhysicalSize<u32>, scale_factor: f64) -> Self {
+        Self {
+            size,
+            scale_factor,
+            cursor: PhysicalPoint { x: 0.0, y: 0.0 },
+            pressed: None,
+            modifiers: Modifiers::default(),
+            touches: Vec::new(),
+            touch_start: None,
+            touch_dragging: false,
+            relative_motion: false,
+        }
+    }
+
+    /// Appends the three_d events corresponding to `event` to `events`.
+    pub fn handle(&mut self, event: &WindowEvent<'_>, events: &mut Vec<Event>) {
+        match event {
+            WindowEvent::Resized(size) => self.size = *size,
+            WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                new_inner_size,
+            } => {
+                self.scale_factor = *scale_factor;
+                self.size = **new_inner_size;
+            }
+            WindowEvent::ModifiersChanged(state) => {
+                self.modifiers = modifiers(*state);
+                events.push(Event::ModifiersChange {
+                    modifiers: self.modifiers,
+                });
+            }
+            WindowEvent::CursorEntered { .. } => events.push(Event::MouseEnter),
+            WindowEvent::CursorLeft { .. } => events.push(Event::MouseLeave),
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = self.point(*position);
+                let delta = (position.x - self.cursor.x, self.cursor.y - position.y);
+                self.cursor = position;
+                if self.relative_motion {
+                    return;
+                }
+                events.push(Event::MouseMotion {
+                    button: self.pressed,
+                    delta,
+                    position,
+                    modifiers: self.modifiers,
+                    handled: false,
+                });
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                let Some(button) = mouse_button(*button) else {
+                    return;
+                };
+                match state {
+                    ElementState::Pressed => {
+                        self.pressed = Some(button);
+                        events.push(Event::MousePress {
+                            button,
+                            position: self.cursor,
+                            modifiers: self.modifiers,
+                            handled: false,
+                        });
+                    }
+                    ElementState::Released => {
+                        self.pressed = None;
+                        events.push(Event::MouseRelease {
+                            button,
+                            position: self.cursor,
+                            modifiers: self.modifiers,
+                            handled: false,
+                        });
+                    }
+                    _ => {}
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let delta = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (x * LINE_HEIGHT, y * LINE_HEIGHT),
+                    MouseScrollDelta::PixelDelta(delta) => (delta.x as f32, delta.y as f32),
+                    _ => return,
+                };
+                events.push(self.wheel(delta));
+            }
+            WindowEvent::Touch(touch) => self.touch(touch, events),
+            WindowEvent::KeyboardInput { event, .. } => {
+                let Some(kind) = key(event.physical_key) else {
+                    return;
+                };
+                events.push(match event.state {
+                    ElementState::Pressed => Event::KeyPress {
+                        kind,
+                        modifiers: self.modifiers,
+                        handled: false,
+                    },
+                    _ => Event::KeyRelease {
+                        kind,
+                        modifiers: self.modifiers,
+                        handled: false,
+                    },
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// While enabled, cursor moves no longer produce motion events; feed the deltas of a
+    /// [crate::pointer_grab::PointerGrab] through [EventTranslator::relative_motion] instead.
+    pub fn set_relative_motion(&mut self, enabled: bool) {
+        self.relative_motion = enabled;
+    }
+
+    /// A motion event for a relative `delta`, as produced by
+    /// [crate::pointer_grab::PointerGrab::take_delta].
+    pub fn relative_motion(&self, delta: (f32, f32)) -> Event {
+        Event::MouseMotion {
+            button: self.pressed,
+            delta,
+            position: self.cursor,
+            modifiers: self.modifiers,
+            handled: false,
+        }
+    }
+
+    /// Translates a trackpad magnification gesture, where `delta` is the relative change in
+    /// scale (positive to zoom in).
+    ///
+    /// tao does not forward the platform's trackpad gesture events; this is the entry point for
+    /// integrations that receive them some other way.
+    pub fn magnify(&self, delta: f64) -> Event {
+        self.wheel((0.0, pinch_zoom(delta)))
+    }
+
+    /// Translates a trackpad rotation gesture of `delta` degrees into a horizontal orbit.
+    pub fn rotate(&self, delta: f32) -> Event {
+        self.drag((delta, 0.0))
+    }
+
+    fn touch(&mut self, touch: &Touch, events: &mut Vec<Event>) {
+        let previous = self.touches.clone();
+        match touch.phase {
+            TouchPhase::Started => {
+                if self.touches.len() < 2 {
+                    self.touches.push((touch.id, touch.location));
+                }
+                if self.touches.len() == 1 {
+                    self.touch_start = Some(touch.location);
+                    self.touch_dragging = false;
+                }
+            }
+            TouchPhase::Moved => {
+                if let Some((_, location)) = self.touches.iter_mut().find(|(id, _)| *id == touch.id)
+                {
+                    *location = touch.location;
+                }
+            }
+            _ => {
+                self.touches.retain(|(id, _)| *id != touch.id);
+                self.touch_start = None;
+                self.touch_dragging = false;
+                return;
+            }
+        }
+        if touch.phase != TouchPhase::Moved || previous.len() != self.touches.len() {
+            return;
+        }
+
+        match self.touches.as_slice() {
+            [(_, location)] => {
+                let start = self.touch_start.unwrap_or(*location);
+                let travelled = ((location.x - start.x).powi(2) + (location.y - start.y).powi(2))
+                    .sqrt()
+                    / self.scale_factor;
+                if travelled > TOUCH_SLOP {
+                    self.touch_dragging = true;
+                }
+                if self.touch_dragging {
+                    let [(_, before)] = previous.as_slice() else {
+                        return;
+                    };
+                    let delta = (location.x - before.x, before.y - location.y);
+                    events.push(self.drag(self.logical_delta(delta)));
+                }
+            }
+            [(_, a), (_, b)] => {
+                let [(_, before_a), (_, before_b)] = previous.as_slice() else {
+                    return;
+                };
+                let distance = |p: &PhysicalPosition<f64>, q: &PhysicalPosition<f64>| {
+                    ((p.x - q.x).powi(2) + (p.y - q.y).powi(2)).sqrt()
+                };
+                let before = distance(before_a, before_b);
+                if before > 0.0 {
+                    let magnification = distance(a, b) / before - 1.0;
+                    events.push(self.wheel((0.0, pinch_zoom(magnification))));
+                }
+                let centroid_delta = (
+                    ((a.x + b.x) - (before_a.x + before_b.x)) / 2.0,
+                    ((before_a.y + before_b.y) - (a.y + b.y)) / 2.0,
+                );
+                events.push(self.drag(self.logical_delta(centroid_delta)));
+            }
+            _ => {}
+        }
+    }
+
+    fn wheel(&self, delta: (f32, f32)) -> Event {
+        Event::MouseWheel {
+            delta,
+            position: self.cursor,
+            modifiers: self.modifiers,
+            handled: false,
+        }
+    }
+
+    fn drag(&self, delta: (f32, f32)) -> Event {
+        Event::MouseMotion {
+            button: Some(MouseButton::Left),
+            delta,
+            position: self.cursor,
+            modifiers: self.modifiers,
+            handled: false,
+        }
+    }
+
+    fn logical_delta(&self, delta: (f64, f64)) -> (f32, f32) {
+        (
+            (delta.0 / self.scale_factor) as f32,
+            (delta.1 / self.scale_factor) as f32,
+        )
+    }
+
+    /// Converts a tao position (top-left origin) into a three_d one (bottom-left origin).
+    fn point(&self, position: PhysicalPosition<f64>) -> PhysicalPoint {
+        PhysicalPoint {
+            x: position.x as f32,
+            y: (self.size.height as f64 - position.y) as f32,
+        }
+    }
+}
+
+/// The scroll amount a relative pinch magnification maps to.
+pub fn pinch_zoom(magnification: f64) -> f32 {
+    magnification as f32 * PINCH_ZOOM_SCALE
+}
+
+fn modifiers(state: ModifiersState) -> Modifiers {
+    Modifiers {
+        alt: state.alt_key(),
+        ctrl: state.control_key(),
+        shift: state.shift_key(),
+        command: if cfg!(target_os = "macos") {
+            state.super_key()
+        } else {
+            state.control_key()
+        },
+    }
+}
+
+fn mouse_button(button: TaoMouseButton) -> Option<MouseButton> {
+    match button {
+        TaoMouseButton::Left => Some(MouseButton::Left),
+        TaoMouseButton::Right => Some(MouseButton::Right),
+        TaoMouseButton::Middle => Some(MouseButton::Middle),
+        _ => None,
+    }
+}
+
+fn key(code: KeyCode) -> Option<Key> {
+    Some(match code {
+        KeyCode::ArrowDown => Key::ArrowDown,
+        KeyCode::ArrowLeft => Key::ArrowLeft,
+        KeyCode::ArrowRight => Key::ArrowRight,
+        KeyCode::ArrowUp => Key::ArrowUp,
+        KeyCode::Escape => Key::Escape,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Space => Key::Space,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::Digit0 => Key::Num0,
+        KeyCode::Digit1 => Key::Num1,
+        KeyCode::Digit2 => Key::Num2,
+        KeyCode::Digit3 => Key::Num3,
+        KeyCode::Digit4 => Key::Num4,
+        KeyCode::Digit5 => Key::Num5,
+        KeyCode::Digit6 => Key::Num6,
+        KeyCode::Digit7 => Key::Num7,
+        KeyCode::Digit8 => Key::Num8,
+        KeyCode::Digit9 => Key::Num9,
+        KeyCode::KeyA => Key::A,
+        KeyCode::KeyB => Key::B,
+        KeyCode::KeyC => Key::C,
+        KeyCode::KeyD => Key::D,
+        KeyCode::KeyE => Key::E,
+        KeyCode::KeyF => Key::F,
+        KeyCode::KeyG => Key::G,
+        KeyCode::KeyH => Key::H,
+        KeyCode::KeyI => Key::I,
+        KeyCode::KeyJ => Key::J,
+        KeyCode::KeyK => Key::K,
+        KeyCode::KeyL => Key::L,
+        KeyCode::KeyM => Key::M,
+        KeyCode::KeyN => Key::N,
+        KeyCode::KeyO => Key::O,
+        KeyCode::KeyP => Key::P,
+        KeyCode::KeyQ => Key::Q,
+        KeyCode::KeyR => Key::R,
+        KeyCode::KeyS => Key::S,
+        KeyCode::KeyT => Key::T,
+        KeyCode::KeyU => Key::U,
+        KeyCode::KeyV => Key::V,
+        KeyCode::KeyW => Key::W,
+        KeyCode::KeyX => Key::X,
+        KeyCode::KeyY => Key::Y,
+        KeyCode::KeyZ => Key::Z,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use dioxus::desktop::tao::event::DeviceId;
+
+    use super::*;
+
+    fn translator() -> EventTranslator {
+        EventTranslator::new(PhysicalSize::new(200, 100), 2.0)
+    }
+
+    /// The events for finger `id` at `x`, `y` in `phase`.
+    fn touch(
+        translator: &mut EventTranslator,
+        id: u64,
+        phase: TouchPhase,
+        (x, y): (f64, f64),
+    ) -> Vec<Event> {
+        let touch = Touch {
+            device_id: unsafe { DeviceId::dummy() },
+            phase,
+            location: PhysicalPosition::new(x, y),
+            force: None,
+            id,
+        };
+        let mut events = Vec::new();
+        translator.handle(&WindowEvent::Touch(touch), &mut events);
+        events
+    }
+
+    fn wheel_delta(event: &Event) -> (f32, f32) {
+        match event {
+            Event::MouseWheel { delta, .. } => *delta,
+            other => panic!("expected a wheel event, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn magnify_maps_to_a_zoom_amount() {
+        let translator = translator();
+        assert_eq!(
+            wheel_delta(&translator.magnify(0.5)),
+            (0.0, 0.5 * PINCH_ZOOM_SCALE)
+        );
+        assert_eq!(
+            wheel_delta(&translator.magnify(-0.25)),
+            (0.0, -0.25 * PINCH_ZOOM_SCALE)
+        );
+        assert_eq!(pinch_zoom(0.0), 0.0);
+    }
+
+    #[test]
+    fn pinch_zooms_by_the_relative_spread() {
+        let mut translator = translator();
+        touch(&mut translator, 1, TouchPhase::Started, (50.0, 50.0));
+        touch(&mut translator, 2, TouchPhase::Started, (150.0, 50.0));
+        let events = touch(&mut translator, 2, TouchPhase::Moved, (200.0, 50.0));
+        assert_eq!(wheel_delta(&events[0]), (0.0, pinch_zoom(0.5)));
+        // The centroid moved by 25 physical pixels, half that in logical ones.
+        assert!(matches!(
+            events[1],
+            Event::MouseMotion {
+                button: Some(MouseButton::Left),
+                delta: (12.5, 0.0),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn single_touch_tap_produces_nothing() {
+        let mut translator = translator();
+        assert!(touch(&mut translator, 1, TouchPhase::Started, (50.0, 50.0)).is_empty());
+        assert!(touch(&mut translator, 1, TouchPhase::Moved, (54.0, 50.0)).is_empty());
+        assert!(touch(&mut translator, 1, TouchPhase::Ended, (54.0, 50.0)).is_empty());
+    }
+
+    #[test]
+    fn single_touch_drag_orbits_once_past_the_slop() {
+        let mut translator = translator();
+        touch(&mut translator, 1, TouchPhase::Started, (50.0, 50.0));
+        let events = touch(&mut translator, 1, TouchPhase::Moved, (90.0, 50.0));
+        assert!(matches!(
+            events.as_slice(),
+            [Event::MouseMotion {
+                button: Some(MouseButton::Left),
+                delta: (20.0, 0.0),
+                ..
+            }]
+        ));
+    }
+
+    // tao still requires the deprecated `modifiers` field.
+    #[allow(deprecated)]
+    #[test]
+    fn cursor_positions_flip_to_a_bottom_left_origin() {
+        let mut translator = translator();
+        let mut events = Vec::new();
+        translator.handle(
+            &WindowEvent::CursorMoved {
+                device_id: unsafe { DeviceId::dummy() },
+                position: PhysicalPosition::new(10.0, 30.0),
+                modifiers: ModifiersState::default(),
+            },
+            &mut events,
+        );
+        assert!(matches!(
+            events.as_slice(),
+            [Event::MouseMotion {
+                position: PhysicalPoint { x: 10.0, y: 70.0 },
+                ..
+            }]
+        ));
+    }
+}
@@ -0,0 +1,235 @@
+use dioxus::desktop::tao::dpi::PhysicalPosition;
+use dioxus::desktop::tao::event::{ElementState, TouchPhase, WindowEvent};
+
+/// A rectangle of the window in logical pixels, top-left origin, as the DOM reports element
+/// bounds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UiRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl UiRect {
+    fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+}
+
+/// Decides which window events belong to the 3D canvas rather than the UI drawn over it.
+///
+/// The canvas is the whole window minus the registered UI rectangles. Pointer events go to
+/// the canvas while the pointer is over it; a press on the canvas captures the pointer, so
+/// the drag keeps working and its release is delivered even after leaving the canvas or the
+/// window. Losing focus ends the capture, as the release may then never arrive. Keyboard
+/// events go to whichever of canvas and UI was clicked last, and only while the window has
+/// focus.
+pub struct InputFocus {
+    scale_factor: f64,
+    ui_rects: Vec<UiRect>,
+    pointer: Option<PhysicalPosition<f64>>,
+    pressed_buttons: u32,
+    touches: Vec<u64>,
+    keyboard_on_canvas: bool,
+    window_focused: bool,
+}
+
+impl InputFocus {
+    pub fn new(scale_factor: f64) -> Self {
+        Self {
+            scale_factor,
+            ui_rects: Vec::new(),
+            pointer: None,
+            pressed_buttons: 0,
+            touches: Vec::new(),
+            keyboard_on_canvas: true,
+            window_focused: true,
+        }
+    }
+
+    /// Replaces the areas covered by UI, where the canvas doesn't receive input.
+    pub fn set_ui_rects(&mut self, rects: Vec<UiRect>) {
+        self.ui_rects = rects;
+    }
+
+    /// Whether the canvas currently receives pointer input: the pointer is over it, or a drag
+    /// that started on it is still going on.
+    pub fn has_pointer(&self) -> bool {
+        self.is_captured()
+            || self
+                .pointer
+                .is_some_and(|position| self.on_canvas(position))
+    }
+
+    /// Whether the canvas currently receives keyboard input.
+    pub fn has_keyboard(&self) -> bool {
+        self.window_focused && self.keyboard_on_canvas
+    }
+
+    fn is_captured(&self) -> bool {
+        self.pressed_buttons > 0 || !self.touches.is_empty()
+    }
+
+    fn on_canvas(&self, position: PhysicalPosition<f64>) -> bool {
+        let logical = position.to_logical::<f64>(self.scale_factor);
+        !self
+            .ui_rects
+            .iter()
+            .any(|rect| rect.contains(logical.x, logical.y))
+    }
+
+    /// Updates the focus state with `event` and returns whether it should be passed on to
+    /// the 3D controls.
+    pub fn filter(&mut self, event: &WindowEvent<'_>) -> bool {
+        match event {
+            WindowEvent::Focused(focused) => {
+                self.window_focused = *focused;
+                if !focused {
+                    self.pressed_buttons = 0;
+                    self.touches.clear();
+                }
+                true
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.scale_factor = *scale_factor;
+                true
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.pointer = Some(*position);
+                self.has_pointer()
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.pointer = None;
+                true
+            }
+            WindowEvent::MouseInput { state, .. } => match state {
+                ElementState::Pressed => {
+                    let on_canvas = self.has_pointer();
+                    self.keyboard_on_canvas = on_canvas;
+                    if on_canvas {
+                        self.pressed_buttons += 1;
+                    }
+                    on_canvas
+                }
+                ElementState::Released if self.pressed_buttons > 0 => {
+                    self.pressed_buttons -= 1;
+                    true
+                }
+                _ => false,
+            },
+            WindowEvent::MouseWheel { .. } => self.has_pointer(),
+            WindowEvent::Touch(touch) => match touch.phase {
+                TouchPhase::Started => {
+                    let on_canvas = self.on_canvas(touch.location);
+                    self.keyboard_on_canvas = on_canvas;
+                    if on_canvas {
+                        self.touches.push(touch.id);
+                    }
+                    on_canvas
+                }
+                TouchPhase::Moved => self.touches.contains(&touch.id),
+                TouchPhase::Ended | TouchPhase::Cancelled => {
+                    let tracked = self.touches.contains(&touch.id);
+                    self.touches.retain(|id| *id != touch.id);
+                    tracked
+                }
+                _ => false,
+            },
+            WindowEvent::KeyboardInput { .. } => self.has_keyboard(),
+            _ => true,
+        }
+    }
+}
+
+// tao still requires the deprecated `modifiers` field on the mouse events.
+#[allow(deprecated)]
+#[cfg(test)]
+mod tests {
+    use dioxus::desktop::tao::event::{DeviceId, MouseButton};
+    use dioxus::desktop::tao::keyboard::ModifiersState;
+
+    use super::*;
+
+    /// A focus with a 100 by 50 logical pixel toolbar in the top left, at scale factor 2.
+    fn focus() -> InputFocus {
+        let mut focus = InputFocus::new(2.0);
+        focus.set_ui_rects(vec![UiRect {
+            x: 0.0,
+            y: 0.0,
+            width: 100.0,
+            height: 50.0,
+        }]);
+        focus
+    }
+
+    fn moved(x: f64, y: f64) -> WindowEvent<'static> {
+        WindowEvent::CursorMoved {
+            device_id: unsafe { DeviceId::dummy() },
+            position: PhysicalPosition::new(x, y),
+            modifiers: ModifiersState::default(),
+        }
+    }
+
+    fn left() -> WindowEvent<'static> {
+        WindowEvent::CursorLeft {
+            device_id: unsafe { DeviceId::dummy() },
+        }
+    }
+
+    fn button(state: ElementState) -> WindowEvent<'static> {
+        WindowEvent::MouseInput {
+            device_id: unsafe { DeviceId::dummy() },
+            state,
+            button: MouseButton::Left,
+            modifiers: ModifiersState::default(),
+        }
+    }
+
+    #[test]
+    fn clicks_on_the_ui_are_kept_from_the_canvas() {
+        let mut focus = focus();
+        // Physical pixels, so (150, 50) is inside the toolbar.
+        assert!(!focus.filter(&moved(150.0, 50.0)));
+        assert!(!focus.has_pointer());
+        assert!(!focus.filter(&button(ElementState::Pressed)));
+        assert!(!focus.filter(&button(ElementState::Released)));
+        assert!(!focus.has_keyboard());
+
+        assert!(focus.filter(&moved(250.0, 50.0)));
+        assert!(focus.has_pointer());
+        assert!(focus.filter(&button(ElementState::Pressed)));
+        assert!(focus.has_keyboard());
+    }
+
+    #[test]
+    fn drag_started_on_the_canvas_is_delivered_until_released() {
+        let mut focus = focus();
+        focus.filter(&moved(300.0, 300.0));
+        assert!(focus.filter(&button(ElementState::Pressed)));
+        assert!(focus.filter(&moved(50.0, 50.0)));
+        assert!(focus.filter(&left()));
+        assert!(focus.has_pointer());
+        assert!(focus.filter(&button(ElementState::Released)));
+        assert!(!focus.has_pointer());
+        assert!(!focus.filter(&moved(50.0, 50.0)));
+    }
+
+    #[test]
+    fn losing_window_focus_ends_the_capture_and_the_keyboard() {
+        let mut focus = focus();
+        focus.filter(&moved(300.0, 300.0));
+        focus.filter(&button(ElementState::Pressed));
+        focus.filter(&left());
+        assert!(focus.has_keyboard());
+
+        focus.filter(&WindowEvent::Focused(false));
+        assert!(!focus.has_pointer());
+        assert!(!focus.has_keyboard());
+        // The release that never came to this window is not delivered late.
+        assert!(!focus.filter(&button(ElementState::Released)));
+
+        focus.filter(&WindowEvent::Focused(true));
+        assert!(focus.has_keyboard());
+    }
+}
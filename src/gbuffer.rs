@@ -0,0 +1,139 @@
+use three_d::Camera;
+use three_d_asset::ProjectionType;
+
+use crate::depth::DepthConfig;
+
+/// Color and linear depth of one rendered frame, for compositing the 3D view with other
+/// layers, see [crate::scene::Scene::capture_gbuffer].
+///
+/// Both are `width * height` pixels, top row first, so `color[i]` and `depth[i]` are the same
+/// pixel.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GBuffer {
+    pub width: u32,
+    pub height: u32,
+    /// The final colors, as they would be presented.
+    pub color: Vec<[u8; 4]>,
+    /// The distance from the camera plane along the view direction, in world units.
+    /// [f32::INFINITY] where nothing was drawn.
+    pub depth: Vec<f32>,
+}
+
+impl GBuffer {
+    /// The color and depth at `x`, `y`, counted from the top left.
+    pub fn get(&self, x: u32, y: u32) -> Option<([u8; 4], f32)> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let index = (y * self.width + x) as usize;
+        Some((self.color[index], self.depth[index]))
+    }
+}
+
+/// Turns a window space depth of `camera` under `depth_config` into the distance in front of
+/// the camera.
+pub(crate) fn linearize_depth(depth: f32, camera: &Camera, depth_config: DepthConfig) -> f32 {
+    let depth = if depth_config.reverse_z {
+        1.0 - depth
+    } else {
+        depth
+    };
+    if depth >= 1.0 {
+        return f32::INFINITY;
+    }
+    let (near, far) = (camera.z_near(), camera.z_far());
+    match camera.projection_type() {
+        ProjectionType::Perspective { .. } => {
+            let ndc = depth * 2.0 - 1.0;
+            2.0 * near * far / (far + near - ndc * (far - near))
+        }
+        ProjectionType::Orthographic { .. } => near + depth * (far - near),
+    }
+}
+
+/// Reverses the row order of a `width` pixels wide image in place.
+pub(crate) fn flip_rows<T>(pixels: &mut [T], width: usize) {
+    if width == 0 {
+        return;
+    }
+    let height = pixels.len() / width;
+    for row in 0..height / 2 {
+        let (top, bottom) = pixels.split_at_mut((height - 1 - row) * width);
+        top[row * width..(row + 1) * width].swap_with_slice(&mut bottom[..width]);
+    }
+}
+
+/// Picks the nearest of `source`'s `source_width` by `source_height` pixels for each of
+/// `width` by `height`; depth can't be filtered, averaging across an edge makes up surfaces.
+pub(crate) fn resample_nearest(
+    source: &[f32],
+    (source_width, source_height): (u32, u32),
+    (width, height): (u32, u32),
+) -> Vec<f32> {
+    if (source_width, source_height) == (width, height) {
+        return source.to_vec();
+    }
+    let mut result = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        let source_y = ((y as u64 * 2 + 1) * source_height as u64 / (height as u64 * 2)) as u32;
+        for x in 0..width {
+            let source_x = ((x as u64 * 2 + 1) * source_width as u64 / (width as u64 * 2)) as u32;
+            let index =
+                source_y.min(source_height - 1) * source_width + source_x.min(source_width - 1);
+            result.push(source[index as usize]);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::{degrees, vec3, Viewport};
+
+    use super::*;
+
+    fn camera(perspective: bool) -> Camera {
+        let (viewport, position, target, up) = (
+            Viewport::new_at_origo(4, 4),
+            vec3(0.0, 0.0, 5.0),
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+        );
+        if perspective {
+            Camera::new_perspective(viewport, position, target, up, degrees(45.0), 1.0, 11.0)
+        } else {
+            Camera::new_orthographic(viewport, position, target, up, 2.0, 1.0, 11.0)
+        }
+    }
+
+    #[test]
+    fn window_depth_becomes_the_distance_from_the_camera() {
+        let standard = DepthConfig { reverse_z: false };
+        let reverse = DepthConfig { reverse_z: true };
+        let perspective = camera(true);
+        assert!((linearize_depth(0.0, &perspective, standard) - 1.0).abs() < 1e-4);
+        assert!((linearize_depth(1.0, &perspective, reverse) - 1.0).abs() < 1e-4);
+        assert_eq!(linearize_depth(1.0, &perspective, standard), f32::INFINITY);
+        assert_eq!(linearize_depth(0.0, &perspective, reverse), f32::INFINITY);
+        // Perspective depth is spent mostly near the camera.
+        assert!(linearize_depth(0.5, &perspective, standard) < 6.0);
+        let orthographic = camera(false);
+        assert!((linearize_depth(0.5, &orthographic, standard) - 6.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn rows_flip_in_place() {
+        let mut pixels = [1, 2, 3, 4, 5, 6];
+        flip_rows(&mut pixels, 2);
+        assert_eq!(pixels, [5, 6, 3, 4, 1, 2]);
+        flip_rows(&mut pixels, 0);
+        assert_eq!(pixels, [5, 6, 3, 4, 1, 2]);
+    }
+
+    #[test]
+    fn supersampled_depth_picks_the_nearest_sample() {
+        let source = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
+        assert_eq!(resample_nearest(&source, (4, 2), (2, 1)), [6.0, 8.0]);
+        assert_eq!(resample_nearest(&source, (4, 2), (4, 2)), source);
+    }
+}
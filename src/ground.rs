@@ -0,0 +1,110 @@
+use three_d::{
+    degrees, vec3, AxisAlignedBoundingBox, Context, CpuMaterial, CpuMesh, DepthTest, Gm, Mat4,
+    Mesh, PhysicalMaterial, Srgba,
+};
+
+/// A large horizontal quad under the model that receives the shadows of the directional lights.
+pub struct GroundPlane {
+    plane: Gm<Mesh, PhysicalMaterial>,
+    height: f32,
+    size: f32,
+    enabled: bool,
+}
+
+impl GroundPlane {
+    /// A plane of `size` by `size` world units at `height`, centered on the origin.
+    pub fn new(context: &Context, height: f32, size: f32) -> Self {
+        let material = PhysicalMaterial::new_opaque(
+            context,
+            &CpuMaterial {
+                albedo: Srgba::new_opaque(200, 200, 200),
+                metallic: 0.0,
+                roughness: 1.0,
+                ..Default::default()
+            },
+        );
+        let mut ground = Self {
+            plane: Gm::new(Mesh::new(context, &CpuMesh::square()), material),
+            height,
+            size,
+            enabled: true,
+        };
+        ground.update_transformation();
+        ground
+    }
+
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    pub fn set_height(&mut self, height: f32) {
+        self.height = height;
+        self.update_transformation();
+    }
+
+    /// Moves the plane to touch the bottom of `aabb`.
+    pub fn place_below(&mut self, aabb: &AxisAlignedBoundingBox) {
+        if !aabb.is_empty() {
+            self.set_height(aabb.min().y);
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn set_depth_test(&mut self, depth_test: DepthTest) {
+        self.plane.material.render_states.depth_test = depth_test;
+    }
+
+    /// The renderable plane, or `None` while disabled.
+    pub fn object(&self) -> Option<&Gm<Mesh, PhysicalMaterial>> {
+        self.enabled.then_some(&self.plane)
+    }
+
+    fn update_transformation(&mut self) {
+        // `CpuMesh::square` spans [-1, 1] in the xy plane, facing +z.
+        self.plane.set_transformation(
+            Mat4::from_translation(vec3(0.0, self.height, 0.0))
+                * Mat4::from_angle_x(degrees(-90.0))
+                * Mat4::from_scale(self.size / 2.0),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::context::HasContext;
+    use three_d::Geometry;
+
+    use super::*;
+    use crate::testing::TestHarness;
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn plane_is_created_without_gl_errors_and_follows_the_model() {
+        let harness = TestHarness::new(8, 8).unwrap();
+        let context = &harness.context().context;
+        let mut ground = GroundPlane::new(context, 0.0, 10.0);
+        assert_eq!(unsafe { context.get_error() }, three_d::context::NO_ERROR);
+
+        let model = AxisAlignedBoundingBox::new_with_positions(&[
+            vec3(-1.0, -2.0, -1.0),
+            vec3(1.0, 1.0, 1.0),
+        ]);
+        ground.place_below(&model);
+        assert_eq!(ground.height(), -2.0);
+        let aabb = ground.object().unwrap().aabb();
+        assert!((aabb.min().y + 2.0).abs() < 1e-5 && (aabb.max().y + 2.0).abs() < 1e-5);
+        assert!((aabb.size().x - 10.0).abs() < 1e-4 && (aabb.size().z - 10.0).abs() < 1e-4);
+
+        ground.place_below(&AxisAlignedBoundingBox::EMPTY);
+        assert_eq!(ground.height(), -2.0);
+        ground.set_enabled(false);
+        assert!(ground.object().is_none());
+    }
+}
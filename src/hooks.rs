@@ -0,0 +1,122 @@
+//! Dioxus hooks that package the loading pieces of this crate.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use dioxus::prelude::*;
+use three_d::CpuModel;
+
+use crate::assets;
+
+/// The progress of a [use_model] load.
+#[derive(Clone, Debug)]
+pub enum ModelLoadState {
+    Loading,
+    /// The parsed model. It still has to be uploaded on the GL thread, e.g. with
+    /// [crate::part::ScenePart::from_cpu_model]; compare with [Arc::ptr_eq] to upload each
+    /// model only once.
+    Ready(Arc<CpuModel>),
+    Error(String),
+}
+
+impl ModelLoadState {
+    pub fn is_loading(&self) -> bool {
+        matches!(self, ModelLoadState::Loading)
+    }
+}
+
+/// Loads the model at `path`, resolved with [assets::resolve_asset_path], on a blocking worker
+/// thread and tracks the progress in the returned signal.
+///
+/// Changing `path` starts over with [ModelLoadState::Loading]. A load still in flight is
+/// abandoned, so after rapid changes only the latest path's model is ever reported; the same
+/// happens when the component unmounts. The worker itself can't be interrupted mid-parse, its
+/// result is just dropped.
+pub fn use_model(path: ReadOnlySignal<PathBuf>) -> Signal<ModelLoadState> {
+    let mut state = use_signal(|| ModelLoadState::Loading);
+    let _load = use_resource(move || async move {
+        let path = path();
+        state.set(ModelLoadState::Loading);
+        let loaded = tokio::task::spawn_blocking(move || assets::load_model(path)).await;
+        state.set(match loaded {
+            Ok(Ok(cpu_model)) => ModelLoadState::Ready(Arc::new(cpu_model)),
+            Ok(Err(error)) => ModelLoadState::Error(error.to_string()),
+            Err(error) => ModelLoadState::Error(format!("model loader crashed: {error}")),
+        });
+    });
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::path::Path;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Lets the test change the path [use_model] loads and see the states it went through.
+    #[derive(Clone, Default)]
+    struct Probe {
+        path: Rc<RefCell<Option<Signal<PathBuf>>>>,
+        states: Rc<RefCell<Vec<String>>>,
+    }
+
+    fn app(probe: Probe) -> Element {
+        let path = use_signal(|| fixture("missing.stl"));
+        probe.path.borrow_mut().get_or_insert(path);
+        let state = use_model(ReadOnlySignal::new(path));
+        let summary = match &*state.read() {
+            ModelLoadState::Loading => "loading".to_string(),
+            ModelLoadState::Ready(cpu_model) => format!("ready {}", cpu_model.geometries.len()),
+            ModelLoadState::Error(_) => "error".to_string(),
+        };
+        let mut states = probe.states.borrow_mut();
+        // Setting the state to loading again at the start of a load re-renders as well.
+        if states.last() != Some(&summary) {
+            states.push(summary);
+        }
+        rsx! {}
+    }
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    /// Runs `dom` until the last reported state is no longer loading.
+    async fn settle(dom: &mut VirtualDom, probe: &Probe) {
+        while probe.states.borrow().last().map(String::as_str) == Some("loading") {
+            tokio::time::timeout(Duration::from_secs(10), dom.wait_for_work())
+                .await
+                .expect("the load never finished");
+            dom.render_immediate(&mut dioxus::dioxus_core::NoOpMutations);
+        }
+    }
+
+    #[tokio::test]
+    async fn path_change_mid_flight_only_reports_the_latest_model() {
+        let probe = Probe::default();
+        let mut dom = VirtualDom::new_with_props(app, probe.clone());
+        dom.rebuild_in_place();
+        assert_eq!(*probe.states.borrow(), ["loading"]);
+
+        // Before the missing file's load could report its error.
+        let mut path = probe.path.borrow().unwrap();
+        dom.in_runtime(|| path.set(fixture("triangle.obj")));
+        settle(&mut dom, &probe).await;
+        let states = probe.states.borrow();
+        assert_eq!(*states, ["loading", "ready 1"]);
+    }
+
+    #[tokio::test]
+    async fn missing_file_ends_in_an_error() {
+        let probe = Probe::default();
+        let mut dom = VirtualDom::new_with_props(app, probe.clone());
+        dom.rebuild_in_place();
+        settle(&mut dom, &probe).await;
+        assert_eq!(*probe.states.borrow(), ["loading", "error"]);
+    }
+}
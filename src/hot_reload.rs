@@ -0,0 +1,173 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use three_d::CpuModel;
+
+/// How long the file has to stay untouched before it is reloaded. Editors often save in
+/// several steps (truncate, write, rename), each producing its own event.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Collapses a burst of change events into a single reload.
+#[derive(Clone, Copy, Debug)]
+pub struct Debouncer {
+    delay: Duration,
+    last_event: Option<Instant>,
+}
+
+impl Debouncer {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            last_event: None,
+        }
+    }
+
+    /// Records a change at `now`, restarting the quiet period.
+    pub fn event(&mut self, now: Instant) {
+        self.last_event = Some(now);
+    }
+
+    /// How long until a pending change fires, or `None` if nothing is pending.
+    pub fn remaining(&self, now: Instant) -> Option<Duration> {
+        self.last_event
+            .map(|last| self.delay.saturating_sub(now.duration_since(last)))
+    }
+
+    /// Returns `true` exactly once per burst, when the quiet period has passed.
+    pub fn fire(&mut self, now: Instant) -> bool {
+        if self.remaining(now) == Some(Duration::ZERO) {
+            self.last_event = None;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// A model file that is re-parsed on a worker thread whenever it changes on disk.
+///
+/// Only the CPU side is produced here. The render loop drains [WatchedModel::poll] and uploads
+/// the new model on the GL thread; when parsing fails the previous model stays in place and the
+/// error is kept in [WatchedModel::error].
+pub struct WatchedModel {
+    path: PathBuf,
+    _watcher: RecommendedWatcher,
+    reloads: Receiver<Result<CpuModel, String>>,
+    error: Option<String>,
+}
+
+impl WatchedModel {
+    pub fn new(path: impl AsRef<Path>) -> notify::Result<Self> {
+        Self::with_debounce(path, DEFAULT_DEBOUNCE)
+    }
+
+    pub fn with_debounce(path: impl AsRef<Path>, debounce: Duration) -> notify::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let (changes_sender, changes) = mpsc::channel::<()>();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if event.is_ok_and(|event| event.kind.is_modify() || event.kind.is_create()) {
+                    let _ = changes_sender.send(());
+                }
+            })?;
+        watcher.watch(&path, RecursiveMode::NonRecursive)?;
+
+        let (reload_sender, reloads) = mpsc::channel();
+        let worker_path = path.clone();
+        std::thread::spawn(move || {
+            let mut debouncer = Debouncer::new(debounce);
+            loop {
+                let timeout = debouncer
+                    .remaining(Instant::now())
+                    .unwrap_or(Duration::from_secs(3600));
+                match changes.recv_timeout(timeout) {
+                    Ok(()) => debouncer.event(Instant::now()),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+                if debouncer.fire(Instant::now()) {
+                    let model = three_d_asset::io::load_and_deserialize(&worker_path)
+                        .map_err(|e| e.to_string());
+                    if reload_sender.send(model).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            path,
+            _watcher: watcher,
+            reloads,
+            error: None,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The newest successfully parsed model since the last poll, if any.
+    pub fn poll(&mut self) -> Option<CpuModel> {
+        let mut latest = None;
+        while let Ok(result) = self.reloads.try_recv() {
+            match result {
+                Ok(model) => {
+                    self.error = None;
+                    latest = Some(model);
+                }
+                Err(error) => self.error = Some(error),
+            }
+        }
+        latest
+    }
+
+    /// The error of the last reload, cleared by the next successful one.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burst_of_changes_fires_once_after_the_quiet_period() {
+        let start = Instant::now();
+        let ms = |ms| start + Duration::from_millis(ms);
+        let mut debouncer = Debouncer::new(Duration::from_millis(100));
+        assert!(!debouncer.fire(start));
+        for at in [0, 30, 60, 90] {
+            debouncer.event(ms(at));
+            assert!(!debouncer.fire(ms(at)));
+        }
+        assert_eq!(
+            debouncer.remaining(ms(150)),
+            Some(Duration::from_millis(40))
+        );
+        assert!(!debouncer.fire(ms(150)));
+        assert!(debouncer.fire(ms(190)));
+        assert!(!debouncer.fire(ms(300)));
+        assert_eq!(debouncer.remaining(ms(300)), None);
+    }
+
+    #[test]
+    fn unparsable_change_is_reported_and_keeps_the_model() {
+        let path = std::env::temp_dir().join(format!("hot-reload-{}.glb", std::process::id()));
+        std::fs::write(&path, b"").unwrap();
+        let mut watched = WatchedModel::with_debounce(&path, Duration::from_millis(20)).unwrap();
+        std::fs::write(&path, b"not a model").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut reloaded = None;
+        while watched.error().is_none() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+            reloaded = reloaded.or(watched.poll());
+        }
+        let _ = std::fs::remove_file(&path);
+        assert!(reloaded.is_none());
+        assert!(watched.error().is_some());
+    }
+}
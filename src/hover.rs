@@ -0,0 +1,158 @@
+use std::time::{Duration, Instant};
+
+use three_d::{Event, PhysicalPoint};
+
+use crate::scene::Scene;
+use crate::windowed_context::WindowedContext;
+
+/// The default of [HoverPicker::new].
+pub const DEFAULT_HOVER_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Tracks which part is under the cursor as it moves, for highlighting it or showing a
+/// tooltip, see [Scene::pick_part].
+///
+/// Each pick renders and reads back, too costly for every mouse move, so the cursor is only
+/// recorded from the events and picked at most once per [HoverPicker::interval], always with
+/// the latest position. Leaving the canvas clears the hover right away, without a pick; with
+/// UI drawn over the canvas, call [HoverPicker::clear] while
+/// [crate::focus::InputFocus::has_pointer] is false, as its events don't reach the canvas.
+#[derive(Clone, Debug)]
+pub struct HoverPicker {
+    interval: Duration,
+    cursor: Option<PhysicalPoint>,
+    stale: bool,
+    last_pick: Option<Instant>,
+    hovered: Option<String>,
+    /// What the last [HoverPicker::update] reported.
+    reported: Option<String>,
+}
+
+impl Default for HoverPicker {
+    fn default() -> Self {
+        Self::new(DEFAULT_HOVER_INTERVAL)
+    }
+}
+
+impl HoverPicker {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            cursor: None,
+            stale: false,
+            last_pick: None,
+            hovered: None,
+            reported: None,
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// The shortest time between two picks, zero to pick on every update the cursor moved.
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    /// The id of the part under the cursor as of the last pick.
+    pub fn hovered(&self) -> Option<&str> {
+        self.hovered.as_deref()
+    }
+
+    /// Records cursor moves and the cursor leaving the window from `events`, before
+    /// [Scene::update] consumes them.
+    pub fn handle_events(&mut self, events: &[Event]) {
+        for event in events {
+            match event {
+                Event::MouseMotion { position, .. } => {
+                    self.cursor = Some(*position);
+                    self.stale = true;
+                }
+                Event::MouseLeave => self.clear(),
+                _ => {}
+            }
+        }
+    }
+
+    /// Forgets the cursor and the hovered part, until the cursor moves over the canvas again.
+    pub fn clear(&mut self) {
+        self.cursor = None;
+        self.stale = false;
+        self.hovered = None;
+    }
+
+    /// Picks again on the next update even if the cursor didn't move, for when the part under
+    /// it moved instead, as with the camera or an animation.
+    pub fn invalidate(&mut self) {
+        self.stale = self.cursor.is_some();
+    }
+
+    /// Picks the part under the cursor at `now` if it moved and the interval since the last
+    /// pick passed. Returns whether the hovered part changed, also by [HoverPicker::clear]
+    /// since the last update.
+    pub fn update(&mut self, scene: &Scene, context: &WindowedContext, now: Instant) -> bool {
+        let due = self
+            .last_pick
+            .is_none_or(|last_pick| now.saturating_duration_since(last_pick) >= self.interval);
+        if let Some(cursor) = self.cursor.filter(|_| self.stale && due) {
+            self.hovered = scene.pick_part(context, cursor).map(str::to_string);
+            self.stale = false;
+            self.last_pick = Some(now);
+        }
+        if self.hovered == self.reported {
+            return false;
+        }
+        self.reported = self.hovered.clone();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::Modifiers;
+
+    use super::*;
+    use crate::part::ScenePart;
+    use crate::scene::tests::{cubes, empty_scene};
+    use crate::testing::TestHarness;
+
+    fn move_to(x: f32, y: f32) -> Event {
+        Event::MouseMotion {
+            button: None,
+            delta: (0.0, 0.0),
+            position: PhysicalPoint { x, y },
+            modifiers: Modifiers::default(),
+            handled: false,
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn hovering_a_part_reports_it_until_the_cursor_moves_off() {
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        let mut scene = empty_scene();
+        scene.set_parts(ScenePart::from_cpu_model(context, &cubes(1)).unwrap());
+        let mut hover = HoverPicker::new(Duration::from_millis(50));
+        let start = Instant::now();
+
+        hover.handle_events(&[move_to(32.0, 32.0)]);
+        assert!(hover.update(&scene, context, start));
+        assert_eq!(hover.hovered(), Some("cube0"));
+
+        // Off the cube, but too soon after the last pick.
+        hover.handle_events(&[move_to(2.0, 2.0)]);
+        assert!(!hover.update(&scene, context, start + Duration::from_millis(10)));
+        assert_eq!(hover.hovered(), Some("cube0"));
+        assert!(hover.update(&scene, context, start + Duration::from_millis(60)));
+        assert_eq!(hover.hovered(), None);
+
+        hover.handle_events(&[move_to(32.0, 32.0)]);
+        assert!(hover.update(&scene, context, start + Duration::from_millis(120)));
+        assert_eq!(hover.hovered(), Some("cube0"));
+        // Leaving the canvas clears the hover without waiting for a pick.
+        hover.handle_events(&[Event::MouseLeave]);
+        assert!(hover.update(&scene, context, start + Duration::from_millis(130)));
+        assert_eq!(hover.hovered(), None);
+    }
+}
@@ -0,0 +1,181 @@
+use three_d::{Context, CpuMesh, Gm, InstancedMesh, Instances, Mat4, PhysicalMaterial, Srgba};
+
+/// One copy of an [InstancedModel].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Instance {
+    pub transform: Mat4,
+    /// Multiplies the material's base color.
+    pub color: Srgba,
+}
+
+impl Instance {
+    /// An untinted copy placed by `transform`.
+    pub fn new(transform: Mat4) -> Self {
+        Self {
+            transform,
+            color: Srgba::WHITE,
+        }
+    }
+}
+
+/// Many copies of one mesh and material, drawn with a single instanced draw call.
+///
+/// Edits only mark the instances as changed, they are uploaded once by the next
+/// [InstancedModel::object_mut] or [InstancedModel::sync]. The upload refills the existing
+/// instance buffers, so changing every instance each frame doesn't create new GL buffers.
+pub struct InstancedModel {
+    object: Gm<InstancedMesh, PhysicalMaterial>,
+    instances: Vec<Instance>,
+    triangles_per_instance: u64,
+    changed: bool,
+}
+
+impl InstancedModel {
+    pub fn new(
+        context: &Context,
+        mesh: &CpuMesh,
+        material: PhysicalMaterial,
+        instances: Vec<Instance>,
+    ) -> Self {
+        let geometry = InstancedMesh::new(context, &to_instances(&instances), mesh);
+        Self {
+            object: Gm::new(geometry, material),
+            instances,
+            triangles_per_instance: mesh.triangle_count() as u64,
+            changed: false,
+        }
+    }
+
+    pub fn instances(&self) -> &[Instance] {
+        &self.instances
+    }
+
+    /// Appends `instance` and returns its index.
+    pub fn add(&mut self, instance: Instance) -> usize {
+        self.instances.push(instance);
+        self.changed = true;
+        self.instances.len() - 1
+    }
+
+    /// Removes the instance at `index`, moving the later ones down by one.
+    pub fn remove(&mut self, index: usize) -> Option<Instance> {
+        if index >= self.instances.len() {
+            return None;
+        }
+        self.changed = true;
+        Some(self.instances.remove(index))
+    }
+
+    /// Replaces the instance at `index`. Returns `false` if there is none.
+    pub fn update(&mut self, index: usize, instance: Instance) -> bool {
+        match self.instances.get_mut(index) {
+            Some(existing) => {
+                if *existing != instance {
+                    *existing = instance;
+                    self.changed = true;
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_instances(&mut self, instances: Vec<Instance>) {
+        self.instances = instances;
+        self.changed = true;
+    }
+
+    /// Uploads the instances if they changed since the last upload.
+    pub fn sync(&mut self) {
+        if self.changed {
+            self.object
+                .geometry
+                .set_instances(&to_instances(&self.instances));
+            self.changed = false;
+        }
+    }
+
+    /// The drawable object, for [crate::scene::Scene::render_provided]. Call
+    /// [InstancedModel::sync] first, edits since then aren't drawn yet.
+    pub fn object(&self) -> &Gm<InstancedMesh, PhysicalMaterial> {
+        &self.object
+    }
+
+    /// The drawable object with the instances uploaded, e.g. to change the material.
+    pub fn object_mut(&mut self) -> &mut Gm<InstancedMesh, PhysicalMaterial> {
+        self.sync();
+        &mut self.object
+    }
+
+    /// The triangles drawn by the single instanced draw, across all instances.
+    pub fn triangles(&self) -> u64 {
+        self.triangles_per_instance * self.instances.len() as u64
+    }
+}
+
+fn to_instances(instances: &[Instance]) -> Instances {
+    Instances {
+        transformations: instances
+            .iter()
+            .map(|instance| instance.transform)
+            .collect(),
+        colors: Some(instances.iter().map(|instance| instance.color).collect()),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::{vec3, CpuMaterial, Object};
+
+    use super::*;
+    use crate::scene::tests::empty_scene;
+    use crate::scene::FrameContents;
+    use crate::testing::TestHarness;
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn hundred_instances_are_one_draw() {
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        let instances = (0..100)
+            .map(|i| Instance::new(Mat4::from_translation(vec3(i as f32 * 0.1, 0.0, -5.0))))
+            .collect();
+        let material = PhysicalMaterial::new_opaque(context, &CpuMaterial::default());
+        let mut model = InstancedModel::new(context, &CpuMesh::cube(), material, instances);
+        assert_eq!(model.triangles(), 1200);
+
+        let mut scene = empty_scene();
+        model.sync();
+        scene.render_provided(context, harness.viewport(), |_| FrameContents {
+            objects: vec![model.object() as &dyn Object],
+            lights: Vec::new(),
+        });
+        assert_eq!(scene.stats().draw_calls, 1);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn edits_are_uploaded_on_sync() {
+        let harness = TestHarness::new(8, 8).unwrap();
+        let context = harness.context();
+        let material = PhysicalMaterial::new_opaque(context, &CpuMaterial::default());
+        let instances = vec![Instance::new(Mat4::from_scale(1.0)); 3];
+        let mut model = InstancedModel::new(context, &CpuMesh::cube(), material, instances);
+        assert!(model.update(1, Instance::new(Mat4::from_scale(1.0))));
+        assert!(!model.changed);
+        assert!(!model.update(3, Instance::new(Mat4::from_scale(1.0))));
+
+        assert_eq!(model.add(Instance::new(Mat4::from_scale(2.0))), 3);
+        assert_eq!(model.add(Instance::new(Mat4::from_scale(3.0))), 4);
+        assert!(model.remove(0).is_some());
+        assert!(model.remove(4).is_none());
+        assert_eq!(model.object().geometry.instance_count(), 3);
+        assert_eq!(model.object_mut().geometry.instance_count(), 4);
+        assert_eq!(model.instances()[2].transform, Mat4::from_scale(2.0));
+        model.set_instances(Vec::new());
+        model.sync();
+        assert_eq!(model.object().geometry.instance_count(), 0);
+        assert_eq!(model.triangles(), 0);
+    }
+}
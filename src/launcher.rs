@@ -0,0 +1,177 @@
+use std::sync::Mutex;
+use std::time::Instant;
+
+use dioxus::desktop::tao::event::{Event as TaoEvent, WindowEvent};
+use dioxus::desktop::tao::event_loop::{ControlFlow, EventLoop};
+use dioxus::desktop::tao::window::{Window, WindowBuilder};
+use three_d::Event;
+
+use crate::error::ContextError;
+use crate::events::EventTranslator;
+use crate::scheduler::{RenderMode, RenderScheduler};
+use crate::windowed_context::{WindowedContext, WindowedContextBuilder};
+
+/// Runs `render` in a plain tao window, without Dioxus or a webview.
+///
+/// Useful for benchmarking the graphics path in isolation. `render` is called once per frame
+/// with the context and the input events since the last frame, and buffers are swapped after
+/// it returns. Only returns if the context cannot be created.
+pub fn launch_windowed(
+    window: WindowBuilder,
+    context: WindowedContextBuilder,
+    mut render: impl FnMut(&WindowedContext, &mut Vec<Event>) + 'static,
+) -> Result<(), ContextError> {
+    let event_loop = EventLoop::new();
+    let mut launched = Launched::new(&event_loop, window, context)?;
+
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Poll;
+        match event {
+            TaoEvent::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => *control_flow = ControlFlow::Exit,
+            TaoEvent::WindowEvent { event, .. } => launched.window_event(&event),
+            TaoEvent::MainEventsCleared => launched.window.request_redraw(),
+            TaoEvent::RedrawRequested(_) => launched.frame(&mut render),
+            _ => {}
+        }
+    })
+}
+
+/// Like [launch_windowed], but paces frames with `scheduler` instead of polling, see
+/// [RenderScheduler::control_flow].
+///
+/// `render` returns whether the scene still changes on its own, e.g. because an animation is
+/// playing; in [RenderMode::OnDemand] the loop then keeps rendering, otherwise it sleeps until
+/// the next input event or [crate::scheduler::RedrawHandle::request]. Take redraw handles from
+/// `scheduler` before passing it in, they wake the loop from any thread.
+pub fn launch_scheduled(
+    window: WindowBuilder,
+    context: WindowedContextBuilder,
+    mut scheduler: RenderScheduler,
+    mut render: impl FnMut(&WindowedContext, &mut Vec<Event>) -> bool + 'static,
+) -> Result<(), ContextError> {
+    let event_loop = EventLoop::new();
+    let mut launched = Launched::new(&event_loop, window, context)?;
+    let proxy = Mutex::new(event_loop.create_proxy());
+    scheduler.set_waker(move || {
+        let _ = proxy
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .send_event(());
+    });
+    let mut needs_redraw = true;
+    let mut last_frame = Instant::now();
+
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            TaoEvent::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => {
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+            TaoEvent::WindowEvent { event, .. } => {
+                scheduler.handle_window_event(&event);
+                launched.window_event(&event);
+                needs_redraw = true;
+                scheduler.track_activity(true, Instant::now());
+            }
+            TaoEvent::MainEventsCleared => {
+                let due = match scheduler.effective_mode() {
+                    RenderMode::Continuous { .. } => scheduler
+                        .next_frame(last_frame)
+                        .is_none_or(|next_frame| Instant::now() >= next_frame),
+                    RenderMode::OnDemand => needs_redraw || scheduler.redraw_requested(),
+                };
+                if due {
+                    launched.window.request_redraw();
+                }
+            }
+            TaoEvent::RedrawRequested(_) => {
+                scheduler.take_redraw_request();
+                needs_redraw = launched.frame(&mut render);
+                last_frame = Instant::now();
+                scheduler.track_activity(needs_redraw, last_frame);
+            }
+            _ => {}
+        }
+        *control_flow = scheduler.control_flow(needs_redraw, last_frame);
+    })
+}
+
+/// The window and context a launcher drives, with the input collected since the last frame.
+struct Launched {
+    window: Window,
+    context: WindowedContext,
+    translator: EventTranslator,
+    events: Vec<Event>,
+}
+
+impl Launched {
+    fn new<T>(
+        event_loop: &EventLoop<T>,
+        window: WindowBuilder,
+        context: WindowedContextBuilder,
+    ) -> Result<Self, ContextError> {
+        let window = window
+            .build(event_loop)
+            .expect("failed to create the launcher window");
+        let context = context.build_from_tao_window(&window)?;
+        let translator = EventTranslator::new(window.inner_size(), window.scale_factor());
+        Ok(Self {
+            window,
+            context,
+            translator,
+            events: Vec::new(),
+        })
+    }
+
+    fn window_event(&mut self, event: &WindowEvent<'_>) {
+        if let WindowEvent::Resized(size) = event {
+            self.context.resize(*size);
+        }
+        self.translator.handle(event, &mut self.events);
+    }
+
+    /// Runs `render` with the input since the last frame and presents the result.
+    fn frame<R>(&mut self, render: impl FnOnce(&WindowedContext, &mut Vec<Event>) -> R) -> R {
+        let result = render(&self.context, &mut self.events);
+        self.events.clear();
+        if let Err(_error) = self.context.swap_buffers() {
+            log_warn!(error = %_error, "failed to present frame");
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dioxus::desktop::tao::dpi::PhysicalSize;
+
+    use super::*;
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn sets_up_a_context_and_runs_a_frame() {
+        let event_loop = crate::testing::new_event_loop();
+        let window = WindowBuilder::new()
+            .with_visible(false)
+            .with_inner_size(PhysicalSize::new(32, 32));
+        let context = WindowedContextBuilder::new()
+            .hardware_acceleration(three_d::HardwareAcceleration::Preferred);
+        let mut launched = Launched::new(&event_loop, window, context).unwrap();
+        launched.window_event(&WindowEvent::Resized(PhysicalSize::new(16, 8)));
+        launched.window_event(&WindowEvent::Focused(true));
+        let mut frames = 0;
+        let size = launched.frame(|context, _events| {
+            frames += 1;
+            context.size()
+        });
+        assert_eq!(frames, 1);
+        assert_eq!(size, (16, 8));
+        assert!(launched.events.is_empty());
+    }
+}
@@ -0,0 +1,53 @@
+#[macro_use]
+mod trace;
+
+pub mod animation;
+pub mod assets;
+pub mod background;
+pub mod batch;
+pub mod bloom;
+pub mod camera;
+pub mod clear;
+pub mod clip;
+pub mod composite;
+pub mod controls;
+pub mod debug_view;
+pub mod depth;
+pub mod error;
+pub mod events;
+pub mod focus;
+pub mod gbuffer;
+pub mod ground;
+pub mod hooks;
+pub mod hot_reload;
+pub mod hover;
+pub mod instancing;
+#[cfg(feature = "launcher")]
+pub mod launcher;
+pub mod lights;
+pub mod loader;
+pub mod lod;
+pub mod outline;
+pub mod overlay;
+pub mod part;
+pub mod passes;
+pub mod picking;
+pub mod pointer_grab;
+pub mod post;
+pub mod raster;
+pub mod reference;
+pub mod render_texture;
+pub mod resize;
+pub mod scene;
+pub mod scheduler;
+pub mod shortcuts;
+pub mod stats;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod textures;
+#[cfg(feature = "thumbnail")]
+pub mod thumbnail;
+pub mod windowed_context;
+pub mod windows;
+#[cfg(target_os = "linux")]
+mod xlib_errors;
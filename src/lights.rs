@@ -0,0 +1,278 @@
+use three_d::context::{self, HasContext};
+use three_d::{AmbientLight, Context, DirectionalLight, Geometry, Light, Srgba};
+
+/// A light owned by [Lights], kept as its concrete type so it can be reconfigured later.
+pub enum SceneLight {
+    Ambient(AmbientLight),
+    Directional(DirectionalLight),
+}
+
+impl SceneLight {
+    pub fn as_light(&self) -> &dyn Light {
+        match self {
+            SceneLight::Ambient(light) => light,
+            SceneLight::Directional(light) => light,
+        }
+    }
+
+    pub fn intensity(&self) -> f32 {
+        match self {
+            SceneLight::Ambient(light) => light.intensity,
+            SceneLight::Directional(light) => light.intensity,
+        }
+    }
+
+    /// Negative intensities are raised to zero, they would darken the scene instead.
+    pub fn set_intensity(&mut self, intensity: f32) {
+        let intensity = intensity.max(0.0);
+        match self {
+            SceneLight::Ambient(light) => light.intensity = intensity,
+            SceneLight::Directional(light) => light.intensity = intensity,
+        }
+    }
+
+    pub fn color(&self) -> Srgba {
+        match self {
+            SceneLight::Ambient(light) => light.color,
+            SceneLight::Directional(light) => light.color,
+        }
+    }
+
+    pub fn set_color(&mut self, color: Srgba) {
+        match self {
+            SceneLight::Ambient(light) => light.color = color,
+            SceneLight::Directional(light) => light.color = color,
+        }
+    }
+}
+
+impl From<AmbientLight> for SceneLight {
+    fn from(light: AmbientLight) -> Self {
+        SceneLight::Ambient(light)
+    }
+}
+
+impl From<DirectionalLight> for SceneLight {
+    fn from(light: DirectionalLight) -> Self {
+        SceneLight::Directional(light)
+    }
+}
+
+/// Shadow map configuration of a directional light.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShadowSettings {
+    /// Width and height of the shadow map in texels.
+    pub texture_size: u32,
+    /// Depth offset scaled by the caster's depth slope (`glPolygonOffset` factor). Raise it to
+    /// fight shadow acne on surfaces at grazing angles to the light.
+    pub slope_bias: f32,
+    /// Constant depth offset (`glPolygonOffset` units). Too much detaches shadows from their
+    /// casters ("peter-panning").
+    pub constant_bias: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            texture_size: 1024,
+            slope_bias: 1.5,
+            constant_bias: 2.0,
+        }
+    }
+}
+
+struct Entry {
+    light: SceneLight,
+    shadows: Option<ShadowSettings>,
+}
+
+/// The lights of a scene, addressed by the index [Lights::add] returns.
+#[derive(Default)]
+pub struct Lights {
+    lights: Vec<Entry>,
+}
+
+impl Lights {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a light and returns its index.
+    pub fn add(&mut self, light: impl Into<SceneLight>) -> usize {
+        self.lights.push(Entry {
+            light: light.into(),
+            shadows: None,
+        });
+        self.lights.len() - 1
+    }
+
+    pub fn get(&self, index: usize) -> Option<&SceneLight> {
+        self.lights.get(index).map(|entry| &entry.light)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut SceneLight> {
+        self.lights.get_mut(index).map(|entry| &mut entry.light)
+    }
+
+    /// Sets the intensity of the light at `index`, see [SceneLight::set_intensity]. Returns
+    /// `false` if there is no light at `index`.
+    ///
+    /// Lights are only read when rendering, so through [crate::scene::Scene::lights_mut] the
+    /// change shows from the next frame on.
+    pub fn set_intensity(&mut self, index: usize, intensity: f32) -> bool {
+        let Some(light) = self.get_mut(index) else {
+            return false;
+        };
+        light.set_intensity(intensity);
+        true
+    }
+
+    /// Sets the color of the light at `index`. Returns `false` if there is no light at
+    /// `index`.
+    pub fn set_color(&mut self, index: usize, color: Srgba) -> bool {
+        let Some(light) = self.get_mut(index) else {
+            return false;
+        };
+        light.set_color(color);
+        true
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SceneLight> {
+        self.lights.iter().map(|entry| &entry.light)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    /// The lights in the form [three_d::RenderTarget::render] takes them.
+    pub fn as_refs(&self) -> Vec<&dyn Light> {
+        self.lights
+            .iter()
+            .map(|entry| entry.light.as_light())
+            .collect()
+    }
+
+    /// Makes the directional light at `index` cast shadows. Returns `false` if there is no
+    /// directional light at `index`.
+    pub fn enable_shadows(&mut self, index: usize, settings: ShadowSettings) -> bool {
+        match self.lights.get_mut(index) {
+            Some(
+                entry @ Entry {
+                    light: SceneLight::Directional(_),
+                    ..
+                },
+            ) => {
+                entry.shadows = Some(settings);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn disable_shadows(&mut self, index: usize) {
+        if let Some(entry) = self.lights.get_mut(index) {
+            entry.shadows = None;
+            if let SceneLight::Directional(light) = &mut entry.light {
+                light.clear_shadow_map();
+            }
+        }
+    }
+
+    pub fn shadows(&self, index: usize) -> Option<ShadowSettings> {
+        self.lights.get(index).and_then(|entry| entry.shadows)
+    }
+
+    /// Renders the shadow maps of the shadow casting lights from `casters`.
+    ///
+    /// `three_d` fits each light's orthographic shadow frustum to the combined bounds of
+    /// `casters`, so passing exactly the model keeps all of it covered at the best resolution.
+    /// Geometry that only receives shadows, like a ground plane, should not be passed: it would
+    /// blow up the frustum and waste shadow map texels. Without any casters the shadow maps are
+    /// cleared, there would be nothing to fit the frustum to.
+    pub fn generate_shadow_maps<G: Geometry>(
+        &mut self,
+        context: &Context,
+        casters: impl IntoIterator<Item = G> + Clone,
+    ) {
+        let no_casters = casters.clone().into_iter().next().is_none();
+        for entry in self.lights.iter_mut() {
+            let (SceneLight::Directional(light), Some(shadows)) = (&mut entry.light, entry.shadows)
+            else {
+                continue;
+            };
+            if no_casters {
+                light.clear_shadow_map();
+                continue;
+            }
+            // three_d doesn't expose a shadow bias, but it leaves the polygon offset alone while
+            // rendering the depth pass, so it can be applied around it.
+            unsafe {
+                context.enable(context::POLYGON_OFFSET_FILL);
+                context.polygon_offset(shadows.slope_bias, shadows.constant_bias);
+            }
+            light.generate_shadow_map(shadows.texture_size, casters.clone());
+            unsafe { context.disable(context::POLYGON_OFFSET_FILL) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::{vec3, Srgba};
+
+    use super::*;
+    use crate::testing::TestHarness;
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn only_directional_lights_cast_shadows() {
+        let harness = TestHarness::new(8, 8).unwrap();
+        let context = &harness.context().context;
+        let mut lights = Lights::new();
+        let ambient = lights.add(AmbientLight::new(context, 0.2, Srgba::WHITE));
+        let sun = lights.add(DirectionalLight::new(
+            context,
+            1.0,
+            Srgba::WHITE,
+            &vec3(0.0, -1.0, 0.0),
+        ));
+        let settings = ShadowSettings {
+            texture_size: 512,
+            ..Default::default()
+        };
+        assert!(!lights.enable_shadows(ambient, settings));
+        assert!(!lights.enable_shadows(2, settings));
+        assert!(lights.enable_shadows(sun, settings));
+        assert_eq!(lights.shadows(ambient), None);
+        assert_eq!(lights.shadows(sun), Some(settings));
+        lights.disable_shadows(sun);
+        assert_eq!(lights.shadows(sun), None);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn brighter_ambient_light_renders_brighter_from_the_next_frame() {
+        let harness = TestHarness::new(64, 64).unwrap();
+        let mut scene = crate::scene::tests::shadow_scene(harness.context());
+        let lights = scene.lights_mut();
+        assert!(lights.set_intensity(1, 0.0));
+        assert!(!lights.set_intensity(2, 1.0));
+        let dim = harness.render_scene(&mut scene, 0.0).pixel(32, 32);
+
+        let lights = scene.lights_mut();
+        assert!(lights.set_intensity(0, 1.0));
+        assert_eq!(lights.get(0).map(SceneLight::intensity), Some(1.0));
+        let bright = harness.render_scene(&mut scene, 0.0).pixel(32, 32);
+        assert!(bright[0] > dim[0], "{bright:?} vs {dim:?}");
+
+        let lights = scene.lights_mut();
+        assert!(lights.set_color(0, Srgba::RED));
+        let red = harness.render_scene(&mut scene, 0.0).pixel(32, 32);
+        assert!(red[0] > red[1] && red[0] > red[2], "{red:?}");
+
+        // Negative intensities would darken the scene.
+        scene.lights_mut().set_intensity(0, -1.0);
+        assert_eq!(scene.lights().get(0).map(SceneLight::intensity), Some(0.0));
+    }
+}
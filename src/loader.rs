@@ -0,0 +1,224 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use three_d::{CpuModel, CpuTexture};
+
+use crate::assets;
+use crate::error::LoadError;
+
+/// Identifies a load queued on an [AssetLoader], to match it with its result.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LoadToken(u64);
+
+/// What an [AssetLoader] decoded, still on the CPU.
+#[derive(Debug)]
+pub enum LoadedAsset {
+    Model(CpuModel),
+    Texture(CpuTexture),
+}
+
+/// Returned by [AssetLoader::queue_load] while [AssetLoader::max_pending] loads are pending.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueueFull {
+    pub path: PathBuf,
+}
+
+impl std::fmt::Display for QueueFull {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "asset load queue is full, {} not queued",
+            self.path.display()
+        )
+    }
+}
+
+impl std::error::Error for QueueFull {}
+
+enum JobKind {
+    Model,
+    Texture,
+}
+
+struct Job {
+    token: LoadToken,
+    path: PathBuf,
+    kind: JobKind,
+}
+
+/// Decodes models and textures on a small pool of worker threads.
+///
+/// Only the CPU side is produced here, like [crate::hot_reload::WatchedModel] does: the
+/// render loop drains [AssetLoader::poll_completed] and uploads on the GL thread, e.g. with
+/// [crate::textures::upload_parts].
+///
+/// Loads count as pending from [AssetLoader::queue_load] until their result is drained, and
+/// at most `max_pending` can be, so a caller queuing faster than the workers decode, or not
+/// draining, gets [QueueFull] back instead of piling up decoded models in memory.
+///
+/// Dropping the loader drops the queue; each worker stops after at most one more load.
+pub struct AssetLoader {
+    jobs: Sender<Job>,
+    completed: Receiver<(LoadToken, Result<LoadedAsset, LoadError>)>,
+    threads: usize,
+    max_pending: usize,
+    pending: usize,
+    next_token: u64,
+}
+
+impl Default for AssetLoader {
+    /// Up to four threads, fewer on machines with fewer cores, and 64 pending loads.
+    fn default() -> Self {
+        let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+        Self::new(threads.min(4), 64)
+    }
+}
+
+impl AssetLoader {
+    /// Starts `threads` workers, at least one, accepting at most `max_pending` loads at a time,
+    /// also at least one.
+    pub fn new(threads: usize, max_pending: usize) -> Self {
+        let threads = threads.max(1);
+        let (job_sender, jobs) = mpsc::channel::<Job>();
+        let jobs = Arc::new(Mutex::new(jobs));
+        let (completed_sender, completed) = mpsc::channel();
+        for _ in 0..threads {
+            let jobs = jobs.clone();
+            let completed_sender = completed_sender.clone();
+            std::thread::spawn(move || loop {
+                // The lock is only held while waiting for a job, not while decoding it.
+                let job = jobs.lock().unwrap_or_else(|e| e.into_inner()).recv();
+                let Ok(job) = job else {
+                    return;
+                };
+                log_debug!(path = %job.path.display(), "decoding asset");
+                let result = match job.kind {
+                    JobKind::Model => assets::load_model(&job.path).map(LoadedAsset::Model),
+                    JobKind::Texture => load_texture(&job.path).map(LoadedAsset::Texture),
+                };
+                if completed_sender.send((job.token, result)).is_err() {
+                    return;
+                }
+            });
+        }
+        Self {
+            jobs: job_sender,
+            completed,
+            threads,
+            max_pending: max_pending.max(1),
+            pending: 0,
+            next_token: 0,
+        }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    pub fn max_pending(&self) -> usize {
+        self.max_pending
+    }
+
+    /// Loads queued or decoded but not yet drained with [AssetLoader::poll_completed].
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Queues the model at `path` for [assets::load_model] on a worker.
+    pub fn queue_load(&mut self, path: impl AsRef<Path>) -> Result<LoadToken, QueueFull> {
+        self.queue(path.as_ref(), JobKind::Model)
+    }
+
+    /// Queues the image at `path`, resolved like a model path, see
+    /// [assets::resolve_asset_path].
+    pub fn queue_texture_load(&mut self, path: impl AsRef<Path>) -> Result<LoadToken, QueueFull> {
+        self.queue(path.as_ref(), JobKind::Texture)
+    }
+
+    fn queue(&mut self, path: &Path, kind: JobKind) -> Result<LoadToken, QueueFull> {
+        let path = path.to_path_buf();
+        if self.pending >= self.max_pending {
+            return Err(QueueFull { path });
+        }
+        let token = LoadToken(self.next_token);
+        let job = Job { token, path, kind };
+        // Workers only stop early by panicking in a decoder, and only if all of them did can
+        // this fail; the load then stays pending, like one a panicking worker was on.
+        let _ = self.jobs.send(job);
+        self.next_token += 1;
+        self.pending += 1;
+        Ok(token)
+    }
+
+    /// The loads that finished since the last call, in the order they finished.
+    pub fn poll_completed(&mut self) -> Vec<(LoadToken, Result<LoadedAsset, LoadError>)> {
+        let completed: Vec<_> = self.completed.try_iter().collect();
+        self.pending -= completed.len();
+        completed
+    }
+}
+
+fn load_texture(path: &Path) -> Result<CpuTexture, LoadError> {
+    let path = assets::resolve_asset_path(path)?;
+    Ok(three_d_asset::io::load_and_deserialize(&path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::assets::tests::fixtures;
+
+    /// Polls `loader` until nothing is pending, for at most a few seconds.
+    fn drain(loader: &mut AssetLoader) -> HashMap<LoadToken, Result<LoadedAsset, LoadError>> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut completed = HashMap::new();
+        while loader.pending() > 0 {
+            assert!(Instant::now() < deadline, "loads did not complete");
+            completed.extend(loader.poll_completed());
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        completed
+    }
+
+    #[test]
+    fn queued_fixtures_all_complete() {
+        let mut loader = AssetLoader::new(2, 3);
+        let stl = loader.queue_load(fixtures().join("triangle.stl")).unwrap();
+        let obj = loader.queue_load(fixtures().join("quad/quad.obj")).unwrap();
+        let missing = loader.queue_load(fixtures().join("missing.glb")).unwrap();
+        assert_eq!(loader.pending(), 3);
+
+        let mut completed = drain(&mut loader);
+        assert_eq!(completed.len(), 3);
+        for token in [stl, obj] {
+            let Some(Ok(LoadedAsset::Model(cpu_model))) = completed.remove(&token) else {
+                panic!("expected a model for {token:?}");
+            };
+            assert_eq!(cpu_model.geometries.len(), 1);
+        }
+        assert!(matches!(
+            completed.remove(&missing),
+            Some(Err(LoadError::NotFound { .. }))
+        ));
+    }
+
+    #[test]
+    fn loads_beyond_the_limit_are_refused_until_drained() {
+        let mut loader = AssetLoader::new(1, 2);
+        let path = fixtures().join("triangle.stl");
+        let first = loader.queue_load(&path).unwrap();
+        let second = loader.queue_load(&path).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(
+            loader.queue_load(&path),
+            Err(QueueFull { path: path.clone() })
+        );
+
+        assert_eq!(drain(&mut loader).len(), 2);
+        assert!(loader.queue_load(&path).is_ok());
+    }
+}
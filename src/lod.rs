@@ -0,0 +1,150 @@
+use three_d::{Camera, Geometry, InnerSpace};
+
+use crate::part::ScenePart;
+
+/// The default of [Lod::set_hysteresis].
+pub const DEFAULT_HYSTERESIS: f32 = 0.1;
+
+/// Versions of one model at decreasing detail, of which only the one fitting the camera
+/// distance is drawn, see [crate::scene::Scene::set_lods].
+///
+/// Each level after the first is used from its distance on, measured from the camera to the
+/// center of the level drawn last. So a level is only switched to once the camera is
+/// [Lod::hysteresis] past its threshold, and only switched away from once it is that far back
+/// on the other side; the camera hovering right at a threshold doesn't flicker between two.
+pub struct Lod {
+    /// Sorted by distance, the first at distance zero.
+    levels: Vec<(f32, ScenePart)>,
+    hysteresis: f32,
+    current: usize,
+}
+
+impl Lod {
+    /// A LOD with only `finest`, drawn at all distances until coarser levels are added.
+    pub fn new(finest: ScenePart) -> Self {
+        Self {
+            levels: vec![(0.0, finest)],
+            hysteresis: DEFAULT_HYSTERESIS,
+            current: 0,
+        }
+    }
+
+    /// Adds `part` to be drawn from `distance` on, until the next farther level's distance.
+    /// Levels can be added in any order.
+    pub fn with_level(mut self, distance: f32, part: ScenePart) -> Self {
+        let distance = distance.max(0.0);
+        let index = self.levels.partition_point(|(level, _)| *level <= distance);
+        self.levels.insert(index, (distance, part));
+        self
+    }
+
+    /// How far past a threshold, as a fraction of its distance, the camera has to move before
+    /// the level switches.
+    pub fn hysteresis(&self) -> f32 {
+        self.hysteresis
+    }
+
+    /// Clamped to `0.0..1.0`, defaults to [DEFAULT_HYSTERESIS].
+    pub fn set_hysteresis(&mut self, hysteresis: f32) {
+        self.hysteresis = hysteresis.clamp(0.0, 0.99);
+    }
+
+    /// The levels with the distance each is used from on, finest first.
+    pub fn levels(&self) -> impl Iterator<Item = (f32, &ScenePart)> {
+        self.levels.iter().map(|(distance, part)| (*distance, part))
+    }
+
+    pub fn levels_mut(&mut self) -> impl Iterator<Item = &mut ScenePart> {
+        self.levels.iter_mut().map(|(_, part)| part)
+    }
+
+    /// The index of the level picked by the last [Lod::select], the finest before the first.
+    pub fn level(&self) -> usize {
+        self.current
+    }
+
+    pub fn selected(&self) -> &ScenePart {
+        &self.levels[self.current].1
+    }
+
+    pub(crate) fn selected_mut(&mut self) -> &mut ScenePart {
+        &mut self.levels[self.current].1
+    }
+
+    /// Picks the level for the distance of `camera`. Returns whether it changed.
+    pub fn select(&mut self, camera: &Camera) -> bool {
+        let center = self.selected().model().aabb().center();
+        let distance = (*camera.position() - center).magnitude();
+        let mut level = self.current;
+        while level + 1 < self.levels.len()
+            && distance > self.levels[level + 1].0 * (1.0 + self.hysteresis)
+        {
+            level += 1;
+        }
+        while level > 0 && distance < self.levels[level].0 * (1.0 - self.hysteresis) {
+            level -= 1;
+        }
+        let changed = level != self.current;
+        if changed {
+            log_trace!(
+                part = self.selected().id(),
+                level,
+                distance,
+                "LOD level changed"
+            );
+        }
+        self.current = level;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::{degrees, vec3, Viewport};
+
+    use super::*;
+    use crate::scene::tests::cubes;
+    use crate::testing::TestHarness;
+
+    fn camera_at(distance: f32) -> Camera {
+        Camera::new_perspective(
+            Viewport::new_at_origo(8, 8),
+            vec3(0.0, 0.0, distance),
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            degrees(45.0),
+            0.1,
+            100.0,
+        )
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn levels_switch_past_the_thresholds_with_hysteresis() {
+        let harness = TestHarness::new(8, 8).unwrap();
+        let mut parts = ScenePart::from_cpu_model(harness.context(), &cubes(3)).unwrap();
+        let (coarse, medium) = (parts.pop().unwrap(), parts.pop().unwrap());
+        // Added out of order, they are sorted by distance.
+        let mut lod = Lod::new(parts.pop().unwrap())
+            .with_level(20.0, coarse)
+            .with_level(10.0, medium);
+        let distances: Vec<f32> = lod.levels().map(|(distance, _)| distance).collect();
+        assert_eq!(distances, [0.0, 10.0, 20.0]);
+        assert_eq!(lod.hysteresis(), DEFAULT_HYSTERESIS);
+
+        let mut level_at = |distance| {
+            lod.select(&camera_at(distance));
+            lod.level()
+        };
+        // Within 10% of the threshold at 10 the level holds, whichever side it came from.
+        assert_eq!(level_at(5.0), 0);
+        assert_eq!(level_at(10.5), 0);
+        assert_eq!(level_at(11.5), 1);
+        assert_eq!(level_at(9.5), 1);
+        assert_eq!(level_at(8.5), 0);
+        // A jump crosses several levels at once.
+        assert_eq!(level_at(30.0), 2);
+        assert_eq!(level_at(1.0), 0);
+        assert_eq!(lod.selected().id(), "cube0");
+    }
+}
@@ -1,13 +1,28 @@
-mod windowed_context;
-
-use std::time::Instant;
+use crate::manganis;
 use dioxus::desktop::tao::event::Event as WryEvent;
 use dioxus::desktop::tao::window::WindowBuilder;
-use dioxus::desktop::{use_window, use_wry_event_handler, window};
+use dioxus::desktop::{use_wry_event_handler, window};
 use dioxus::prelude::*;
-use crate::manganis;
+use dioxus_three_d::assets::{self, NormalOptions, TangentMode};
+use dioxus_three_d::controls::{Controls, Inertia};
+use dioxus_three_d::debug_view::DebugView;
+use dioxus_three_d::error::describe_error;
+use dioxus_three_d::events::EventTranslator;
+use dioxus_three_d::focus::{InputFocus, UiRect};
+use dioxus_three_d::hot_reload::WatchedModel;
+use dioxus_three_d::hover::HoverPicker;
+use dioxus_three_d::lights::Lights;
+use dioxus_three_d::part::{PartAnimation, ScenePart};
+use dioxus_three_d::resize::ResizeDebouncer;
+use dioxus_three_d::scene::Scene;
+use dioxus_three_d::scheduler::RenderScheduler;
+use dioxus_three_d::shortcuts::SceneCommand;
+use dioxus_three_d::textures::{self, TextureOptions, TextureQuality};
+use dioxus_three_d::windowed_context::{self, EnvOverrides, WindowMode, WindowedContextBuilder};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use three_d::{
-    degrees, radians, vec3, AmbientLight, Camera, ClearState, CpuModel, Geometry, Light, Mat4, OrbitControl, RenderTarget, Srgba, SurfaceSettings, Viewport, PhysicalMaterial, Model, ModelPart
+    degrees, radians, vec2, vec3, AmbientLight, Camera, ClearState, CpuModel, Mat4, Srgba, Viewport,
 };
 
 // Urls are relative to your Cargo.toml file
@@ -22,21 +37,52 @@ fn main() {
         .launch(app);
 }
 
+const MODEL_PATH: &str = "DamagedHelmet.glb";
+
+/// Uploads the parts of `cpu_model` and sets up their spinning animation.
+fn upload_model(
+    context: &three_d::Context,
+    mut cpu_model: CpuModel,
+) -> Result<Vec<ScenePart>, three_d::RendererError> {
+    assets::compute_normals(&mut cpu_model, &NormalOptions::default());
+    assets::compute_tangents(&mut cpu_model, TangentMode::Auto);
+    let options = TextureOptions::from(TextureQuality::High);
+    let mut parts = textures::upload_parts(context, &mut cpu_model, options)?;
+    let spin: PartAnimation = Arc::new(|time| Mat4::from_angle_z(radians(time * 0.0005)));
+    for part in &mut parts {
+        part.set_animation(Some(spin.clone()), 0.0);
+    }
+    Ok(parts)
+}
+
 struct GraphicsResources {
     context: windowed_context::WindowedContext,
-    camera: Camera,
-    control: OrbitControl,
-    model: ModelPart<PhysicalMaterial>,
-    lights: Vec<Box<dyn Light>>,
-    time_since_start: Instant,
+    scene: Scene,
+    scheduler: RenderScheduler,
+    events: EventTranslator,
+    focus: InputFocus,
+    pending_events: Vec<three_d::Event>,
+    hover: HoverPicker,
+    resize: ResizeDebouncer,
+    watched_model: Option<WatchedModel>,
+    last_frame: Instant,
 }
 
 fn app() -> Element {
+    let mut model_error = use_signal(|| None::<String>);
+    let mut hovered_part = use_signal(|| None::<String>);
+    let mut debug_view = use_signal(DebugView::default);
     let mut graphics_resources = use_signal(|| {
         println!("recreating resources");
         let desktop_context = window();
         let window = &desktop_context.window;
-        let context = windowed_context::WindowedContext::from_tao_window(window, SurfaceSettings::default()).unwrap();
+        // Matches `with_as_child_window` above.
+        let context = WindowedContextBuilder::new()
+            .window_mode(WindowMode::Child)
+            .transparent(true)
+            .env_overrides(EnvOverrides::from_env())
+            .build_from_tao_window(window)
+            .unwrap_or_else(|error| panic!("{}", describe_error(&error)));
         // Create camera
         let camera = Camera::new_perspective(
             Viewport::new_at_origo(1, 1),
@@ -47,85 +93,243 @@ fn app() -> Element {
             0.1,
             10.0,
         );
-        let control = OrbitControl::new(*camera.target(), 1.0, 100.0);
+        let mut control = Controls::new(*camera.target(), 1.0, 100.0);
+        control.set_zoom_to_cursor(true);
+        control.set_inertia(Some(Inertia::default()));
 
-        let mut cpu_model: CpuModel = three_d_asset::io::load_and_deserialize("DamagedHelmet.glb").unwrap();
-        cpu_model
-            .geometries
-            .iter_mut()
-            .for_each(|m| m.compute_tangents());
-        let mut model = Model::<PhysicalMaterial>::new(&context, &cpu_model)
-            .unwrap()
-            .remove(0);
-        model.set_animation(|time| Mat4::from_angle_z(radians(time * 0.0005)));
+        let model_path =
+            assets::resolve_asset_path(MODEL_PATH).unwrap_or_else(|error| panic!("{error}"));
+        let cpu_model = assets::load_model(&model_path).unwrap();
+        let parts = upload_model(&context, cpu_model).unwrap();
 
-        let lights: Vec<Box<dyn Light>> = vec![Box::new(AmbientLight::new(&context, 1.0, Srgba::WHITE))];
+        let mut lights = Lights::new();
+        lights.add(AmbientLight::new(&context, 1.0, Srgba::WHITE));
 
         GraphicsResources {
             context,
-            camera,
-            control,
-            model,
-            lights,
-            time_since_start: Instant::now(),
+            scene: {
+                let mut scene = Scene::new(camera, control, parts, lights);
+                scene.execute(SceneCommand::ResetView);
+                scene
+            },
+            scheduler: {
+                let mut scheduler = RenderScheduler::default();
+                scheduler.set_unfocused_fps(Some(10.0));
+                scheduler.set_auto_idle_after(Some(Duration::from_secs(5)));
+                scheduler
+            },
+            events: EventTranslator::new(window.inner_size(), window.scale_factor()),
+            focus: InputFocus::new(window.scale_factor()),
+            pending_events: Vec::new(),
+            hover: HoverPicker::default(),
+            resize: ResizeDebouncer::default(),
+            watched_model: WatchedModel::new(&model_path).ok(),
+            last_frame: Instant::now(),
         }
     });
 
-    let _: Coroutine<()> = use_coroutine(|_rx| async move {
+    let _: Coroutine<()> = use_coroutine(move |_rx| async move {
         loop {
-            window().window.request_redraw();
-            tokio::time::sleep(tokio::time::Duration::from_secs_f64(1.0 / 90.0)).await;
+            let interval = graphics_resources.with(|graphics_resources| {
+                if graphics_resources
+                    .scheduler
+                    .should_redraw(&graphics_resources.scene)
+                {
+                    window().window.request_redraw();
+                }
+                graphics_resources.scheduler.frame_interval()
+            });
+            tokio::time::sleep(interval).await;
         }
     });
 
-    let desktop_context = use_window();
-
     use_wry_event_handler(move |event, _| {
         match event {
             WryEvent::RedrawRequested(_id) => {}
             WryEvent::WindowEvent {
-                event: dioxus::desktop::tao::event::WindowEvent::Resized(size),
+                event: event @ dioxus::desktop::tao::event::WindowEvent::Resized(size),
                 ..
             } => {
-                graphics_resources.with_mut(|graphics_resources| graphics_resources.context.resize(*size));
+                graphics_resources.with_mut(|graphics_resources| {
+                    // Applied, throttled, before the next frame.
+                    graphics_resources.resize.push(*size);
+                    graphics_resources.scene.mark_dirty();
+                    graphics_resources
+                        .events
+                        .handle(event, &mut graphics_resources.pending_events);
+                });
+            }
+            WryEvent::WindowEvent { event, .. } => {
+                graphics_resources.with_mut(|graphics_resources| {
+                    graphics_resources.scheduler.handle_window_event(event);
+                    if graphics_resources.focus.filter(event) {
+                        graphics_resources
+                            .events
+                            .handle(event, &mut graphics_resources.pending_events);
+                    }
+                });
             }
             WryEvent::MainEventsCleared => {
-                let window = &desktop_context.window;
                 graphics_resources.with_mut(|graphics_resources| {
-                    let mut events = Vec::new();
-                    graphics_resources.control.handle_events(&mut graphics_resources.camera, &mut events);
-                    graphics_resources.model.animate(Instant::now().duration_since(graphics_resources.time_since_start).as_millis() as f32);
-                    let viewport = Viewport { x: 0, y: 0, width: window.inner_size().width, height: window.inner_size().height};
-                    graphics_resources.camera.set_viewport(viewport);
-                    RenderTarget::screen(&graphics_resources.context, viewport.width, viewport.height)
-                        .clear(ClearState::color_and_depth(0.0, 0.0, 0.0, 1.0, 1.0))
-                        .render(
-                            &graphics_resources.camera, 
-                            &graphics_resources.model, 
-                            graphics_resources.lights.iter().map(|light| light.as_ref()).collect::<Vec<_>>().as_slice()
-                        );
-                    graphics_resources.context.swap_buffers().unwrap();
+                    if let Some(watched_model) = &mut graphics_resources.watched_model {
+                        if let Some(cpu_model) = watched_model.poll() {
+                            match upload_model(&graphics_resources.context, cpu_model) {
+                                Ok(parts) => {
+                                    graphics_resources.scene.set_parts(parts);
+                                    model_error.set(None);
+                                }
+                                Err(error) => model_error.set(Some(error.to_string())),
+                            }
+                        } else if let Some(error) = watched_model.error() {
+                            if model_error.peek().as_deref() != Some(error) {
+                                model_error.set(Some(error.to_string()));
+                            }
+                        }
+                    }
+                    let now = Instant::now();
+                    if let Some(size) = graphics_resources.resize.poll(now) {
+                        graphics_resources
+                            .scene
+                            .resize(&graphics_resources.context, size);
+                    }
+                    if graphics_resources.resize.is_pending() {
+                        // Keeps frames, and with them the polling, coming until the last
+                        // size of the drag is applied.
+                        graphics_resources.scene.mark_dirty();
+                    }
+                    let dt = now.duration_since(graphics_resources.last_frame);
+                    graphics_resources.last_frame = now;
+
+                    graphics_resources
+                        .hover
+                        .handle_events(&graphics_resources.pending_events);
+                    graphics_resources.scene.update(
+                        &graphics_resources.context,
+                        dt,
+                        &mut graphics_resources.pending_events,
+                    );
+                    if !graphics_resources.focus.has_pointer() {
+                        graphics_resources.hover.clear();
+                    } else if graphics_resources.scene.is_dirty() {
+                        // The camera or the parts moved under the cursor.
+                        graphics_resources.hover.invalidate();
+                    }
+                    if graphics_resources.hover.update(
+                        &graphics_resources.scene,
+                        &graphics_resources.context,
+                        now,
+                    ) {
+                        hovered_part.set(graphics_resources.hover.hovered().map(str::to_string));
+                    }
+                    // Follows the shortcut, for the dropdown.
+                    let current_view = graphics_resources.scene.debug_view();
+                    if *debug_view.peek() != current_view {
+                        debug_view.set(current_view);
+                    }
+                    graphics_resources
+                        .scheduler
+                        .apply_requests(&mut graphics_resources.scene);
+                    graphics_resources
+                        .scheduler
+                        .track_activity(graphics_resources.scene.is_dirty(), now);
+                    if !graphics_resources
+                        .scheduler
+                        .should_redraw(&graphics_resources.scene)
+                    {
+                        return;
+                    }
+                    let context = &graphics_resources.context;
+                    let viewport = graphics_resources.resize.viewport(context.surface_size());
+                    if viewport != context.viewport() {
+                        // The letterbox bars let the webview show through.
+                        let bars = ClearState::color(0.0, 0.0, 0.0, 0.0);
+                        context.clear_region(context.viewport(), bars);
+                    }
+                    let center = vec2(viewport.width as f32, viewport.height as f32) * 0.5;
+                    let overlay = graphics_resources.scene.overlay_mut();
+                    let crosshair = Srgba::new(255, 255, 255, 160);
+                    overlay.line(
+                        center - vec2(8.0, 0.0),
+                        center + vec2(8.0, 0.0),
+                        2.0,
+                        crosshair,
+                    );
+                    overlay.line(
+                        center - vec2(0.0, 8.0),
+                        center + vec2(0.0, 8.0),
+                        2.0,
+                        crosshair,
+                    );
+                    graphics_resources
+                        .scene
+                        .render(&graphics_resources.context, viewport);
+                    if let Err(error) = graphics_resources.context.swap_buffers() {
+                        eprintln!("{}", describe_error(&error));
+                    }
                 })
             }
             _ => {}
         }
     });
-    
+
     rsx! {
         document::Link { rel: "stylesheet", href: asset!("./public/tailwind.css") }
         header {
             class: "text-gray-400 body-font",
+            // The header is UI, clicks and drags on it must not move the camera.
+            onmounted: move |event| async move {
+                if let Ok(rect) = event.get_client_rect().await {
+                    graphics_resources.with_mut(|graphics_resources| {
+                        graphics_resources.focus.set_ui_rects(vec![UiRect {
+                            x: rect.origin.x,
+                            y: rect.origin.y,
+                            width: rect.size.width,
+                            height: rect.size.height,
+                        }]);
+                    });
+                }
+            },
             div { class: "container mx-auto flex flex-wrap p-5 flex-col md:flex-row items-center",
                 a { class: "flex title-font font-medium items-center text-white mb-4 md:mb-0",
                     StacksIcon {}
                     span { class: "ml-3 text-xl", "Hello Dioxus!" }
                 }
+                if let Some(error) = model_error() {
+                    span { class: "ml-5 text-red-400", "Failed to reload model: {error}" }
+                }
+                if let Some(part) = hovered_part() {
+                    span { class: "ml-5 text-gray-300", "{part}" }
+                }
                 nav { class: "md:ml-auto flex flex-wrap items-center text-base justify-center",
                     a { class: "mr-5 hover:text-white", "First Link" }
                     a { class: "mr-5 hover:text-white", "Second Link" }
                     a { class: "mr-5 hover:text-white", "Third Link" }
                     a { class: "mr-5 hover:text-white", "Fourth Link" }
                 }
+                select { class: "bg-gray-800 border-0 py-1 px-3 mr-3 rounded text-base mt-4 md:mt-0",
+                    onchange: move |event| {
+                        let value = event.value();
+                        let view = DebugView::ALL.into_iter().find(|view| view.label() == value);
+                        if let Some(view) = view {
+                            graphics_resources.with_mut(|graphics_resources| {
+                                graphics_resources.scene.set_debug_view(view)
+                            });
+                        }
+                    },
+                    for view in DebugView::ALL {
+                        option {
+                            value: view.label(),
+                            selected: view == debug_view(),
+                            "{view.label()}"
+                        }
+                    }
+                }
+                button { class: "inline-flex items-center bg-gray-800 border-0 py-1 px-3 mr-3 focus:outline-none hover:bg-gray-700 rounded text-base mt-4 md:mt-0",
+                    onclick: move |_| graphics_resources.with_mut(|graphics_resources| {
+                        graphics_resources.scene.execute(SceneCommand::ResetView)
+                    }),
+                    "Reset view"
+                }
                 button { class: "inline-flex items-center bg-gray-800 border-0 py-1 px-3 focus:outline-none hover:bg-gray-700 rounded text-base mt-4 md:mt-0",
                     "Button"
                     RightArrowIcon {}
@@ -165,4 +369,4 @@ pub fn RightArrowIcon() -> Element {
             path { d: "M5 12h14M12 5l7 7-7 7" }
         }
     )
-}
\ No newline at end of file
+}
@@ -0,0 +1,161 @@
+use three_d::{
+    vec4, Blend, BlendEquationType, BlendMultiplierType, Camera, ClearState, ColorMaterial,
+    Context, Geometry, Interpolation, RenderStates, Srgba, Texture2D, Viewport, Wrapping,
+};
+
+use crate::post;
+
+const OUTLINE_SHADER: &str = "
+uniform sampler2D maskMap;
+uniform vec4 outlineColor;
+uniform float width;
+in vec2 uvs;
+layout (location = 0) out vec4 outColor;
+
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(maskMap, 0));
+    if (texture(maskMap, uvs).r > 0.5) {
+        discard;
+    }
+    int radius = int(ceil(width));
+    for (int y = -radius; y <= radius; y++) {
+        for (int x = -radius; x <= radius; x++) {
+            vec2 offset = vec2(float(x), float(y));
+            if (dot(offset, offset) <= width * width
+                && texture(maskMap, uvs + offset * texel).r > 0.5) {
+                outColor = outlineColor;
+                return;
+            }
+        }
+    }
+    discard;
+}
+";
+
+/// Draws a colored outline around the silhouette of a selected object.
+///
+/// The object is rendered flat into a mask and the outline is every pixel within `width`
+/// pixels of the mask, so its thickness is the same on screen however far away the object is.
+/// The mask ignores depth, so the outline also shows where other objects cover the selection.
+pub struct Outline {
+    color: Srgba,
+    width: f32,
+    mask: Option<Texture2D>,
+}
+
+impl Default for Outline {
+    fn default() -> Self {
+        Self {
+            color: Srgba::new_opaque(255, 165, 0),
+            width: 3.0,
+            mask: None,
+        }
+    }
+}
+
+impl Outline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn color(&self) -> Srgba {
+        self.color
+    }
+
+    pub fn set_color(&mut self, color: Srgba) {
+        self.color = color;
+    }
+
+    /// The thickness in physical pixels.
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn set_width(&mut self, width: f32) {
+        self.width = width.max(0.0);
+    }
+
+    /// Outlines `geometry` as seen by `camera` into `viewport` of the bound target.
+    pub(crate) fn render(
+        &mut self,
+        context: &Context,
+        camera: &Camera,
+        geometry: impl Geometry,
+        viewport: Viewport,
+    ) {
+        let (width, height) = (viewport.width.max(1), viewport.height.max(1));
+        if let Some(mask) = &self.mask {
+            if mask.width() != width || mask.height() != height {
+                self.mask = None;
+            }
+        }
+        let mask = self.mask.get_or_insert_with(|| {
+            Texture2D::new_empty::<[u8; 4]>(
+                context,
+                width,
+                height,
+                Interpolation::Nearest,
+                Interpolation::Nearest,
+                None,
+                Wrapping::ClampToEdge,
+                Wrapping::ClampToEdge,
+            )
+        });
+        let mut mask_camera = camera.clone();
+        mask_camera.set_viewport(Viewport::new_at_origo(width, height));
+        let material = ColorMaterial {
+            color: Srgba::WHITE,
+            ..Default::default()
+        };
+        mask.as_color_target(None)
+            .clear(ClearState::color(0.0, 0.0, 0.0, 0.0))
+            .render_with_material(&material, &mask_camera, std::iter::once(geometry), &[]);
+
+        let color = self.color;
+        let outline_color = vec4(
+            color.r as f32 / 255.0,
+            color.g as f32 / 255.0,
+            color.b as f32 / 255.0,
+            color.a as f32 / 255.0,
+        );
+        let outline_width = self.width;
+        // Regular alpha blending, except alpha accumulates too, so the outline still shows
+        // against a transparent background.
+        let render_states = RenderStates {
+            blend: Blend::Enabled {
+                source_rgb_multiplier: BlendMultiplierType::SrcAlpha,
+                source_alpha_multiplier: BlendMultiplierType::One,
+                destination_rgb_multiplier: BlendMultiplierType::OneMinusSrcAlpha,
+                destination_alpha_multiplier: BlendMultiplierType::OneMinusSrcAlpha,
+                rgb_equation: BlendEquationType::Add,
+                alpha_equation: BlendEquationType::Add,
+            },
+            ..post::fullscreen_render_states()
+        };
+        post::apply_effect(
+            context,
+            OUTLINE_SHADER,
+            render_states,
+            viewport,
+            |program| {
+                program.use_texture("maskMap", &*mask);
+                program.use_uniform("outlineColor", outline_color);
+                program.use_uniform("width", outline_width);
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn width_is_never_negative() {
+        let mut outline = Outline::new();
+        outline.set_width(-2.0);
+        assert_eq!(outline.width(), 0.0);
+        outline.set_width(5.0);
+        assert_eq!(outline.width(), 5.0);
+    }
+}
@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use three_d::{
+    radians, Blend, BlendEquationType, BlendMultiplierType, Camera, ColorMaterial, Context,
+    Geometry, Line, Radians, Rectangle, RenderStates, Srgba, Texture2D, Texture2DRef, Vec2,
+    Viewport,
+};
+
+use crate::post;
+
+enum OverlayItem {
+    Quad {
+        center: Vec2,
+        size: Vec2,
+        rotation: Radians,
+        color: Srgba,
+        texture: Option<Arc<Texture2D>>,
+    },
+    Line {
+        from: Vec2,
+        to: Vec2,
+        thickness: f32,
+        color: Srgba,
+    },
+}
+
+/// 2D shapes drawn over the rendered scene in the same GL context, like a crosshair, a compass
+/// or a scale bar, see [crate::scene::Scene::overlay_mut].
+///
+/// Immediate mode: shapes are queued for the next frame and drawn once, so queue them again
+/// before every render they should show in. Queueing doesn't trigger a redraw by itself.
+///
+/// Coordinates are physical pixels from the bottom left of the viewport the scene renders
+/// into, as in [three_d::Event], so the overlay follows the picture into a letterboxed or
+/// confined viewport and lines up with [crate::picking::world_to_screen] scaled by the scale
+/// factor. Shapes are drawn without depth test, in the order queued, blended by their alpha
+/// and after tone mapping, so their colors show as given. Text is a texture rendered
+/// elsewhere, drawn with [Overlay::textured_quad].
+#[derive(Default)]
+pub struct Overlay {
+    items: Vec<OverlayItem>,
+    quad: Option<Rectangle>,
+    line: Option<Line>,
+}
+
+impl Overlay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A rectangle filled with `color`, its bottom left corner at `min`.
+    pub fn quad(&mut self, min: Vec2, size: Vec2, color: Srgba) {
+        self.rotated_quad(min + size * 0.5, size, radians(0.0), color, None);
+    }
+
+    /// `texture` stretched over the rectangle with its bottom left corner at `min`, multiplied
+    /// by `tint`, [Srgba::WHITE] to draw it as is. The texture's first row is drawn at the
+    /// bottom, as `three_d` uploads it.
+    pub fn textured_quad(&mut self, min: Vec2, size: Vec2, texture: Arc<Texture2D>, tint: Srgba) {
+        self.rotated_quad(min + size * 0.5, size, radians(0.0), tint, Some(texture));
+    }
+
+    /// A rectangle of `size` centered at `center`, turned counterclockwise by `rotation`,
+    /// filled with `color` or, with `texture`, that texture multiplied by it.
+    pub fn rotated_quad(
+        &mut self,
+        center: Vec2,
+        size: Vec2,
+        rotation: Radians,
+        color: Srgba,
+        texture: Option<Arc<Texture2D>>,
+    ) {
+        self.items.push(OverlayItem::Quad {
+            center,
+            size,
+            rotation,
+            color,
+            texture,
+        });
+    }
+
+    /// A line `thickness` pixels wide from `from` to `to`.
+    pub fn line(&mut self, from: Vec2, to: Vec2, thickness: f32, color: Srgba) {
+        self.items.push(OverlayItem::Line {
+            from,
+            to,
+            thickness,
+            color,
+        });
+    }
+
+    /// Drops everything queued without drawing it.
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Draws the queued shapes into `viewport` of the bound render target and empties the
+    /// queue. The two geometries are created once and moved for each shape.
+    pub(crate) fn render(&mut self, context: &Context, viewport: Viewport) {
+        if self.items.is_empty() {
+            return;
+        }
+        let camera = Camera::new_2d(viewport);
+        for item in self.items.drain(..) {
+            match item {
+                OverlayItem::Quad {
+                    center,
+                    size,
+                    rotation,
+                    color,
+                    texture,
+                } => {
+                    let quad = self.quad.get_or_insert_with(|| {
+                        Rectangle::new(context, center, rotation, size.x, size.y)
+                    });
+                    quad.set_center(center);
+                    quad.set_size(size.x, size.y);
+                    quad.set_rotation(rotation);
+                    let texture = texture.map(Texture2DRef::from);
+                    quad.render_with_material(&material(color, texture), &camera, &[]);
+                }
+                OverlayItem::Line {
+                    from,
+                    to,
+                    thickness,
+                    color,
+                } => {
+                    let line = self
+                        .line
+                        .get_or_insert_with(|| Line::new(context, from, to, thickness));
+                    line.set_endpoints(from, to);
+                    line.set_thickness(thickness);
+                    line.render_with_material(&material(color, None), &camera, &[]);
+                }
+            }
+        }
+    }
+}
+
+fn material(color: Srgba, texture: Option<Texture2DRef>) -> ColorMaterial {
+    // Regular alpha blending, except alpha accumulates too, so the overlay still shows
+    // against a transparent background.
+    let render_states = RenderStates {
+        blend: Blend::Enabled {
+            source_rgb_multiplier: BlendMultiplierType::SrcAlpha,
+            source_alpha_multiplier: BlendMultiplierType::One,
+            destination_rgb_multiplier: BlendMultiplierType::OneMinusSrcAlpha,
+            destination_alpha_multiplier: BlendMultiplierType::OneMinusSrcAlpha,
+            rgb_equation: BlendEquationType::Add,
+            alpha_equation: BlendEquationType::Add,
+        },
+        ..post::fullscreen_render_states()
+    };
+    ColorMaterial {
+        color,
+        texture,
+        render_states,
+        is_transparent: true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::vec2;
+
+    use super::*;
+    use crate::scene::tests::empty_scene;
+    use crate::testing::{assert_pixel_near, TestHarness};
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn queued_quad_shows_at_its_coordinates_for_one_frame() {
+        let harness = TestHarness::new(64, 64).unwrap();
+        let mut scene = empty_scene();
+        scene.set_clear_color(Srgba::BLACK);
+        let red = Srgba::new_opaque(255, 0, 0);
+        scene
+            .overlay_mut()
+            .quad(vec2(8.0, 16.0), vec2(16.0, 8.0), red);
+        let image = harness.render_scene(&mut scene, 0.0);
+        assert!(scene.overlay_mut().is_empty());
+        // From the bottom left, so its center at (16, 20) is row 43 of the image.
+        assert_pixel_near(&image, 16, 43, [255, 0, 0, 255], 1);
+        for (x, y) in [(4, 43), (28, 43), (16, 35), (16, 51)] {
+            assert_pixel_near(&image, x, y, [0, 0, 0, 255], 1);
+        }
+
+        let image = harness.render_scene(&mut scene, 0.0);
+        assert_pixel_near(&image, 16, 43, [0, 0, 0, 255], 1);
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use three_d::{
+    Blend, BlendEquationType, BlendMultiplierType, Context, CpuModel, Geometry, Mat4, Model,
+    ModelPart, PhysicalMaterial, RendererError, SquareMatrix, Srgba, WriteMask,
+};
+
+use crate::stats;
+
+/// Replacement values for the PBR parameters of a part's material, see
+/// [ScenePart::override_material]. `None` fields keep their current value.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MaterialOverride {
+    pub base_color: Option<Srgba>,
+    pub metallic: Option<f32>,
+    pub roughness: Option<f32>,
+    pub emissive: Option<Srgba>,
+}
+
+/// A part animation: the transformation on top of the part's own at a time in milliseconds,
+/// see [ScenePart::set_animation].
+pub type PartAnimation = Arc<dyn Fn(f32) -> Mat4 + Send + Sync>;
+
+/// How the color of a transparent part combines with what is drawn behind it, see
+/// [crate::scene::Scene::set_blend_mode].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Regular alpha blending, as `three_d` sets up for transparent materials.
+    #[default]
+    Normal,
+    /// Adds the color weighted by its alpha, for glows and other light-like effects.
+    Additive,
+    /// Multiplies what is behind by the color, for tinted glass; alpha is ignored.
+    Multiply,
+}
+
+impl BlendMode {
+    pub(crate) fn blend(self) -> Blend {
+        let (source_rgb_multiplier, destination_rgb_multiplier) = match self {
+            BlendMode::Normal => (
+                BlendMultiplierType::SrcAlpha,
+                BlendMultiplierType::OneMinusSrcAlpha,
+            ),
+            BlendMode::Additive => (BlendMultiplierType::SrcAlpha, BlendMultiplierType::One),
+            BlendMode::Multiply => (BlendMultiplierType::DstColor, BlendMultiplierType::Zero),
+        };
+        // Alpha accumulates like the premultiplied color does, so translucent parts still
+        // show over a transparent background, see [crate::composite::apply].
+        let (source_alpha_multiplier, destination_alpha_multiplier) = match self {
+            BlendMode::Normal | BlendMode::Additive => (
+                BlendMultiplierType::One,
+                BlendMultiplierType::OneMinusSrcAlpha,
+            ),
+            BlendMode::Multiply => (BlendMultiplierType::Zero, BlendMultiplierType::One),
+        };
+        Blend::Enabled {
+            source_rgb_multiplier,
+            source_alpha_multiplier,
+            destination_rgb_multiplier,
+            destination_alpha_multiplier,
+            rgb_equation: BlendEquationType::Add,
+            alpha_equation: BlendEquationType::Add,
+        }
+    }
+}
+
+/// The overridable values of a material as loaded.
+#[derive(Clone, Copy, Debug)]
+struct MaterialValues {
+    base_color: Srgba,
+    metallic: f32,
+    roughness: f32,
+    emissive: Srgba,
+}
+
+impl MaterialValues {
+    fn of(material: &PhysicalMaterial) -> Self {
+        Self {
+            base_color: material.albedo,
+            metallic: material.metallic,
+            roughness: material.roughness,
+            emissive: material.emissive,
+        }
+    }
+
+    fn apply(&self, material: &mut PhysicalMaterial) {
+        material.albedo = self.base_color;
+        material.metallic = self.metallic;
+        material.roughness = self.roughness;
+        material.emissive = self.emissive;
+    }
+}
+
+/// One drawable part of a loaded model, the [ModelPart] together with what the scene needs
+/// to know about it beyond the GPU data.
+pub struct ScenePart {
+    id: String,
+    model: ModelPart<PhysicalMaterial>,
+    triangles: u64,
+    double_sided: bool,
+    visible: bool,
+    blend_mode: Option<BlendMode>,
+    sort_key: Option<f32>,
+    animation: Option<PartAnimation>,
+    /// The material values before the first override, restored by `reset_material`.
+    loaded_material: Option<MaterialValues>,
+    /// The write mask before `set_double_sided` turned depth writes off, restored when it is
+    /// turned off again.
+    single_sided_write_mask: Option<WriteMask>,
+}
+
+impl ScenePart {
+    /// `id` should be unique within a scene. `triangles` is only used for render statistics,
+    /// see [crate::stats::triangle_count].
+    pub fn new(id: impl Into<String>, model: ModelPart<PhysicalMaterial>, triangles: u64) -> Self {
+        Self {
+            id: id.into(),
+            model,
+            triangles,
+            double_sided: false,
+            visible: true,
+            blend_mode: None,
+            sort_key: None,
+            animation: None,
+            loaded_material: None,
+            single_sided_write_mask: None,
+        }
+    }
+
+    /// Uploads every primitive of `cpu_model`, one part per primitive and in the same order,
+    /// so part indices match indices into `cpu_model.geometries`.
+    ///
+    /// The ids are the primitive names, which the glTF loader takes from the nodes and meshes,
+    /// see [part_ids].
+    pub fn from_cpu_model(
+        context: &Context,
+        cpu_model: &CpuModel,
+    ) -> Result<Vec<Self>, RendererError> {
+        let mut models = Model::<PhysicalMaterial>::new(context, cpu_model)?;
+        let ids = part_ids(cpu_model);
+        Ok(models
+            .drain(..)
+            .zip(&cpu_model.geometries)
+            .zip(ids)
+            .map(|((model, primitive), id)| {
+                Self::new(id, model, stats::triangle_count(&primitive.geometry))
+            })
+            .collect())
+    }
+
+    /// The id the part is looked up by, see [crate::scene::Scene::part].
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn model(&self) -> &ModelPart<PhysicalMaterial> {
+        &self.model
+    }
+
+    pub fn model_mut(&mut self) -> &mut ModelPart<PhysicalMaterial> {
+        &mut self.model
+    }
+
+    pub fn triangles(&self) -> u64 {
+        self.triangles
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Hidden parts are not rendered, picked, included in the scene bounds or casting shadows.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// The animation set with [ScenePart::set_animation], if any.
+    pub fn animation(&self) -> Option<&PartAnimation> {
+        self.animation.as_ref()
+    }
+
+    /// Replaces the part's animation, or with `None` stops it, and poses the part for `time`.
+    ///
+    /// Stopping freezes the part in the pose the previous animation has at `time`, on top of
+    /// the part's own transformation, which is kept. That needs the previous animation to have
+    /// been set here; one set on the [ModelPart] directly, or key frames from the file, can't
+    /// be evaluated from outside and the part returns to its own transformation instead.
+    pub fn set_animation(&mut self, animation: Option<PartAnimation>, time: f32) {
+        match &animation {
+            Some(animation) => {
+                let animation = animation.clone();
+                self.model.set_animation(move |time| animation(time));
+            }
+            None => {
+                let pose = self
+                    .animation
+                    .as_ref()
+                    .map_or(Mat4::identity(), |animation| animation(time));
+                self.model.set_animation(move |_| pose);
+            }
+        }
+        self.animation = animation;
+        self.model.animate(time);
+    }
+
+    /// Whether the material blends, which makes the part drawn after the opaque ones, sorted
+    /// back to front.
+    pub fn is_transparent(&self) -> bool {
+        self.model.material.is_transparent
+    }
+
+    /// The blend mode this part overrides the scene's with, if any.
+    pub fn blend_mode(&self) -> Option<BlendMode> {
+        self.blend_mode
+    }
+
+    /// Blends this part with `blend_mode` instead of the scene's
+    /// [crate::scene::Scene::blend_mode], or with the scene's again for `None`. Only applies
+    /// while the part [ScenePart::is_transparent].
+    pub fn set_blend_mode(&mut self, blend_mode: Option<BlendMode>) {
+        self.blend_mode = blend_mode;
+    }
+
+    /// The sort key overriding the part's camera distance, if any.
+    pub fn sort_key(&self) -> Option<f32> {
+        self.sort_key
+    }
+
+    /// Sorts this transparent part as if it were `sort_key` away from the camera, instead of
+    /// the distance to the center of its bounds. Parts are drawn from the largest key to the
+    /// smallest, so of two overlapping parts the one with the smaller key ends up in front.
+    pub fn set_sort_key(&mut self, sort_key: Option<f32>) {
+        self.sort_key = sort_key;
+    }
+
+    /// Changes the parameters of the material in place. Overrides accumulate until
+    /// [ScenePart::reset_material]; the textures are kept and still modulate the new values.
+    ///
+    /// Metallic and roughness are clamped to `0.0..=1.0`. A base color alpha below 255 does
+    /// not make an opaque material blend, use a transparent material for that.
+    pub fn override_material(&mut self, values: MaterialOverride) {
+        let material = &mut self.model.material;
+        self.loaded_material
+            .get_or_insert_with(|| MaterialValues::of(material));
+        if let Some(base_color) = values.base_color {
+            material.albedo = base_color;
+        }
+        if let Some(metallic) = values.metallic {
+            material.metallic = metallic.clamp(0.0, 1.0);
+        }
+        if let Some(roughness) = values.roughness {
+            material.roughness = roughness.clamp(0.0, 1.0);
+        }
+        if let Some(emissive) = values.emissive {
+            material.emissive = emissive;
+        }
+    }
+
+    /// Restores the material values the part was created with. Returns whether any override
+    /// was undone.
+    pub fn reset_material(&mut self) -> bool {
+        match self.loaded_material.take() {
+            Some(values) => {
+                values.apply(&mut self.model.material);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn double_sided(&self) -> bool {
+        self.double_sided
+    }
+
+    /// Renders both faces of every triangle regardless of the scene's cull mode, for models
+    /// with inconsistent winding or open, single-layer surfaces.
+    ///
+    /// Blended materials stop writing depth, otherwise their back faces, when drawn first,
+    /// would hide the front faces of the same part. They write depth as before once the part
+    /// is single-sided again.
+    pub fn set_double_sided(&mut self, double_sided: bool) {
+        self.double_sided = double_sided;
+        let render_states = &mut self.model.material.render_states;
+        if !double_sided {
+            if let Some(write_mask) = self.single_sided_write_mask.take() {
+                render_states.write_mask = write_mask;
+            }
+        } else if matches!(render_states.blend, Blend::Enabled { .. })
+            && self.single_sided_write_mask.is_none()
+        {
+            self.single_sided_write_mask = Some(render_states.write_mask);
+            render_states.write_mask = WriteMask::COLOR;
+        }
+    }
+}
+
+/// Unique ids for the primitives of `cpu_model`, in order.
+///
+/// A primitive's id is its name, or its index if it has none. Later duplicates get `#2`,
+/// `#3` and so on appended, skipping suffixes that are taken, so the same file always gives
+/// the same ids.
+pub fn part_ids(cpu_model: &CpuModel) -> Vec<String> {
+    let mut taken = HashSet::new();
+    cpu_model
+        .geometries
+        .iter()
+        .enumerate()
+        .map(|(index, primitive)| {
+            let base = if primitive.name.is_empty() {
+                index.to_string()
+            } else {
+                primitive.name.clone()
+            };
+            let mut id = base.clone();
+            let mut suffix = 2;
+            while taken.contains(&id) {
+                id = format!("{base}#{suffix}");
+                suffix += 1;
+            }
+            taken.insert(id.clone());
+            id
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::tests::cubes;
+    use crate::testing::TestHarness;
+
+    /// A model of single-triangle primitives with the given names.
+    fn named(names: &[&str]) -> CpuModel {
+        CpuModel {
+            name: "named".to_string(),
+            geometries: names
+                .iter()
+                .map(|name| three_d_asset::Primitive {
+                    name: name.to_string(),
+                    transformation: Mat4::identity(),
+                    animations: Vec::new(),
+                    geometry: three_d_asset::Geometry::Triangles(three_d::CpuMesh::default()),
+                    material_index: None,
+                })
+                .collect(),
+            materials: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn part_ids_are_the_names_made_unique() {
+        let ids = part_ids(&named(&["wheel", "body", "wheel", "", "wheel#2", "wheel"]));
+        assert_eq!(
+            ids,
+            ["wheel", "body", "wheel#2", "3", "wheel#2#2", "wheel#3"]
+        );
+        assert_eq!(
+            ids,
+            part_ids(&named(&["wheel", "body", "wheel", "", "wheel#2", "wheel"]))
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn double_sided_blending_parts_write_depth_again_once_single_sided() {
+        let harness = TestHarness::new(8, 8).unwrap();
+        let mut part = ScenePart::from_cpu_model(harness.context(), &cubes(1))
+            .unwrap()
+            .remove(0);
+        part.model_mut().material.render_states.blend = Blend::TRANSPARENCY;
+        let loaded = part.model().material.render_states.write_mask;
+        assert_eq!(loaded, WriteMask::default());
+
+        part.set_double_sided(true);
+        assert_eq!(
+            part.model().material.render_states.write_mask,
+            WriteMask::COLOR
+        );
+        part.set_double_sided(false);
+        assert_eq!(part.model().material.render_states.write_mask, loaded);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn overrides_survive_animation_until_reset() {
+        let harness = TestHarness::new(8, 8).unwrap();
+        let mut part = ScenePart::from_cpu_model(harness.context(), &cubes(1))
+            .unwrap()
+            .remove(0);
+        let loaded = MaterialValues::of(&part.model().material);
+        part.override_material(MaterialOverride {
+            roughness: Some(0.25),
+            ..Default::default()
+        });
+        part.override_material(MaterialOverride {
+            metallic: Some(2.0),
+            ..Default::default()
+        });
+        let lift = Mat4::from_translation(three_d::vec3(0.0, 1.0, 0.0));
+        part.set_animation(Some(Arc::new(move |_| lift)), 1.0);
+        let material = &part.model().material;
+        assert_eq!(material.roughness, 0.25);
+        assert_eq!(material.metallic, 1.0);
+        assert_eq!(material.albedo, loaded.base_color);
+
+        assert!(part.reset_material());
+        let material = &part.model().material;
+        assert_eq!(material.roughness, loaded.roughness);
+        assert_eq!(material.metallic, loaded.metallic);
+        assert!(!part.reset_material());
+    }
+
+    fn alpha_multipliers(blend: Blend) -> (BlendMultiplierType, BlendMultiplierType) {
+        match blend {
+            Blend::Enabled {
+                source_alpha_multiplier,
+                destination_alpha_multiplier,
+                ..
+            } => (source_alpha_multiplier, destination_alpha_multiplier),
+            Blend::Disabled => panic!("blending disabled"),
+        }
+    }
+
+    #[test]
+    fn translucent_parts_accumulate_alpha() {
+        let over = (
+            BlendMultiplierType::One,
+            BlendMultiplierType::OneMinusSrcAlpha,
+        );
+        assert_eq!(alpha_multipliers(BlendMode::Normal.blend()), over);
+        assert_eq!(alpha_multipliers(BlendMode::Additive.blend()), over);
+        let keep = (BlendMultiplierType::Zero, BlendMultiplierType::One);
+        assert_eq!(alpha_multipliers(BlendMode::Multiply.blend()), keep);
+    }
+}
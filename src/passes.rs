@@ -0,0 +1,351 @@
+use three_d::{f16, vec2, Context, Interpolation, Texture2D, Viewport, Wrapping};
+
+use crate::post::{self, SRGB_FUNCTIONS};
+use crate::render_texture::Draw;
+
+/// One post-process step of a [PassChain].
+pub trait Pass {
+    /// What [PassChain] finds the pass by, unique within a chain.
+    fn name(&self) -> &str;
+
+    /// Renders `source` processed into `viewport` of the bound target, which is the size of
+    /// `source`. Every pixel has to be written, the target holds the output of an earlier
+    /// frame or pass.
+    fn render(&mut self, context: &Context, source: &Texture2D, viewport: Viewport);
+}
+
+/// A fullscreen fragment shader as a [Pass].
+///
+/// The shader gets the source as `uniform sampler2D colorMap`, the size of one of its texels
+/// as `uniform vec2 texelSize` and the texture coordinates as `in vec2 uvs`, and writes
+/// `layout (location = 0) out vec4 outColor`. The source holds sRGB encoded color, as
+/// `three_d` materials write it, possibly above `1.0` before tone mapping;
+/// `srgb_to_linear` and `linear_to_srgb` are defined for working in linear color.
+pub struct ShaderPass {
+    name: String,
+    shader: String,
+}
+
+impl ShaderPass {
+    pub fn new(name: impl Into<String>, shader: &str) -> Self {
+        Self {
+            name: name.into(),
+            shader: format!("{}{}", SRGB_FUNCTIONS, shader),
+        }
+    }
+
+    /// A pass copying its source unchanged, for checking a chain or as a placeholder.
+    pub fn identity(name: impl Into<String>) -> Self {
+        Self::new(name, IDENTITY_SHADER)
+    }
+}
+
+const IDENTITY_SHADER: &str = "
+uniform sampler2D colorMap;
+in vec2 uvs;
+layout (location = 0) out vec4 outColor;
+
+void main() {
+    outColor = texture(colorMap, uvs);
+}
+";
+
+impl Pass for ShaderPass {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn render(&mut self, context: &Context, source: &Texture2D, viewport: Viewport) {
+        let texel_size = vec2(1.0 / source.width() as f32, 1.0 / source.height() as f32);
+        let render_states = post::fullscreen_render_states();
+        post::apply_effect(context, &self.shader, render_states, viewport, |program| {
+            program.use_texture("colorMap", source);
+            program.use_uniform_if_required("texelSize", texel_size);
+        });
+    }
+}
+
+struct Entry {
+    pass: Box<dyn Pass>,
+    enabled: bool,
+    order: i32,
+}
+
+/// Post-process passes run one after the other over the offscreen color of a frame, see
+/// [crate::scene::Scene::post_passes_mut].
+///
+/// Passes run by ascending order, those of the same order as they were added, and can be
+/// switched off without losing their place, so a settings panel can drive the chain from its
+/// state. Each pass reads the output of the one before and writes into the other of two
+/// textures the chain keeps, so the frame it started from is never overwritten; the last
+/// output is what the scene's bloom, tone mapping and color grade then present.
+#[derive(Default)]
+pub struct PassChain {
+    entries: Vec<Entry>,
+    targets: Vec<Texture2D>,
+}
+
+impl PassChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `pass`, enabled, to run after all others. A pass with the same name is replaced.
+    pub fn push(&mut self, pass: impl Pass + 'static) {
+        let order = self
+            .entries
+            .iter()
+            .map(|entry| entry.order + 1)
+            .max()
+            .unwrap_or(0);
+        self.insert(order, pass);
+    }
+
+    /// Adds `pass`, enabled, at `order`, after the passes already at that order. A pass with
+    /// the same name is replaced.
+    pub fn insert(&mut self, order: i32, pass: impl Pass + 'static) {
+        self.remove(pass.name());
+        self.entries.push(Entry {
+            pass: Box::new(pass),
+            enabled: true,
+            order,
+        });
+    }
+
+    /// Takes the pass named `name` out of the chain.
+    pub fn remove(&mut self, name: &str) -> Option<Box<dyn Pass>> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.pass.name() == name)?;
+        Some(self.entries.remove(index).pass)
+    }
+
+    /// Whether the pass named `name` runs, `None` if there is none.
+    pub fn is_enabled(&self, name: &str) -> Option<bool> {
+        self.entry(name).map(|entry| entry.enabled)
+    }
+
+    /// Switches the pass named `name` on or off. Returns whether there is one.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        self.entry_mut(name)
+            .map(|entry| entry.enabled = enabled)
+            .is_some()
+    }
+
+    pub fn order(&self, name: &str) -> Option<i32> {
+        self.entry(name).map(|entry| entry.order)
+    }
+
+    /// Moves the pass named `name` to `order`, after the passes already there. Returns
+    /// whether there is one.
+    pub fn set_order(&mut self, name: &str, order: i32) -> bool {
+        let Some(index) = self
+            .entries
+            .iter()
+            .position(|entry| entry.pass.name() == name)
+        else {
+            return false;
+        };
+        let mut entry = self.entries.remove(index);
+        entry.order = order;
+        self.entries.push(entry);
+        true
+    }
+
+    /// The names of all passes, enabled or not, in the order they run.
+    pub fn names(&self) -> Vec<&str> {
+        self.sorted()
+            .into_iter()
+            .map(|index| self.entries[index].pass.name())
+            .collect()
+    }
+
+    /// Whether any pass is enabled, that is whether [PassChain::render] does anything.
+    pub fn has_enabled(&self) -> bool {
+        self.entries.iter().any(|entry| entry.enabled)
+    }
+
+    /// Runs the enabled passes over `source` and returns the result, `source` itself when
+    /// none are enabled. Binds its own targets, so rebind the one rendered to before.
+    pub fn render<'a>(&'a mut self, context: &Context, source: &'a Texture2D) -> &'a Texture2D {
+        let enabled: Vec<usize> = self
+            .sorted()
+            .into_iter()
+            .filter(|&index| self.entries[index].enabled)
+            .collect();
+        if enabled.is_empty() {
+            self.release();
+            return source;
+        }
+        let (width, height) = (source.width(), source.height());
+        self.resize(context, width, height);
+        let viewport = Viewport::new_at_origo(width, height);
+        let Self { entries, targets } = self;
+        for (step, &index) in enabled.iter().enumerate() {
+            // Even steps write the first target, odd ones the second, each reading the other.
+            let (first, second) = targets.split_at_mut(1);
+            let (input, output) = if step % 2 == 0 {
+                (&second[0], &mut first[0])
+            } else {
+                (&first[0], &mut second[0])
+            };
+            let input = if step == 0 { source } else { input };
+            let pass = &mut entries[index].pass;
+            output
+                .as_color_target(None)
+                .draw(|| pass.render(context, input, viewport));
+        }
+        &targets[(enabled.len() - 1) % 2]
+    }
+
+    /// Drops the textures the passes ping-pong between, until the next [PassChain::render]
+    /// with a pass enabled.
+    pub(crate) fn release(&mut self) {
+        self.targets.clear();
+    }
+
+    fn entry(&self, name: &str) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.pass.name() == name)
+    }
+
+    fn entry_mut(&mut self, name: &str) -> Option<&mut Entry> {
+        self.entries
+            .iter_mut()
+            .find(|entry| entry.pass.name() == name)
+    }
+
+    /// The entry indices by order, ties in the order they were added.
+    fn sorted(&self) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..self.entries.len()).collect();
+        indices.sort_by_key(|&index| self.entries[index].order);
+        indices
+    }
+
+    fn resize(&mut self, context: &Context, width: u32, height: u32) {
+        let sized = self
+            .targets
+            .iter()
+            .all(|target| target.width() == width && target.height() == height);
+        if self.targets.len() != 2 || !sized {
+            self.targets = (0..2).map(|_| new_target(context, width, height)).collect();
+        }
+    }
+}
+
+/// Half float like the offscreen color, so values above `1.0` survive until tone mapping.
+fn new_target(context: &Context, width: u32, height: u32) -> Texture2D {
+    Texture2D::new_empty::<[f16; 4]>(
+        context,
+        width,
+        height,
+        Interpolation::Linear,
+        Interpolation::Linear,
+        None,
+        Wrapping::ClampToEdge,
+        Wrapping::ClampToEdge,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A pass that draws nothing, for the bookkeeping of the chain.
+    struct Named(&'static str);
+
+    impl Pass for Named {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn render(&mut self, _: &Context, _: &Texture2D, _: Viewport) {}
+    }
+
+    #[test]
+    fn passes_run_by_order_then_as_added() {
+        let mut chain = PassChain::new();
+        chain.push(Named("a"));
+        chain.push(Named("b"));
+        chain.insert(0, Named("c"));
+        assert_eq!(chain.names(), ["a", "c", "b"]);
+        assert!(chain.set_order("a", 5));
+        assert_eq!(chain.names(), ["c", "b", "a"]);
+        assert_eq!(chain.order("a"), Some(5));
+        assert!(!chain.set_order("missing", 0));
+
+        // Replacing keeps one pass of the name, at the new place.
+        chain.push(Named("c"));
+        assert_eq!(chain.names(), ["b", "a", "c"]);
+
+        assert!(chain.set_enabled("b", false));
+        assert_eq!(chain.is_enabled("b"), Some(false));
+        assert_eq!(chain.is_enabled("missing"), None);
+        assert_eq!(chain.names(), ["b", "a", "c"]);
+        for name in ["a", "c"] {
+            chain.set_enabled(name, false);
+        }
+        assert!(!chain.has_enabled());
+        assert!(chain.remove("a").is_some());
+        assert_eq!(chain.names(), ["b", "c"]);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn identity_passes_leave_the_frame_as_it_was() {
+        use three_d::{ClearState, CpuTexture, TextureData};
+
+        use crate::testing::{diff, TestHarness};
+
+        let harness = TestHarness::new(8, 8).unwrap();
+        let context = &harness.context().context;
+        let texels = (0..64)
+            .map(|i| {
+                [
+                    i as f32 * 4.0 / 255.0,
+                    (255 - i * 4) as f32 / 255.0,
+                    0.5,
+                    1.0,
+                ]
+            })
+            .collect();
+        let source = Texture2D::new(
+            context,
+            &CpuTexture {
+                data: TextureData::RgbaF32(texels),
+                width: 8,
+                height: 8,
+                min_filter: Interpolation::Nearest,
+                mag_filter: Interpolation::Nearest,
+                mip_map_filter: None,
+                ..Default::default()
+            },
+        );
+        let viewport = harness.viewport();
+        let show = |texture: &Texture2D| {
+            harness.render(|screen| {
+                screen
+                    .clear(ClearState::default())
+                    .draw(|| ShaderPass::identity("show").render(context, texture, viewport));
+            })
+        };
+        let expected = show(&source);
+
+        let mut chain = PassChain::new();
+        chain.push(ShaderPass::identity("first"));
+        chain.push(ShaderPass::identity("second"));
+        let output = chain.render(context, &source);
+        assert!(!std::ptr::eq(output, &source));
+        assert_eq!(diff(&show(output), &expected, 1).mismatched, 0);
+
+        // An odd number of passes ends in the other target.
+        chain.push(ShaderPass::identity("third"));
+        let output = chain.render(context, &source);
+        assert_eq!(diff(&show(output), &expected, 1).mismatched, 0);
+
+        for name in ["first", "second", "third"] {
+            chain.set_enabled(name, false);
+        }
+        assert!(std::ptr::eq(chain.render(context, &source), &source));
+    }
+}
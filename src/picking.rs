@@ -0,0 +1,269 @@
+use three_d::{
+    Camera, Context, Geometry, InnerSpace, PhysicalPoint, SquareMatrix, Vec3, Vec4, Viewport,
+};
+
+use crate::windowed_context::WindowedContext;
+
+/// A ray in world space, shot from the camera through a pixel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    /// The point at distance `t` along the ray.
+    pub fn at(&self, t: f32) -> Vec3 {
+        self.origin + self.direction * t
+    }
+}
+
+/// Whether `pixel` (physical pixels, bottom-left origin) lies inside the camera's viewport.
+pub fn in_viewport(camera: &Camera, pixel: PhysicalPoint) -> bool {
+    viewport_contains(camera.viewport(), pixel)
+}
+
+/// Whether `pixel` (physical pixels, bottom-left origin) lies inside `viewport`. The left and
+/// bottom edges belong to it, the right and top ones don't, so of two adjacent viewports, or
+/// a viewport and its letterbox bars, exactly one contains a point on the edge between them.
+pub fn viewport_contains(viewport: Viewport, pixel: PhysicalPoint) -> bool {
+    pixel.x >= viewport.x as f32
+        && pixel.y >= viewport.y as f32
+        && pixel.x < (viewport.x + viewport.width as i32) as f32
+        && pixel.y < (viewport.y + viewport.height as i32) as f32
+}
+
+/// The ray through `pixel`, or `None` if the pixel is outside the camera's viewport.
+///
+/// `pixel` is in physical pixels with the origin in the bottom left corner of the window,
+/// the same convention as the positions in [three_d::Event].
+pub fn pick_ray(camera: &Camera, pixel: PhysicalPoint) -> Option<Ray> {
+    if !in_viewport(camera, pixel) {
+        return None;
+    }
+    Some(Ray {
+        origin: camera.position_at_pixel(pixel),
+        direction: camera.view_direction_at_pixel(pixel),
+    })
+}
+
+/// The ray through `pixel` when `camera` renders into `viewport`, or `None` if the pixel is
+/// outside of it, such as in the bars around a letterboxed viewport, see
+/// [crate::resize::letterbox], or beside an embedded canvas.
+///
+/// Like [pick_ray], but with the viewport actually rendered into rather than the camera's,
+/// the two only agree once the camera viewport was set for the frame. Only the camera's view
+/// and projection are used.
+pub fn pick_ray_in(camera: &Camera, viewport: Viewport, pixel: PhysicalPoint) -> Option<Ray> {
+    if !viewport_contains(viewport, pixel) {
+        return None;
+    }
+    let ndc_x = (pixel.x - viewport.x as f32) / viewport.width as f32 * 2.0 - 1.0;
+    let ndc_y = (pixel.y - viewport.y as f32) / viewport.height as f32 * 2.0 - 1.0;
+    ndc_ray(camera, ndc_x, ndc_y)
+}
+
+/// The closest point on `geometries` under `pixel`, if any.
+pub fn pick_point(
+    context: &Context,
+    camera: &Camera,
+    pixel: PhysicalPoint,
+    geometries: impl IntoIterator<Item = impl Geometry>,
+) -> Option<Vec3> {
+    pick_ray(camera, pixel)?;
+    three_d::pick(context, camera, pixel, geometries)
+}
+
+/// Where `world` shows up on the screen when `camera` renders into `viewport`, in logical
+/// pixels from the top left of the window, the coordinates Dioxus lays out in. For placing
+/// UI labels over points of the scene.
+///
+/// `None` for points behind the near plane, beyond the far plane or outside the viewport.
+///
+/// Only the camera's view and projection are used, not its viewport, so this works with the
+/// viewport passed to [crate::scene::Scene::render] however the scene rendered it. Like
+/// [WindowedContext::viewport_for] the current size and scale factor are read from `context`,
+/// so for labels following the scene call it each frame.
+pub fn world_to_screen(
+    context: &WindowedContext,
+    camera: &Camera,
+    viewport: Viewport,
+    world: Vec3,
+) -> Option<(f32, f32)> {
+    let (_, height) = context.surface_size();
+    project(
+        camera,
+        viewport,
+        height,
+        context.scale_factor() as f32,
+        world,
+    )
+}
+
+/// [world_to_screen] in a window `height` physical pixels high at `scale` physical pixels per
+/// logical one.
+fn project(
+    camera: &Camera,
+    viewport: Viewport,
+    height: u32,
+    scale: f32,
+    world: Vec3,
+) -> Option<(f32, f32)> {
+    let clip = camera.projection() * camera.view() * world.extend(1.0);
+    if clip.w <= f32::EPSILON {
+        return None;
+    }
+    let ndc = clip.truncate() / clip.w;
+    if ndc.x.abs() > 1.0 || ndc.y.abs() > 1.0 || ndc.z.abs() > 1.0 {
+        return None;
+    }
+    let x = viewport.x as f32 + (ndc.x + 1.0) * 0.5 * viewport.width as f32;
+    let y = viewport.y as f32 + (ndc.y + 1.0) * 0.5 * viewport.height as f32;
+    Some((x / scale, (height as f32 - y) / scale))
+}
+
+/// The ray through the point at `x`, `y` in logical pixels from the top left of the window,
+/// when `camera` renders into `viewport`; the inverse of [world_to_screen]. `None` outside
+/// the viewport, with its edges as in [viewport_contains].
+pub fn screen_to_ray(
+    context: &WindowedContext,
+    camera: &Camera,
+    viewport: Viewport,
+    (x, y): (f32, f32),
+) -> Option<Ray> {
+    let (_, height) = context.surface_size();
+    unproject(
+        camera,
+        viewport,
+        height,
+        context.scale_factor() as f32,
+        (x, y),
+    )
+}
+
+/// [screen_to_ray] in a window `height` physical pixels high at `scale` physical pixels per
+/// logical one.
+fn unproject(
+    camera: &Camera,
+    viewport: Viewport,
+    height: u32,
+    scale: f32,
+    (x, y): (f32, f32),
+) -> Option<Ray> {
+    let pixel = PhysicalPoint {
+        x: x * scale,
+        y: height as f32 - y * scale,
+    };
+    pick_ray_in(camera, viewport, pixel)
+}
+
+/// The ray through the point at `ndc_x`, `ndc_y` in normalized device coordinates, from the
+/// near to the far plane.
+fn ndc_ray(camera: &Camera, ndc_x: f32, ndc_y: f32) -> Option<Ray> {
+    let inverse = (camera.projection() * camera.view()).invert()?;
+    let at_depth = |z: f32| {
+        let point = inverse * Vec4::new(ndc_x, ndc_y, z, 1.0);
+        point.truncate() / point.w
+    };
+    let near = at_depth(-1.0);
+    let far = at_depth(1.0);
+    Some(Ray {
+        origin: near,
+        direction: (far - near).normalize(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::{degrees, vec3};
+
+    use super::*;
+
+    /// Looks down -z from 5 units away, a unit off the axis at the origin is a fifth of the
+    /// way to the edge of its 90 degree view.
+    fn camera(viewport: Viewport) -> Camera {
+        Camera::new_perspective(
+            viewport,
+            vec3(0.0, 0.0, 5.0),
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            degrees(90.0),
+            0.1,
+            100.0,
+        )
+    }
+
+    fn near(a: (f32, f32), b: (f32, f32)) -> bool {
+        (a.0 - b.0).abs() < 1e-3 && (a.1 - b.1).abs() < 1e-3
+    }
+
+    #[test]
+    fn known_points_land_in_logical_pixels_from_the_top_left() {
+        // The right half of a 200x100 window at a scale factor of 2.
+        let viewport = Viewport {
+            x: 100,
+            y: 0,
+            width: 100,
+            height: 100,
+        };
+        let camera = camera(viewport);
+        let at = |world| project(&camera, viewport, 100, 2.0, world);
+        assert!(near(at(vec3(0.0, 0.0, 0.0)).unwrap(), (75.0, 25.0)));
+        assert!(near(at(vec3(1.0, 0.0, 0.0)).unwrap(), (80.0, 25.0)));
+        assert!(near(at(vec3(0.0, 1.0, 0.0)).unwrap(), (75.0, 20.0)));
+    }
+
+    #[test]
+    fn points_behind_the_camera_or_outside_the_frustum_are_not_on_screen() {
+        let viewport = Viewport::new_at_origo(100, 100);
+        let camera = camera(viewport);
+        let at = |world| project(&camera, viewport, 100, 1.0, world);
+        assert_eq!(at(vec3(0.0, 0.0, 10.0)), None);
+        assert_eq!(at(vec3(0.0, 0.0, 4.95)), None);
+        assert_eq!(at(vec3(10.0, 0.0, 0.0)), None);
+        assert_eq!(at(vec3(0.0, 0.0, -200.0)), None);
+    }
+
+    #[test]
+    fn screen_rays_go_back_through_the_projected_point() {
+        let viewport = Viewport {
+            x: 100,
+            y: 0,
+            width: 100,
+            height: 100,
+        };
+        let camera = camera(viewport);
+        let world = vec3(1.0, 1.0, 0.0);
+        let screen = project(&camera, viewport, 100, 2.0, world).unwrap();
+        assert!(near(screen, (80.0, 20.0)));
+        let ray = unproject(&camera, viewport, 100, 2.0, screen).unwrap();
+        let hit = ray.at(-ray.origin.z / ray.direction.z);
+        assert!((hit - world).magnitude() < 1e-3);
+        // The left half of the window is outside the viewport.
+        assert_eq!(unproject(&camera, viewport, 100, 2.0, (25.0, 25.0)), None);
+    }
+
+    #[test]
+    fn clicks_in_letterbox_bars_pick_nothing() {
+        use dioxus::desktop::tao::dpi::PhysicalSize;
+
+        // A square frame in a wide surface, with bars left and right.
+        let viewport = crate::resize::letterbox((200, 100), PhysicalSize::new(64, 64));
+        assert_eq!(
+            (viewport.x, viewport.width, viewport.height),
+            (50, 100, 100)
+        );
+        let camera = camera(viewport);
+        let at = |x, y| pick_ray_in(&camera, viewport, PhysicalPoint { x, y });
+
+        let center = at(100.0, 50.0).unwrap();
+        assert!((center.direction - vec3(0.0, 0.0, -1.0)).magnitude() < 1e-4);
+        assert!(at(20.0, 50.0).is_none());
+        assert!(at(180.0, 50.0).is_none());
+        // The left edge belongs to the frame, the right one to the bar.
+        assert!(at(50.0, 50.0).is_some());
+        assert!(at(49.9, 50.0).is_none());
+        assert!(at(150.0, 50.0).is_none());
+        assert!(at(149.9, 50.0).is_some());
+    }
+}
@@ -0,0 +1,136 @@
+use dioxus::desktop::tao::event::{ElementState, MouseButton, WindowEvent};
+use dioxus::desktop::tao::window::Window;
+
+/// Grabs and hides the cursor while the right mouse button is held, for mouse-look with
+/// [three_d::FlyControl], and accumulates relative motion from raw device events.
+///
+/// What a grab does differs per platform: macOS and Wayland lock the cursor in place, while
+/// Windows and X11 only confine it to the window, where it still stops at the edges. Because
+/// of that, look deltas are taken from raw `DeviceEvent::MouseMotion` deltas rather than from
+/// cursor positions, which keep working in every mode.
+///
+/// The first raw delta after entering a grab is dropped, as some platforms report the warp of
+/// the cursor into its locked position as motion, and the accumulator is reset on every
+/// transition so a look never jumps.
+#[derive(Debug, Default)]
+pub struct PointerGrab {
+    grabbed: bool,
+    skip_next: bool,
+    accumulated: (f64, f64),
+}
+
+impl PointerGrab {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_grabbed(&self) -> bool {
+        self.grabbed
+    }
+
+    /// Grabs and hides the cursor. The grab may be refused by the platform, in which case
+    /// relative motion is still tracked with the cursor visible.
+    pub fn begin(&mut self, window: &Window) {
+        let _ = window.set_cursor_grab(true);
+        window.set_cursor_visible(false);
+        self.enter();
+    }
+
+    /// Releases and shows the cursor.
+    pub fn end(&mut self, window: &Window) {
+        let _ = window.set_cursor_grab(false);
+        window.set_cursor_visible(true);
+        self.exit();
+    }
+
+    /// Starts or ends the grab from right button and focus events.
+    pub fn handle_window_event(&mut self, window: &Window, event: &WindowEvent<'_>) {
+        match event {
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Right,
+                ..
+            } => match state {
+                ElementState::Pressed if !self.grabbed => self.begin(window),
+                ElementState::Released if self.grabbed => self.end(window),
+                _ => {}
+            },
+            WindowEvent::Focused(false) if self.grabbed => self.end(window),
+            _ => {}
+        }
+    }
+
+    /// Feeds a raw `DeviceEvent::MouseMotion` delta. Ignored unless grabbed.
+    pub fn handle_device_motion(&mut self, delta: (f64, f64)) {
+        if !self.grabbed {
+            return;
+        }
+        if self.skip_next {
+            self.skip_next = false;
+            return;
+        }
+        self.accumulated.0 += delta.0;
+        self.accumulated.1 += delta.1;
+    }
+
+    /// The motion accumulated since the last call, in the look delta convention of
+    /// [three_d::Event::MouseMotion] (y up).
+    pub fn take_delta(&mut self) -> (f32, f32) {
+        let (x, y) = std::mem::take(&mut self.accumulated);
+        (x as f32, -y as f32)
+    }
+
+    fn enter(&mut self) {
+        self.grabbed = true;
+        self.skip_next = true;
+        self.accumulated = (0.0, 0.0);
+    }
+
+    fn exit(&mut self) {
+        self.grabbed = false;
+        self.skip_next = false;
+        self.accumulated = (0.0, 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn motion_is_ignored_while_not_grabbed() {
+        let mut grab = PointerGrab::new();
+        grab.handle_device_motion((5.0, 5.0));
+        assert_eq!(grab.take_delta(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn first_delta_after_entering_is_dropped() {
+        let mut grab = PointerGrab::new();
+        grab.enter();
+        grab.handle_device_motion((300.0, -200.0));
+        grab.handle_device_motion((2.0, 3.0));
+        grab.handle_device_motion((1.0, 1.0));
+        assert_eq!(grab.take_delta(), (3.0, -4.0));
+        assert_eq!(grab.take_delta(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn accumulated_motion_resets_across_transitions() {
+        let mut grab = PointerGrab::new();
+        grab.enter();
+        grab.handle_device_motion((0.0, 0.0));
+        grab.handle_device_motion((4.0, 4.0));
+        grab.exit();
+        assert!(!grab.is_grabbed());
+        assert_eq!(grab.take_delta(), (0.0, 0.0));
+
+        grab.enter();
+        grab.handle_device_motion((4.0, 4.0));
+        grab.enter();
+        assert_eq!(grab.take_delta(), (0.0, 0.0));
+        grab.handle_device_motion((10.0, 0.0));
+        grab.handle_device_motion((1.0, 0.0));
+        assert_eq!(grab.take_delta(), (1.0, 0.0));
+    }
+}
@@ -0,0 +1,463 @@
+//! Fullscreen passes applied to the offscreen render before it is presented.
+
+use three_d::{
+    vec2, vec3, Blend, Context, Cull, DepthTest, Program, RenderStates, Texture2D, VertexBuffer,
+    Viewport, WriteMask,
+};
+
+/// Maps HDR scene color into the displayable range.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ToneMapping {
+    /// No curve, colors are only scaled by the exposure and clipped. With the default exposure
+    /// the scene renders straight to the screen, without an offscreen pass.
+    #[default]
+    None,
+    Reinhard,
+    /// The Narkowicz fit of the ACES filmic curve.
+    Aces,
+    /// John Hable's Uncharted 2 curve.
+    Filmic,
+}
+
+impl ToneMapping {
+    fn id(self) -> i32 {
+        match self {
+            ToneMapping::None => 0,
+            ToneMapping::Reinhard => 1,
+            ToneMapping::Aces => 2,
+            ToneMapping::Filmic => 3,
+        }
+    }
+}
+
+/// Simple color grading applied after tone mapping, see [crate::scene::Scene::set_color_grade].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorGrade {
+    /// Spreads colors away from middle gray, above `1.0`, or pulls them towards it, below.
+    /// Black stays black.
+    pub contrast: f32,
+    /// `0.0` is grayscale, above `1.0` is more colorful.
+    pub saturation: f32,
+    /// How much the corners darken, `0.0` not at all, `1.0` to black.
+    pub vignette: f32,
+}
+
+impl Default for ColorGrade {
+    fn default() -> Self {
+        Self::NEUTRAL
+    }
+}
+
+impl ColorGrade {
+    /// Leaves colors as they are.
+    pub const NEUTRAL: Self = Self {
+        contrast: 1.0,
+        saturation: 1.0,
+        vignette: 0.0,
+    };
+
+    pub fn is_neutral(&self) -> bool {
+        *self == Self::NEUTRAL
+    }
+
+    /// Negative contrast and saturation are raised to zero and the vignette is clamped to
+    /// `0.0..=1.0`.
+    pub fn clamped(self) -> Self {
+        Self {
+            contrast: self.contrast.max(0.0),
+            saturation: self.saturation.max(0.0),
+            vignette: self.vignette.clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Render states for a pass that overwrites the color of the target.
+pub fn fullscreen_render_states() -> RenderStates {
+    RenderStates {
+        write_mask: WriteMask::COLOR,
+        depth_test: DepthTest::Always,
+        blend: Blend::Disabled,
+        cull: Cull::None,
+    }
+}
+
+/// Prefixes the [Context::programs] cache key of [apply_effect] programs, keeping them apart
+/// from the ids `three_d` uses for its own.
+const EFFECT_PROGRAM_KEY: &[u8] = b"dioxus-three-d effect\0";
+
+const FULLSCREEN_VERTEX_SHADER: &str = "
+in vec3 position;
+out vec2 uvs;
+
+void main() {
+    uvs = 0.5 * position.xy + 0.5;
+    gl_Position = vec4(position, 1.0);
+}
+";
+
+/// Runs `fragment_shader` for every pixel of `viewport` of the bound render target, with the
+/// texture coordinates as `in vec2 uvs`, after `use_uniforms` has set its uniforms.
+///
+/// Each shader is compiled once and kept in [Context::programs], keyed by its source.
+pub(crate) fn apply_effect(
+    context: &Context,
+    fragment_shader: &str,
+    render_states: RenderStates,
+    viewport: Viewport,
+    use_uniforms: impl FnOnce(&Program),
+) {
+    let mut key = EFFECT_PROGRAM_KEY.to_vec();
+    key.extend_from_slice(fragment_shader.as_bytes());
+    let mut programs = context.programs.write().unwrap();
+    let program = programs.entry(key).or_insert_with(|| {
+        Program::from_source(context, FULLSCREEN_VERTEX_SHADER, fragment_shader)
+            .expect("failed compiling a fullscreen shader")
+    });
+    use_uniforms(program);
+    // One triangle covering the whole viewport, clipped to it.
+    let positions = VertexBuffer::new_with_data(
+        context,
+        &[
+            vec3(-3.0, -1.0, 0.0),
+            vec3(3.0, -1.0, 0.0),
+            vec3(0.0, 2.0, 0.0),
+        ],
+    );
+    program.use_vertex_attribute("position", &positions);
+    program.draw_arrays(render_states, viewport, 3);
+}
+
+/// GLSL helpers shared by the post-processing shaders.
+pub(crate) const SRGB_FUNCTIONS: &str = "
+vec3 srgb_to_linear(vec3 c) {
+    return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(vec3(0.04045), c));
+}
+vec3 linear_to_srgb(vec3 c) {
+    return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, step(vec3(0.0031308), c));
+}
+";
+
+const TONE_MAPPING_SHADER: &str = "
+uniform sampler2D colorMap;
+uniform float exposure;
+uniform int toneOperator;
+uniform float contrast;
+uniform float saturation;
+uniform float vignette;
+uniform sampler2D bloomMap;
+uniform float bloomIntensity;
+uniform int taps;
+uniform vec2 footprint;
+in vec2 uvs;
+layout (location = 0) out vec4 outColor;
+
+vec3 hable(vec3 x) {
+    const float A = 0.15, B = 0.50, C = 0.10, D = 0.20, E = 0.02, F = 0.30;
+    return ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
+}
+
+// In linear color, so hues don't shift. The neutral values skip their step, which keeps
+// the output bit for bit what it is without grading.
+vec3 grade(vec3 c, vec2 uv) {
+    if (contrast != 1.0) {
+        // Around middle gray, the linear value sRGB encodes as 0.5.
+        const float pivot = 0.214;
+        c = pivot * pow(c / pivot, vec3(contrast));
+    }
+    if (saturation != 1.0) {
+        float luma = dot(c, vec3(0.2126, 0.7152, 0.0722));
+        c = max(mix(vec3(luma), c, saturation), vec3(0.0));
+    }
+    if (vignette != 0.0) {
+        // 0 in the center, 1 in the corners.
+        vec2 d = (uv - 0.5) * 1.41421356;
+        c *= 1.0 - vignette * smoothstep(0.0, 1.0, dot(d, d));
+    }
+    return c;
+}
+
+vec4 tone_map(vec2 uv) {
+    vec4 color = texture(colorMap, uv);
+    vec3 c = srgb_to_linear(max(color.rgb, vec3(0.0))) * exposure;
+    if (bloomIntensity > 0.0) {
+        // Linear and exposed already, see BloomChain.
+        c += texture(bloomMap, uv).rgb * bloomIntensity;
+    }
+    if (toneOperator == 1) {
+        c = c / (1.0 + c);
+    } else if (toneOperator == 2) {
+        c = clamp((c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14), 0.0, 1.0);
+    } else if (toneOperator == 3) {
+        c = hable(2.0 * c) / hable(vec3(11.2));
+    }
+    c = grade(clamp(c, 0.0, 1.0), uv);
+    return vec4(linear_to_srgb(clamp(c, 0.0, 1.0)), color.a);
+}
+
+void main() {
+    // A box filter over the source texels covering this pixel, tone mapped per tap so
+    // bright supersamples don't dominate an edge.
+    vec4 sum = vec4(0.0);
+    for (int y = 0; y < taps; y++) {
+        for (int x = 0; x < taps; x++) {
+            vec2 offset = ((vec2(float(x), float(y)) + 0.5) / float(taps) - 0.5) * footprint;
+            sum += tone_map(uvs + offset);
+        }
+    }
+    outColor = sum / float(taps * taps);
+}
+";
+
+/// Applies `tone_mapping` to `source` and writes the result into `viewport` of the bound target.
+///
+/// `three_d` materials write sRGB encoded color, so the pass decodes to linear, applies the
+/// exposure and the curve there and encodes again; tone mapping in sRGB space would shift hues.
+pub fn tone_map(
+    context: &Context,
+    source: &Texture2D,
+    tone_mapping: ToneMapping,
+    exposure: f32,
+    viewport: Viewport,
+) {
+    tone_map_downsampled(context, source, tone_mapping, exposure, viewport, 1.0);
+}
+
+/// Like [tone_map], for a `source` rendered at `scale` times the size of `viewport`, which is
+/// box filtered down to it. See [crate::render_texture::RenderScale].
+pub fn tone_map_downsampled(
+    context: &Context,
+    source: &Texture2D,
+    tone_mapping: ToneMapping,
+    exposure: f32,
+    viewport: Viewport,
+    scale: f32,
+) {
+    let grade = ColorGrade::NEUTRAL;
+    tone_map_graded(
+        context,
+        source,
+        tone_mapping,
+        exposure,
+        grade,
+        viewport,
+        scale,
+    );
+}
+
+/// Like [tone_map_downsampled], with `grade` applied to the tone mapped color before it is
+/// encoded to sRGB again.
+pub fn tone_map_graded(
+    context: &Context,
+    source: &Texture2D,
+    tone_mapping: ToneMapping,
+    exposure: f32,
+    grade: ColorGrade,
+    viewport: Viewport,
+    scale: f32,
+) {
+    tone_map_bloomed(
+        context,
+        source,
+        tone_mapping,
+        exposure,
+        grade,
+        None,
+        viewport,
+        scale,
+    );
+}
+
+/// Like [tone_map_graded], with `bloom`, a texture from [crate::bloom::BloomChain] and its
+/// intensity, added to the exposed color before the curve.
+#[allow(clippy::too_many_arguments)]
+pub fn tone_map_bloomed(
+    context: &Context,
+    source: &Texture2D,
+    tone_mapping: ToneMapping,
+    exposure: f32,
+    grade: ColorGrade,
+    bloom: Option<(&Texture2D, f32)>,
+    viewport: Viewport,
+    scale: f32,
+) {
+    let grade = grade.clamped();
+    // Without bloom the source stands in, the shader doesn't sample it then.
+    let (bloom_map, bloom_intensity) = bloom.unwrap_or((source, 0.0));
+    // Each bilinear tap lands between texels and averages 2x2 of them, so half as many taps
+    // per axis cover the footprint.
+    let taps = (scale / 2.0).ceil().max(1.0) as i32;
+    let footprint = vec2(
+        1.0 / viewport.width.max(1) as f32,
+        1.0 / viewport.height.max(1) as f32,
+    );
+    let shader = format!("{}{}", SRGB_FUNCTIONS, TONE_MAPPING_SHADER);
+    apply_effect(
+        context,
+        &shader,
+        fullscreen_render_states(),
+        viewport,
+        |program| {
+            program.use_texture("colorMap", source);
+            program.use_uniform("exposure", exposure);
+            program.use_uniform("toneOperator", tone_mapping.id());
+            program.use_uniform("contrast", grade.contrast);
+            program.use_uniform("saturation", grade.saturation);
+            program.use_uniform("vignette", grade.vignette);
+            program.use_texture("bloomMap", bloom_map);
+            program.use_uniform("bloomIntensity", bloom_intensity.max(0.0));
+            program.use_uniform("taps", taps);
+            program.use_uniform("footprint", footprint);
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_grade_is_clamped_into_range() {
+        let grade = ColorGrade {
+            contrast: -1.0,
+            saturation: -0.5,
+            vignette: 2.0,
+        };
+        let clamped = grade.clamped();
+        assert_eq!(clamped.contrast, 0.0);
+        assert_eq!(clamped.saturation, 0.0);
+        assert_eq!(clamped.vignette, 1.0);
+        assert!(ColorGrade::default().is_neutral());
+        assert!(!clamped.is_neutral());
+    }
+
+    /// The ACES fit the shader applies, per channel.
+    fn aces(c: f32) -> f32 {
+        ((c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14)).clamp(0.0, 1.0)
+    }
+
+    fn linear_to_srgb(c: f32) -> f32 {
+        if c < 0.0031308 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn aces_maps_hdr_values_to_the_expected_ldr_ones() {
+        use three_d::{ClearState, CpuTexture, Interpolation, TextureData};
+
+        use crate::render_texture::Draw;
+        use crate::testing::{assert_pixel_near, TestHarness};
+
+        let harness = TestHarness::new(8, 8).unwrap();
+        let context = &harness.context().context;
+        for linear in [0.18_f32, 1.0, 4.0, 16.0] {
+            // The pass decodes its source from sRGB, so feed the encoded HDR value.
+            let encoded = linear_to_srgb(linear);
+            let source = Texture2D::new(
+                context,
+                &CpuTexture {
+                    data: TextureData::RgbaF32(vec![[encoded, encoded, encoded, 1.0]]),
+                    min_filter: Interpolation::Nearest,
+                    mag_filter: Interpolation::Nearest,
+                    mip_map_filter: None,
+                    ..Default::default()
+                },
+            );
+            let viewport = harness.viewport();
+            let image = harness.render(|screen| {
+                screen
+                    .clear(ClearState::default())
+                    .draw(|| tone_map(context, &source, ToneMapping::Aces, 1.0, viewport));
+            });
+            let expected = (linear_to_srgb(aces(linear)) * 255.0).round() as u8;
+            assert_pixel_near(&image, 4, 4, [expected, expected, expected, 255], 2);
+        }
+    }
+
+    fn srgb_to_linear(c: f32) -> f32 {
+        if c < 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn doubling_the_exposure_doubles_the_linear_luminance() {
+        use three_d::{ClearState, CpuTexture, Interpolation, TextureData};
+
+        use crate::render_texture::Draw;
+        use crate::testing::TestHarness;
+
+        let harness = TestHarness::new(8, 8).unwrap();
+        let context = &harness.context().context;
+        let gray = linear_to_srgb(0.18);
+        let source = Texture2D::new(
+            context,
+            &CpuTexture {
+                data: TextureData::RgbaF32(vec![[gray, gray, gray, 1.0]]),
+                min_filter: Interpolation::Nearest,
+                mag_filter: Interpolation::Nearest,
+                mip_map_filter: None,
+                ..Default::default()
+            },
+        );
+        let viewport = harness.viewport();
+        let luminance = |exposure: f32| {
+            let image = harness.render(|screen| {
+                screen
+                    .clear(ClearState::default())
+                    .draw(|| tone_map(context, &source, ToneMapping::None, exposure, viewport));
+            });
+            srgb_to_linear(image.pixel(4, 4)[1] as f32 / 255.0)
+        };
+        let ratio = luminance(2.0) / luminance(1.0);
+        assert!((ratio - 2.0).abs() < 0.1, "{ratio}");
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn neutral_grade_keeps_mid_gray_and_contrast_spreads_around_it() {
+        use three_d::{ClearState, CpuTexture, Interpolation, TextureData};
+
+        use crate::render_texture::Draw;
+        use crate::testing::TestHarness;
+
+        let harness = TestHarness::new(8, 8).unwrap();
+        let context = &harness.context().context;
+        let viewport = harness.viewport();
+        // The sRGB encoded value written for `encoded` once graded with `grade`.
+        let graded = |encoded: f32, grade: ColorGrade| {
+            let source = Texture2D::new(
+                context,
+                &CpuTexture {
+                    data: TextureData::RgbaF32(vec![[encoded, encoded, encoded, 1.0]]),
+                    min_filter: Interpolation::Nearest,
+                    mag_filter: Interpolation::Nearest,
+                    mip_map_filter: None,
+                    ..Default::default()
+                },
+            );
+            let image = harness.render(|screen| {
+                screen.clear(ClearState::default()).draw(|| {
+                    let tone_mapping = ToneMapping::None;
+                    tone_map_graded(context, &source, tone_mapping, 1.0, grade, viewport, 1.0)
+                });
+            });
+            image.pixel(4, 4)[1] as f32 / 255.0
+        };
+        let neutral = graded(0.5, ColorGrade::NEUTRAL);
+        assert!((neutral - 0.5).abs() < 1.5 / 255.0, "{neutral}");
+
+        let contrast = ColorGrade {
+            contrast: 1.5,
+            ..ColorGrade::NEUTRAL
+        };
+        assert!(graded(0.7, contrast) > graded(0.7, ColorGrade::NEUTRAL) + 0.02);
+        assert!(graded(0.3, contrast) < graded(0.3, ColorGrade::NEUTRAL) - 0.02);
+    }
+}
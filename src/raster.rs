@@ -0,0 +1,114 @@
+use std::ffi::{c_void, CStr};
+use std::sync::OnceLock;
+
+use three_d::context::{self as gl, HasContext};
+use three_d::Context;
+
+/// `glPointSize`, which the `glow` bindings of `three_d` leave out, see [load_point_size].
+static POINT_SIZE: OnceLock<unsafe extern "system" fn(f32)> = OnceLock::new();
+
+/// How triangles are rasterized.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FillMode {
+    #[default]
+    Solid,
+    /// Only the triangle edges, `line_width` wide.
+    Wireframe,
+    /// Only the vertices, as `point_size` squares.
+    Points,
+}
+
+/// Rasterization settings for the scene's objects, see [crate::scene::Scene::set_raster].
+///
+/// Sizes are in logical pixels and scaled by the window's scale factor, so wireframes and
+/// points look the same on HiDPI screens, then clamped to what the driver can draw.
+///
+/// This uses `glPolygonMode`, which OpenGL ES lacks; there the scene always renders solid.
+/// Core profile drivers may also only draw 1 pixel wide lines, see [line_width_range]. Thick
+/// wireframes then need real geometry, e.g. instanced cylinders along the edges.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RasterSettings {
+    pub fill_mode: FillMode,
+    pub point_size: f32,
+    pub line_width: f32,
+}
+
+impl Default for RasterSettings {
+    fn default() -> Self {
+        Self {
+            fill_mode: FillMode::default(),
+            point_size: 1.0,
+            line_width: 1.0,
+        }
+    }
+}
+
+impl RasterSettings {
+    /// Applies the settings to the GL state before drawing the objects.
+    pub(crate) fn begin(&self, context: &Context, scale_factor: f32) {
+        if self.fill_mode == FillMode::Solid || context.version().is_embedded {
+            return;
+        }
+        let (min_point, max_point) = point_size_range(context);
+        let (min_line, max_line) = line_width_range(context);
+        let point_size = (self.point_size * scale_factor).clamp(min_point, max_point);
+        let line_width = (self.line_width * scale_factor).clamp(min_line, max_line);
+        let mode = match self.fill_mode {
+            FillMode::Wireframe => gl::LINE,
+            _ => gl::POINT,
+        };
+        unsafe {
+            set_point_size(point_size);
+            context.line_width(line_width);
+            context.polygon_mode(gl::FRONT_AND_BACK, mode);
+        }
+    }
+
+    /// Restores the defaults, so passes drawn afterwards rasterize normally.
+    pub(crate) fn end(&self, context: &Context) {
+        if self.fill_mode == FillMode::Solid || context.version().is_embedded {
+            return;
+        }
+        unsafe {
+            context.polygon_mode(gl::FRONT_AND_BACK, gl::FILL);
+            set_point_size(1.0);
+            context.line_width(1.0);
+        }
+    }
+}
+
+/// Looks up `glPointSize` for [RasterSettings::point_size], done by
+/// [crate::windowed_context::WindowedContext] when it creates a context. Without it points are
+/// drawn at the default size of one pixel.
+pub(crate) fn load_point_size(get_proc_address: impl FnOnce(&CStr) -> *const c_void) {
+    let pointer = get_proc_address(c"glPointSize");
+    if !pointer.is_null() {
+        // SAFETY: a non-null address for glPointSize is that function, `void(GLfloat)`.
+        let point_size = unsafe {
+            std::mem::transmute::<*const c_void, unsafe extern "system" fn(f32)>(pointer)
+        };
+        let _ = POINT_SIZE.set(point_size);
+    }
+}
+
+/// Calls `glPointSize` on the current context, if [load_point_size] found it.
+unsafe fn set_point_size(size: f32) {
+    if let Some(point_size) = POINT_SIZE.get() {
+        point_size(size);
+    }
+}
+
+/// The smallest and largest point size, in physical pixels, the driver draws.
+pub fn point_size_range(context: &Context) -> (f32, f32) {
+    let mut range = [1.0; 2];
+    unsafe { context.get_parameter_f32_slice(gl::POINT_SIZE_RANGE, &mut range) };
+    (range[0].max(1.0), range[1].max(1.0))
+}
+
+/// The smallest and largest line width, in physical pixels, the driver draws. Core profile
+/// drivers commonly report `(1.0, 1.0)`.
+pub fn line_width_range(context: &Context) -> (f32, f32) {
+    let mut range = [1.0; 2];
+    unsafe { context.get_parameter_f32_slice(gl::ALIASED_LINE_WIDTH_RANGE, &mut range) };
+    (range[0].max(1.0), range[1].max(1.0))
+}
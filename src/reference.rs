@@ -0,0 +1,129 @@
+use three_d::{
+    vec2, vec3, CpuMaterial, CpuMesh, CpuModel, CpuTexture, Interpolation, Mat4, SquareMatrix,
+    Srgba, TextureData,
+};
+use three_d_asset::{Geometry, Primitive};
+
+/// Squares per side of the checker texture of [reference_model].
+pub const REFERENCE_CHECKER_SQUARES: u32 = 8;
+
+/// Pixels per side of each checker square.
+const CHECKER_SQUARE_PIXELS: u32 = 8;
+
+/// A checkered sphere next to a plain cube, built in code so the pipeline can be tried
+/// without any files, see [crate::scene::Scene::default_reference].
+///
+/// Always the same model: the parts are `reference_sphere` and `reference_cube`, together
+/// spanning -2.5 to 1 along X and -1 to 1 along Y and Z. The checker makes texture coordinates,
+/// filtering and the sRGB handling visible, the cube flat faces and hard edges.
+pub fn reference_model() -> CpuModel {
+    let checker = CpuMaterial {
+        name: "reference_checker".to_string(),
+        albedo: Srgba::WHITE,
+        albedo_texture: Some(checker_texture(
+            Srgba::new_opaque(230, 230, 230),
+            Srgba::new_opaque(60, 60, 60),
+        )),
+        roughness: 0.5,
+        metallic: 0.0,
+        ..Default::default()
+    };
+    let plain = CpuMaterial {
+        name: "reference_plain".to_string(),
+        albedo: Srgba::new_opaque(70, 130, 200),
+        roughness: 0.4,
+        metallic: 0.0,
+        ..Default::default()
+    };
+    let cube = Mat4::from_translation(vec3(-1.75, 0.0, 0.0)) * Mat4::from_scale(0.75);
+    CpuModel {
+        name: "reference".to_string(),
+        geometries: vec![
+            primitive("reference_sphere", sphere(), Mat4::identity(), 0),
+            primitive("reference_cube", CpuMesh::cube(), cube, 1),
+        ],
+        materials: vec![checker, plain],
+    }
+}
+
+/// A unit sphere with latitude and longitude texture coordinates, which
+/// [CpuMesh::sphere] doesn't come with.
+fn sphere() -> CpuMesh {
+    let mut mesh = CpuMesh::sphere(32);
+    let uvs = mesh
+        .positions
+        .to_f32()
+        .into_iter()
+        .map(|position| {
+            let u = position.z.atan2(position.x) / std::f32::consts::TAU + 0.5;
+            let v = position.y.clamp(-1.0, 1.0).asin() / std::f32::consts::PI + 0.5;
+            vec2(u, v)
+        })
+        .collect();
+    mesh.uvs = Some(uvs);
+    mesh
+}
+
+fn primitive(name: &str, mesh: CpuMesh, transformation: Mat4, material: usize) -> Primitive {
+    Primitive {
+        name: name.to_string(),
+        transformation,
+        animations: Vec::new(),
+        geometry: Geometry::Triangles(mesh),
+        material_index: Some(material),
+    }
+}
+
+/// A [REFERENCE_CHECKER_SQUARES] by [REFERENCE_CHECKER_SQUARES] checker of `light` and
+/// `dark`, sampled without blurring the squares up close.
+fn checker_texture(light: Srgba, dark: Srgba) -> CpuTexture {
+    let size = REFERENCE_CHECKER_SQUARES * CHECKER_SQUARE_PIXELS;
+    let pixels = (0..size * size)
+        .map(|index| {
+            let (x, y) = (index % size, index / size);
+            let odd = (x / CHECKER_SQUARE_PIXELS + y / CHECKER_SQUARE_PIXELS) % 2 == 1;
+            let color = if odd { dark } else { light };
+            [color.r, color.g, color.b, color.a]
+        })
+        .collect();
+    CpuTexture {
+        name: "reference_checker".to_string(),
+        data: TextureData::RgbaU8(pixels),
+        width: size,
+        height: size,
+        mag_filter: Interpolation::Nearest,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::{AxisAlignedBoundingBox, InnerSpace};
+
+    use super::*;
+
+    #[test]
+    fn reference_model_is_the_same_every_time_and_spans_its_documented_bounds() {
+        let model = reference_model();
+        let names: Vec<&str> = model
+            .geometries
+            .iter()
+            .map(|part| part.name.as_str())
+            .collect();
+        assert_eq!(names, ["reference_sphere", "reference_cube"]);
+        assert_eq!(format!("{model:?}"), format!("{:?}", reference_model()));
+
+        let mut bounds = AxisAlignedBoundingBox::EMPTY;
+        for part in &model.geometries {
+            let Geometry::Triangles(mesh) = &part.geometry else {
+                panic!("expected triangles");
+            };
+            bounds.expand_with_aabb(&AxisAlignedBoundingBox::new_with_transformed_positions(
+                &mesh.positions.to_f32(),
+                &part.transformation,
+            ));
+        }
+        assert!((bounds.min() - vec3(-2.5, -1.0, -1.0)).magnitude() < 1e-4);
+        assert!((bounds.max() - vec3(1.0, 1.0, 1.0)).magnitude() < 1e-4);
+    }
+}
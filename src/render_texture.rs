@@ -0,0 +1,382 @@
+use std::convert::Infallible;
+
+use three_d::context::{self as gl, HasContext};
+use three_d::{
+    f16, ColorTarget, Context, DepthTexture2D, DepthTextureDataType, Interpolation, RenderTarget,
+    RenderTargetMultisample, ScissorBox, Texture2D, TextureDataType, Wrapping,
+};
+
+use crate::windowed_context::ResizeListener;
+
+/// A single-sampled offscreen color + depth target, for render-to-texture and post-processing.
+///
+/// The color attachment is half-float so values above 1.0 survive until tone mapping.
+pub struct RenderTexture {
+    context: Context,
+    color: Texture2D,
+    depth: DepthTexture2D,
+}
+
+impl RenderTexture {
+    pub fn new(context: &Context, width: u32, height: u32) -> Self {
+        let (width, height) = (width.max(1), height.max(1));
+        Self {
+            context: context.clone(),
+            color: new_color_texture(context, width, height),
+            depth: new_depth_texture(context, width, height),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.color.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.color.height()
+    }
+
+    /// Reallocates the attachments if the size changed. The contents are lost when it did.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let (width, height) = (width.max(1), height.max(1));
+        if width == self.width() && height == self.height() {
+            return;
+        }
+        self.color = new_color_texture(&self.context, width, height);
+        self.depth = new_depth_texture(&self.context, width, height);
+    }
+
+    /// A render target writing into this texture's color and depth attachments.
+    pub fn render_target(&mut self) -> RenderTarget<'_> {
+        RenderTarget::new(
+            self.color.as_color_target(None),
+            self.depth.as_depth_target(),
+        )
+    }
+
+    pub fn color_texture(&self) -> &Texture2D {
+        &self.color
+    }
+
+    pub fn depth_texture(&self) -> &DepthTexture2D {
+        &self.depth
+    }
+
+    /// Reads the depth attachment back, bottom row first, in window space `0.0..=1.0`.
+    pub fn read_depth(&mut self) -> Vec<f32> {
+        self.depth.as_depth_target().read()
+    }
+}
+
+/// Supersampling: the offscreen target is `factor` times the viewport's size in each
+/// dimension and filtered down when it is presented, for crisper edges than MSAA, e.g. in
+/// screenshots, at `factor²` times the shading cost.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderScale {
+    pub factor: f32,
+}
+
+impl Default for RenderScale {
+    fn default() -> Self {
+        Self { factor: 1.0 }
+    }
+}
+
+impl RenderScale {
+    /// The largest factor used, beyond it each step costs a lot of memory for little gain.
+    pub const MAX_FACTOR: f32 = 4.0;
+
+    /// Whether this renders at the viewport's resolution.
+    pub fn is_native(&self) -> bool {
+        self.factor == 1.0
+    }
+
+    /// The offscreen size for a `width` by `height` viewport, and the factor it amounts to.
+    ///
+    /// The factor is limited to `1.0..=`[RenderScale::MAX_FACTOR] and so that neither
+    /// dimension exceeds the driver's [max_texture_size]; lowering it is logged as a warning.
+    pub fn scaled_size(&self, context: &Context, width: u32, height: u32) -> (u32, u32, f32) {
+        let requested = if self.factor.is_finite() {
+            self.factor
+        } else {
+            1.0
+        };
+        let largest = width.max(height).max(1) as f32;
+        let limit = (max_texture_size(context) as f32 / largest).max(1.0);
+        let factor = requested.clamp(1.0, Self::MAX_FACTOR.min(limit));
+        if factor < requested {
+            log_warn!(
+                requested,
+                factor,
+                "render scale lowered to fit the texture size limit"
+            );
+        }
+        let scaled = |size: u32| ((size as f32 * factor).round() as u32).max(1);
+        (scaled(width), scaled(height), factor)
+    }
+}
+
+/// The largest width and height the driver supports for textures.
+pub fn max_texture_size(context: &Context) -> u32 {
+    unsafe { context.get_parameter_i32(gl::MAX_TEXTURE_SIZE) }.max(1) as u32
+}
+
+/// The highest sample count the driver supports for multisampled render targets.
+pub fn max_samples(context: &Context) -> u32 {
+    unsafe { context.get_parameter_i32(gl::MAX_SAMPLES) }.max(0) as u32
+}
+
+/// A multisampled offscreen target together with the single-sampled [RenderTexture] it
+/// resolves into.
+///
+/// Render into [MultisampledRenderTexture::target], then call
+/// [MultisampledRenderTexture::resolve] before sampling the result in a post-process pass.
+pub struct MultisampledRenderTexture {
+    context: Context,
+    target: RenderTargetMultisample<[f16; 4], f32>,
+    resolved: RenderTexture,
+    number_of_samples: u32,
+}
+
+impl MultisampledRenderTexture {
+    pub fn new(context: &Context, width: u32, height: u32, number_of_samples: u32) -> Self {
+        let (width, height) = (width.max(1), height.max(1));
+        Self {
+            context: context.clone(),
+            target: RenderTargetMultisample::new(context, width, height, number_of_samples),
+            resolved: RenderTexture::new(context, width, height),
+            number_of_samples,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.resolved.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.resolved.height()
+    }
+
+    pub fn number_of_samples(&self) -> u32 {
+        self.number_of_samples
+    }
+
+    /// Reallocates the multisampled attachments with a new sample count. The resolve texture
+    /// is kept, it doesn't depend on the sample count.
+    pub fn set_number_of_samples(&mut self, number_of_samples: u32) {
+        if number_of_samples == self.number_of_samples {
+            return;
+        }
+        self.number_of_samples = number_of_samples;
+        self.target = RenderTargetMultisample::new(
+            &self.context,
+            self.width(),
+            self.height(),
+            number_of_samples,
+        );
+    }
+
+    /// Reallocates both the multisampled and the resolve attachments at the new size, so the
+    /// two never disagree about dimensions.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let (width, height) = (width.max(1), height.max(1));
+        if width == self.width() && height == self.height() {
+            return;
+        }
+        self.target =
+            RenderTargetMultisample::new(&self.context, width, height, self.number_of_samples);
+        self.resolved.resize(width, height);
+    }
+
+    /// The multisampled target to render into.
+    pub fn target(&self) -> &RenderTargetMultisample<[f16; 4], f32> {
+        &self.target
+    }
+
+    /// Blits the multisampled color and depth into the resolve texture and returns it.
+    pub fn resolve(&mut self) -> &RenderTexture {
+        self.target.resolve_to(&self.resolved.render_target());
+        &self.resolved
+    }
+
+    /// The resolve texture as of the last [MultisampledRenderTexture::resolve].
+    pub fn resolved_mut(&mut self) -> &mut RenderTexture {
+        &mut self.resolved
+    }
+}
+
+impl ResizeListener for RenderTexture {
+    fn resized(&mut self, width: u32, height: u32) {
+        self.resize(width, height);
+    }
+}
+
+impl ResizeListener for MultisampledRenderTexture {
+    fn resized(&mut self, width: u32, height: u32) {
+        self.resize(width, height);
+    }
+}
+
+/// `write` and `write_partially` for drawing that can't fail, returning the target so the
+/// calls chain like `clear` and `render` do.
+pub(crate) trait Draw {
+    /// Runs `draw` with this target bound, limited to its scissor box.
+    fn draw(&self, draw: impl FnOnce()) -> &Self;
+
+    /// Runs `draw` with this target bound, limited to `scissor_box`.
+    fn draw_partially(&self, scissor_box: ScissorBox, draw: impl FnOnce()) -> &Self;
+}
+
+macro_rules! impl_draw {
+    ($target:ty $(, $param:ident: $bound:ident)*) => {
+        impl<$($param: $bound),*> Draw for $target {
+            fn draw(&self, draw: impl FnOnce()) -> &Self {
+                self.draw_partially(self.scissor_box(), draw)
+            }
+
+            fn draw_partially(&self, scissor_box: ScissorBox, draw: impl FnOnce()) -> &Self {
+                let result = self.write_partially::<Infallible>(scissor_box, || {
+                    draw();
+                    Ok(())
+                });
+                match result {
+                    Ok(target) => target,
+                    Err(never) => match never {},
+                }
+            }
+        }
+    };
+}
+
+impl_draw!(RenderTarget<'_>);
+impl_draw!(ColorTarget<'_>);
+impl_draw!(RenderTargetMultisample<C, D>, C: TextureDataType, D: DepthTextureDataType);
+
+fn new_color_texture(context: &Context, width: u32, height: u32) -> Texture2D {
+    Texture2D::new_empty::<[f16; 4]>(
+        context,
+        width,
+        height,
+        Interpolation::Linear,
+        Interpolation::Linear,
+        None,
+        Wrapping::ClampToEdge,
+        Wrapping::ClampToEdge,
+    )
+}
+
+fn new_depth_texture(context: &Context, width: u32, height: u32) -> DepthTexture2D {
+    DepthTexture2D::new::<f32>(
+        context,
+        width,
+        height,
+        Wrapping::ClampToEdge,
+        Wrapping::ClampToEdge,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::{
+        vec3, Camera, ClearState, ColorMaterial, CpuMesh, Gm, Mesh, Object, Positions, Srgba,
+        Viewport,
+    };
+
+    use super::*;
+    use crate::testing::TestHarness;
+
+    const SIZE: u32 = 32;
+
+    /// A white triangle on black, its hypotenuse running diagonally across the target.
+    fn triangle(context: &Context) -> (Camera, Gm<Mesh, ColorMaterial>) {
+        let camera = Camera::new_2d(Viewport::new_at_origo(SIZE, SIZE));
+        let size = SIZE as f32;
+        let mesh = CpuMesh {
+            positions: Positions::F32(vec![
+                vec3(0.0, 0.0, 0.0),
+                vec3(size, 0.0, 0.0),
+                vec3(0.0, size * 0.7, 0.0),
+            ]),
+            ..Default::default()
+        };
+        let material = ColorMaterial {
+            color: Srgba::WHITE,
+            ..Default::default()
+        };
+        (camera, Gm::new(Mesh::new(context, &mesh), material))
+    }
+
+    /// Pixels only partly covered by the triangle, neither black nor white.
+    fn edge_pixels(texture: &mut RenderTexture) -> usize {
+        texture
+            .render_target()
+            .read_color::<[u8; 4]>()
+            .iter()
+            .filter(|[red, ..]| (13..242).contains(red))
+            .count()
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn resolved_msaa_edge_is_smoother_than_single_sampled() {
+        let harness = TestHarness::new(SIZE, SIZE).unwrap();
+        let context = &harness.context().context;
+        let (camera, triangle) = triangle(context);
+        let clear = ClearState::color_and_depth(0.0, 0.0, 0.0, 1.0, 1.0);
+
+        let mut single = RenderTexture::new(context, SIZE, SIZE);
+        single
+            .render_target()
+            .clear(clear)
+            .render(&camera, &triangle, &[]);
+        let multisampled = MultisampledRenderTexture::new(context, SIZE, SIZE, 4);
+        multisampled
+            .target()
+            .clear(clear)
+            .draw(|| triangle.render(&camera, &[]));
+        let mut resolved = RenderTexture::new(context, SIZE, SIZE);
+        multisampled.target().resolve_to(&resolved.render_target());
+
+        assert_eq!(edge_pixels(&mut single), 0);
+        assert!(edge_pixels(&mut resolved) > 0);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn resize_reallocates_both_attachments() {
+        let harness = TestHarness::new(SIZE, SIZE).unwrap();
+        let mut multisampled = MultisampledRenderTexture::new(&harness.context().context, 8, 8, 4);
+        multisampled.resize(20, 10);
+        assert_eq!((multisampled.width(), multisampled.height()), (20, 10));
+        let target = multisampled.target();
+        assert_eq!((target.width(), target.height()), (20, 10));
+        assert_eq!(multisampled.number_of_samples(), 4);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn sample_count_changes_keep_the_size() {
+        let harness = TestHarness::new(SIZE, SIZE).unwrap();
+        let mut multisampled = MultisampledRenderTexture::new(&harness.context().context, 8, 8, 2);
+        multisampled.set_number_of_samples(4);
+        assert_eq!(multisampled.number_of_samples(), 4);
+        let target = multisampled.target();
+        assert_eq!((target.width(), target.height()), (8, 8));
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn render_scale_is_limited_by_the_texture_size() {
+        let harness = TestHarness::new(SIZE, SIZE).unwrap();
+        let context = &harness.context().context;
+        let scale = |factor| RenderScale { factor };
+        assert_eq!(scale(2.0).scaled_size(context, 30, 20), (60, 40, 2.0));
+        assert_eq!(
+            scale(16.0).scaled_size(context, 30, 20),
+            (120, 80, RenderScale::MAX_FACTOR)
+        );
+        assert_eq!(scale(0.5).scaled_size(context, 30, 20), (30, 20, 1.0));
+        assert_eq!(scale(f32::NAN).scaled_size(context, 30, 20), (30, 20, 1.0));
+        let max = max_texture_size(context);
+        assert_eq!(scale(2.0).scaled_size(context, max, 10), (max, 10, 1.0));
+    }
+}
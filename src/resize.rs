@@ -0,0 +1,192 @@
+use std::time::{Duration, Instant};
+
+use dioxus::desktop::tao::dpi::PhysicalSize;
+use three_d::Viewport;
+
+/// The default of [ResizeDebouncer::new].
+pub const DEFAULT_MIN_RESIZE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Throttles surface resizes while the window is dragged to a new size.
+///
+/// Some drivers reallocate the swapchain on every resize, which stutters when done for each of
+/// the many `Resized` events of a drag. Sizes are [pushed](ResizeDebouncer::push) as they
+/// arrive, and [ResizeDebouncer::poll] hands out the latest one at most once per
+/// [ResizeDebouncer::min_interval]. The first resize after a quiet period goes through right
+/// away, and the last one of a drag is never dropped: it is due as soon as the interval since
+/// the previous one passed, so keep polling while [ResizeDebouncer::is_pending].
+///
+/// In between, [ResizeDebouncer::viewport] letterboxes the frame in the surface of the old
+/// size, so the picture keeps the window's proportions instead of stretching.
+#[derive(Clone, Debug)]
+pub struct ResizeDebouncer {
+    min_interval: Duration,
+    pending: Option<PhysicalSize<u32>>,
+    last_applied: Option<Instant>,
+}
+
+impl Default for ResizeDebouncer {
+    fn default() -> Self {
+        Self::new(DEFAULT_MIN_RESIZE_INTERVAL)
+    }
+}
+
+impl ResizeDebouncer {
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            pending: None,
+            last_applied: None,
+        }
+    }
+
+    pub fn min_interval(&self) -> Duration {
+        self.min_interval
+    }
+
+    /// The shortest time between two resizes [ResizeDebouncer::poll] hands out. Zero applies
+    /// every resize as it comes.
+    pub fn set_min_interval(&mut self, min_interval: Duration) {
+        self.min_interval = min_interval;
+    }
+
+    /// Records the window's new size, replacing one that wasn't applied yet.
+    pub fn push(&mut self, size: PhysicalSize<u32>) {
+        self.pending = Some(size);
+    }
+
+    /// The size waiting to be applied, if any.
+    pub fn pending(&self) -> Option<PhysicalSize<u32>> {
+        self.pending
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// When the pending size can be applied, `None` without one.
+    pub fn due(&self) -> Option<Instant> {
+        self.pending?;
+        Some(match self.last_applied {
+            Some(last_applied) => last_applied + self.min_interval,
+            None => Instant::now(),
+        })
+    }
+
+    /// The size to resize the surface to at `now`, if one is pending and the interval since
+    /// the last one passed. It counts as applied from then on.
+    pub fn poll(&mut self, now: Instant) -> Option<PhysicalSize<u32>> {
+        self.pending?;
+        let since_last = self
+            .last_applied
+            .map(|last_applied| now.saturating_duration_since(last_applied));
+        if since_last.is_some_and(|since_last| since_last < self.min_interval) {
+            return None;
+        }
+        self.last_applied = Some(now);
+        self.pending.take()
+    }
+
+    /// Where to render in a surface of `surface` size: all of it, unless a resize is pending,
+    /// then the largest centered part of it with the proportions of the pending size.
+    pub fn viewport(&self, surface: (u32, u32)) -> Viewport {
+        match self.pending {
+            Some(size) => letterbox(surface, size),
+            None => Viewport::new_at_origo(surface.0, surface.1),
+        }
+    }
+}
+
+/// The largest viewport centered in `surface` with the aspect ratio of `size`, with bars on
+/// two opposite sides to make up the difference. Degenerate sizes get the whole surface.
+pub fn letterbox(surface: (u32, u32), size: PhysicalSize<u32>) -> Viewport {
+    let (surface_width, surface_height) = surface;
+    if size.width == 0 || size.height == 0 || surface_width == 0 || surface_height == 0 {
+        return Viewport::new_at_origo(surface_width, surface_height);
+    }
+    let aspect = size.width as f64 / size.height as f64;
+    let surface_aspect = surface_width as f64 / surface_height as f64;
+    let (width, height) = if aspect > surface_aspect {
+        let height = (surface_width as f64 / aspect).round() as u32;
+        (surface_width, height.clamp(1, surface_height))
+    } else {
+        let width = (surface_height as f64 * aspect).round() as u32;
+        (width.clamp(1, surface_width), surface_height)
+    };
+    Viewport {
+        x: ((surface_width - width) / 2) as i32,
+        y: ((surface_height - height) / 2) as i32,
+        width,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letterbox_centers_a_frame_of_another_aspect() {
+        let wide = letterbox((800, 600), PhysicalSize::new(400, 100));
+        assert_eq!(
+            (wide.x, wide.y, wide.width, wide.height),
+            (0, 200, 800, 200)
+        );
+        let tall = letterbox((800, 600), PhysicalSize::new(300, 300));
+        assert_eq!(
+            (tall.x, tall.y, tall.width, tall.height),
+            (100, 0, 600, 600)
+        );
+    }
+
+    #[test]
+    fn whole_surface_without_a_pending_resize() {
+        let mut debouncer = ResizeDebouncer::new(Duration::from_secs(1));
+        assert_eq!(
+            debouncer.viewport((640, 480)),
+            Viewport::new_at_origo(640, 480)
+        );
+        debouncer.push(PhysicalSize::new(480, 480));
+        let viewport = debouncer.viewport((640, 480));
+        assert_eq!((viewport.x, viewport.width), (80, 480));
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn letterboxed_frame_is_centered_rather_than_cropped() {
+        use three_d::{degrees, vec3, Camera, ClearState, Srgba};
+
+        use crate::controls::Controls;
+        use crate::lights::Lights;
+        use crate::scene::Scene;
+        use crate::testing::TestHarness;
+
+        let harness = TestHarness::new(80, 40).unwrap();
+        let context = harness.context();
+        let camera = Camera::new_perspective(
+            Viewport::new_at_origo(1, 1),
+            vec3(0.0, 0.0, 4.0),
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            degrees(45.0),
+            0.1,
+            100.0,
+        );
+        let controls = Controls::new(*camera.target(), 1.0, 100.0);
+        let mut scene = Scene::new(camera, controls, Vec::new(), Lights::new());
+        scene.set_clear_color(Srgba::RED);
+        let mut debouncer = ResizeDebouncer::default();
+        debouncer.push(PhysicalSize::new(40, 40));
+        let viewport = debouncer.viewport(context.surface_size());
+        assert_eq!((viewport.x, viewport.width), (20, 40));
+
+        let image = harness.render(|_| {
+            context.clear_region(context.viewport(), ClearState::color(0.0, 0.0, 0.0, 0.0));
+            scene.render(context, viewport);
+        });
+        assert_eq!(image.pixel(5, 20), [0, 0, 0, 0]);
+        assert_eq!(image.pixel(25, 20)[3], 255);
+        // Right of the viewport's middle, which a target of the viewport's size cropped away.
+        assert_eq!(image.pixel(55, 20)[3], 255);
+        assert_eq!(image.pixel(75, 20), [0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,2583 @@
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use dioxus::desktop::tao::dpi::PhysicalSize;
+use three_d::{
+    degrees, vec3, AmbientLight, AxisAlignedBoundingBox, Camera, ClearState, Context, Cull,
+    DirectionalLight, Event, Geometry, InnerSpace, Light, Object, PhysicalPoint, Radians,
+    RenderTarget, RendererError, ScissorBox, Srgba, Vec3, Viewport,
+};
+
+use crate::animation::AnimationClock;
+use crate::background::Background;
+use crate::batch::MaterialBatch;
+use crate::bloom::{Bloom, BloomChain};
+use crate::camera::{self, CameraPresets, FovAxis};
+use crate::clear::ClearConfig;
+use crate::clip::{self, ClipPlane};
+use crate::composite::{self, CompositeMode, OverlayMode};
+use crate::controls::Controls;
+use crate::debug_view::{DebugMaterial, DebugView};
+use crate::depth::DepthConfig;
+use crate::error::ContextError;
+use crate::gbuffer::{self, GBuffer};
+use crate::ground::GroundPlane;
+use crate::lights::Lights;
+use crate::lod::Lod;
+use crate::outline::Outline;
+use crate::overlay::Overlay;
+use crate::part::{BlendMode, MaterialOverride, PartAnimation, ScenePart};
+use crate::passes::PassChain;
+use crate::picking;
+use crate::post::{self, ColorGrade, ToneMapping};
+use crate::raster::{FillMode, RasterSettings};
+use crate::reference;
+use crate::render_texture::{self, Draw, MultisampledRenderTexture, RenderScale, RenderTexture};
+use crate::shortcuts::{SceneCommand, Shortcuts};
+use crate::stats::{FrameTimer, FrameTimings, RenderStats, RenderStatsCollector};
+use crate::windowed_context::{self, MsaaSamples, WindowedContext};
+
+/// The room [Scene::execute] leaves around what it frames, see [Scene::frame_to_aabb].
+pub const DEFAULT_FRAMING_PADDING: f32 = 1.1;
+
+/// A callback run around the main pass, see [Scene::set_pre_render].
+pub type RenderHook = Box<dyn FnMut(&Context)>;
+
+/// The default of [Scene::set_max_frame_delta].
+pub const DEFAULT_MAX_FRAME_DELTA: Duration = Duration::from_millis(100);
+
+/// The lowest exposure [Scene::set_exposure] accepts.
+pub const MIN_EXPOSURE: f32 = 1e-3;
+
+/// Objects and lights that are not part of a [Scene] but rendered with it for one frame, see
+/// [Scene::render_provided].
+#[derive(Default)]
+pub struct FrameContents<'a> {
+    pub objects: Vec<&'a dyn Object>,
+    pub lights: Vec<&'a dyn Light>,
+}
+
+/// Everything that is rendered each frame, plus a dirty flag tracking whether any of it
+/// changed since the last frame.
+///
+/// All mutation goes through methods so that the dirty flag stays accurate; the scheduler
+/// relies on it to skip frames when nothing changed.
+pub struct Scene {
+    camera: Camera,
+    control: Controls,
+    camera_presets: CameraPresets,
+    parts: Vec<ScenePart>,
+    batches: Vec<MaterialBatch>,
+    lods: Vec<Lod>,
+    bounds: Cell<Option<AxisAlignedBoundingBox>>,
+    lights: Lights,
+    ground: Option<GroundPlane>,
+    stats: RenderStatsCollector,
+    frame_timer: FrameTimer,
+    pre_render: Option<RenderHook>,
+    post_render: Option<RenderHook>,
+    clock: AnimationClock,
+    max_frame_delta: Option<Duration>,
+    shadows_dirty: bool,
+    background: Background,
+    composite_mode: CompositeMode,
+    alpha_to_coverage: bool,
+    tone_mapping: ToneMapping,
+    exposure: f32,
+    color_grade: ColorGrade,
+    bloom: Option<Bloom>,
+    bloom_chain: Option<BloomChain>,
+    clip_planes: Vec<ClipPlane>,
+    depth: DepthConfig,
+    cull: Cull,
+    blend_mode: BlendMode,
+    field_of_view: Option<(Radians, FovAxis)>,
+    raster: RasterSettings,
+    clear: ClearConfig,
+    render_scale: RenderScale,
+    offscreen: Option<RenderTexture>,
+    msaa: MsaaSamples,
+    multisampled: Option<MultisampledRenderTexture>,
+    selected: Option<String>,
+    outline: Outline,
+    overlay: Overlay,
+    post_passes: PassChain,
+    debug_view: DebugView,
+    shortcuts: Shortcuts,
+    dirty: bool,
+}
+
+impl Scene {
+    pub fn new(camera: Camera, control: Controls, parts: Vec<ScenePart>, lights: Lights) -> Self {
+        Self {
+            camera,
+            control,
+            camera_presets: CameraPresets::new(),
+            parts,
+            batches: Vec::new(),
+            lods: Vec::new(),
+            bounds: Cell::new(None),
+            lights,
+            ground: None,
+            stats: RenderStatsCollector::new(),
+            frame_timer: FrameTimer::new(),
+            pre_render: None,
+            post_render: None,
+            clock: AnimationClock::new(),
+            max_frame_delta: Some(DEFAULT_MAX_FRAME_DELTA),
+            shadows_dirty: true,
+            background: Background::default(),
+            composite_mode: CompositeMode::default(),
+            alpha_to_coverage: false,
+            tone_mapping: ToneMapping::default(),
+            exposure: 1.0,
+            color_grade: ColorGrade::NEUTRAL,
+            bloom: None,
+            bloom_chain: None,
+            clip_planes: Vec::new(),
+            depth: DepthConfig::default(),
+            cull: Cull::Back,
+            blend_mode: BlendMode::default(),
+            field_of_view: None,
+            raster: RasterSettings::default(),
+            clear: ClearConfig::default(),
+            render_scale: RenderScale::default(),
+            offscreen: None,
+            msaa: MsaaSamples::Off,
+            multisampled: None,
+            selected: None,
+            outline: Outline::new(),
+            overlay: Overlay::new(),
+            post_passes: PassChain::new(),
+            debug_view: DebugView::None,
+            shortcuts: Shortcuts::default(),
+            dirty: true,
+        }
+    }
+
+    /// A scene showing [reference::reference_model], lit and framed, for trying the pipeline
+    /// without any files, such as in tests, on a first run or in a headless context.
+    ///
+    /// The camera looks at it from the front and slightly above, framed with
+    /// [DEFAULT_FRAMING_PADDING] for a square viewport, as the real one only comes with the
+    /// first [Scene::render]; wider viewports show all of it too, for taller ones run
+    /// [SceneCommand::ResetView] after the first frame. Everything is built the same way every
+    /// time, so renders of it can be compared against golden images.
+    pub fn default_reference(context: &Context) -> Result<Self, RendererError> {
+        let camera = Camera::new_perspective(
+            Viewport::new_at_origo(1, 1),
+            vec3(0.0, 1.5, 4.0),
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            degrees(45.0),
+            0.1,
+            100.0,
+        );
+        let controls = Controls::new(*camera.target(), 1.0, 100.0);
+        let parts = ScenePart::from_cpu_model(context, &reference::reference_model())?;
+        let mut lights = Lights::new();
+        lights.add(AmbientLight::new(context, 0.4, Srgba::WHITE));
+        lights.add(DirectionalLight::new(
+            context,
+            2.0,
+            Srgba::WHITE,
+            &vec3(-1.0, -1.0, -1.0),
+        ));
+        let mut scene = Self::new(camera, controls, parts, lights);
+        let bounds = scene.bounding_box();
+        scene.frame_to_aabb(bounds, DEFAULT_FRAMING_PADDING);
+        Ok(scene)
+    }
+
+    /// Whether the next frame would look different from the last rendered one.
+    ///
+    /// A playing animation, auto-rotation, inertia or a camera focus or flight in progress is
+    /// always dirty, a static scene settles to clean after one frame.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+            || self.clock.is_playing()
+            || self.control.is_auto_rotating()
+            || self.control.is_coasting()
+            || self.control.is_focusing()
+            || self.camera_presets.is_flying()
+    }
+
+    /// Resizes the surface of `context` and sets the camera viewport, and with it the aspect
+    /// ratio of the projection, to the new size in the same step, so nothing in between, like
+    /// picking, sees the two disagree and the next frame isn't stretched.
+    ///
+    /// For a scene covering the whole window; one confined to a viewport gets its camera
+    /// viewport from [Scene::render]. Degenerate sizes are ignored, see
+    /// [WindowedContext::resize].
+    pub fn resize(&mut self, context: &WindowedContext, physical_size: PhysicalSize<u32>) {
+        if !windowed_context::is_renderable_size(physical_size) {
+            return;
+        }
+        context.resize(physical_size);
+        set_camera_viewport(&mut self.camera, self.field_of_view, context.viewport());
+        self.dirty = true;
+    }
+
+    /// The field of view and the axis it is locked to, if set with [Scene::set_field_of_view].
+    pub fn field_of_view(&self) -> Option<(Radians, FovAxis)> {
+        self.field_of_view
+    }
+
+    /// Keeps `field_of_view` on `axis` as the viewport changes shape, recomputing the camera's
+    /// perspective on every resize and render, see [camera::field_of_view_y]. `None` leaves
+    /// the camera's projection as it is set.
+    pub fn set_field_of_view(&mut self, field_of_view: Option<(Radians, FovAxis)>) {
+        if self.field_of_view != field_of_view {
+            self.field_of_view = field_of_view;
+            let viewport = self.camera.viewport();
+            set_camera_viewport(&mut self.camera, field_of_view, viewport);
+            self.dirty = true;
+        }
+    }
+
+    /// Forces the next frame to be rendered.
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        self.dirty = true;
+        &mut self.camera
+    }
+
+    pub fn controls(&self) -> &Controls {
+        &self.control
+    }
+
+    pub fn controls_mut(&mut self) -> &mut Controls {
+        &mut self.control
+    }
+
+    pub fn camera_presets(&self) -> &CameraPresets {
+        &self.camera_presets
+    }
+
+    /// The named viewpoints [CameraPresets::goto] flies the camera to, advanced by
+    /// [Scene::advance]. Moving the camera with the controls cancels a flight.
+    pub fn camera_presets_mut(&mut self) -> &mut CameraPresets {
+        &mut self.camera_presets
+    }
+
+    /// Points the camera at `aabb` and orbits around its center, see
+    /// [crate::camera::frame_to_aabb].
+    pub fn frame_to_aabb(&mut self, aabb: AxisAlignedBoundingBox, padding: f32) -> bool {
+        let framed = camera::frame_to_aabb(&mut self.camera, &mut self.control, aabb, padding);
+        self.dirty |= framed;
+        framed
+    }
+
+    /// The key bindings [Scene::handle_events] runs commands for.
+    pub fn shortcuts(&self) -> &Shortcuts {
+        &self.shortcuts
+    }
+
+    /// Rebind or, with [Shortcuts::none], disable the keyboard shortcuts.
+    pub fn shortcuts_mut(&mut self) -> &mut Shortcuts {
+        &mut self.shortcuts
+    }
+
+    /// Carries out `command`, as bound to a key by the [Shortcuts].
+    pub fn execute(&mut self, command: SceneCommand) {
+        match command {
+            SceneCommand::FrameModel => {
+                let selected = self.selected.as_deref().and_then(|id| self.part(id));
+                let aabb = match selected {
+                    Some(part) if part.is_visible() => part.model().aabb(),
+                    _ => self.bounding_box(),
+                };
+                self.frame_to_aabb(aabb, DEFAULT_FRAMING_PADDING);
+            }
+            SceneCommand::ResetView => {
+                self.frame_to_aabb(self.bounding_box(), DEFAULT_FRAMING_PADDING);
+            }
+            SceneCommand::ToggleWireframe => {
+                let fill_mode = match self.raster.fill_mode {
+                    FillMode::Wireframe => FillMode::Solid,
+                    _ => FillMode::Wireframe,
+                };
+                self.set_raster(RasterSettings {
+                    fill_mode,
+                    ..self.raster
+                });
+            }
+            SceneCommand::TogglePlayback => {
+                if self.clock.is_playing() {
+                    self.clock.pause();
+                } else {
+                    self.clock.play();
+                }
+                self.dirty = true;
+            }
+            SceneCommand::CycleDebugView => self.set_debug_view(self.debug_view.next()),
+        }
+    }
+
+    pub fn parts(&self) -> &[ScenePart] {
+        &self.parts
+    }
+
+    /// The part with `id`, see [ScenePart::id].
+    pub fn part(&self, id: &str) -> Option<&ScenePart> {
+        self.parts.iter().find(|part| part.id() == id)
+    }
+
+    pub fn part_mut(&mut self, id: &str) -> Option<&mut ScenePart> {
+        let part = self.parts.iter_mut().find(|part| part.id() == id)?;
+        self.dirty = true;
+        self.shadows_dirty = true;
+        self.bounds.set(None);
+        Some(part)
+    }
+
+    /// Overrides material parameters of the part with `id`, see [ScenePart::override_material].
+    /// Returns `false` if there is no such part.
+    ///
+    /// Overrides live on the part, so they persist across frames and animation but not across
+    /// [Scene::set_parts]. Batches keep their own merged material and don't pick them up.
+    pub fn override_material(&mut self, id: &str, values: MaterialOverride) -> bool {
+        let Some(part) = self.parts.iter_mut().find(|part| part.id() == id) else {
+            return false;
+        };
+        part.override_material(values);
+        self.dirty = true;
+        true
+    }
+
+    /// Undoes all material overrides of the part with `id`. Returns `false` if there is no
+    /// such part.
+    pub fn reset_material(&mut self, id: &str) -> bool {
+        let Some(part) = self.parts.iter_mut().find(|part| part.id() == id) else {
+            return false;
+        };
+        if part.reset_material() {
+            self.dirty = true;
+        }
+        true
+    }
+
+    /// Replaces or, with `None`, stops the animation of the part with `id` at the current
+    /// animation time, see [ScenePart::set_animation]. Returns `false` if there is no such
+    /// part. The other parts keep animating.
+    pub fn set_part_animation(&mut self, id: &str, animation: Option<PartAnimation>) -> bool {
+        let time = self.clock.time();
+        let Some(part) = self.parts.iter_mut().find(|part| part.id() == id) else {
+            return false;
+        };
+        part.set_animation(animation, time);
+        self.dirty = true;
+        self.shadows_dirty = true;
+        self.bounds.set(None);
+        true
+    }
+
+    /// Shows or hides the part with `id`, see [ScenePart::set_visible]. Returns `false` if
+    /// there is no such part.
+    pub fn set_visible(&mut self, id: &str, visible: bool) -> bool {
+        match self.parts.iter_mut().find(|part| part.id() == id) {
+            Some(part) if part.is_visible() != visible => {
+                part.set_visible(visible);
+                self.dirty = true;
+                self.shadows_dirty = true;
+                self.bounds.set(None);
+                true
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    pub fn parts_mut(&mut self) -> &mut [ScenePart] {
+        self.dirty = true;
+        self.bounds.set(None);
+        self.shadows_dirty = true;
+        &mut self.parts
+    }
+
+    /// Replaces all parts. This drops the batches as well, their part indices would no
+    /// longer match.
+    pub fn set_parts(&mut self, parts: Vec<ScenePart>) {
+        self.parts = parts;
+        self.batches.clear();
+        self.bounds.set(None);
+        self.dirty = true;
+        self.shadows_dirty = true;
+    }
+
+    /// The id of the outlined part, if any.
+    pub fn selected(&self) -> Option<&str> {
+        self.selected.as_deref()
+    }
+
+    /// Outlines the part with `id`, typically the result of picking, or nothing with `None`.
+    ///
+    /// The selection is kept by id, so it survives [Scene::set_parts] as long as the new parts
+    /// contain one with the same id.
+    pub fn set_selected(&mut self, id: Option<&str>) {
+        if self.selected.as_deref() != id {
+            self.selected = id.map(str::to_string);
+            self.dirty = true;
+        }
+    }
+
+    /// The id of the visible part under `pixel`, physical pixels from the bottom left as in
+    /// [three_d::Event], or `None` over the background or outside the camera's viewport.
+    ///
+    /// One GPU pick finds the point under the cursor; only where the bounds of several parts
+    /// contain it, each of those is picked again to find the one it lies on.
+    pub fn pick_part(&self, context: &WindowedContext, pixel: PhysicalPoint) -> Option<&str> {
+        let visible: Vec<&ScenePart> = self
+            .parts
+            .iter()
+            .chain(self.lods.iter().map(Lod::selected))
+            .filter(|part| part.is_visible())
+            .collect();
+        let models = visible.iter().map(|part| part.model());
+        let point = picking::pick_point(context, &self.camera, pixel, models)?;
+        let candidates: Vec<&ScenePart> = visible
+            .into_iter()
+            .filter(|part| aabb_contains(&part.model().aabb(), point))
+            .collect();
+        if let [part] = candidates[..] {
+            return Some(part.id());
+        }
+        candidates
+            .into_iter()
+            .filter_map(|part| {
+                let model = std::iter::once(part.model());
+                let hit = picking::pick_point(context, &self.camera, pixel, model)?;
+                Some((part, (hit - point).magnitude2()))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(part, _)| part.id())
+    }
+
+    pub fn outline(&self) -> &Outline {
+        &self.outline
+    }
+
+    /// The color and width of the selection outline.
+    pub fn outline_mut(&mut self) -> &mut Outline {
+        self.dirty = true;
+        &mut self.outline
+    }
+
+    /// The 2D shapes to draw over the next frame, in the viewport passed to [Scene::render].
+    /// Unlike the other settings this doesn't mark the scene dirty, as shapes are queued anew
+    /// for every frame; call [Scene::mark_dirty] when they change on their own.
+    pub fn overlay_mut(&mut self) -> &mut Overlay {
+        &mut self.overlay
+    }
+
+    pub fn batches(&self) -> &[MaterialBatch] {
+        &self.batches
+    }
+
+    /// Sets the merged batches [Scene::render_batched] draws in place of the parts they
+    /// contain, see [crate::batch::batch_by_material].
+    pub fn set_batches(&mut self, batches: Vec<MaterialBatch>) {
+        self.batches = batches;
+        self.dirty = true;
+        self.shadows_dirty = true;
+    }
+
+    pub fn lods(&self) -> &[Lod] {
+        &self.lods
+    }
+
+    pub fn lods_mut(&mut self) -> &mut [Lod] {
+        self.dirty = true;
+        self.bounds.set(None);
+        self.shadows_dirty = true;
+        &mut self.lods
+    }
+
+    /// Sets models drawn at the detail level fitting their distance, next to the parts.
+    ///
+    /// The level is picked with [Lod::select] before every frame, only that level is drawn,
+    /// picked and casts shadows, and LODs are never batched. Hiding the selected level with
+    /// [ScenePart::set_visible] hides the LOD at that distance.
+    pub fn set_lods(&mut self, lods: Vec<Lod>) {
+        self.lods = lods;
+        self.bounds.set(None);
+        self.dirty = true;
+        self.shadows_dirty = true;
+    }
+
+    pub fn lights(&self) -> &Lights {
+        &self.lights
+    }
+
+    pub fn lights_mut(&mut self) -> &mut Lights {
+        self.dirty = true;
+        self.shadows_dirty = true;
+        &mut self.lights
+    }
+
+    pub fn ground(&self) -> Option<&GroundPlane> {
+        self.ground.as_ref()
+    }
+
+    pub fn ground_mut(&mut self) -> Option<&mut GroundPlane> {
+        self.dirty = true;
+        self.ground.as_mut()
+    }
+
+    /// Adds or removes the shadow-receiving ground plane.
+    pub fn set_ground(&mut self, ground: Option<GroundPlane>) {
+        self.ground = ground;
+        self.dirty = true;
+    }
+
+    /// Statistics of the last rendered frame.
+    pub fn stats(&self) -> RenderStats {
+        self.stats.last()
+    }
+
+    /// The CPU time breakdown of the last frame rendered with [Scene::render_frame].
+    pub fn frame_timings(&self) -> FrameTimings {
+        self.frame_timer.last()
+    }
+
+    /// For loops that don't use [Scene::render_frame] to time their frames themselves, and
+    /// to report when input arrived, see [FrameTimer::input_received].
+    pub fn frame_timer_mut(&mut self) -> &mut FrameTimer {
+        &mut self.frame_timer
+    }
+
+    pub fn stats_collector_mut(&mut self) -> &mut RenderStatsCollector {
+        &mut self.stats
+    }
+
+    pub fn clock(&self) -> &AnimationClock {
+        &self.clock
+    }
+
+    pub fn clock_mut(&mut self) -> &mut AnimationClock {
+        self.dirty = true;
+        &mut self.clock
+    }
+
+    pub fn max_frame_delta(&self) -> Option<Duration> {
+        self.max_frame_delta
+    }
+
+    /// The most [Scene::advance] moves time on in one call, defaults to
+    /// [DEFAULT_MAX_FRAME_DELTA]. After the app stalled, in a debugger or while the window was
+    /// dragged, animations then resume where they were instead of jumping ahead. `None`
+    /// follows the wall clock however far it moved. [Scene::render_once] sets the time
+    /// directly and is not limited.
+    pub fn set_max_frame_delta(&mut self, max_frame_delta: Option<Duration>) {
+        self.max_frame_delta = max_frame_delta;
+    }
+
+    /// The solid background color, or transparent black for gradients and images.
+    pub fn clear_color(&self) -> Srgba {
+        self.background.clear_color()
+    }
+
+    /// Shorthand for a [Background::Solid] background.
+    pub fn set_clear_color(&mut self, color: Srgba) {
+        if !matches!(self.background, Background::Solid(current) if current == color) {
+            self.set_background(Background::Solid(color));
+        }
+    }
+
+    pub fn background(&self) -> &Background {
+        &self.background
+    }
+
+    pub fn set_background(&mut self, background: Background) {
+        self.background = background;
+        self.dirty = true;
+    }
+
+    pub fn composite_mode(&self) -> CompositeMode {
+        self.composite_mode
+    }
+
+    pub fn set_composite_mode(&mut self, mode: CompositeMode) {
+        if self.composite_mode != mode {
+            self.composite_mode = mode;
+            self.dirty = true;
+        }
+    }
+
+    /// Sets up background, clearing and compositing for `mode` in one go. Everything it sets
+    /// can still be changed on its own afterwards.
+    ///
+    /// Silhouettes need no extra setup to stay clean over the page: with MSAA, on the window
+    /// or with [Scene::set_msaa], the resolve turns edge coverage into fractional alpha of
+    /// samples [OverlayMode::Transparent] keeps premultiplied, see
+    /// [Scene::set_alpha_to_coverage]. Alpha to coverage itself is left as it was, it is for
+    /// cutout materials and not needed for the edges of opaque ones.
+    pub fn set_overlay_mode(&mut self, mode: OverlayMode) {
+        let (clear_color, composite_mode) = match mode {
+            OverlayMode::Transparent => (true, CompositeMode::Premultiplied),
+            OverlayMode::KeepColor => (false, CompositeMode::Straight),
+        };
+        self.set_clear_color(Srgba::new(0, 0, 0, 0));
+        self.set_clear_config(ClearConfig {
+            color: clear_color,
+            depth: true,
+            ..self.clear
+        });
+        self.set_composite_mode(composite_mode);
+    }
+
+    pub fn alpha_to_coverage(&self) -> bool {
+        self.alpha_to_coverage
+    }
+
+    /// Makes material alpha control MSAA coverage, which smooths alpha-tested edges such as
+    /// foliage cutouts. Intended for materials rendered without blending, blended ones would
+    /// apply their alpha twice.
+    ///
+    /// Only takes effect when rendering straight to a multisampled window or through the
+    /// multisampled target of [Scene::set_msaa]. The resolve averages samples, so silhouettes
+    /// end up with fractional alpha either way; in [CompositeMode::Premultiplied] every sample
+    /// is premultiplied from the background on, which keeps the resolved edge premultiplied.
+    pub fn set_alpha_to_coverage(&mut self, enabled: bool) {
+        if self.alpha_to_coverage != enabled {
+            self.alpha_to_coverage = enabled;
+            self.dirty = true;
+        }
+    }
+
+    /// Feeds input events to the camera control, dirtying the scene if the camera moved.
+    pub fn handle_events(&mut self, context: &WindowedContext, events: &mut [Event]) {
+        for command in self.shortcuts.commands(events) {
+            self.execute(command);
+        }
+        let parts = &self.parts;
+        let lods = &self.lods;
+        let pick = |camera: &Camera, pixel: PhysicalPoint| {
+            let visible = parts
+                .iter()
+                .chain(lods.iter().map(Lod::selected))
+                .filter(|part| part.is_visible());
+            picking::pick_point(context, camera, pixel, visible.map(ScenePart::model))
+        };
+        if self.control.handle_events(&mut self.camera, events, pick) {
+            self.camera_presets.cancel();
+            self.dirty = true;
+        }
+    }
+
+    /// Advances the animation clock by `dt` and applies the new time to the parts, flies the
+    /// camera towards a preset, see [CameraPresets::goto], and turns it if auto-rotation is
+    /// on, see [Controls::update].
+    ///
+    /// `dt` is limited to [Scene::max_frame_delta].
+    pub fn advance(&mut self, dt: Duration) {
+        let dt = self
+            .max_frame_delta
+            .map_or(dt, |max_frame_delta| dt.min(max_frame_delta));
+        if self
+            .camera_presets
+            .update(&mut self.camera, &mut self.control, dt)
+        {
+            self.dirty = true;
+        }
+        if self.control.update(&mut self.camera, dt) {
+            self.dirty = true;
+        }
+        if self.clock.advance(dt) {
+            self.animate(self.clock.time());
+            self.dirty = true;
+            self.shadows_dirty = true;
+        }
+    }
+
+    /// One tick of everything that changes the scene between frames: applies `events` to the
+    /// controls, then advances animation and auto-rotation by `dt`. `events` is emptied.
+    ///
+    /// A zero `dt` leaves the time based state alone, events still apply. Returns
+    /// [Scene::is_dirty], so the loop can follow up with [Scene::render] only when needed.
+    pub fn update(
+        &mut self,
+        context: &WindowedContext,
+        dt: Duration,
+        events: &mut Vec<Event>,
+    ) -> bool {
+        self.handle_events(context, events);
+        events.clear();
+        self.advance(dt);
+        self.is_dirty()
+    }
+
+    pub fn tone_mapping(&self) -> ToneMapping {
+        self.tone_mapping
+    }
+
+    /// Selects the tone mapping operator. Anything but [ToneMapping::None] renders through an
+    /// offscreen HDR texture first.
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMapping) {
+        if self.tone_mapping != tone_mapping {
+            self.tone_mapping = tone_mapping;
+            self.dirty = true;
+        }
+    }
+
+    pub fn exposure(&self) -> f32 {
+        self.exposure
+    }
+
+    /// Scales the linear scene color before tone mapping, with or without a tone mapping
+    /// operator. Any exposure but `1.0` renders through the offscreen HDR texture.
+    ///
+    /// Zero, negative and NaN values are raised to [MIN_EXPOSURE], so the scene never goes
+    /// fully black or inverts.
+    pub fn set_exposure(&mut self, exposure: f32) {
+        let exposure = exposure.max(MIN_EXPOSURE);
+        if self.exposure != exposure {
+            self.exposure = exposure;
+            self.dirty = true;
+        }
+    }
+
+    pub fn color_grade(&self) -> ColorGrade {
+        self.color_grade
+    }
+
+    /// Grades the tone mapped color, see [ColorGrade]. Anything but [ColorGrade::NEUTRAL]
+    /// renders through the offscreen HDR texture; out of range values are clamped, see
+    /// [ColorGrade::clamped].
+    pub fn set_color_grade(&mut self, color_grade: ColorGrade) {
+        let color_grade = color_grade.clamped();
+        if self.color_grade != color_grade {
+            self.color_grade = color_grade;
+            self.dirty = true;
+        }
+    }
+
+    pub fn bloom(&self) -> Option<Bloom> {
+        self.bloom
+    }
+
+    /// Makes the bright parts of the picture glow, see [Bloom], or turns that off for `None`,
+    /// the default. The glow is taken from the exposed linear color and added back before
+    /// tone mapping, so it renders through the offscreen HDR texture. Out of range values
+    /// are clamped, see [Bloom::clamped].
+    pub fn set_bloom(&mut self, bloom: Option<Bloom>) {
+        let bloom = bloom.map(Bloom::clamped);
+        if self.bloom != bloom {
+            self.bloom = bloom;
+            self.dirty = true;
+        }
+    }
+
+    pub fn post_passes(&self) -> &PassChain {
+        &self.post_passes
+    }
+
+    /// The post-process passes run over the frame before bloom, tone mapping and the color
+    /// grade, see [PassChain]. While any is enabled the scene renders through the offscreen
+    /// HDR texture.
+    pub fn post_passes_mut(&mut self) -> &mut PassChain {
+        self.dirty = true;
+        &mut self.post_passes
+    }
+
+    pub fn debug_view(&self) -> DebugView {
+        self.debug_view
+    }
+
+    /// Shows a material or geometry channel of the parts instead of the shaded picture, or
+    /// the picture again for [DebugView::None], the default.
+    ///
+    /// Debug views draw the visible parts unlit, opaque and straight to the screen: batches,
+    /// the ground, provided objects, the background and every post-process are left out, the
+    /// clip planes, the selection outline and the overlay stay.
+    pub fn set_debug_view(&mut self, debug_view: DebugView) {
+        if self.debug_view != debug_view {
+            self.debug_view = debug_view;
+            self.dirty = true;
+        }
+    }
+
+    pub fn clip_planes(&self) -> &[ClipPlane] {
+        &self.clip_planes
+    }
+
+    /// Cuts the parts with `planes`, keeping only what is on the kept side of all of them,
+    /// for cutaway views; see [clip::Clipped] for what the material shaders need. Up to
+    /// [clip::MAX_CLIP_PLANES] are kept, the rest are dropped.
+    ///
+    /// Parts merged into batches are drawn individually while clipping. The ground, provided
+    /// objects and shadows stay uncut, and the cut isn't capped.
+    pub fn set_clip_planes(&mut self, mut planes: Vec<ClipPlane>) {
+        if planes.len() > clip::MAX_CLIP_PLANES {
+            log_warn!(
+                planes = planes.len(),
+                "too many clip planes, dropping the rest"
+            );
+            planes.truncate(clip::MAX_CLIP_PLANES);
+        }
+        if self.clip_planes != planes {
+            self.clip_planes = planes;
+            self.dirty = true;
+        }
+    }
+
+    pub fn depth_config(&self) -> DepthConfig {
+        self.depth
+    }
+
+    /// Switches depth conventions, see [DepthConfig]. Reverse-Z renders through the offscreen
+    /// target for its float depth buffer.
+    pub fn set_depth_config(&mut self, depth: DepthConfig) {
+        if self.depth != depth {
+            self.depth = depth;
+            self.dirty = true;
+        }
+    }
+
+    pub fn msaa(&self) -> MsaaSamples {
+        self.msaa
+    }
+
+    /// Renders through an offscreen multisampled target with `samples` samples per pixel,
+    /// independent of the window's own MSAA, so it can change without recreating the context.
+    /// Returns the setting actually applied, lowered to what the driver supports.
+    pub fn set_msaa(&mut self, context: &WindowedContext, samples: MsaaSamples) -> MsaaSamples {
+        let applied = MsaaSamples::at_most(
+            (samples.count() as u32).min(render_texture::max_samples(context)),
+        );
+        if applied != samples {
+            log_warn!(requested = ?samples, applied = ?applied, "MSAA sample count clamped");
+        }
+        if self.msaa != applied {
+            self.msaa = applied;
+            if let Some(multisampled) = &mut self.multisampled {
+                multisampled.set_number_of_samples(applied.count() as u32);
+            }
+            self.dirty = true;
+        }
+        applied
+    }
+
+    pub fn cull(&self) -> Cull {
+        self.cull
+    }
+
+    /// The faces culled on every part except the double-sided ones, see
+    /// [ScenePart::set_double_sided]. Defaults to [Cull::Back].
+    pub fn set_cull(&mut self, cull: Cull) {
+        if self.cull != cull {
+            self.cull = cull;
+            self.dirty = true;
+        }
+    }
+
+    /// Runs `hook` once per frame, with the target of the main pass bound, right before the
+    /// parts, batches and ground are drawn; the background is already there.
+    ///
+    /// For render state `three_d` doesn't manage, like a polygon offset or a color mask.
+    /// `three_d` sets the render states it knows per draw, but anything else the hook changes
+    /// it has to restore itself, in [Scene::set_post_render]. The viewport and scissor box are
+    /// put back after the hook either way.
+    pub fn set_pre_render(&mut self, hook: impl FnMut(&Context) + 'static) {
+        self.pre_render = Some(Box::new(hook));
+        self.dirty = true;
+    }
+
+    /// Runs `hook` once per frame, right after the main pass, before tone mapping and the
+    /// selection outline. See [Scene::set_pre_render].
+    pub fn set_post_render(&mut self, hook: impl FnMut(&Context) + 'static) {
+        self.post_render = Some(Box::new(hook));
+        self.dirty = true;
+    }
+
+    /// Removes both render hooks.
+    pub fn clear_render_hooks(&mut self) {
+        self.pre_render = None;
+        self.post_render = None;
+        self.dirty = true;
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// How transparent parts blend, unless they override it with [ScenePart::set_blend_mode].
+    ///
+    /// Transparent parts are drawn after everything else, from the farthest to the nearest by
+    /// the distance of the camera to the center of their bounds. That is only approximate:
+    /// parts that overlap or intersect, or a small part inside a large one, can come out in
+    /// the wrong order, which [ScenePart::set_sort_key] fixes by hand. Triangles within a
+    /// part are not sorted, and batches are drawn with the opaque parts.
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        if self.blend_mode != blend_mode {
+            self.blend_mode = blend_mode;
+            self.dirty = true;
+        }
+    }
+
+    pub fn raster(&self) -> RasterSettings {
+        self.raster
+    }
+
+    /// Switches between solid, wireframe and point rendering of the parts, see
+    /// [RasterSettings]. The ground and provided objects follow it too, the background and
+    /// outline always render solid.
+    pub fn set_raster(&mut self, raster: RasterSettings) {
+        if self.raster != raster {
+            self.raster = raster;
+            self.dirty = true;
+        }
+    }
+
+    pub fn render_scale(&self) -> RenderScale {
+        self.render_scale
+    }
+
+    /// Renders at a multiple of the viewport's resolution and filters down, see
+    /// [RenderScale]. Anything but `1.0` renders through the offscreen target.
+    pub fn set_render_scale(&mut self, render_scale: RenderScale) {
+        if self.render_scale != render_scale {
+            self.render_scale = render_scale;
+            self.dirty = true;
+        }
+    }
+
+    pub fn clear_config(&self) -> ClearConfig {
+        self.clear
+    }
+
+    /// Chooses which buffers are cleared before each frame, see [ClearConfig].
+    pub fn set_clear_config(&mut self, clear: ClearConfig) {
+        if self.clear != clear {
+            self.clear = clear;
+            self.dirty = true;
+        }
+    }
+
+    fn clear_state(&self) -> ClearState {
+        let color = self.background.clear_color();
+        let channel = |value: u8| self.clear.color.then_some(value as f32 / 255.0);
+        let depth = self.clear.depth_value.unwrap_or(self.depth.clear_depth());
+        ClearState {
+            red: channel(color.r),
+            green: channel(color.g),
+            blue: channel(color.b),
+            alpha: channel(color.a),
+            depth: self.clear.depth.then_some(depth),
+        }
+    }
+
+    fn animate(&mut self, time: f32) {
+        let lod_parts = self.lods.iter_mut().flat_map(Lod::levels_mut);
+        for part in self.parts.iter_mut().chain(lod_parts) {
+            part.model_mut().animate(time);
+        }
+        self.bounds.set(None);
+    }
+
+    /// The union of the bounds of the visible parts in their current, animated placement.
+    /// Without visible parts this is [AxisAlignedBoundingBox::EMPTY], check with `is_empty`.
+    ///
+    /// Computed on first use and cached until the parts change or move.
+    pub fn bounding_box(&self) -> AxisAlignedBoundingBox {
+        if let Some(bounds) = self.bounds.get() {
+            return bounds;
+        }
+        let mut bounds = AxisAlignedBoundingBox::EMPTY;
+        let lod_parts = self.lods.iter().map(Lod::selected);
+        for part in self
+            .parts
+            .iter()
+            .chain(lod_parts)
+            .filter(|part| part.is_visible())
+        {
+            bounds.expand_with_aabb(&part.model().aabb());
+        }
+        self.bounds.set(Some(bounds));
+        bounds
+    }
+
+    /// Renders the scene to the screen, one draw per part, and clears the dirty flag.
+    ///
+    /// Only `viewport` is cleared and drawn to, so it can confine the scene to an element of
+    /// the page, see [WindowedContext::viewport_for].
+    pub fn render(&mut self, context: &WindowedContext, viewport: Viewport) {
+        self.render_with(context, viewport, false, |_| FrameContents::default());
+    }
+
+    /// Like [Scene::render], but draws each batch from [Scene::set_batches] in place of the
+    /// parts merged into it.
+    pub fn render_batched(&mut self, context: &WindowedContext, viewport: Viewport) {
+        self.render_with(context, viewport, true, |_| FrameContents::default());
+    }
+
+    /// Like [Scene::render], but `provide` adds objects and lights for this frame only, such
+    /// as gizmos, other models or custom geometry. It is called once with the scene's camera.
+    ///
+    /// Provided objects go through the same passes as the parts and are lit by both the
+    /// scene's and the provided lights, but they don't cast shadows. Each counts as one draw
+    /// in the [RenderStats], without triangles since `Object` doesn't report them.
+    pub fn render_provided<'a>(
+        &mut self,
+        context: &WindowedContext,
+        viewport: Viewport,
+        provide: impl FnOnce(&Camera) -> FrameContents<'a>,
+    ) {
+        self.render_with(context, viewport, false, provide);
+    }
+
+    fn render_with<'a>(
+        &mut self,
+        context: &WindowedContext,
+        viewport: Viewport,
+        batched: bool,
+        provide: impl FnOnce(&Camera) -> FrameContents<'a>,
+    ) {
+        let _span = log_span!("Scene::render");
+        log_trace!(
+            width = viewport.width,
+            height = viewport.height,
+            batched,
+            "render start"
+        );
+        if viewport.width == 0 || viewport.height == 0 {
+            // Nothing to see, and a zero aspect ratio would fill the projection with NaNs.
+            return;
+        }
+        let clear_state = self.clear_state();
+        let composite_mode = self.composite_mode;
+        let depth = self.depth;
+        let cull = self.cull;
+        let blend_mode = self.blend_mode;
+        for lod in &mut self.lods {
+            if lod.select(&self.camera) {
+                self.shadows_dirty = true;
+                self.bounds.set(None);
+            }
+        }
+        let lod_parts = self.lods.iter_mut().map(Lod::selected_mut);
+        for part in self.parts.iter_mut().chain(lod_parts) {
+            let part_cull = if part.double_sided() {
+                Cull::None
+            } else {
+                cull
+            };
+            let part_blend = part.blend_mode().unwrap_or(blend_mode).blend();
+            let transparent = part.is_transparent();
+            let render_states = &mut part.model_mut().material.render_states;
+            render_states.depth_test = depth.depth_test();
+            render_states.cull = part_cull;
+            if transparent {
+                render_states.blend = part_blend;
+            }
+        }
+        for batch in &mut self.batches {
+            let render_states = &mut batch.object_mut().material.render_states;
+            render_states.depth_test = depth.depth_test();
+            render_states.cull = cull;
+        }
+        if let Some(ground) = &mut self.ground {
+            ground.set_depth_test(depth.depth_test());
+        }
+        // A batch can't hide one of its parts, so while any is hidden the visible ones are
+        // drawn individually instead.
+        let all_visible = |batch: &&MaterialBatch| {
+            batch
+                .parts()
+                .iter()
+                .all(|&index| self.parts.get(index).is_none_or(ScenePart::is_visible))
+        };
+        let clip_planes = &self.clip_planes;
+        let batches: Vec<&MaterialBatch> = if batched && clip_planes.is_empty() {
+            self.batches.iter().filter(all_visible).collect()
+        } else {
+            Vec::new()
+        };
+        let mut in_batch = vec![false; self.parts.len()];
+        for &index in batches.iter().flat_map(|batch| batch.parts()) {
+            if let Some(flag) = in_batch.get_mut(index) {
+                *flag = true;
+            }
+        }
+        let parts: Vec<&ScenePart> = self
+            .parts
+            .iter()
+            .zip(&in_batch)
+            .filter(|(part, batched)| part.is_visible() && !**batched)
+            .map(|(part, _)| part)
+            .chain(
+                self.lods
+                    .iter()
+                    .map(Lod::selected)
+                    .filter(|part| part.is_visible()),
+            )
+            .collect();
+        // Shadows only depend on the casters and the lights, not on the camera.
+        if self.shadows_dirty {
+            // Only the parts cast shadows, the ground just receives them.
+            let casters: Vec<&dyn Geometry> = parts
+                .iter()
+                .map(|part| part.model() as &dyn Geometry)
+                .chain(batches.iter().map(|batch| batch.object() as &dyn Geometry))
+                .collect();
+            self.lights
+                .generate_shadow_maps(context, casters.iter().copied());
+            self.shadows_dirty = false;
+        }
+        let provided = provide(&self.camera);
+        let mut lights = self.lights.as_refs();
+        lights.extend(provided.lights);
+        let mut objects: Vec<&dyn Object> = Vec::new();
+        let mut clipped = Vec::new();
+        let mut transparent = Vec::new();
+        self.stats.begin_frame(context);
+        for part in &parts {
+            if part.is_transparent() {
+                transparent.push(*part);
+            } else if clip_planes.is_empty() {
+                objects.push(part.model());
+            } else {
+                clipped.push(*part);
+            }
+            self.stats.record_draw(part.triangles());
+        }
+        let transparent = sort_back_to_front(&self.camera, transparent);
+        for batch in batches {
+            objects.push(batch.object());
+            self.stats.record_draw(batch.triangles());
+        }
+        for object in provided.objects {
+            objects.push(object);
+            self.stats.record_draw(0);
+        }
+        if let Some(ground) = self.ground.as_ref().and_then(GroundPlane::object) {
+            objects.push(ground);
+        }
+        // The target spans the whole surface and every write is scissored to the viewport, so
+        // a viewport away from the origin, like a split view or letterboxed frame, isn't cropped.
+        let (surface_width, surface_height) = context.surface_size();
+        let screen = RenderTarget::screen(context, surface_width, surface_height);
+        let scissor_box = viewport.into();
+        let msaa = self.msaa;
+        let (raster, scale_factor) = (self.raster, context.scale_factor() as f32);
+        let clear = self.clear;
+        if self.debug_view != DebugView::None {
+            self.offscreen = None;
+            self.multisampled = None;
+            self.bloom_chain = None;
+            self.post_passes.release();
+            set_camera_viewport(&mut self.camera, self.field_of_view, viewport);
+            let (debug_view, bounds) = (self.debug_view, self.bounding_box());
+            // Every visible part, also those a batch would draw or that are transparent.
+            let debug_parts = self
+                .parts
+                .iter()
+                .chain(self.lods.iter().map(Lod::selected))
+                .filter(|part| part.is_visible());
+            screen
+                .clear_partially(scissor_box, clear_state)
+                .draw_partially(scissor_box, || {
+                    clear.clear_stencil(context, viewport);
+                    if clear.color {
+                        composite::apply(context, composite_mode, viewport);
+                    }
+                })
+                .draw_partially(scissor_box, || {
+                    for part in debug_parts {
+                        let model = part.model();
+                        let material =
+                            DebugMaterial::new(&model.material, debug_view, bounds, clip_planes);
+                        model
+                            .geometry
+                            .render_with_material(&material, &self.camera, &[]);
+                    }
+                });
+        } else if self.tone_mapping == ToneMapping::None
+            && self.exposure == 1.0
+            && self.color_grade.is_neutral()
+            && self.bloom.is_none()
+            && !self.post_passes.has_enabled()
+            && !depth.reverse_z
+            && msaa == MsaaSamples::Off
+            && self.render_scale.is_native()
+        {
+            self.offscreen = None;
+            self.multisampled = None;
+            self.bloom_chain = None;
+            self.post_passes.release();
+            set_camera_viewport(&mut self.camera, self.field_of_view, viewport);
+            let alpha_to_coverage = self.alpha_to_coverage && context.samples() > 0;
+            if alpha_to_coverage {
+                composite::begin_alpha_to_coverage(context);
+            }
+            // Only the viewport is cleared, so a scene confined to part of the window leaves
+            // the rest alone.
+            screen
+                .clear_partially(scissor_box, clear_state)
+                .draw_partially(scissor_box, || {
+                    clear.clear_stencil(context, viewport);
+                    if clear.color {
+                        self.background.render(context, viewport);
+                        composite::apply(context, composite_mode, viewport);
+                    }
+                })
+                .draw_partially(scissor_box, || raster.begin(context, scale_factor))
+                .draw_partially(scissor_box, || {
+                    run_hook(&mut self.pre_render, context, viewport)
+                })
+                .render_partially(scissor_box, &self.camera, &objects, &lights)
+                .draw_partially(scissor_box, || {
+                    render_in_order(&self.camera, &clipped, &lights, clip_planes)
+                })
+                .draw_partially(scissor_box, || {
+                    render_in_order(&self.camera, &transparent, &lights, clip_planes)
+                })
+                .draw_partially(scissor_box, || {
+                    run_hook(&mut self.post_render, context, viewport)
+                })
+                .draw_partially(scissor_box, || raster.end(context));
+            if alpha_to_coverage {
+                composite::end_alpha_to_coverage(context);
+            }
+        } else {
+            let (width, height, scale) =
+                self.render_scale
+                    .scaled_size(context, viewport.width, viewport.height);
+            let offscreen_viewport = Viewport::new_at_origo(width, height);
+            set_camera_viewport(&mut self.camera, self.field_of_view, offscreen_viewport);
+            depth.begin(context);
+            let color = if msaa == MsaaSamples::Off {
+                self.multisampled = None;
+                let offscreen = self
+                    .offscreen
+                    .get_or_insert_with(|| RenderTexture::new(context, width, height));
+                offscreen.resize(width, height);
+                offscreen
+                    .render_target()
+                    .clear(clear_state)
+                    .draw(|| {
+                        clear.clear_stencil(context, offscreen_viewport);
+                        if clear.color {
+                            self.background.render(context, offscreen_viewport);
+                            composite::apply(context, composite_mode, offscreen_viewport);
+                        }
+                    })
+                    .draw(|| raster.begin(context, scale_factor))
+                    .draw(|| run_hook(&mut self.pre_render, context, offscreen_viewport))
+                    .render(&self.camera, &objects, &lights)
+                    .draw(|| render_in_order(&self.camera, &clipped, &lights, clip_planes))
+                    .draw(|| render_in_order(&self.camera, &transparent, &lights, clip_planes))
+                    .draw(|| run_hook(&mut self.post_render, context, offscreen_viewport))
+                    .draw(|| raster.end(context));
+                offscreen.color_texture()
+            } else {
+                self.offscreen = None;
+                let multisampled = self.multisampled.get_or_insert_with(|| {
+                    MultisampledRenderTexture::new(context, width, height, msaa.count() as u32)
+                });
+                multisampled.resize(width, height);
+                if self.alpha_to_coverage {
+                    composite::begin_alpha_to_coverage(context);
+                }
+                multisampled
+                    .target()
+                    .clear(clear_state)
+                    .draw(|| {
+                        clear.clear_stencil(context, offscreen_viewport);
+                        if clear.color {
+                            self.background.render(context, offscreen_viewport);
+                            composite::apply(context, composite_mode, offscreen_viewport);
+                        }
+                    })
+                    .draw(|| raster.begin(context, scale_factor))
+                    .draw(|| run_hook(&mut self.pre_render, context, offscreen_viewport))
+                    .render(&self.camera, &objects, &lights)
+                    .draw(|| render_in_order(&self.camera, &clipped, &lights, clip_planes))
+                    .draw(|| render_in_order(&self.camera, &transparent, &lights, clip_planes))
+                    .draw(|| run_hook(&mut self.post_render, context, offscreen_viewport))
+                    .draw(|| raster.end(context));
+                if self.alpha_to_coverage {
+                    composite::end_alpha_to_coverage(context);
+                }
+                multisampled.resolve().color_texture()
+            };
+            depth.end(context);
+            let color = self.post_passes.render(context, color);
+            let (tone_mapping, exposure, grade) =
+                (self.tone_mapping, self.exposure, self.color_grade);
+            let bloom = match self.bloom {
+                Some(bloom) => {
+                    let chain = self
+                        .bloom_chain
+                        .get_or_insert_with(|| BloomChain::new(context));
+                    Some((chain.render(color, exposure, bloom), bloom.intensity))
+                }
+                None => {
+                    self.bloom_chain = None;
+                    None
+                }
+            };
+            screen.draw_partially(scissor_box, || {
+                post::tone_map_bloomed(
+                    context,
+                    color,
+                    tone_mapping,
+                    exposure,
+                    grade,
+                    bloom,
+                    viewport,
+                    scale,
+                )
+            });
+            // Picking and the outline work in screen pixels.
+            set_camera_viewport(&mut self.camera, self.field_of_view, viewport);
+        }
+        let selected = self.selected.as_deref();
+        let outlined = selected.and_then(|id| self.parts.iter().find(|part| part.id() == id));
+        if let Some(part) = outlined.filter(|part| part.is_visible()) {
+            screen.draw_partially(scissor_box, || {
+                self.outline
+                    .render(context, &self.camera, part.model(), viewport)
+            });
+        }
+        screen.draw_partially(scissor_box, || self.overlay.render(context, viewport));
+        self.stats.end_frame();
+        self.dirty = false;
+        log_trace!("render end");
+    }
+
+    /// One complete frame for a loop this crate doesn't own: [Scene::update] with `dt` and
+    /// `events`, [Scene::render] into `viewport` and [WindowedContext::swap_buffers].
+    ///
+    /// With a zero-area `viewport`, as while minimized, the scene is updated but nothing is
+    /// rendered or presented. How long each step took ends up in [Scene::frame_timings].
+    pub fn render_frame(
+        &mut self,
+        context: &WindowedContext,
+        viewport: Viewport,
+        dt: Duration,
+        events: &mut Vec<Event>,
+    ) -> Result<(), ContextError> {
+        let start = Instant::now();
+        self.frame_timer.begin_frame(start);
+        if !events.is_empty() {
+            self.frame_timer.input_received(start);
+        }
+        self.update(context, dt, events);
+        self.frame_timer.update_done(Instant::now());
+        if viewport.width == 0 || viewport.height == 0 {
+            return Ok(());
+        }
+        self.render(context, viewport);
+        self.frame_timer.render_done(Instant::now());
+        let result = context.swap_buffers();
+        self.frame_timer.presented(Instant::now());
+        result
+    }
+
+    /// Re-evaluates the part animations at the current time, for animations that depend on
+    /// more than the time, like [crate::animation::pose_blend].
+    pub fn reanimate(&mut self) {
+        self.animate(self.clock.time());
+        self.dirty = true;
+        self.shadows_dirty = true;
+    }
+
+    /// Renders a frame into `viewport` like [Scene::render] and reads back its color together
+    /// with its linear depth, before anything is presented, so both are from the same frame.
+    ///
+    /// Depth comes from wherever the scene rendered it: the default framebuffer, which
+    /// OpenGL ES can't read depth from, or the offscreen target, picking the nearest sample
+    /// when supersampling. The selection outline only shows in the color.
+    pub fn capture_gbuffer(&mut self, context: &WindowedContext, viewport: Viewport) -> GBuffer {
+        let (width, height) = (viewport.width, viewport.height);
+        if width == 0 || height == 0 {
+            return GBuffer::default();
+        }
+        self.render(context, viewport);
+        context.finish();
+        let (surface_width, surface_height) = context.surface_size();
+        let screen = RenderTarget::screen(context, surface_width, surface_height);
+        let mut color = screen.read_color_partially::<[u8; 4]>(viewport.into());
+        let offscreen = match (&mut self.offscreen, &mut self.multisampled) {
+            (Some(offscreen), _) => Some(offscreen),
+            (None, Some(multisampled)) => Some(multisampled.resolved_mut()),
+            (None, None) => None,
+        };
+        let window_depth = match offscreen {
+            Some(offscreen) => {
+                let size = (offscreen.width(), offscreen.height());
+                gbuffer::resample_nearest(&offscreen.read_depth(), size, (width, height))
+            }
+            None => screen.read_depth_partially(viewport.into()),
+        };
+        let (camera, depth_config) = (&self.camera, self.depth);
+        let mut depth: Vec<f32> = window_depth
+            .into_iter()
+            .map(|depth| gbuffer::linearize_depth(depth, camera, depth_config))
+            .collect();
+        gbuffer::flip_rows(&mut color, width as usize);
+        gbuffer::flip_rows(&mut depth, width as usize);
+        GBuffer {
+            width,
+            height,
+            color,
+            depth,
+        }
+    }
+
+    /// Renders exactly one frame at the absolute animation time `time` (milliseconds),
+    /// independent of the wall clock, for golden-image tests and video export.
+    ///
+    /// `events` are applied to the controls first. With `read_back` the rendered pixels are
+    /// returned, bottom row first, before anything is presented.
+    pub fn render_once(
+        &mut self,
+        context: &WindowedContext,
+        viewport: Viewport,
+        time: f32,
+        events: &mut [Event],
+        read_back: bool,
+    ) -> Option<Vec<[u8; 4]>> {
+        self.handle_events(context, events);
+        self.clock.set_time(time);
+        self.animate(time);
+        self.shadows_dirty = true;
+        self.render(context, viewport);
+        read_back.then(|| {
+            context.finish();
+            let (surface_width, surface_height) = context.surface_size();
+            RenderTarget::screen(context, surface_width, surface_height)
+                .read_color_partially::<[u8; 4]>(viewport.into())
+        })
+    }
+}
+
+/// `parts` from the farthest to the nearest, by [ScenePart::sort_key] or else the distance of
+/// `camera` to the center of their bounds.
+fn sort_back_to_front<'a>(camera: &Camera, mut parts: Vec<&'a ScenePart>) -> Vec<&'a ScenePart> {
+    let position = *camera.position();
+    let key = |part: &ScenePart| {
+        part.sort_key()
+            .unwrap_or_else(|| (part.model().aabb().center() - position).magnitude())
+    };
+    parts.sort_by(|a, b| key(b).total_cmp(&key(a)));
+    parts
+}
+
+/// Whether `aabb` contains `point`, with some slack for the precision of the depth buffer the
+/// point was read back from.
+fn aabb_contains(aabb: &AxisAlignedBoundingBox, point: Vec3) -> bool {
+    if aabb.is_empty() {
+        return false;
+    }
+    let slack = Vec3::new(1.0, 1.0, 1.0) * (aabb.size().magnitude() * 1e-3 + 1e-4);
+    let (min, max) = (aabb.min() - slack, aabb.max() + slack);
+    (min.x..=max.x).contains(&point.x)
+        && (min.y..=max.y).contains(&point.y)
+        && (min.z..=max.z).contains(&point.z)
+}
+
+/// Renders `parts` one after the other, where [RenderTarget::render] would reorder them, cut
+/// by `clip_planes` if there are any.
+fn render_in_order(
+    camera: &Camera,
+    parts: &[&ScenePart],
+    lights: &[&dyn Light],
+    clip_planes: &[ClipPlane],
+) {
+    for part in parts {
+        let model = part.model();
+        if clip_planes.is_empty() {
+            model.render(camera, lights);
+        } else {
+            let material = clip::Clipped::new(&model.material, clip_planes);
+            model
+                .geometry
+                .render_with_material(&material, camera, lights);
+        }
+    }
+}
+
+/// Runs `hook`, then restores the viewport and scissor box of the pass rendering into
+/// `viewport`, in case the hook changed them.
+fn run_hook(hook: &mut Option<RenderHook>, context: &Context, viewport: Viewport) {
+    if let Some(hook) = hook {
+        hook(context);
+        context.set_viewport(viewport);
+        context.set_scissor(ScissorBox::from(viewport));
+    }
+}
+
+/// Sets the viewport of `camera` together with the projection that depends on its shape, see
+/// [Scene::set_field_of_view].
+fn set_camera_viewport(
+    camera: &mut Camera,
+    field_of_view: Option<(Radians, FovAxis)>,
+    viewport: Viewport,
+) {
+    if let Some((field_of_view, axis)) = field_of_view {
+        camera::apply_field_of_view(camera, field_of_view, axis, viewport);
+    }
+    camera.set_viewport(viewport);
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use three_d::{
+        degrees, vec3, AmbientLight, Context, CpuMesh, DirectionalLight, Mat4, Modifiers,
+        MouseButton, PhysicalPoint, SquareMatrix,
+    };
+
+    use super::*;
+    use crate::scheduler::{RenderMode, RenderScheduler};
+    use crate::testing::TestHarness;
+
+    /// A scene without parts or lights, the camera at `(0, 0, 4)` looking at the origin.
+    pub(crate) fn empty_scene() -> Scene {
+        let camera = Camera::new_perspective(
+            Viewport::new_at_origo(64, 64),
+            vec3(0.0, 0.0, 4.0),
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            degrees(45.0),
+            0.1,
+            100.0,
+        );
+        let control = Controls::new(vec3(0.0, 0.0, 0.0), 1.0, 100.0);
+        Scene::new(camera, control, Vec::new(), Lights::new())
+    }
+
+    /// [empty_scene] with its clock paused and nothing left to draw.
+    pub(crate) fn clean_scene() -> Scene {
+        let mut scene = empty_scene();
+        scene.clock_mut().pause();
+        scene.dirty = false;
+        scene
+    }
+
+    /// A model of `count` unit cubes at the origin, each its own primitive without a material.
+    pub(crate) fn cubes(count: usize) -> three_d::CpuModel {
+        three_d::CpuModel {
+            name: "cubes".to_string(),
+            geometries: (0..count)
+                .map(|index| three_d_asset::Primitive {
+                    name: format!("cube{index}"),
+                    transformation: Mat4::identity(),
+                    animations: Vec::new(),
+                    geometry: three_d_asset::Geometry::Triangles(CpuMesh::cube()),
+                    material_index: None,
+                })
+                .collect(),
+            materials: Vec::new(),
+        }
+    }
+
+    /// [empty_scene] with a single unit cube.
+    fn cube_scene(context: &Context) -> Scene {
+        let mut scene = empty_scene();
+        scene.set_parts(ScenePart::from_cpu_model(context, &cubes(1)).unwrap());
+        scene
+    }
+
+    /// A unit cube on a ground plane, seen from straight above by a 64 pixel orthographic
+    /// camera showing 8 world units, so a pixel is an eighth of a unit and the origin is at the
+    /// center. The directional light shines along `(1, -1, 0)`, which would throw the cube's
+    /// shadow onto the ground to its right, at `x` from 1 to 3.
+    pub(crate) fn shadow_scene(context: &Context) -> Scene {
+        let camera = Camera::new_orthographic(
+            Viewport::new_at_origo(64, 64),
+            vec3(0.0, 10.0, 0.0),
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 0.0, -1.0),
+            8.0,
+            0.1,
+            100.0,
+        );
+        let control = Controls::new(vec3(0.0, 0.0, 0.0), 1.0, 100.0);
+        let mut lights = Lights::new();
+        lights.add(AmbientLight::new(context, 0.2, Srgba::WHITE));
+        lights.add(DirectionalLight::new(
+            context,
+            2.0,
+            Srgba::WHITE,
+            &vec3(1.0, -1.0, 0.0),
+        ));
+        let parts = ScenePart::from_cpu_model(context, &cubes(1)).unwrap();
+        let mut scene = Scene::new(camera, control, parts, lights);
+        scene.set_ground(Some(GroundPlane::new(context, -1.0, 20.0)));
+        scene.clock_mut().pause();
+        scene
+    }
+
+    /// A drag with the left button by `delta` physical pixels, starting at the center of a
+    /// 64 pixel viewport.
+    fn drag(delta: (f32, f32)) -> Vec<Event> {
+        let position = PhysicalPoint { x: 32.0, y: 32.0 };
+        vec![
+            Event::MousePress {
+                button: MouseButton::Left,
+                position,
+                modifiers: Modifiers::default(),
+                handled: false,
+            },
+            Event::MouseMotion {
+                button: Some(MouseButton::Left),
+                delta,
+                position,
+                modifiers: Modifiers::default(),
+                handled: false,
+            },
+            Event::MouseRelease {
+                button: MouseButton::Left,
+                position,
+                modifiers: Modifiers::default(),
+                handled: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn exposure_stays_positive() {
+        let mut scene = empty_scene();
+        for exposure in [0.0, -2.0, f32::NAN] {
+            scene.set_exposure(exposure);
+            assert_eq!(scene.exposure(), MIN_EXPOSURE);
+        }
+        scene.set_exposure(2.0);
+        assert_eq!(scene.exposure(), 2.0);
+    }
+
+    #[test]
+    fn empty_scene_has_an_empty_bounding_box() {
+        assert!(empty_scene().bounding_box().is_empty());
+    }
+
+    #[test]
+    fn playing_clock_keeps_the_scene_dirty() {
+        let mut scene = empty_scene();
+        assert!(scene.is_dirty());
+        scene.dirty = false;
+        assert!(scene.is_dirty());
+        scene.advance(Duration::from_millis(16));
+        assert!(scene.is_dirty());
+        scene.clock_mut().pause();
+        scene.dirty = false;
+        scene.advance(Duration::from_millis(16));
+        assert!(!scene.is_dirty());
+        scene.set_clear_color(Srgba::RED);
+        assert!(scene.is_dirty());
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn static_scene_settles_and_dirties_on_a_camera_event() {
+        let harness = TestHarness::new(64, 64).unwrap();
+        let scheduler = RenderScheduler::new(RenderMode::OnDemand);
+        let mut scene = cube_scene(harness.context());
+        scene.clock_mut().pause();
+        assert!(scheduler.should_redraw(&scene));
+        scene.render(harness.context(), harness.viewport());
+        scene.advance(Duration::from_millis(16));
+        assert!(!scene.is_dirty());
+        assert!(!scheduler.should_redraw(&scene));
+
+        scene.handle_events(harness.context(), &mut drag((10.0, 0.0)));
+        assert!(scene.is_dirty());
+        assert!(scheduler.should_redraw(&scene));
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn triangle_counts_accumulate_over_a_frame() {
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        let mut scene = cube_scene(context);
+        for _ in 0..2 {
+            scene.render(context, harness.viewport());
+            let stats = scene.stats();
+            assert_eq!(stats.draw_calls, 1);
+            assert_eq!(stats.triangles, 12);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn render_once_poses_by_the_given_time() {
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        let viewport = harness.viewport();
+        let mut scene = cube_scene(context);
+        scene.set_clear_color(Srgba::WHITE);
+        scene.parts_mut()[0]
+            .model_mut()
+            .set_animation(|time| Mat4::from_translation(vec3(time / 1000.0, 0.0, 0.0)));
+
+        let mut frame = |time| {
+            scene
+                .render_once(context, viewport, time, &mut [], true)
+                .unwrap()
+        };
+        let start = frame(0.0);
+        let later = frame(1000.0);
+        let start_again = frame(0.0);
+        assert_ne!(start, later);
+        assert_eq!(start, start_again);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn enabling_shadows_darkens_the_ground_under_the_model() {
+        use three_d::context::HasContext;
+
+        use crate::lights::ShadowSettings;
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        let mut scene = shadow_scene(context);
+        // Two units right of the center, where the shadow falls.
+        let unshadowed = harness.render_scene(&mut scene, 0.0).pixel(48, 32);
+        assert!(scene
+            .lights_mut()
+            .enable_shadows(1, ShadowSettings::default()));
+        let shadowed = harness.render_scene(&mut scene, 0.0).pixel(48, 32);
+        assert_eq!(unsafe { context.get_error() }, three_d::context::NO_ERROR);
+        assert!(
+            shadowed[0] < unshadowed[0],
+            "{shadowed:?} vs {unshadowed:?}"
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn shadowed_ground_is_darker_than_lit_ground() {
+        use crate::lights::ShadowSettings;
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let mut scene = shadow_scene(harness.context());
+        let settings = ShadowSettings {
+            texture_size: 512,
+            ..Default::default()
+        };
+        assert!(scene.lights_mut().enable_shadows(1, settings));
+        let image = harness.render_scene(&mut scene, 0.0);
+        // Two units right of the cube lies in its shadow, two units left doesn't.
+        let shadowed = image.pixel(48, 32);
+        let lit = image.pixel(16, 32);
+        assert!(shadowed[0] < lit[0], "{shadowed:?} vs {lit:?}");
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn batching_same_material_parts_saves_draw_calls() {
+        use crate::batch::batch_by_material;
+        use crate::testing::TestHarness;
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        let viewport = harness.viewport();
+        let model = cubes(4);
+        let mut scene = empty_scene();
+        scene.set_parts(ScenePart::from_cpu_model(context, &model).unwrap());
+        scene.set_batches(batch_by_material(context, &model, |_| true));
+
+        scene.render(context, viewport);
+        let naive = scene.stats();
+        scene.render_batched(context, viewport);
+        let batched = scene.stats();
+        assert_eq!(naive.draw_calls, 4);
+        assert_eq!(batched.draw_calls, 1);
+        assert_eq!(batched.triangles, naive.triangles);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn msaa_silhouette_edge_has_fractional_alpha() {
+        use three_d::Mat4;
+
+        use crate::testing::TestHarness;
+        use crate::windowed_context::{MsaaSamples, WindowedContextBuilder};
+
+        let builder = WindowedContextBuilder::new()
+            .transparent(true)
+            .msaa(MsaaSamples::X4);
+        let harness = TestHarness::with_builder(64, 64, builder).unwrap();
+        let context = harness.context();
+        assert!(context.samples() > 0, "no multisampled config");
+        let mut scene = empty_scene();
+        let mut parts = ScenePart::from_cpu_model(context, &cubes(1)).unwrap();
+        // Tilted, so the edges cross pixels instead of running along their borders.
+        parts[0]
+            .model_mut()
+            .set_transformation(Mat4::from_angle_z(degrees(30.0)));
+        scene.set_parts(parts);
+        scene.set_clear_color(Srgba::new(0, 0, 0, 0));
+        let image = harness.render_scene(&mut scene, 0.0);
+        let alphas: Vec<u8> = image.pixels.iter().map(|pixel| pixel[3]).collect();
+        assert!(alphas.contains(&0) && alphas.contains(&255));
+        assert!(
+            alphas.iter().any(|&alpha| alpha > 0 && alpha < 255),
+            "no edge pixel with partial coverage"
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn selection_outline_surrounds_the_silhouette() {
+        use crate::testing::TestHarness;
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        let mut scene = empty_scene();
+        scene.set_clear_color(Srgba::WHITE);
+        scene.set_parts(ScenePart::from_cpu_model(context, &cubes(1)).unwrap());
+        let magenta = Srgba::new_opaque(255, 0, 255);
+        scene.outline_mut().set_color(magenta);
+        let is_outline = |pixel: &[u8; 4]| pixel[0] > 240 && pixel[1] < 16 && pixel[2] > 240;
+
+        let plain = harness.render_scene(&mut scene, 0.0);
+        assert!(!plain.pixels.iter().any(is_outline));
+
+        scene.set_selected(Some("cube0"));
+        let outlined = harness.render_scene(&mut scene, 0.0);
+        assert!(outlined.pixels.iter().any(is_outline));
+        // Around the cube, not over it: the center keeps its shading.
+        assert!(!is_outline(&outlined.pixel(32, 32)));
+        assert_eq!(outlined.pixel(32, 32), plain.pixel(32, 32));
+        assert_eq!(outlined.pixel(0, 0), plain.pixel(0, 0));
+    }
+
+    /// A unit quad facing away from the camera of [empty_scene], so only its back face shows.
+    fn quad_from_behind(context: &Context) -> Vec<ScenePart> {
+        use three_d::{CpuMesh, Mat4};
+
+        let cpu_model = three_d::CpuModel {
+            name: "quad".to_string(),
+            geometries: vec![three_d_asset::Primitive {
+                name: "quad".to_string(),
+                transformation: Mat4::from_angle_y(degrees(180.0)),
+                animations: Vec::new(),
+                geometry: three_d_asset::Geometry::Triangles(CpuMesh::square()),
+                material_index: None,
+            }],
+            materials: Vec::new(),
+        };
+        ScenePart::from_cpu_model(context, &cpu_model).unwrap()
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn back_face_shows_only_without_culling() {
+        use crate::testing::TestHarness;
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        let mut scene = empty_scene();
+        scene.set_clear_color(Srgba::WHITE);
+        scene.set_parts(quad_from_behind(context));
+        let background = harness.render_scene(&mut scene, 0.0).pixel(0, 0);
+
+        assert_eq!(scene.cull(), Cull::Back);
+        let culled = harness.render_scene(&mut scene, 0.0);
+        assert_eq!(culled.pixel(32, 32), background);
+
+        scene.set_cull(Cull::None);
+        let unculled = harness.render_scene(&mut scene, 0.0);
+        assert_ne!(unculled.pixel(32, 32), background);
+
+        scene.set_cull(Cull::Back);
+        scene.parts_mut()[0].set_double_sided(true);
+        let double_sided = harness.render_scene(&mut scene, 0.0);
+        assert_eq!(double_sided.pixel(32, 32), unculled.pixel(32, 32));
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn bounding_box_is_the_union_of_the_placed_parts() {
+        use three_d::Mat4;
+
+        use crate::testing::TestHarness;
+
+        let harness = TestHarness::new(8, 8).unwrap();
+        let mut cpu_model = cubes(2);
+        cpu_model.geometries[0].transformation = Mat4::from_translation(vec3(-2.0, 0.0, 0.0));
+        cpu_model.geometries[1].transformation = Mat4::from_translation(vec3(3.0, 1.0, 0.0));
+        let mut scene = empty_scene();
+        scene.set_parts(ScenePart::from_cpu_model(harness.context(), &cpu_model).unwrap());
+
+        let bounds = scene.bounding_box();
+        assert_eq!(bounds.min(), vec3(-3.0, -1.0, -1.0));
+        assert_eq!(bounds.max(), vec3(4.0, 2.0, 1.0));
+
+        let lift = Mat4::from_translation(vec3(0.0, 5.0, 0.0));
+        scene.parts_mut()[1]
+            .model_mut()
+            .set_animation(move |_| lift);
+        scene.advance(Duration::from_millis(16));
+        let bounds = scene.bounding_box();
+        assert_eq!(bounds.min(), vec3(-3.0, -1.0, -1.0));
+        assert_eq!(bounds.max(), vec3(4.0, 7.0, 1.0));
+
+        scene.set_visible("cube0", false);
+        assert_eq!(scene.bounding_box().min(), vec3(2.0, 4.0, -1.0));
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn msaa_changes_reallocate_the_offscreen_target() {
+        use crate::render_texture;
+        use crate::testing::TestHarness;
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        let max_samples = render_texture::max_samples(context);
+        // GL 3.0 requires support for at least 4 samples.
+        assert!(max_samples >= 4, "only {max_samples} samples supported");
+        let mut scene = empty_scene();
+        assert_eq!(scene.set_msaa(context, MsaaSamples::X2), MsaaSamples::X2);
+        harness.render_scene(&mut scene, 0.0);
+        assert_eq!(scene.multisampled.as_ref().unwrap().number_of_samples(), 2);
+
+        assert_eq!(scene.set_msaa(context, MsaaSamples::X4), MsaaSamples::X4);
+        assert_eq!(scene.multisampled.as_ref().unwrap().number_of_samples(), 4);
+        harness.render_scene(&mut scene, 0.0);
+        assert_eq!(scene.multisampled.as_ref().unwrap().number_of_samples(), 4);
+
+        let applied = scene.set_msaa(context, MsaaSamples::X16);
+        assert_eq!(applied, MsaaSamples::at_most(max_samples));
+        assert_eq!(scene.msaa(), applied);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn gradient_background_runs_top_to_bottom_behind_the_model() {
+        use crate::testing::{assert_pixel_near, TestHarness};
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        let mut scene = empty_scene();
+        scene.set_background(Background::Gradient {
+            top: Srgba::RED,
+            bottom: Srgba::BLUE,
+        });
+        scene.set_parts(ScenePart::from_cpu_model(context, &cubes(1)).unwrap());
+        let image = harness.render_scene(&mut scene, 0.0);
+        assert_pixel_near(&image, 2, 0, [255, 0, 0, 255], 4);
+        assert_pixel_near(&image, 2, 63, [0, 0, 255, 255], 4);
+        let middle = image.pixel(2, 32);
+        assert!(middle[0] > 100 && middle[2] > 100, "{middle:?}");
+        // The cube is drawn over the gradient, not hidden by it.
+        let center = image.pixel(32, 32);
+        assert!(center[0] < 100 && center[2] < 100, "{center:?}");
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn parts_are_looked_up_by_name() {
+        use crate::testing::TestHarness;
+
+        let harness = TestHarness::new(8, 8).unwrap();
+        let mut cpu_model = cubes(3);
+        for (primitive, name) in cpu_model
+            .geometries
+            .iter_mut()
+            .zip(["body", "wheel", "wheel"])
+        {
+            primitive.name = name.to_string();
+        }
+        let mut scene = empty_scene();
+        scene.set_parts(ScenePart::from_cpu_model(harness.context(), &cpu_model).unwrap());
+
+        let ids: Vec<&str> = scene.parts().iter().map(ScenePart::id).collect();
+        assert_eq!(ids, ["body", "wheel", "wheel#2"]);
+        assert_eq!(scene.part("wheel#2").map(ScenePart::id), Some("wheel#2"));
+        assert!(scene.part("wheel#3").is_none());
+        assert!(scene.set_visible("wheel", false));
+        assert!(!scene.part("wheel").unwrap().is_visible());
+        assert!(scene.part("wheel#2").unwrap().is_visible());
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn hidden_parts_are_neither_drawn_nor_picked() {
+        use crate::testing::{assert_pixel_near, TestHarness};
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        let viewport = harness.viewport();
+        let center = PhysicalPoint { x: 32.0, y: 32.0 };
+        let mut scene = empty_scene();
+        scene.set_clear_color(Srgba::WHITE);
+        let mut cpu_model = cubes(2);
+        cpu_model.geometries[1].transformation =
+            three_d::Mat4::from_translation(vec3(0.0, 0.0, -3.0));
+        scene.set_parts(ScenePart::from_cpu_model(context, &cpu_model).unwrap());
+        scene.render(context, viewport);
+        assert_eq!(scene.stats().triangles, 24);
+        assert_eq!(scene.pick_part(context, center), Some("cube0"));
+
+        assert!(scene.set_visible("cube0", false));
+        scene.render(context, viewport);
+        assert_eq!(scene.stats().triangles, 12);
+        assert_eq!(scene.pick_part(context, center), Some("cube1"));
+
+        assert!(scene.set_visible("cube1", false));
+        let image = harness.render_scene(&mut scene, 0.0);
+        assert_eq!(scene.stats().triangles, 0);
+        assert_eq!(scene.pick_part(context, center), None);
+        assert_pixel_near(&image, 32, 32, [255, 255, 255, 255], 0);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn provided_objects_are_drawn_with_the_scene() {
+        use three_d::{ColorMaterial, CpuMesh, Gm, Mat4, Mesh};
+
+        use crate::testing::{assert_pixel_near, TestHarness};
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        let viewport = harness.viewport();
+        let quad = |x: f32, color: Srgba| {
+            let mut object = Gm::new(
+                Mesh::new(context, &CpuMesh::square()),
+                ColorMaterial {
+                    color,
+                    ..Default::default()
+                },
+            );
+            object.set_transformation(
+                Mat4::from_translation(vec3(x, 0.0, 0.0)) * Mat4::from_scale(0.5),
+            );
+            object
+        };
+        let left = quad(-1.0, Srgba::RED);
+        let right = quad(1.0, Srgba::GREEN);
+        let mut scene = empty_scene();
+
+        scene.set_clear_color(Srgba::BLUE);
+        let cleared = harness
+            .render(|_| scene.render_provided(context, viewport, |_| FrameContents::default()));
+        assert_eq!(scene.stats().draw_calls, 0);
+        assert_pixel_near(&cleared, 32, 32, [0, 0, 255, 255], 0);
+
+        let image = harness.render(|_| {
+            scene.render_provided(context, viewport, |_| FrameContents {
+                objects: vec![&left, &right],
+                lights: Vec::new(),
+            })
+        });
+        assert_eq!(scene.stats().draw_calls, 2);
+        // A unit is about 19 pixels at the camera's distance.
+        assert_pixel_near(&image, 13, 32, [255, 0, 0, 255], 1);
+        assert_pixel_near(&image, 51, 32, [0, 255, 0, 255], 1);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn larger_points_cover_more_pixels() {
+        use three_d::context::HasContext;
+
+        use crate::raster::{point_size_range, FillMode, RasterSettings};
+        use crate::testing::TestHarness;
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        // Point fill needs polygon modes, which GLES doesn't have.
+        assert!(!context.context.version().is_embedded);
+        let (_, max_point_size) = point_size_range(context);
+        assert!(
+            max_point_size >= 8.0,
+            "points are at most {max_point_size} pixels"
+        );
+        let mut scene = empty_scene();
+        scene.set_clear_color(Srgba::WHITE);
+        scene.set_parts(ScenePart::from_cpu_model(context, &cubes(1)).unwrap());
+        let mut covered = |point_size| {
+            scene.set_raster(RasterSettings {
+                fill_mode: FillMode::Points,
+                point_size,
+                line_width: 1.0,
+            });
+            let image = harness.render_scene(&mut scene, 0.0);
+            let background = image.pixel(0, 0);
+            image
+                .pixels
+                .iter()
+                .filter(|pixel| **pixel != background)
+                .count()
+        };
+        let small = covered(1.0);
+        let large = covered(8.0);
+        assert!(small > 0);
+        assert!(
+            large > small * 8,
+            "{small} pixels at size 1, {large} at size 8"
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn update_advances_time_and_applies_events() {
+        use crate::testing::TestHarness;
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        let mut scene = empty_scene();
+        let position = *scene.camera().position();
+
+        let mut events = drag((10.0, 0.0));
+        assert!(scene.update(context, Duration::from_millis(16), &mut events));
+        assert!(events.is_empty());
+        assert_eq!(scene.clock().time(), 16.0);
+        assert_ne!(*scene.camera().position(), position);
+
+        let position = *scene.camera().position();
+        scene.update(context, Duration::ZERO, &mut Vec::new());
+        assert_eq!(scene.clock().time(), 16.0);
+        assert_eq!(*scene.camera().position(), position);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn clearing_only_color_keeps_the_depth_of_the_last_frame() {
+        use crate::testing::TestHarness;
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        let viewport = harness.viewport();
+        let center_depth = || {
+            let screen = RenderTarget::screen(context, 64, 64);
+            let depth = screen.read_depth();
+            (depth[32 * 64 + 32], depth[0])
+        };
+        let mut scene = empty_scene();
+        scene.set_msaa(context, MsaaSamples::Off);
+        scene.set_parts(ScenePart::from_cpu_model(context, &cubes(1)).unwrap());
+        scene.render(context, viewport);
+        let (cube, cleared) = center_depth();
+        assert!(cube < cleared);
+
+        assert!(scene.set_visible("cube0", false));
+        scene.set_clear_config(ClearConfig {
+            depth: false,
+            ..ClearConfig::default()
+        });
+        scene.render(context, viewport);
+        assert_eq!(center_depth(), (cube, cleared));
+
+        scene.set_clear_config(ClearConfig::default());
+        scene.render(context, viewport);
+        assert_eq!(center_depth(), (cleared, cleared));
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn render_frame_draws_once_and_skips_empty_viewports() {
+        use crate::testing::TestHarness;
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        let mut scene = empty_scene();
+        scene.set_parts(ScenePart::from_cpu_model(context, &cubes(1)).unwrap());
+        let dt = Duration::from_millis(16);
+
+        let mut events = drag((10.0, 0.0));
+        let minimized = Viewport::new_at_origo(0, 0);
+        scene
+            .render_frame(context, minimized, dt, &mut events)
+            .unwrap();
+        assert!(events.is_empty());
+        assert!(scene.dirty);
+        assert_eq!(scene.stats().triangles, 0);
+
+        scene
+            .render_frame(context, harness.viewport(), dt, &mut Vec::new())
+            .unwrap();
+        assert!(!scene.dirty);
+        assert_eq!(scene.stats().triangles, 12);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn supersampling_smooths_edges_at_the_window_size() {
+        use crate::testing::{Image, TestHarness};
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        // Pixels only partly covered by the black cube on white.
+        let edge_pixels = |image: &Image| {
+            let partial = |pixel: &&[u8; 4]| (16..240).contains(&pixel[0]);
+            image.pixels.iter().filter(partial).count()
+        };
+        let mut scene = empty_scene();
+        scene.set_clear_color(Srgba::WHITE);
+        let mut cpu_model = cubes(1);
+        cpu_model.geometries[0].transformation = three_d::Mat4::from_angle_z(degrees(30.0));
+        scene.set_parts(ScenePart::from_cpu_model(context, &cpu_model).unwrap());
+
+        let native = harness.render_scene(&mut scene, 0.0);
+        scene.set_render_scale(RenderScale { factor: 2.0 });
+        let supersampled = harness.render_scene(&mut scene, 0.0);
+        assert_eq!((supersampled.width, supersampled.height), (64, 64));
+        assert!(edge_pixels(&supersampled) > edge_pixels(&native));
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn transparent_parts_blend_back_to_front() {
+        use three_d::{CpuMaterial, CpuMesh, Mat4};
+
+        use crate::testing::{assert_pixel_near, TestHarness};
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        // Half transparent quads, unlit so their color is the emissive one.
+        let quad = |name: &str, z: f32, material_index| three_d_asset::Primitive {
+            name: name.to_string(),
+            transformation: Mat4::from_translation(vec3(0.0, 0.0, z)),
+            animations: Vec::new(),
+            geometry: three_d_asset::Geometry::Triangles(CpuMesh::square()),
+            material_index: Some(material_index),
+        };
+        let material = |emissive| CpuMaterial {
+            albedo: Srgba::new(0, 0, 0, 128),
+            emissive,
+            ..Default::default()
+        };
+        let cpu_model = three_d::CpuModel {
+            name: "quads".to_string(),
+            // The nearer one first, so drawing them as given would be wrong.
+            geometries: vec![quad("near", 1.0, 0), quad("far", 0.0, 1)],
+            materials: vec![
+                material(Srgba::new_opaque(0, 255, 0)),
+                material(Srgba::new_opaque(255, 0, 0)),
+            ],
+        };
+        let mut scene = empty_scene();
+        scene.set_clear_color(Srgba::WHITE);
+        scene.set_parts(ScenePart::from_cpu_model(context, &cpu_model).unwrap());
+        assert!(scene.parts().iter().all(ScenePart::is_transparent));
+
+        // Red over white, then green over that.
+        let image = harness.render_scene(&mut scene, 0.0);
+        assert_pixel_near(&image, 32, 32, [128, 191, 64, 255], 8);
+
+        scene.parts_mut()[0].set_sort_key(Some(100.0));
+        let image = harness.render_scene(&mut scene, 0.0);
+        assert_pixel_near(&image, 32, 32, [191, 128, 64, 255], 8);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn render_hooks_run_once_per_frame_and_the_viewport_is_restored() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let mut scene = empty_scene();
+        scene.set_clear_color(Srgba::WHITE);
+        scene.set_parts(ScenePart::from_cpu_model(context, &cubes(1)).unwrap());
+        let pre = calls.clone();
+        scene.set_pre_render(move |context| {
+            pre.borrow_mut().push("pre");
+            // Left for the scene to undo.
+            context.set_viewport(Viewport::new_at_origo(1, 1));
+        });
+        let post = calls.clone();
+        scene.set_post_render(move |_| post.borrow_mut().push("post"));
+
+        let image = harness.render_scene(&mut scene, 0.0);
+        assert_eq!(*calls.borrow(), ["pre", "post"]);
+        assert_ne!(image.pixel(32, 32), [255, 255, 255, 255]);
+        harness.render_scene(&mut scene, 0.0);
+        assert_eq!(calls.borrow().len(), 4);
+
+        scene.clear_render_hooks();
+        harness.render_scene(&mut scene, 0.0);
+        assert_eq!(calls.borrow().len(), 4);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn resize_updates_the_camera_in_the_same_step() {
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        let mut scene = clean_scene();
+        scene.resize(context, PhysicalSize::new(96, 48));
+        assert_eq!(context.surface_size(), (96, 48));
+        assert_eq!(scene.camera().viewport(), Viewport::new_at_origo(96, 48));
+        // The projection follows the new aspect ratio of 2.
+        let projection = scene.camera().projection();
+        assert!((projection.y.y / projection.x.x - 2.0).abs() < 1e-4);
+        assert!(scene.is_dirty());
+
+        scene.resize(context, PhysicalSize::new(0, 48));
+        assert_eq!(scene.camera().viewport(), Viewport::new_at_origo(96, 48));
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn gbuffer_of_a_slanted_plane_matches_its_color() {
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        // A plane leaning back, its top farther from the camera than its bottom.
+        let cpu_model = three_d::CpuModel {
+            name: "plane".to_string(),
+            geometries: vec![three_d_asset::Primitive {
+                name: "plane".to_string(),
+                transformation: Mat4::from_angle_x(degrees(-60.0)),
+                animations: Vec::new(),
+                geometry: three_d_asset::Geometry::Triangles(CpuMesh::square()),
+                material_index: None,
+            }],
+            materials: Vec::new(),
+        };
+        let mut scene = empty_scene();
+        scene.set_clear_color(Srgba::WHITE);
+        scene.set_parts(ScenePart::from_cpu_model(context, &cpu_model).unwrap());
+        let gbuffer = scene.capture_gbuffer(context, harness.viewport());
+        assert_eq!((gbuffer.width, gbuffer.height), (64, 64));
+
+        // Drawn exactly where there is depth, give or take the multisampled edge.
+        let mismatched = gbuffer
+            .color
+            .iter()
+            .zip(&gbuffer.depth)
+            .filter(|(color, depth)| (**color == [255, 255, 255, 255]) != depth.is_infinite())
+            .count();
+        assert!(mismatched < 64, "{mismatched} pixels differ");
+        let column: Vec<f32> = (0..64)
+            .filter_map(|y| gbuffer.get(32, y))
+            .map(|(_, depth)| depth)
+            .filter(|depth| depth.is_finite())
+            .collect();
+        assert!(column.len() > 8);
+        assert!(column.windows(2).all(|pair| pair[0] > pair[1]));
+        let (_, center) = gbuffer.get(32, 32).unwrap();
+        assert!((center - 4.0).abs() < 0.1);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn part_animations_are_replaced_and_frozen_in_place() {
+        use std::sync::Arc;
+
+        let harness = TestHarness::new(8, 8).unwrap();
+        let mut cpu_model = cubes(2);
+        let own = Mat4::from_translation(vec3(0.0, 0.0, -1.0));
+        cpu_model.geometries[0].transformation = own;
+        cpu_model.geometries[1].transformation = Mat4::from_translation(vec3(3.0, 0.0, 0.0));
+        let mut scene = clean_scene();
+        scene.set_parts(ScenePart::from_cpu_model(harness.context(), &cpu_model).unwrap());
+        let center = |scene: &Scene, id| scene.part(id).unwrap().model().aabb().center();
+
+        let slide: PartAnimation = Arc::new(|time| Mat4::from_translation(vec3(time, 0.0, 0.0)));
+        assert!(scene.set_part_animation("cube0", Some(slide)));
+        scene.animate(2.0);
+        assert_eq!(center(&scene, "cube0"), vec3(2.0, 0.0, -1.0));
+        assert_eq!(center(&scene, "cube1"), vec3(3.0, 0.0, 0.0));
+
+        let lift: PartAnimation = Arc::new(|time| Mat4::from_translation(vec3(0.0, time, 0.0)));
+        scene.set_part_animation("cube0", Some(lift));
+        scene.animate(2.0);
+        assert_eq!(center(&scene, "cube0"), vec3(0.0, 2.0, -1.0));
+
+        scene.clock_mut().set_time(2.0);
+        scene.set_part_animation("cube0", None);
+        scene.animate(5.0);
+        assert_eq!(center(&scene, "cube0"), vec3(0.0, 2.0, -1.0));
+        assert!(scene.part("cube0").unwrap().animation().is_none());
+        assert!(!scene.set_part_animation("missing", None));
+    }
+
+    #[test]
+    fn a_long_gap_advances_time_by_the_max_frame_delta_only() {
+        let mut scene = empty_scene();
+        scene.advance(Duration::from_secs(2));
+        assert_eq!(scene.clock().time(), 100.0);
+
+        scene.set_max_frame_delta(Some(Duration::from_millis(500)));
+        scene.advance(Duration::from_secs(2));
+        assert_eq!(scene.clock().time(), 600.0);
+
+        scene.set_max_frame_delta(None);
+        scene.advance(Duration::from_secs(2));
+        assert_eq!(scene.clock().time(), 2600.0);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn render_once_jumps_past_the_max_frame_delta() {
+        let harness = TestHarness::new(8, 8).unwrap();
+        let mut scene = empty_scene();
+        let viewport = harness.viewport();
+        scene.render_once(harness.context(), viewport, 5000.0, &mut [], false);
+        assert_eq!(scene.clock().time(), 5000.0);
+    }
+
+    #[test]
+    fn overlay_modes_set_up_clearing_and_compositing() {
+        let mut scene = empty_scene();
+        scene.set_overlay_mode(OverlayMode::Transparent);
+        assert_eq!(scene.clear_color(), Srgba::new(0, 0, 0, 0));
+        assert!(scene.clear_config().color && scene.clear_config().depth);
+        assert_eq!(scene.composite_mode(), CompositeMode::Premultiplied);
+
+        scene.set_overlay_mode(OverlayMode::KeepColor);
+        assert!(!scene.clear_config().color && scene.clear_config().depth);
+        assert_eq!(scene.composite_mode(), CompositeMode::Straight);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn transparent_overlay_leaves_only_the_model_opaque() {
+        use dioxus::desktop::tao::window::WindowBuilder;
+
+        use crate::testing::Image;
+        use crate::windowed_context::WindowedContextBuilder;
+
+        let event_loop = crate::testing::new_event_loop();
+        let window = WindowBuilder::new()
+            .with_visible(false)
+            .with_transparent(true)
+            .with_inner_size(PhysicalSize::new(64, 64))
+            .build(&event_loop)
+            .unwrap();
+        // The surface needs an alpha channel to read the composited alpha back.
+        let context = WindowedContextBuilder::new()
+            .hardware_acceleration(three_d::HardwareAcceleration::Preferred)
+            .transparent(true)
+            .build_from_tao_window(&window)
+            .unwrap();
+        let mut scene = empty_scene();
+        scene.set_parts(ScenePart::from_cpu_model(&context, &cubes(1)).unwrap());
+        scene.set_overlay_mode(OverlayMode::Transparent);
+        let viewport = context.viewport();
+        let pixels = scene
+            .render_once(&context, viewport, 0.0, &mut [], true)
+            .unwrap();
+        let image = Image::from_bottom_up(viewport.width, viewport.height, pixels);
+        assert_eq!(image.pixel(0, 0)[3], 0);
+        assert_eq!(image.pixel(32, 32)[3], 255);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn clip_planes_cut_away_the_clipped_side() {
+        use crate::clip::ClipPlane;
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let mut scene = empty_scene();
+        scene.set_parts(ScenePart::from_cpu_model(harness.context(), &cubes(1)).unwrap());
+        scene.set_background(Background::Solid(Srgba::WHITE));
+        // Unlit, the cube is black; it spans about pixels 6 to 58.
+        let image = harness.render_scene(&mut scene, 0.0);
+        assert_eq!(image.pixel(20, 32)[..3], [0, 0, 0]);
+        assert_eq!(image.pixel(44, 32)[..3], [0, 0, 0]);
+
+        let plane = ClipPlane::through(vec3(0.0, 0.0, 0.0), vec3(1.0, 0.0, 0.0));
+        scene.set_clip_planes(vec![plane]);
+        let image = harness.render_scene(&mut scene, 0.0);
+        // The back faces inside are culled, so the background shows through the cut.
+        assert_eq!(image.pixel(20, 32)[..3], [255, 255, 255]);
+        assert_eq!(image.pixel(44, 32)[..3], [0, 0, 0]);
+
+        scene.set_clip_planes(vec![plane.flipped()]);
+        let image = harness.render_scene(&mut scene, 0.0);
+        assert_eq!(image.pixel(20, 32)[..3], [0, 0, 0]);
+        assert_eq!(image.pixel(44, 32)[..3], [255, 255, 255]);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn bloom_spreads_bright_emissive_parts_into_their_surroundings() {
+        use three_d::{CpuMaterial, CpuMesh, Mat4};
+
+        use crate::bloom::Bloom;
+        use crate::testing::TestHarness;
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        // A small white emissive square, about 10 pixels across, on black.
+        let cpu_model = three_d::CpuModel {
+            name: "glow".to_string(),
+            geometries: vec![three_d_asset::Primitive {
+                name: "glow".to_string(),
+                transformation: Mat4::from_scale(0.25),
+                animations: Vec::new(),
+                geometry: three_d_asset::Geometry::Triangles(CpuMesh::square()),
+                material_index: Some(0),
+            }],
+            materials: vec![CpuMaterial {
+                albedo: Srgba::BLACK,
+                emissive: Srgba::WHITE,
+                ..Default::default()
+            }],
+        };
+        let mut scene = empty_scene();
+        scene.set_clear_color(Srgba::BLACK);
+        scene.set_parts(ScenePart::from_cpu_model(harness.context(), &cpu_model).unwrap());
+        let beside = |image: &crate::testing::Image| image.pixel(42, 32);
+
+        let plain = harness.render_scene(&mut scene, 0.0);
+        assert!(plain.pixel(32, 32)[0] > 200);
+        assert_eq!(beside(&plain)[..3], [0, 0, 0]);
+
+        scene.set_bloom(Some(Bloom {
+            threshold: 0.5,
+            intensity: 1.0,
+            radius: 0.5,
+        }));
+        let bloomed = harness.render_scene(&mut scene, 0.0);
+        assert!(beside(&bloomed)[0] > 8, "{:?}", beside(&bloomed));
+        // Farther out the glow fades.
+        assert!(bloomed.pixel(60, 32)[0] < beside(&bloomed)[0]);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn reference_scene_renders_something_without_assets() {
+        let harness = TestHarness::new(64, 64).unwrap();
+        let mut scene = Scene::default_reference(harness.context()).unwrap();
+        let image = harness.render_scene(&mut scene, 0.0);
+        let background = image.pixel(0, 0);
+        let drawn = image
+            .pixels
+            .iter()
+            .filter(|pixel| **pixel != background)
+            .count();
+        // The sphere alone covers more than a tenth of the frame.
+        assert!(drawn > image.pixels.len() / 10, "{drawn}");
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn normals_view_differs_from_the_shaded_picture_and_none_restores_it() {
+        use crate::debug_view::DebugView;
+        use crate::testing::{assert_pixel_near, diff, TestHarness};
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let mut scene = shadow_scene(harness.context());
+        let shaded = harness.render_scene(&mut scene, 0.0);
+
+        scene.set_debug_view(DebugView::Normals);
+        assert!(scene.is_dirty());
+        let normals = harness.render_scene(&mut scene, 0.0);
+        // Seen from above, the top of the cube faces +Y.
+        assert_pixel_near(&normals, 32, 32, [128, 255, 128, 255], 3);
+        assert!(diff(&normals, &shaded, 8).mismatched > 0);
+
+        scene.set_debug_view(DebugView::None);
+        let again = harness.render_scene(&mut scene, 0.0);
+        assert_eq!(diff(&again, &shaded, 0).mismatched, 0);
+    }
+}
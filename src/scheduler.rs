@@ -0,0 +1,517 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use dioxus::desktop::tao::event::WindowEvent;
+use dioxus::desktop::tao::event_loop::ControlFlow;
+
+use crate::scene::Scene;
+
+/// Wakes the render loop, see [RenderScheduler::set_waker].
+type Waker = Box<dyn Fn() + Send>;
+
+/// How much of a wait [RenderScheduler::limit_frame_rate] spins rather than sleeps, as
+/// sleeps overshoot by up to about a millisecond on common platforms.
+const SPIN_BEFORE_DEADLINE: Duration = Duration::from_micros(1500);
+
+/// The lowest rate [RenderScheduler::set_frame_cap] accepts, a frame every 1000 seconds.
+/// Lower ones would overflow the frame interval.
+pub const MIN_FRAME_CAP: f64 = 1e-3;
+
+/// How often the render loop should produce frames.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderMode {
+    /// Redraw at a fixed rate, whether or not anything changed.
+    Continuous { fps: f64 },
+    /// Only redraw when the scene reports that it is dirty.
+    OnDemand,
+}
+
+/// Requests a frame from outside the render loop, for example from a Dioxus effect when a
+/// signal the 3D view depends on changed.
+///
+/// Cheap to clone and `Send`, so it can be moved into effects, tasks and other threads. The
+/// request is picked up on the redraw coroutine's next wake-up, see
+/// [RenderScheduler::frame_interval], or immediately if the scheduler has a waker, see
+/// [RenderScheduler::set_waker].
+#[derive(Clone, Default)]
+pub struct RedrawHandle {
+    requested: Arc<AtomicBool>,
+    waker: Arc<Mutex<Option<Waker>>>,
+}
+
+impl RedrawHandle {
+    /// Asks for the scene to be redrawn, even if nothing in it changed.
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::Release);
+        if let Some(wake) = &*self.waker.lock().unwrap_or_else(|e| e.into_inner()) {
+            wake();
+        }
+    }
+}
+
+impl fmt::Debug for RedrawHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedrawHandle")
+            .field("requested", &self.requested.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+pub struct RenderScheduler {
+    mode: RenderMode,
+    redraw: RedrawHandle,
+    unfocused_fps: Option<f64>,
+    focused: bool,
+    auto_idle_after: Option<Duration>,
+    last_activity: Instant,
+    idle: bool,
+    frame_cap: Option<f64>,
+    cap_deadline: Option<Instant>,
+}
+
+impl Default for RenderScheduler {
+    fn default() -> Self {
+        Self::new(RenderMode::Continuous { fps: 90.0 })
+    }
+}
+
+impl RenderScheduler {
+    pub fn new(mode: RenderMode) -> Self {
+        Self {
+            mode,
+            redraw: RedrawHandle::default(),
+            unfocused_fps: None,
+            focused: true,
+            auto_idle_after: None,
+            last_activity: Instant::now(),
+            idle: false,
+            frame_cap: None,
+            cap_deadline: None,
+        }
+    }
+
+    pub fn mode(&self) -> RenderMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: RenderMode) {
+        self.mode = mode;
+    }
+
+    /// The mode frames are scheduled in right now: [RenderMode::OnDemand] while idle, see
+    /// [RenderScheduler::set_auto_idle_after], otherwise [RenderScheduler::mode].
+    pub fn effective_mode(&self) -> RenderMode {
+        if self.idle {
+            RenderMode::OnDemand
+        } else {
+            self.mode
+        }
+    }
+
+    pub fn auto_idle_after(&self) -> Option<Duration> {
+        self.auto_idle_after
+    }
+
+    /// Renders on demand once nothing changed for `timeout`, even in
+    /// [RenderMode::Continuous], and goes back to the fixed rate with the next change. `None`,
+    /// the default, keeps the mode as set. Activity is reported with
+    /// [RenderScheduler::track_activity].
+    ///
+    /// A playing animation or auto-rotation keeps the scene dirty, so the scheduler never
+    /// idles while one runs.
+    pub fn set_auto_idle_after(&mut self, timeout: Option<Duration>) {
+        self.auto_idle_after = timeout;
+        self.last_activity = Instant::now();
+        self.idle = false;
+    }
+
+    /// Whether the scheduler idles in on-demand rendering, see
+    /// [RenderScheduler::set_auto_idle_after].
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    /// Reports whether anything changed at `now`, usually [Scene::is_dirty] or that input
+    /// arrived, to enter and leave idling. A pending [RedrawHandle] request counts as a
+    /// change too.
+    ///
+    /// Call once per iteration of the render loop, after [RenderScheduler::apply_requests]
+    /// and before [RenderScheduler::should_redraw].
+    pub fn track_activity(&mut self, active: bool, now: Instant) {
+        if active || self.redraw_requested() {
+            self.last_activity = now;
+            if self.idle {
+                log_debug!("activity, leaving idle rendering");
+                self.idle = false;
+            }
+            return;
+        }
+        let Some(timeout) = self.auto_idle_after else {
+            return;
+        };
+        if !self.idle && now.saturating_duration_since(self.last_activity) >= timeout {
+            log_debug!("nothing changed, rendering on demand until something does");
+            self.idle = true;
+        }
+    }
+
+    pub fn unfocused_fps(&self) -> Option<f64> {
+        self.unfocused_fps
+    }
+
+    /// Lowers the frame rate to `fps` while the window doesn't have focus, to save power, or
+    /// keeps the full rate for `None`, the default. The full rate is also kept if it is lower.
+    ///
+    /// Scenes passing real elapsed time to [Scene::update] animate at the same speed either
+    /// way, only less smoothly. On demand, the scene is then only polled at `fps`, so a
+    /// static scene is hardly woken up at all.
+    pub fn set_unfocused_fps(&mut self, fps: Option<f64>) {
+        self.unfocused_fps = fps;
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Whether the window has focus, see [RenderScheduler::set_unfocused_fps]. Usually set
+    /// through [RenderScheduler::handle_window_event].
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    /// Tracks window focus from `event`.
+    pub fn handle_window_event(&mut self, event: &WindowEvent<'_>) {
+        if let WindowEvent::Focused(focused) = event {
+            self.set_focused(*focused);
+        }
+    }
+
+    pub fn frame_cap(&self) -> Option<f64> {
+        self.frame_cap
+    }
+
+    /// Caps the frame rate at `fps` regardless of the swap interval, e.g. to save power with
+    /// vsync off, or lifts the cap for `None`, the default. Rates that aren't positive count
+    /// as `None`, positive ones below [MIN_FRAME_CAP] are raised to it.
+    ///
+    /// The cap lowers [RenderScheduler::frame_interval] like any other rate limit. Loops
+    /// rendering back to back call [RenderScheduler::limit_frame_rate] after each frame to
+    /// hold the cap precisely.
+    pub fn set_frame_cap(&mut self, fps: Option<f64>) {
+        self.frame_cap = fps
+            .filter(|fps| *fps > 0.0)
+            .map(|fps| fps.max(MIN_FRAME_CAP));
+        self.cap_deadline = None;
+    }
+
+    /// How long to wait at `now`, after a frame, so the next one starts one capped frame
+    /// interval after the previous one did, and counts that frame as started. Time spent
+    /// rendering is part of the interval, so only what is left of it is waited.
+    ///
+    /// Frames are scheduled on a fixed grid, so waits coming up a little short or long even
+    /// out and the rate averages to the cap. A frame that overran by a whole interval or more
+    /// starts the grid over instead of rushing frames to catch up. Zero without a cap.
+    pub fn frame_cap_wait(&mut self, now: Instant) -> Duration {
+        let Some(cap) = self.frame_cap else {
+            return Duration::ZERO;
+        };
+        let interval = Duration::from_secs_f64(1.0 / cap);
+        let deadline = match self.cap_deadline {
+            Some(deadline) if now < deadline + interval => deadline,
+            _ => now,
+        };
+        self.cap_deadline = Some(deadline + interval);
+        deadline.saturating_duration_since(now)
+    }
+
+    /// Blocks until the next frame is due under [RenderScheduler::set_frame_cap], see
+    /// [RenderScheduler::frame_cap_wait]. Sleeps for most of the wait and spins only for the
+    /// last [SPIN_BEFORE_DEADLINE], for precision without burning a core.
+    pub fn limit_frame_rate(&mut self) {
+        let now = Instant::now();
+        let deadline = now + self.frame_cap_wait(now);
+        let sleep = deadline.saturating_duration_since(now + SPIN_BEFORE_DEADLINE);
+        if !sleep.is_zero() {
+            std::thread::sleep(sleep);
+        }
+        while Instant::now() < deadline {
+            std::hint::spin_loop();
+        }
+    }
+
+    /// The rate frames are rendered or polled at, given the focus and the frame cap.
+    fn fps(&self) -> f64 {
+        let fps = match self.effective_mode() {
+            RenderMode::Continuous { fps } => fps,
+            RenderMode::OnDemand => 90.0,
+        };
+        let fps = match self.unfocused_fps {
+            Some(unfocused_fps) if !self.focused => fps.min(unfocused_fps),
+            _ => fps,
+        };
+        self.frame_cap.map_or(fps, |cap| fps.min(cap))
+    }
+
+    /// A handle that requests redraws from this scheduler.
+    pub fn redraw_handle(&self) -> RedrawHandle {
+        self.redraw.clone()
+    }
+
+    /// Whether a [RedrawHandle] requested a frame that hasn't been applied yet.
+    pub fn redraw_requested(&self) -> bool {
+        self.redraw.requested.load(Ordering::Acquire)
+    }
+
+    /// Consumes a pending [RedrawHandle] request, returning whether there was one.
+    pub fn take_redraw_request(&self) -> bool {
+        self.redraw.requested.swap(false, Ordering::AcqRel)
+    }
+
+    /// Called by every [RedrawHandle::request], from whichever thread requests, so a loop
+    /// parked in [ControlFlow::Wait] can be woken up, typically through an
+    /// `EventLoopProxy::send_event`.
+    pub fn set_waker(&mut self, wake: impl Fn() + Send + 'static) {
+        *self.redraw.waker.lock().unwrap_or_else(|e| e.into_inner()) = Some(Box::new(wake));
+    }
+
+    /// Marks `scene` dirty if a [RedrawHandle] requested a frame, consuming the request.
+    ///
+    /// Call once per iteration of the render loop, before [RenderScheduler::should_redraw].
+    pub fn apply_requests(&self, scene: &mut Scene) {
+        if self.take_redraw_request() {
+            scene.mark_dirty();
+        }
+    }
+
+    /// How long the redraw coroutine should sleep between wake-ups.
+    ///
+    /// On demand the coroutine still polls, so that a scene that became dirty gets picked up
+    /// promptly.
+    pub fn frame_interval(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.fps().max(1.0))
+    }
+
+    /// When the frame after one rendered at `last_frame` is due, or `None` on demand, where
+    /// frames are only due when something changed.
+    pub fn next_frame(&self, last_frame: Instant) -> Option<Instant> {
+        match self.effective_mode() {
+            RenderMode::Continuous { .. } => Some(last_frame + self.frame_interval()),
+            RenderMode::OnDemand => None,
+        }
+    }
+
+    /// The [ControlFlow] for an event loop that owns the render loop, as
+    /// [crate::launcher::launch_scheduled] does, after a frame was rendered at `last_frame`.
+    ///
+    /// `needs_redraw` is whether the scene still changes on its own, or has input to process.
+    /// On demand without it the loop waits for the next event, so a static scene costs no CPU;
+    /// otherwise it sleeps until the next frame is due. Requests through a [RedrawHandle] only
+    /// end a [ControlFlow::Wait] if a waker was installed with [RenderScheduler::set_waker].
+    ///
+    /// Dioxus desktop keeps the event loop to itself, there the redraw coroutine sleeping for
+    /// [RenderScheduler::frame_interval] has the same role.
+    pub fn control_flow(&self, needs_redraw: bool, last_frame: Instant) -> ControlFlow {
+        match self.next_frame(last_frame) {
+            Some(next_frame) => ControlFlow::WaitUntil(next_frame),
+            None if needs_redraw || self.redraw_requested() => {
+                ControlFlow::WaitUntil(last_frame + self.frame_interval())
+            }
+            None => ControlFlow::Wait,
+        }
+    }
+
+    /// Whether a frame should be rendered for the current state of `scene`.
+    pub fn should_redraw(&self, scene: &Scene) -> bool {
+        match self.effective_mode() {
+            RenderMode::Continuous { .. } => true,
+            RenderMode::OnDemand => scene.is_dirty() || self.redraw_requested(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+    use crate::scene::tests::{clean_scene, empty_scene};
+
+    #[test]
+    fn request_makes_the_scheduler_need_a_redraw() {
+        let scheduler = RenderScheduler::new(RenderMode::OnDemand);
+        let mut scene = clean_scene();
+        assert!(!scheduler.should_redraw(&scene));
+
+        let handle = scheduler.redraw_handle();
+        std::thread::spawn(move || handle.request()).join().unwrap();
+        assert!(scheduler.redraw_requested());
+        assert!(scheduler.should_redraw(&scene));
+
+        scheduler.apply_requests(&mut scene);
+        assert!(!scheduler.redraw_requested());
+        assert!(scene.is_dirty());
+        assert!(scheduler.should_redraw(&scene));
+    }
+
+    #[test]
+    fn request_wakes_a_waiting_loop() {
+        let mut scheduler = RenderScheduler::new(RenderMode::OnDemand);
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let counter = wakes.clone();
+        scheduler.set_waker(move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+        let now = Instant::now();
+        assert_eq!(scheduler.control_flow(false, now), ControlFlow::Wait);
+
+        scheduler.redraw_handle().request();
+        assert_eq!(wakes.load(Ordering::Relaxed), 1);
+        assert_eq!(
+            scheduler.control_flow(false, now),
+            ControlFlow::WaitUntil(now + scheduler.frame_interval())
+        );
+        assert!(scheduler.take_redraw_request());
+        assert!(!scheduler.take_redraw_request());
+    }
+
+    #[test]
+    fn next_frame_is_one_interval_after_the_last() {
+        let now = Instant::now();
+        let scheduler = RenderScheduler::new(RenderMode::Continuous { fps: 50.0 });
+        assert_eq!(scheduler.frame_interval(), Duration::from_millis(20));
+        assert_eq!(
+            scheduler.next_frame(now),
+            Some(now + Duration::from_millis(20))
+        );
+        assert_eq!(
+            scheduler.control_flow(false, now),
+            ControlFlow::WaitUntil(now + Duration::from_millis(20))
+        );
+
+        let on_demand = RenderScheduler::new(RenderMode::OnDemand);
+        assert_eq!(on_demand.next_frame(now), None);
+        assert_eq!(
+            on_demand.control_flow(true, now),
+            ControlFlow::WaitUntil(now + on_demand.frame_interval())
+        );
+    }
+
+    #[test]
+    fn losing_focus_lowers_the_rate_until_it_comes_back() {
+        let mut scheduler = RenderScheduler::new(RenderMode::Continuous { fps: 50.0 });
+        scheduler.set_unfocused_fps(Some(10.0));
+        assert_eq!(scheduler.frame_interval(), Duration::from_millis(20));
+
+        scheduler.handle_window_event(&WindowEvent::Focused(false));
+        assert!(!scheduler.is_focused());
+        assert_eq!(scheduler.frame_interval(), Duration::from_millis(100));
+
+        scheduler.handle_window_event(&WindowEvent::Focused(true));
+        assert_eq!(scheduler.frame_interval(), Duration::from_millis(20));
+
+        // A full rate below the unfocused one is kept.
+        scheduler.set_unfocused_fps(Some(100.0));
+        scheduler.set_focused(false);
+        assert_eq!(scheduler.frame_interval(), Duration::from_millis(20));
+    }
+
+    #[test]
+    fn unfocused_on_demand_scenes_stay_asleep() {
+        let mut scheduler = RenderScheduler::new(RenderMode::OnDemand);
+        scheduler.set_unfocused_fps(Some(2.0));
+        scheduler.set_focused(false);
+        let scene = clean_scene();
+        let now = Instant::now();
+        assert!(!scheduler.should_redraw(&scene));
+        assert_eq!(scheduler.control_flow(false, now), ControlFlow::Wait);
+        assert_eq!(scheduler.frame_interval(), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn idling_after_the_timeout_drops_to_on_demand_until_something_changes() {
+        let mut scheduler = RenderScheduler::new(RenderMode::Continuous { fps: 50.0 });
+        scheduler.set_auto_idle_after(Some(Duration::from_secs(1)));
+        let start = Instant::now();
+        let mut scene = clean_scene();
+
+        scheduler.track_activity(scene.is_dirty(), start + Duration::from_millis(500));
+        assert!(!scheduler.is_idle());
+        scheduler.track_activity(scene.is_dirty(), start + Duration::from_millis(1100));
+        assert!(scheduler.is_idle());
+        assert_eq!(scheduler.effective_mode(), RenderMode::OnDemand);
+        assert!(!scheduler.should_redraw(&scene));
+        assert_eq!(scheduler.control_flow(false, start), ControlFlow::Wait);
+
+        scene.mark_dirty();
+        scheduler.track_activity(scene.is_dirty(), start + Duration::from_millis(1200));
+        assert!(!scheduler.is_idle());
+        assert_eq!(
+            scheduler.effective_mode(),
+            RenderMode::Continuous { fps: 50.0 }
+        );
+        assert!(scheduler.next_frame(start).is_some());
+    }
+
+    #[test]
+    fn playing_animations_keep_the_scheduler_from_idling() {
+        let mut scheduler = RenderScheduler::new(RenderMode::Continuous { fps: 50.0 });
+        scheduler.set_auto_idle_after(Some(Duration::from_millis(100)));
+        let scene = empty_scene();
+        assert!(scene.clock().is_playing());
+        let start = Instant::now();
+        for seconds in 1..10 {
+            scheduler.track_activity(scene.is_dirty(), start + Duration::from_secs(seconds));
+            assert!(!scheduler.is_idle());
+        }
+    }
+
+    #[test]
+    fn capped_waits_average_to_the_frame_interval() {
+        let mut scheduler = RenderScheduler::new(RenderMode::Continuous { fps: 1000.0 });
+        assert_eq!(scheduler.frame_cap_wait(Instant::now()), Duration::ZERO);
+        scheduler.set_frame_cap(Some(50.0));
+        assert_eq!(scheduler.frame_interval(), Duration::from_millis(20));
+
+        // Rendering takes no time, so the whole interval is waited.
+        let mut now = Instant::now();
+        assert_eq!(scheduler.frame_cap_wait(now), Duration::ZERO);
+        let mut waited = Duration::ZERO;
+        for _ in 0..100 {
+            let wait = scheduler.frame_cap_wait(now);
+            waited += wait;
+            now += wait;
+        }
+        assert_eq!(waited / 100, Duration::from_millis(20));
+
+        // Time spent rendering counts towards the interval.
+        now += Duration::from_millis(5);
+        assert_eq!(scheduler.frame_cap_wait(now), Duration::from_millis(15));
+        now += Duration::from_millis(15);
+
+        // A frame overrunning by more than an interval starts over without catching up.
+        now += Duration::from_millis(50);
+        assert_eq!(scheduler.frame_cap_wait(now), Duration::ZERO);
+        assert_eq!(scheduler.frame_cap_wait(now), Duration::from_millis(20));
+    }
+
+    #[test]
+    fn tiny_frame_caps_are_raised_to_the_minimum() {
+        let mut scheduler = RenderScheduler::new(RenderMode::Continuous { fps: 60.0 });
+        for fps in [1e-300, f64::MIN_POSITIVE, 1e-9] {
+            scheduler.set_frame_cap(Some(fps));
+            assert_eq!(scheduler.frame_cap(), Some(MIN_FRAME_CAP));
+            let now = Instant::now();
+            scheduler.frame_cap_wait(now);
+            assert_eq!(
+                scheduler.frame_cap_wait(now),
+                Duration::from_secs_f64(1.0 / MIN_FRAME_CAP)
+            );
+        }
+        for fps in [0.0, -1.0, f64::NAN] {
+            scheduler.set_frame_cap(Some(fps));
+            assert_eq!(scheduler.frame_cap(), None);
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use three_d::{Event, Key, Modifiers};
+
+/// Something a keyboard shortcut can do to a [crate::scene::Scene], see
+/// [crate::scene::Scene::execute].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SceneCommand {
+    /// Frames the selected part, or all parts without a selection.
+    FrameModel,
+    /// Frames all parts.
+    ResetView,
+    /// Switches between solid and wireframe rendering.
+    ToggleWireframe,
+    /// Plays or pauses the animation clock.
+    TogglePlayback,
+    /// Switches to the next [crate::debug_view::DebugView].
+    CycleDebugView,
+}
+
+/// A key together with the modifiers that have to be held for it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyCombo {
+    pub key: Key,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    /// ⌘ on macOS.
+    pub command: bool,
+}
+
+impl KeyCombo {
+    /// `key` without modifiers.
+    pub fn key(key: Key) -> Self {
+        Self {
+            key,
+            ctrl: false,
+            alt: false,
+            shift: false,
+            command: false,
+        }
+    }
+
+    fn matches(&self, key: Key, modifiers: &Modifiers) -> bool {
+        self.key == key
+            && self.ctrl == modifiers.ctrl
+            && self.alt == modifiers.alt
+            && self.shift == modifiers.shift
+            && self.command == modifiers.command
+    }
+}
+
+/// Maps key combos to [SceneCommand]s.
+///
+/// The defaults are F to frame the model, R to reset the view, W to toggle wireframe, Space to
+/// play or pause and V to cycle the debug views. Only events that reach the canvas are looked
+/// at, so with [crate::focus::InputFocus] filtering the window events, typing into a text
+/// field of the UI doesn't trigger shortcuts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shortcuts {
+    bindings: Vec<(KeyCombo, SceneCommand)>,
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Self {
+            bindings: vec![
+                (KeyCombo::key(Key::F), SceneCommand::FrameModel),
+                (KeyCombo::key(Key::R), SceneCommand::ResetView),
+                (KeyCombo::key(Key::W), SceneCommand::ToggleWireframe),
+                (KeyCombo::key(Key::Space), SceneCommand::TogglePlayback),
+                (KeyCombo::key(Key::V), SceneCommand::CycleDebugView),
+            ],
+        }
+    }
+}
+
+impl Shortcuts {
+    /// The default bindings, see [Shortcuts].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// No bindings at all.
+    pub fn none() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+
+    pub fn bindings(&self) -> &[(KeyCombo, SceneCommand)] {
+        &self.bindings
+    }
+
+    /// Binds `combo` to `command`, replacing what it was bound to before.
+    pub fn bind(&mut self, combo: KeyCombo, command: SceneCommand) {
+        self.unbind(combo);
+        self.bindings.push((combo, command));
+    }
+
+    /// Removes the binding of `combo`, if any.
+    pub fn unbind(&mut self, combo: KeyCombo) {
+        self.bindings.retain(|(bound, _)| *bound != combo);
+    }
+
+    /// The commands bound to the unhandled key presses in `events`, in order. The matching
+    /// presses are marked handled, so the controls don't act on them as well.
+    pub fn commands(&self, events: &mut [Event]) -> Vec<SceneCommand> {
+        let mut commands = Vec::new();
+        for event in events.iter_mut() {
+            let Event::KeyPress {
+                kind,
+                modifiers,
+                handled,
+            } = event
+            else {
+                continue;
+            };
+            if *handled {
+                continue;
+            }
+            let bound = self
+                .bindings
+                .iter()
+                .find(|(combo, _)| combo.matches(*kind, modifiers));
+            if let Some((_, command)) = bound {
+                commands.push(*command);
+                *handled = true;
+            }
+        }
+        commands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(key: Key, modifiers: Modifiers) -> Event {
+        Event::KeyPress {
+            kind: key,
+            modifiers,
+            handled: false,
+        }
+    }
+
+    #[test]
+    fn f_frames_the_model_by_default() {
+        let mut events = vec![press(Key::F, Modifiers::default())];
+        assert_eq!(
+            Shortcuts::default().commands(&mut events),
+            [SceneCommand::FrameModel]
+        );
+        assert!(matches!(events[0], Event::KeyPress { handled: true, .. }));
+    }
+
+    #[test]
+    fn modifiers_and_handled_presses_are_left_alone() {
+        let ctrl = Modifiers {
+            ctrl: true,
+            ..Default::default()
+        };
+        let mut handled = press(Key::F, Modifiers::default());
+        if let Event::KeyPress { handled, .. } = &mut handled {
+            *handled = true;
+        }
+        let mut events = vec![press(Key::F, ctrl), handled];
+        assert!(Shortcuts::default().commands(&mut events).is_empty());
+        assert!(matches!(events[0], Event::KeyPress { handled: false, .. }));
+    }
+
+    #[test]
+    fn bindings_can_be_replaced_and_removed() {
+        let mut shortcuts = Shortcuts::default();
+        shortcuts.bind(KeyCombo::key(Key::F), SceneCommand::ResetView);
+        shortcuts.unbind(KeyCombo::key(Key::Space));
+        let mut events = vec![
+            press(Key::F, Modifiers::default()),
+            press(Key::Space, Modifiers::default()),
+        ];
+        assert_eq!(shortcuts.commands(&mut events), [SceneCommand::ResetView]);
+        let mut events = vec![press(Key::F, Modifiers::default())];
+        assert!(Shortcuts::none().commands(&mut events).is_empty());
+    }
+}
@@ -0,0 +1,329 @@
+use std::time::{Duration, Instant};
+
+use three_d::context::{self, HasContext};
+use three_d::Context;
+
+use crate::windowed_context::supports_extension;
+
+/// What it cost to render one frame.
+///
+/// Plain `Copy` data, so it can be stored straight into a Dioxus signal for a HUD.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub triangles: u64,
+    /// GPU time of a recent frame, if timer queries are available.
+    ///
+    /// Timer results arrive asynchronously, so this typically lags the draw counts by one to
+    /// two frames.
+    pub gpu_time: Option<Duration>,
+}
+
+/// The number of triangles in a loaded geometry, for [RenderStatsCollector::record_draw].
+pub fn triangle_count(geometry: &three_d_asset::Geometry) -> u64 {
+    match geometry {
+        three_d_asset::Geometry::Triangles(mesh) => mesh.triangle_count() as u64,
+        three_d_asset::Geometry::Points(_) => 0,
+    }
+}
+
+/// Accumulates [RenderStats] over a frame.
+///
+/// Call [RenderStatsCollector::begin_frame], [RenderStatsCollector::record_draw] for every
+/// draw and [RenderStatsCollector::end_frame] once the frame is submitted.
+#[derive(Default)]
+pub struct RenderStatsCollector {
+    current: RenderStats,
+    last: RenderStats,
+    gpu_timing: bool,
+    timer: Option<GpuTimer>,
+    gpu_time: Option<Duration>,
+}
+
+impl RenderStatsCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables GL timer queries, if the driver supports them.
+    pub fn set_gpu_timing(&mut self, enabled: bool) {
+        self.gpu_timing = enabled;
+        if !enabled {
+            self.timer = None;
+            self.gpu_time = None;
+        }
+    }
+
+    pub fn begin_frame(&mut self, context: &Context) {
+        self.current = RenderStats::default();
+        if self.gpu_timing && self.timer.is_none() {
+            self.timer = GpuTimer::new(context);
+        }
+        if let Some(timer) = &mut self.timer {
+            timer.begin();
+        }
+    }
+
+    pub fn record_draw(&mut self, triangles: u64) {
+        self.current.draw_calls += 1;
+        self.current.triangles += triangles;
+    }
+
+    pub fn end_frame(&mut self) -> RenderStats {
+        if let Some(timer) = &mut self.timer {
+            timer.end();
+            if let Some(time) = timer.poll() {
+                self.gpu_time = Some(time);
+            }
+        }
+        self.current.gpu_time = self.gpu_time;
+        self.last = self.current;
+        self.last
+    }
+
+    /// The stats of the last completed frame.
+    pub fn last(&self) -> RenderStats {
+        self.last
+    }
+}
+
+/// Where the CPU time of one frame went, see [FrameTimer].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameTimings {
+    /// Input handling, camera controls and animation.
+    pub update: Duration,
+    /// Recording and submitting the draw calls. The GPU may still be busy with them
+    /// afterwards, see [RenderStats::gpu_time].
+    pub render: Duration,
+    /// Blocked in [crate::windowed_context::WindowedContext::swap_buffers].
+    ///
+    /// Only meaningful with vsync on, where this is the wait for the next vertical blank plus
+    /// whatever GPU work was still outstanding. Without vsync the swap returns right away and
+    /// some drivers defer the wait into the next frame's GL calls instead.
+    pub present: Duration,
+    /// From the first input of the frame, see [FrameTimer::input_received], to the end of
+    /// the present. `None` for frames without input.
+    pub input_latency: Option<Duration>,
+}
+
+impl FrameTimings {
+    /// The time from the start of the frame to the end of the present.
+    pub fn total(&self) -> Duration {
+        self.update + self.render + self.present
+    }
+}
+
+/// Turns the instants the phases of a frame end at into [FrameTimings].
+///
+/// Call [FrameTimer::begin_frame], [FrameTimer::update_done], [FrameTimer::render_done] and
+/// [FrameTimer::presented] in order; a phase that is skipped takes no time.
+#[derive(Clone, Debug, Default)]
+pub struct FrameTimer {
+    phase_start: Option<Instant>,
+    input: Option<Instant>,
+    current: FrameTimings,
+    last: FrameTimings,
+}
+
+impl FrameTimer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn begin_frame(&mut self, now: Instant) {
+        self.phase_start = Some(now);
+        self.current = FrameTimings::default();
+    }
+
+    /// Records that input arrived at `at`, for [FrameTimings::input_latency]. Only the first
+    /// input until the next present counts.
+    pub fn input_received(&mut self, at: Instant) {
+        self.input.get_or_insert(at);
+    }
+
+    pub fn update_done(&mut self, now: Instant) {
+        self.current.update = self.phase(now);
+    }
+
+    pub fn render_done(&mut self, now: Instant) {
+        self.current.render = self.phase(now);
+    }
+
+    /// Ends the frame, returning its timings.
+    pub fn presented(&mut self, now: Instant) -> FrameTimings {
+        self.current.present = self.phase(now);
+        self.current.input_latency = self
+            .input
+            .take()
+            .map(|at| now.saturating_duration_since(at));
+        self.phase_start = None;
+        self.last = self.current;
+        self.last
+    }
+
+    /// The timings of the last presented frame.
+    pub fn last(&self) -> FrameTimings {
+        self.last
+    }
+
+    /// The time since the previous phase ended, making `now` the start of the next.
+    fn phase(&mut self, now: Instant) -> Duration {
+        let start = self.phase_start.replace(now).unwrap_or(now);
+        now.saturating_duration_since(start)
+    }
+}
+
+/// A small ring of `GL_TIME_ELAPSED` queries.
+///
+/// Results are only read once `QUERY_RESULT_AVAILABLE` says so, which never stalls the
+/// pipeline; with three queries in flight a result is normally ready two frames later.
+struct GpuTimer {
+    context: Context,
+    queries: Vec<(context::Query, bool)>,
+    next: usize,
+    active: Option<usize>,
+}
+
+const TIMER_QUERIES: usize = 3;
+
+impl GpuTimer {
+    fn new(context: &Context) -> Option<Self> {
+        let version = context.version();
+        let supported = (!version.is_embedded && (version.major, version.minor) >= (3, 3))
+            || supports_extension(context, "GL_ARB_timer_query")
+            || supports_extension(context, "GL_EXT_disjoint_timer_query");
+        if !supported {
+            return None;
+        }
+        let mut queries = Vec::with_capacity(TIMER_QUERIES);
+        for _ in 0..TIMER_QUERIES {
+            queries.push((unsafe { context.create_query() }.ok()?, false));
+        }
+        Some(Self {
+            context: context.clone(),
+            queries,
+            next: 0,
+            active: None,
+        })
+    }
+
+    fn begin(&mut self) {
+        let (query, pending) = self.queries[self.next];
+        if pending {
+            // Every query is still in flight; skip timing this frame rather than waiting.
+            return;
+        }
+        unsafe { self.context.begin_query(context::TIME_ELAPSED, query) };
+        self.active = Some(self.next);
+        self.next = (self.next + 1) % self.queries.len();
+    }
+
+    fn end(&mut self) {
+        if let Some(index) = self.active.take() {
+            unsafe { self.context.end_query(context::TIME_ELAPSED) };
+            self.queries[index].1 = true;
+        }
+    }
+
+    /// The most recent finished measurement, if any finished since the last poll.
+    fn poll(&mut self) -> Option<Duration> {
+        let mut latest = None;
+        for (query, pending) in self.queries.iter_mut() {
+            if !*pending {
+                continue;
+            }
+            let available = unsafe {
+                self.context
+                    .get_query_parameter_u32(*query, context::QUERY_RESULT_AVAILABLE)
+            };
+            if available != 0 {
+                let nanos = unsafe {
+                    self.context
+                        .get_query_parameter_u32(*query, context::QUERY_RESULT)
+                };
+                *pending = false;
+                latest = Some(Duration::from_nanos(nanos as u64));
+            }
+        }
+        latest
+    }
+}
+
+impl Drop for GpuTimer {
+    fn drop(&mut self) {
+        for (query, _) in self.queries.drain(..) {
+            unsafe { self.context.delete_query(query) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::CpuMesh;
+
+    use super::*;
+
+    #[test]
+    fn triangle_count_of_a_known_mesh() {
+        assert_eq!(
+            triangle_count(&three_d_asset::Geometry::Triangles(CpuMesh::cube())),
+            12
+        );
+        assert_eq!(
+            triangle_count(&three_d_asset::Geometry::Triangles(CpuMesh::square())),
+            2
+        );
+    }
+
+    #[test]
+    fn triangles_accumulate_over_a_frame() {
+        let mut collector = RenderStatsCollector::new();
+        let cube = triangle_count(&three_d_asset::Geometry::Triangles(CpuMesh::cube()));
+        collector.record_draw(cube);
+        collector.record_draw(cube);
+        collector.record_draw(0);
+        let stats = collector.end_frame();
+        assert_eq!(stats.draw_calls, 3);
+        assert_eq!(stats.triangles, 24);
+        assert_eq!(stats.gpu_time, None);
+        assert_eq!(collector.last(), stats);
+    }
+
+    #[test]
+    fn frame_timings_add_up() {
+        let start = Instant::now();
+        let mut timer = FrameTimer::new();
+        timer.begin_frame(start);
+        timer.input_received(start);
+        timer.update_done(start + Duration::from_millis(2));
+        timer.render_done(start + Duration::from_millis(5));
+        let timings = timer.presented(start + Duration::from_millis(16));
+        assert_eq!(timings.update, Duration::from_millis(2));
+        assert_eq!(timings.render, Duration::from_millis(3));
+        assert_eq!(timings.present, Duration::from_millis(11));
+        assert_eq!(timings.total(), Duration::from_millis(16));
+        assert_eq!(timings.input_latency, Some(Duration::from_millis(16)));
+    }
+
+    #[test]
+    fn input_latency_counts_from_the_first_input_of_a_frame() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut timer = FrameTimer::new();
+        timer.input_received(start);
+        timer.begin_frame(start + ms(4));
+        timer.input_received(start + ms(6));
+        timer.update_done(start + ms(8));
+        timer.render_done(start + ms(10));
+        let timings = timer.presented(start + ms(20));
+        assert_eq!(timings.total(), ms(16));
+        assert_eq!(timings.input_latency, Some(ms(20)));
+
+        timer.begin_frame(start + ms(20));
+        timer.update_done(start + ms(21));
+        timer.render_done(start + ms(22));
+        let timings = timer.presented(start + ms(23));
+        assert_eq!(timings.input_latency, None);
+        assert_eq!(timer.last(), timings);
+    }
+}
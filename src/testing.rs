@@ -0,0 +1,376 @@
+use std::path::Path;
+
+use dioxus::desktop::tao::dpi::PhysicalSize;
+use dioxus::desktop::tao::event_loop::{EventLoop, EventLoopBuilder};
+use dioxus::desktop::tao::window::{Window, WindowBuilder};
+use three_d::{CpuTexture, RenderTarget, TextureData, Viewport};
+use three_d_asset::io::Serialize;
+
+use crate::error::{ContextError, LoadError};
+use crate::scene::Scene;
+use crate::windowed_context::{WindowedContext, WindowedContextBuilder};
+
+/// Why a [TestHarness] could not be set up.
+#[derive(Debug)]
+pub enum HarnessError {
+    /// No window could be opened, as on CI machines without a display server.
+    NoDisplay(String),
+    /// A window opened, but no GL context could be created for it.
+    Context(ContextError),
+}
+
+impl std::fmt::Display for HarnessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HarnessError::NoDisplay(reason) => write!(f, "no display available: {reason}"),
+            HarnessError::Context(e) => write!(f, "no GL context available: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for HarnessError {}
+
+/// Rendered pixels, top row first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl Image {
+    /// Takes `pixels` as OpenGL reads them back, bottom row first.
+    pub fn from_bottom_up(width: u32, height: u32, pixels: Vec<[u8; 4]>) -> Self {
+        let pixels = pixels
+            .chunks_exact(width.max(1) as usize)
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// The pixel at `x`, `y`, counted from the top left.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        assert!(
+            x < self.width && y < self.height,
+            "pixel {x}, {y} outside the image"
+        );
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Loads a PNG, as written by [Image::save].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        let texture: CpuTexture = three_d_asset::io::load_and_deserialize(path)?;
+        let pixels = match texture.data {
+            TextureData::RgbaU8(pixels) => pixels,
+            TextureData::RgbU8(pixels) => {
+                pixels.into_iter().map(|[r, g, b]| [r, g, b, 255]).collect()
+            }
+            _ => {
+                return Err(LoadError::Malformed(
+                    "not an 8-bit RGB or RGBA image".to_string(),
+                ))
+            }
+        };
+        Ok(Self {
+            width: texture.width,
+            height: texture.height,
+            pixels,
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), three_d_asset::Error> {
+        let texture = CpuTexture {
+            data: TextureData::RgbaU8(self.pixels.clone()),
+            width: self.width,
+            height: self.height,
+            ..Default::default()
+        };
+        three_d_asset::io::save(&texture.serialize(path)?)
+    }
+}
+
+/// How far two images are apart, see [diff].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ImageDiff {
+    /// Pixels with a channel differing by more than the tolerance.
+    pub mismatched: usize,
+    /// The largest channel difference over all pixels.
+    pub max_difference: u8,
+}
+
+/// Compares `actual` with `expected` channel by channel, counting pixels off by more than
+/// `tolerance`. Images of different sizes mismatch in every pixel.
+pub fn diff(actual: &Image, expected: &Image, tolerance: u8) -> ImageDiff {
+    if (actual.width, actual.height) != (expected.width, expected.height) {
+        return ImageDiff {
+            mismatched: actual.pixels.len().max(expected.pixels.len()),
+            max_difference: u8::MAX,
+        };
+    }
+    let mut result = ImageDiff::default();
+    for (a, e) in actual.pixels.iter().zip(&expected.pixels) {
+        let difference = channel_difference(*a, *e);
+        result.max_difference = result.max_difference.max(difference);
+        if difference > tolerance {
+            result.mismatched += 1;
+        }
+    }
+    result
+}
+
+fn channel_difference(a: [u8; 4], b: [u8; 4]) -> u8 {
+    a.iter()
+        .zip(&b)
+        .map(|(a, b)| a.abs_diff(*b))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Panics unless the pixel at `x`, `y` is within `tolerance` of `expected` in every channel.
+#[track_caller]
+pub fn assert_pixel_near(image: &Image, x: u32, y: u32, expected: [u8; 4], tolerance: u8) {
+    let actual = image.pixel(x, y);
+    assert!(
+        channel_difference(actual, expected) <= tolerance,
+        "pixel {x}, {y} is {actual:?}, expected {expected:?} ± {tolerance}"
+    );
+}
+
+/// Panics unless `image` matches the golden image at `path` within `tolerance`.
+///
+/// A missing golden image, or any with `UPDATE_GOLDEN` set in the environment, is written
+/// from `image` instead, so new goldens are created by running the test once. On a mismatch
+/// the actual image is saved next to the golden one, with `.actual.png` appended.
+#[track_caller]
+pub fn assert_matches_golden(image: &Image, path: impl AsRef<Path>, tolerance: u8) {
+    let path = path.as_ref();
+    if std::env::var_os("UPDATE_GOLDEN").is_some() || !path.exists() {
+        image
+            .save(path)
+            .unwrap_or_else(|e| panic!("failed to write golden image {}: {e}", path.display()));
+        return;
+    }
+    let expected = Image::load(path)
+        .unwrap_or_else(|e| panic!("failed to read golden image {}: {e}", path.display()));
+    let result = diff(image, &expected, tolerance);
+    if result.mismatched > 0 {
+        let actual_path = path.with_extension("actual.png");
+        let _ = image.save(&actual_path);
+        panic!(
+            "{} pixels differ from {} by up to {}, tolerance {tolerance}; actual image at {}",
+            result.mismatched,
+            path.display(),
+            result.max_difference,
+            actual_path.display()
+        );
+    }
+}
+
+/// A hidden window with a [WindowedContext], for rendering in tests and reading the result
+/// back.
+///
+/// Hardware acceleration is preferred but not required, so the harness also works with
+/// Mesa's llvmpipe on CI. Where no window can be opened at all, tests should skip rather than
+/// fail, which [TestHarness::or_skip] makes a one-liner. Some drivers don't keep the contents
+/// of hidden windows, which makes the readback undefined; software renderers do.
+///
+/// The event loop is created on the calling thread, which works on linux and windows. macOS
+/// only allows it on the main thread, so there tests need `harness = false` and their own
+/// `main`.
+pub struct TestHarness {
+    context: WindowedContext,
+    _window: Window,
+    _event_loop: EventLoop<()>,
+}
+
+impl TestHarness {
+    /// Opens a hidden window of `width` by `height` physical pixels.
+    pub fn new(width: u32, height: u32) -> Result<Self, HarnessError> {
+        let builder = WindowedContextBuilder::new()
+            .hardware_acceleration(three_d::HardwareAcceleration::Preferred);
+        Self::with_builder(width, height, builder)
+    }
+
+    /// Like [TestHarness::new], with the context built by `builder`.
+    pub fn with_builder(
+        width: u32,
+        height: u32,
+        builder: WindowedContextBuilder,
+    ) -> Result<Self, HarnessError> {
+        let event_loop = std::panic::catch_unwind(new_event_loop).map_err(|panic| {
+            let reason = panic
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| {
+                    panic
+                        .downcast_ref::<&str>()
+                        .map(|reason| reason.to_string())
+                })
+                .unwrap_or_default();
+            HarnessError::NoDisplay(reason)
+        })?;
+        let size = PhysicalSize::new(width.max(1), height.max(1));
+        let window = WindowBuilder::new()
+            .with_visible(false)
+            .with_inner_size(size)
+            .build(&event_loop)
+            .map_err(|e| HarnessError::NoDisplay(e.to_string()))?;
+        let context = builder
+            .build_from_tao_window(&window)
+            .map_err(HarnessError::Context)?;
+        context.resize(size);
+        Ok(Self {
+            context,
+            _window: window,
+            _event_loop: event_loop,
+        })
+    }
+
+    /// Like [TestHarness::new], but prints why and returns `None` if there is no display or
+    /// GL, for tests to return early on.
+    pub fn or_skip(width: u32, height: u32) -> Option<Self> {
+        match Self::new(width, height) {
+            Ok(harness) => Some(harness),
+            Err(error) => {
+                eprintln!("skipping rendering test: {error}");
+                None
+            }
+        }
+    }
+
+    pub fn context(&self) -> &WindowedContext {
+        &self.context
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        self.context.viewport()
+    }
+
+    /// Renders one frame of `scene` at animation time `time` and reads it back, see
+    /// [Scene::render_once].
+    pub fn render_scene(&self, scene: &mut Scene, time: f32) -> Image {
+        let viewport = self.viewport();
+        let pixels = scene
+            .render_once(&self.context, viewport, time, &mut [], true)
+            .unwrap_or_default();
+        Image::from_bottom_up(viewport.width, viewport.height, pixels)
+    }
+
+    /// Runs `render` against the screen and reads the result back.
+    pub fn render(&self, render: impl FnOnce(&RenderTarget<'_>)) -> Image {
+        let (width, height) = self.context.surface_size();
+        let screen = RenderTarget::screen(&self.context, width, height);
+        render(&screen);
+        self.context.finish();
+        Image::from_bottom_up(width, height, screen.read_color())
+    }
+}
+
+pub(crate) fn new_event_loop() -> EventLoop<()> {
+    let mut builder = EventLoopBuilder::new();
+    #[cfg(target_os = "linux")]
+    {
+        use dioxus::desktop::tao::platform::unix::EventLoopBuilderExtUnix;
+        builder.with_any_thread(true);
+    }
+    #[cfg(target_os = "windows")]
+    {
+        use dioxus::desktop::tao::platform::windows::EventLoopBuilderExtWindows;
+        builder.with_any_thread(true);
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::ClearState;
+
+    use super::*;
+
+    fn image(pixels: Vec<[u8; 4]>) -> Image {
+        Image {
+            width: 2,
+            height: pixels.len() as u32 / 2,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn readback_is_turned_top_row_first() {
+        let (bottom, top) = ([1, 0, 0, 255], [2, 0, 0, 255]);
+        let image = Image::from_bottom_up(2, 2, vec![bottom, bottom, top, top]);
+        assert_eq!(image.pixel(1, 0), top);
+        assert_eq!(image.pixel(0, 1), bottom);
+    }
+
+    #[test]
+    fn diff_counts_pixels_beyond_the_tolerance() {
+        let expected = image(vec![[100, 100, 100, 255]; 4]);
+        let mut actual = expected.clone();
+        actual.pixels[0] = [103, 100, 100, 255];
+        actual.pixels[3] = [100, 90, 100, 255];
+        let result = diff(&actual, &expected, 3);
+        assert_eq!(
+            result,
+            ImageDiff {
+                mismatched: 1,
+                max_difference: 10
+            }
+        );
+        let smaller = image(vec![[100, 100, 100, 255]; 2]);
+        assert_eq!(diff(&smaller, &expected, 255).mismatched, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "pixel 1, 0 is [0, 0, 0, 255]")]
+    fn pixel_assert_fails_outside_the_tolerance() {
+        let image = image(vec![[0, 0, 0, 255]; 2]);
+        assert_pixel_near(&image, 1, 0, [0, 0, 0, 255], 0);
+        assert_pixel_near(&image, 1, 0, [10, 0, 0, 255], 9);
+    }
+
+    #[test]
+    #[cfg_attr(
+        not(feature = "testing"),
+        ignore = "PNG support comes with the testing feature"
+    )]
+    fn golden_images_are_written_once_and_then_compared() {
+        let dir = std::env::temp_dir().join(format!("golden-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("red.png");
+        let red = image(vec![[255, 0, 0, 255]; 4]);
+        assert_matches_golden(&red, &path, 0);
+        assert_eq!(Image::load(&path).unwrap(), red);
+        assert_matches_golden(&red, &path, 0);
+
+        let mut darker = red.clone();
+        darker.pixels[2] = [250, 0, 0, 255];
+        let mismatch = std::panic::catch_unwind(|| assert_matches_golden(&darker, &path, 2));
+        assert!(mismatch.is_err());
+        assert_eq!(
+            Image::load(path.with_extension("actual.png")).unwrap(),
+            darker
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn harness_reads_back_the_clear_color() {
+        let harness = TestHarness::new(16, 8).unwrap();
+        assert_eq!(harness.viewport(), Viewport::new_at_origo(16, 8));
+        let image = harness.render(|screen| {
+            screen.clear(ClearState::color(1.0, 0.5, 0.0, 1.0));
+        });
+        assert_eq!((image.width, image.height), (16, 8));
+        for (x, y) in [(0, 0), (15, 0), (0, 7), (15, 7)] {
+            assert_pixel_near(&image, x, y, [255, 128, 0, 255], 1);
+        }
+    }
+}
@@ -0,0 +1,595 @@
+use three_d::context::{self as gl, HasContext};
+use three_d::{
+    Context, CpuMaterial, CpuModel, CpuTexture, Interpolation, PhysicalMaterial, RendererError,
+    Texture2D, Texture2DRef, TextureData,
+};
+
+use crate::part::ScenePart;
+use crate::windowed_context::supports_extension;
+
+/// Sampling quality for the textures of an uploaded model.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureOptions {
+    /// Generates mipmaps for every texture, which stops minified textures from shimmering.
+    pub mipmaps: bool,
+    /// The maximum anisotropic filtering level, `1.0` disables it. Clamped to what the driver
+    /// supports, see [max_anisotropy].
+    pub anisotropy: f32,
+    /// Replaces the minification and magnification filters of every texture. With it set,
+    /// `mipmaps: false` also turns off the mipmapping a file asks for; with `None` the file's
+    /// filters are kept.
+    pub filter: Option<Interpolation>,
+    /// How the color of the albedo textures relates to their alpha.
+    pub albedo_alpha: AlphaMode,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self {
+            mipmaps: true,
+            anisotropy: 1.0,
+            filter: None,
+            albedo_alpha: AlphaMode::default(),
+        }
+    }
+}
+
+/// How a texture with an alpha channel stores its color, see [TextureOptions::albedo_alpha].
+///
+/// glTF specifies straight alpha and has no way to flag the other, so this is up to the
+/// caller, per model.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AlphaMode {
+    /// The color is independent of the alpha, as `three_d` shades and blends it.
+    #[default]
+    Straight,
+    /// The color was multiplied by the alpha, as some texture tools export it. Shading it as
+    /// straight darkens everything partly transparent, and the edges of cutouts turn into
+    /// dark halos. Such textures are converted to straight alpha before the upload, see
+    /// [unpremultiply].
+    Premultiplied,
+}
+
+/// How many texels [unpremultiply] spreads color into the fully transparent area, enough for
+/// the filtering of the first few mip levels.
+const ALPHA_BLEED_TEXELS: usize = 16;
+
+/// Texture filtering presets trading sharpness at grazing angles for sampling cost, see
+/// [TextureOptions::from].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TextureQuality {
+    /// Bilinear filtering without mipmaps. Minified textures shimmer, but nothing extra is
+    /// allocated or sampled.
+    Low,
+    /// Trilinear filtering.
+    #[default]
+    Medium,
+    /// Trilinear filtering with the highest anisotropy the driver supports.
+    High,
+}
+
+impl From<TextureQuality> for TextureOptions {
+    fn from(quality: TextureQuality) -> Self {
+        let (mipmaps, anisotropy) = match quality {
+            TextureQuality::Low => (false, 1.0),
+            TextureQuality::Medium => (true, 1.0),
+            TextureQuality::High => (true, f32::MAX),
+        };
+        Self {
+            mipmaps,
+            anisotropy,
+            filter: Some(Interpolation::Linear),
+            albedo_alpha: AlphaMode::default(),
+        }
+    }
+}
+
+/// The highest anisotropy level the driver supports, `1.0` without
+/// `GL_EXT_texture_filter_anisotropic`.
+pub fn max_anisotropy(context: &Context) -> f32 {
+    if supports_extension(context, "GL_EXT_texture_filter_anisotropic")
+        || supports_extension(context, "GL_ARB_texture_filter_anisotropic")
+    {
+        unsafe { context.get_parameter_f32(gl::MAX_TEXTURE_MAX_ANISOTROPY_EXT) }.max(1.0)
+    } else {
+        1.0
+    }
+}
+
+/// Like [ScenePart::from_cpu_model], but uploads the textures with `options` applied.
+///
+/// Textures with 32-bit float data are left without mipmaps: linear filtering of them is an
+/// optional extension on GLES and WebGL. `cpu_model` is borrowed mutably to move the textures
+/// out while the geometry uploads; afterwards only the filters of its textures differ, and
+/// premultiplied albedo textures are straight.
+///
+/// The options apply per call, so each model can have its own. The filters are fixed at
+/// upload: to change them, for a new [TextureQuality], upload the model again.
+pub fn upload_parts(
+    context: &Context,
+    cpu_model: &mut CpuModel,
+    options: TextureOptions,
+) -> Result<Vec<ScenePart>, RendererError> {
+    let anisotropy = options.anisotropy.clamp(1.0, max_anisotropy(context));
+    // Without their textures, the materials `Model::new` creates are cheap, and each texture
+    // is uploaded only once, below.
+    let mut textures: Vec<MaterialTextures> = cpu_model
+        .materials
+        .iter_mut()
+        .map(MaterialTextures::take)
+        .collect();
+    prepare_textures(&mut textures, &options);
+    let parts = ScenePart::from_cpu_model(context, cpu_model);
+    let materials: Vec<PhysicalMaterial> = cpu_model
+        .materials
+        .iter()
+        .zip(&textures)
+        .map(|(material, textures)| build_material(context, material, textures, anisotropy))
+        .collect();
+    for (material, textures) in cpu_model.materials.iter_mut().zip(textures) {
+        textures.restore(material);
+    }
+    let mut parts = parts?;
+    for (part, primitive) in parts.iter_mut().zip(&cpu_model.geometries) {
+        if let Some(material) = primitive
+            .material_index
+            .and_then(|index| materials.get(index))
+        {
+            part.model_mut().material = material.clone();
+        }
+    }
+    Ok(parts)
+}
+
+/// Sets the filters `options` ask for on `textures` and converts premultiplied albedo, before
+/// the upload.
+fn prepare_textures(textures: &mut [MaterialTextures], options: &TextureOptions) {
+    if let Some(filter) = options.filter {
+        for texture in textures.iter_mut().flat_map(MaterialTextures::iter_mut) {
+            texture.min_filter = filter;
+            texture.mag_filter = filter;
+            if !options.mipmaps {
+                texture.mip_map_filter = None;
+            }
+        }
+    }
+    if options.albedo_alpha == AlphaMode::Premultiplied {
+        for texture in textures
+            .iter_mut()
+            .filter_map(|textures| textures.albedo.as_mut())
+        {
+            unpremultiply(texture);
+        }
+    }
+    if options.mipmaps {
+        for texture in textures.iter_mut().flat_map(MaterialTextures::iter_mut) {
+            if texture.mip_map_filter.is_some() {
+                continue;
+            }
+            if is_float(texture) {
+                log_warn!(name = %texture.name, "float texture left without mipmaps");
+            } else {
+                texture.mip_map_filter = Some(Interpolation::Linear);
+            }
+        }
+    }
+}
+
+/// The textures of a [CpuMaterial], moved out of it.
+struct MaterialTextures {
+    albedo: Option<CpuTexture>,
+    metallic_roughness: Option<CpuTexture>,
+    occlusion_metallic_roughness: Option<CpuTexture>,
+    normal: Option<CpuTexture>,
+    occlusion: Option<CpuTexture>,
+    emissive: Option<CpuTexture>,
+}
+
+impl MaterialTextures {
+    fn take(material: &mut CpuMaterial) -> Self {
+        Self {
+            albedo: material.albedo_texture.take(),
+            metallic_roughness: material.metallic_roughness_texture.take(),
+            occlusion_metallic_roughness: material.occlusion_metallic_roughness_texture.take(),
+            normal: material.normal_texture.take(),
+            occlusion: material.occlusion_texture.take(),
+            emissive: material.emissive_texture.take(),
+        }
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = &mut CpuTexture> {
+        [
+            &mut self.albedo,
+            &mut self.metallic_roughness,
+            &mut self.occlusion_metallic_roughness,
+            &mut self.normal,
+            &mut self.occlusion,
+            &mut self.emissive,
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    fn restore(self, material: &mut CpuMaterial) {
+        material.albedo_texture = self.albedo;
+        material.metallic_roughness_texture = self.metallic_roughness;
+        material.occlusion_metallic_roughness_texture = self.occlusion_metallic_roughness;
+        material.normal_texture = self.normal;
+        material.occlusion_texture = self.occlusion;
+        material.emissive_texture = self.emissive;
+    }
+}
+
+/// Builds the material `three_d` would, from the untextured `material` and its `textures`.
+fn build_material(
+    context: &Context,
+    material: &CpuMaterial,
+    textures: &MaterialTextures,
+    anisotropy: f32,
+) -> PhysicalMaterial {
+    let transparent =
+        material.albedo.a != 255 || textures.albedo.as_ref().is_some_and(has_transparency);
+    let mut gpu_material = if transparent {
+        PhysicalMaterial::new_transparent(context, material)
+    } else {
+        PhysicalMaterial::new_opaque(context, material)
+    };
+    let upload = |texture: &Option<CpuTexture>| {
+        texture
+            .as_ref()
+            .map(|texture| upload_texture(context, texture, anisotropy))
+    };
+    gpu_material.albedo_texture = upload(&textures.albedo);
+    gpu_material.normal_texture = upload(&textures.normal);
+    gpu_material.emissive_texture = upload(&textures.emissive);
+    // A combined texture serves both slots, as in `PhysicalMaterial::new`.
+    if let Some(combined) = upload(&textures.occlusion_metallic_roughness) {
+        gpu_material.metallic_roughness_texture = Some(combined.clone());
+        gpu_material.occlusion_texture = Some(combined);
+    } else {
+        gpu_material.metallic_roughness_texture = upload(&textures.metallic_roughness);
+        gpu_material.occlusion_texture = upload(&textures.occlusion);
+    }
+    gpu_material
+}
+
+fn upload_texture(context: &Context, texture: &CpuTexture, anisotropy: f32) -> Texture2DRef {
+    let gpu_texture = Texture2D::new(context, texture);
+    if anisotropy > 1.0 {
+        // `Texture2D::new` leaves the new texture bound, and `three_d` offers no other way to
+        // reach the GL object.
+        unsafe {
+            context.tex_parameter_f32(gl::TEXTURE_2D, gl::TEXTURE_MAX_ANISOTROPY_EXT, anisotropy)
+        };
+    }
+    Texture2DRef::from_texture(gpu_texture)
+}
+
+fn is_float(texture: &CpuTexture) -> bool {
+    matches!(
+        texture.data,
+        TextureData::RF32(_)
+            | TextureData::RgF32(_)
+            | TextureData::RgbF32(_)
+            | TextureData::RgbaF32(_)
+    )
+}
+
+/// Whether any texel of `texture` is less than fully opaque.
+fn has_transparency(texture: &CpuTexture) -> bool {
+    match &texture.data {
+        TextureData::RgbaU8(data) => data.iter().any(|texel| texel[3] < 255),
+        TextureData::RgbaF16(data) => data.iter().any(|texel| texel[3] < three_d::f16::ONE),
+        TextureData::RgbaF32(data) => data.iter().any(|texel| texel[3] < 1.0),
+        _ => false,
+    }
+}
+
+/// Turns the premultiplied 8-bit RGBA `texture` into straight alpha, leaving opaque textures
+/// and other formats as they are. Returns whether it changed.
+///
+/// Fully transparent texels have no color left to recover. Those next to visible ones take
+/// the average color of their neighbors instead, spreading up to [ALPHA_BLEED_TEXELS] texels
+/// out, so filtering across the edge of a cutout blends towards the edge's own color rather
+/// than black.
+pub fn unpremultiply(texture: &mut CpuTexture) -> bool {
+    let TextureData::RgbaU8(texels) = &mut texture.data else {
+        if has_transparency(texture) {
+            log_warn!(name = %texture.name, "only 8-bit RGBA textures can be unpremultiplied");
+        }
+        return false;
+    };
+    if texels.iter().all(|texel| texel[3] == 255) {
+        return false;
+    }
+    for texel in texels.iter_mut().filter(|texel| texel[3] > 0) {
+        let alpha = texel[3] as u32;
+        for channel in &mut texel[..3] {
+            *channel = ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+        }
+    }
+    bleed_color(texels, texture.width as usize, texture.height as usize);
+    true
+}
+
+/// Gives the fully transparent texels of the `width` by `height` `texels` the average color
+/// of their colored neighbors, one ring of texels per pass. Each pass only visits the ring,
+/// so large textures with small cutouts stay cheap.
+fn bleed_color(texels: &mut [[u8; 4]], width: usize, height: usize) {
+    let mut colored: Vec<bool> = texels.iter().map(|texel| texel[3] > 0).collect();
+    let mut queued = colored.clone();
+    let mut ring: Vec<(usize, usize)> = Vec::new();
+    let enqueue_around = |x, y, queued: &mut Vec<bool>, ring: &mut Vec<(usize, usize)>| {
+        for (nx, ny) in neighbors(x, y, width, height) {
+            if !queued[ny * width + nx] {
+                queued[ny * width + nx] = true;
+                ring.push((nx, ny));
+            }
+        }
+    };
+    for y in 0..height {
+        for x in 0..width {
+            if colored[y * width + x] {
+                enqueue_around(x, y, &mut queued, &mut ring);
+            }
+        }
+    }
+    for _ in 0..ALPHA_BLEED_TEXELS {
+        if ring.is_empty() {
+            break;
+        }
+        let filled: Vec<(usize, usize, [u8; 3])> = ring
+            .drain(..)
+            .map(|(x, y)| {
+                let mut sum = [0u32; 3];
+                let mut count = 0;
+                for (nx, ny) in neighbors(x, y, width, height) {
+                    if colored[ny * width + nx] {
+                        let neighbor = texels[ny * width + nx];
+                        for (sum, channel) in sum.iter_mut().zip(neighbor) {
+                            *sum += channel as u32;
+                        }
+                        count += 1;
+                    }
+                }
+                // Queued next to a colored texel, so `count` is at least one.
+                (x, y, sum.map(|sum| (sum / count.max(1)) as u8))
+            })
+            .collect();
+        for (x, y, [r, g, b]) in filled {
+            texels[y * width + x] = [r, g, b, 0];
+            colored[y * width + x] = true;
+            enqueue_around(x, y, &mut queued, &mut ring);
+        }
+    }
+}
+
+/// The up to eight texels around `x`, `y` inside a `width` by `height` image.
+fn neighbors(
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    (-1isize..=1)
+        .flat_map(|dy| (-1isize..=1).map(move |dx| (dx, dy)))
+        .filter(|&offset| offset != (0, 0))
+        .filter_map(move |(dx, dy)| {
+            let (nx, ny) = (x.checked_add_signed(dx)?, y.checked_add_signed(dy)?);
+            (nx < width && ny < height).then_some((nx, ny))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 4 by 4 texture without mipmaps.
+    fn texture(data: TextureData) -> CpuTexture {
+        CpuTexture {
+            data,
+            width: 4,
+            height: 4,
+            mip_map_filter: None,
+            ..Default::default()
+        }
+    }
+
+    fn material(albedo: CpuTexture, emissive: CpuTexture) -> CpuMaterial {
+        CpuMaterial {
+            albedo_texture: Some(albedo),
+            emissive_texture: Some(emissive),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn mipmaps_are_added_except_to_float_textures() {
+        let albedo = texture(TextureData::RgbaU8(vec![[255; 4]; 16]));
+        let emissive = texture(TextureData::RgbF32(vec![[1.0; 3]; 16]));
+        let mut textures = [MaterialTextures::take(&mut material(albedo, emissive))];
+        prepare_textures(&mut textures, &TextureOptions::default());
+        let [textures] = textures;
+        assert_eq!(
+            textures.albedo.unwrap().mip_map_filter,
+            Some(Interpolation::Linear)
+        );
+        assert_eq!(textures.emissive.unwrap().mip_map_filter, None);
+    }
+
+    #[test]
+    fn low_quality_drops_the_mipmaps_of_the_file() {
+        let albedo = CpuTexture {
+            min_filter: Interpolation::Nearest,
+            mip_map_filter: Some(Interpolation::Nearest),
+            ..texture(TextureData::RgbaU8(vec![[255; 4]; 16]))
+        };
+        let emissive = texture(TextureData::RgbaU8(vec![[255; 4]; 16]));
+        let mut textures = [MaterialTextures::take(&mut material(albedo, emissive))];
+        prepare_textures(&mut textures, &TextureQuality::Low.into());
+        for texture in textures[0].iter_mut() {
+            assert_eq!(texture.min_filter, Interpolation::Linear);
+            assert_eq!(texture.mip_map_filter, None);
+        }
+    }
+
+    /// A cube with a 4 by 4 albedo texture and no other.
+    fn textured_cube() -> CpuModel {
+        let mut cpu_model = crate::scene::tests::cubes(1);
+        cpu_model.materials.push(CpuMaterial {
+            albedo_texture: Some(texture(TextureData::RgbaU8(vec![[255; 4]; 16]))),
+            ..Default::default()
+        });
+        cpu_model.geometries[0].material_index = Some(0);
+        cpu_model
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn uploaded_textures_are_mipmapped_with_the_clamped_anisotropy() {
+        use crate::testing::TestHarness;
+
+        let harness = TestHarness::new(8, 8).unwrap();
+        let context = &harness.context().context;
+        let mut cpu_model = textured_cube();
+        let options = TextureOptions {
+            mipmaps: true,
+            anisotropy: 4.0,
+            ..TextureQuality::High.into()
+        };
+        upload_parts(context, &mut cpu_model, options).unwrap();
+
+        // The albedo texture is the only one, so the last one `upload_texture` bound.
+        let min_filter =
+            unsafe { context.get_tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER) };
+        assert_eq!(min_filter as u32, gl::LINEAR_MIPMAP_LINEAR);
+        let expected = 4.0f32.min(max_anisotropy(context));
+        if expected > 1.0 {
+            let anisotropy = unsafe {
+                context.get_tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_MAX_ANISOTROPY_EXT)
+            };
+            assert_eq!(anisotropy, expected as i32);
+        }
+        let albedo = cpu_model.materials[0].albedo_texture.as_ref().unwrap();
+        assert_eq!(albedo.mip_map_filter, Some(Interpolation::Linear));
+    }
+
+    #[test]
+    fn presets_go_from_bilinear_to_full_anisotropy() {
+        let options = |quality| TextureOptions::from(quality);
+        let low = options(TextureQuality::Low);
+        assert_eq!((low.mipmaps, low.anisotropy), (false, 1.0));
+        let medium = options(TextureQuality::Medium);
+        assert_eq!((medium.mipmaps, medium.anisotropy), (true, 1.0));
+        let high = options(TextureQuality::High);
+        assert!(high.mipmaps && high.anisotropy == f32::MAX);
+        for quality in [
+            TextureQuality::Low,
+            TextureQuality::Medium,
+            TextureQuality::High,
+        ] {
+            assert_eq!(options(quality).filter, Some(Interpolation::Linear));
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn quality_presets_set_the_uploaded_filters() {
+        use crate::testing::TestHarness;
+
+        let harness = TestHarness::new(8, 8).unwrap();
+        let context = &harness.context().context;
+        let parameter = |name| unsafe { context.get_tex_parameter_i32(gl::TEXTURE_2D, name) };
+
+        upload_parts(context, &mut textured_cube(), TextureQuality::Low.into()).unwrap();
+        assert_eq!(parameter(gl::TEXTURE_MIN_FILTER) as u32, gl::LINEAR);
+
+        upload_parts(context, &mut textured_cube(), TextureQuality::High.into()).unwrap();
+        assert_eq!(
+            parameter(gl::TEXTURE_MIN_FILTER) as u32,
+            gl::LINEAR_MIPMAP_LINEAR
+        );
+        let max = max_anisotropy(context);
+        if max > 1.0 {
+            assert_eq!(parameter(gl::TEXTURE_MAX_ANISOTROPY_EXT), max as i32);
+        }
+    }
+
+    #[test]
+    fn premultiplied_texels_are_made_straight_and_bled_into_transparency() {
+        let mut cutout = CpuTexture {
+            width: 4,
+            height: 1,
+            ..texture(TextureData::RgbaU8(vec![
+                [255, 0, 0, 255],
+                [64, 32, 0, 128],
+                [0, 0, 0, 0],
+                [0, 0, 0, 0],
+            ]))
+        };
+        assert!(unpremultiply(&mut cutout));
+        let TextureData::RgbaU8(texels) = &cutout.data else {
+            unreachable!();
+        };
+        assert_eq!(texels[..2], [[255, 0, 0, 255], [128, 64, 0, 128]]);
+        // The transparent ones take the color of their colored neighbors.
+        assert_eq!(texels[2], [128, 64, 0, 0]);
+        assert_eq!(texels[3], [128, 64, 0, 0]);
+
+        let mut opaque = texture(TextureData::RgbaU8(vec![[10, 20, 30, 255]; 16]));
+        let before = opaque.clone();
+        assert!(!unpremultiply(&mut opaque));
+        assert_eq!(opaque, before);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn premultiplied_cutouts_have_no_dark_fringe() {
+        use three_d::{AmbientLight, CpuMesh, Srgba};
+
+        use crate::scene::tests::empty_scene;
+        use crate::testing::TestHarness;
+
+        let harness = TestHarness::new(64, 64).unwrap();
+        let context = &harness.context().context;
+        // White on the left, transparent on the right, blended across the middle of a quad.
+        let cutout = CpuTexture {
+            width: 2,
+            height: 1,
+            mag_filter: Interpolation::Linear,
+            ..texture(TextureData::RgbaU8(vec![[255; 4], [0; 4]]))
+        };
+        let edge = |albedo_alpha| {
+            let mut cpu_model = crate::scene::tests::cubes(1);
+            cpu_model.geometries[0].geometry =
+                three_d_asset::Geometry::Triangles(CpuMesh::square());
+            cpu_model.geometries[0].material_index = Some(0);
+            cpu_model.materials.push(CpuMaterial {
+                albedo_texture: Some(cutout.clone()),
+                ..Default::default()
+            });
+            let options = TextureOptions {
+                mipmaps: false,
+                albedo_alpha,
+                ..Default::default()
+            };
+            let mut scene = empty_scene();
+            scene.set_clear_color(Srgba::WHITE);
+            scene
+                .lights_mut()
+                .add(AmbientLight::new(context, 1.0, Srgba::WHITE));
+            scene.set_parts(upload_parts(context, &mut cpu_model, options).unwrap());
+            harness.render_scene(&mut scene, 0.0).pixel(32, 32)
+        };
+        let straight = edge(AlphaMode::Straight);
+        let premultiplied = edge(AlphaMode::Premultiplied);
+        // Read as straight, the transparent texel is black and darkens the edge.
+        assert!(
+            premultiplied[0] > straight[0].saturating_add(20),
+            "{premultiplied:?} vs {straight:?}"
+        );
+        assert!(premultiplied[0] > 220, "{premultiplied:?}");
+    }
+}
@@ -0,0 +1,183 @@
+use dioxus::desktop::tao::dpi::PhysicalSize;
+use three_d::{
+    degrees, vec3, AmbientLight, Camera, CpuModel, CpuTexture, InnerSpace, Srgba, TextureData,
+    Viewport,
+};
+use three_d_asset::io::Serialize;
+
+use crate::background::Background;
+use crate::controls::Controls;
+use crate::error::ContextError;
+use crate::lights::Lights;
+use crate::part::ScenePart;
+use crate::scene::{Scene, DEFAULT_FRAMING_PADDING};
+use crate::windowed_context::{self, WindowedContext};
+
+/// Why [render_thumbnail] failed.
+#[derive(Debug)]
+pub enum ThumbnailError {
+    /// The thumbnail context, or the one to restore afterwards, could not be made current.
+    Context(ContextError),
+    /// The model could not be uploaded to the thumbnail context.
+    Upload(three_d::RendererError),
+    /// The rendered pixels could not be encoded.
+    Encode(three_d_asset::Error),
+}
+
+impl std::fmt::Display for ThumbnailError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThumbnailError::Context(e) => write!(f, "no GL context for the thumbnail: {e}"),
+            ThumbnailError::Upload(e) => write!(f, "failed to upload the thumbnail model: {e}"),
+            ThumbnailError::Encode(e) => write!(f, "failed to encode the thumbnail: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ThumbnailError {}
+
+/// A rendered thumbnail, top row first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Thumbnail {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl Thumbnail {
+    /// Whether any pixel differs from the top left one, that is whether anything but the
+    /// background was drawn, unless the background is a gradient.
+    pub fn has_content(&self) -> bool {
+        let first = self.pixels.first();
+        self.pixels.iter().any(|pixel| Some(pixel) != first)
+    }
+
+    /// The thumbnail as a PNG file.
+    pub fn encode_png(&self) -> Result<Vec<u8>, ThumbnailError> {
+        let texture = CpuTexture {
+            data: TextureData::RgbaU8(self.pixels.clone()),
+            width: self.width,
+            height: self.height,
+            ..Default::default()
+        };
+        let mut raw = texture
+            .serialize("thumbnail.png")
+            .map_err(ThumbnailError::Encode)?;
+        raw.remove("thumbnail.png").map_err(ThumbnailError::Encode)
+    }
+}
+
+/// Renders `cpu_model` framed to fit into a `width` by `height` thumbnail, looking at it from
+/// the direction the camera of `look` does and with its background, tone mapping, exposure
+/// and color grade.
+///
+/// Everything is rendered in `context`, typically a headless context for a hidden window,
+/// whose surface is resized to the thumbnail. GL objects aren't shared between contexts, so
+/// the model is uploaded to `context` for the thumbnail and dropped again afterwards, and
+/// `look` only lends its settings: its camera, parts and the on-screen view stay untouched,
+/// and a background image, which lives in the other context, is left out. `context` is made
+/// current first, and `restore`, usually the on-screen context, is made current again at the
+/// end. Sizes are clamped to what the driver can render, see [windowed_context::sanitize_size].
+pub fn render_thumbnail(
+    context: &WindowedContext,
+    cpu_model: &CpuModel,
+    look: &Scene,
+    width: u32,
+    height: u32,
+    restore: Option<&WindowedContext>,
+) -> Result<Thumbnail, ThumbnailError> {
+    context.make_current().map_err(ThumbnailError::Context)?;
+    let thumbnail = render_current(context, cpu_model, look, width, height);
+    if let Some(restore) = restore {
+        restore.make_current().map_err(ThumbnailError::Context)?;
+    }
+    thumbnail
+}
+
+fn render_current(
+    context: &WindowedContext,
+    cpu_model: &CpuModel,
+    look: &Scene,
+    width: u32,
+    height: u32,
+) -> Result<Thumbnail, ThumbnailError> {
+    let size = PhysicalSize::new(width, height);
+    let (width, height) = windowed_context::sanitize_size(size, context.max_viewport_size());
+    context.resize(PhysicalSize::new(width, height));
+    let viewport = Viewport::new_at_origo(width, height);
+    let direction = {
+        let look = look.camera();
+        let direction = *look.position() - *look.target();
+        if direction.magnitude2() > f32::EPSILON {
+            direction.normalize()
+        } else {
+            vec3(0.0, 0.0, 1.0)
+        }
+    };
+    let camera = Camera::new_perspective(
+        viewport,
+        direction,
+        vec3(0.0, 0.0, 0.0),
+        *look.camera().up(),
+        degrees(45.0),
+        0.1,
+        100.0,
+    );
+    let controls = Controls::new(*camera.target(), 1.0, 100.0);
+    let parts = ScenePart::from_cpu_model(context, cpu_model).map_err(ThumbnailError::Upload)?;
+    let mut lights = Lights::new();
+    lights.add(AmbientLight::new(context, 1.0, Srgba::WHITE));
+    let mut scene = Scene::new(camera, controls, parts, lights);
+    scene.set_background(match look.background() {
+        Background::Image(_) => Background::default(),
+        background => background.clone(),
+    });
+    scene.set_tone_mapping(look.tone_mapping());
+    scene.set_exposure(look.exposure());
+    scene.set_color_grade(look.color_grade());
+    let bounds = scene.bounding_box();
+    scene.frame_to_aabb(bounds, DEFAULT_FRAMING_PADDING);
+    let pixels = scene
+        .render_once(context, viewport, 0.0, &mut [], true)
+        .unwrap_or_default();
+    // Rows come back bottom first.
+    let pixels = pixels
+        .chunks_exact(width as usize)
+        .rev()
+        .flatten()
+        .copied()
+        .collect();
+    Ok(Thumbnail {
+        width,
+        height,
+        pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::{load_model, tests::fixtures};
+    use crate::scene::tests::empty_scene;
+    use crate::testing::TestHarness;
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn fixture_thumbnail_has_the_requested_size_and_content() {
+        let harness = TestHarness::new(128, 128).unwrap();
+        let cpu_model = load_model(fixtures().join("quad/quad.obj")).unwrap();
+        let mut look = empty_scene();
+        look.set_background(Background::Solid(Srgba::WHITE));
+        let context = harness.context();
+        let thumbnail =
+            render_thumbnail(context, &cpu_model, &look, 128, 128, Some(context)).unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (128, 128));
+        assert_eq!(thumbnail.pixels.len(), 128 * 128);
+        assert!(thumbnail.has_content());
+
+        let png = thumbnail.encode_png().unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        // The look only lends its settings.
+        assert_eq!(*look.camera().position(), vec3(0.0, 0.0, 4.0));
+    }
+}
@@ -0,0 +1,129 @@
+//! Logging macros that forward to `tracing` with the `tracing` feature and compile to nothing
+//! without it, so instrumentation is free when it isn't wanted.
+//!
+//! Arguments are not evaluated when the feature is off, so only log values that are also used
+//! for something else.
+
+#[cfg(feature = "tracing")]
+macro_rules! log_event {
+    ($level:ident, $($arg:tt)*) => { tracing::$level!($($arg)*) };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! log_event {
+    // An empty block rather than nothing, so the macros also work as a match arm.
+    ($level:ident, $($arg:tt)*) => {{}};
+}
+
+macro_rules! log_info {
+    ($($arg:tt)*) => { log_event!(info, $($arg)*) };
+}
+
+macro_rules! log_debug {
+    ($($arg:tt)*) => { log_event!(debug, $($arg)*) };
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => { log_event!(warn, $($arg)*) };
+}
+
+/// Per-frame events; these stay at `trace` so the default levels aren't flooded.
+macro_rules! log_trace {
+    ($($arg:tt)*) => { log_event!(trace, $($arg)*) };
+}
+
+/// Enters a `debug` level span for the rest of the enclosing scope.
+#[cfg(feature = "tracing")]
+macro_rules! log_span {
+    ($name:expr) => {
+        tracing::debug_span!($name).entered()
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! log_span {
+    ($name:expr) => {
+        $crate::trace::NoSpan
+    };
+}
+
+/// Stand-in for an entered span when the `tracing` feature is off.
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
+
+#[cfg(all(test, feature = "tracing"))]
+pub(crate) mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// Collects the messages of all events, at every level.
+    #[derive(Clone, Default)]
+    struct Recorder {
+        messages: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Visit for Recorder {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                self.messages.lock().unwrap().push(format!("{value:?}"));
+            }
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            event.record(&mut self.clone());
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    /// The messages of the events logged while running `f`.
+    pub(crate) fn recorded(f: impl FnOnce()) -> Vec<String> {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), f);
+        let messages = recorder.messages.lock().unwrap().clone();
+        messages
+    }
+
+    #[test]
+    fn macros_forward_to_tracing() {
+        let messages = recorded(|| {
+            let _span = log_span!("frame");
+            log_info!(samples = 4, "selected GL config");
+            log_trace!("rendered frame");
+        });
+        assert_eq!(messages, ["selected GL config", "rendered frame"]);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn config_selection_is_logged_during_construction() {
+        use crate::testing::TestHarness;
+
+        let messages = recorded(|| drop(TestHarness::new(8, 8).unwrap()));
+        assert!(
+            messages
+                .iter()
+                .any(|message| message == "selected GL config"),
+            "{messages:?}"
+        );
+    }
+}
@@ -1,14 +1,245 @@
+use dioxus::desktop::tao::{
+    dpi::{LogicalSize, PhysicalSize},
+    window::Window,
+};
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
 use three_d::Context;
 use three_d::SurfaceSettings;
-use three_d::WindowError;
-use std::sync::Arc;
-use dioxus::desktop::tao::{dpi::PhysicalSize, window::Window};
+use three_d::Viewport;
+use three_d::{ClearState, RenderTarget, ScissorBox};
+
+use crate::error::ContextError;
+use crate::focus::UiRect;
 
 use glutin::{prelude::PossiblyCurrentContextGlSurfaceAccessor, surface::*};
+use three_d::context::HasContext;
+
+/// Called with the new context each time one is created, see
+/// [WindowedContext::on_context_created].
+type ContextCreatedCallback = Rc<dyn Fn(&Context)>;
+
 pub struct WindowedContext {
     pub context: Context,
     surface: Surface<WindowSurface>,
-    glutin_context: glutin::context::PossiblyCurrentContext,
+    /// Only `None` if [WindowedContext::make_not_current] failed and glutin dropped it.
+    glutin_context: RefCell<Option<glutin::context::PossiblyCurrentContext>>,
+    size: Cell<(u32, u32)>,
+    max_viewport_size: (u32, u32),
+    scale_factor: Cell<f64>,
+    hardware_accelerated: bool,
+    fell_back_to_software: bool,
+    finish_before_swap: Cell<bool>,
+    vsync_mode: Cell<VsyncMode>,
+    surface_info: SurfaceInfo,
+    settings: SurfaceSettings,
+    backend_preference: BackendPreference,
+    window_mode: WindowMode,
+    renderer_info: RendererInfo,
+    resize_listeners: RefCell<Vec<Weak<RefCell<dyn ResizeListener>>>>,
+    context_created: RefCell<Vec<ContextCreatedCallback>>,
+    builder: WindowedContextBuilder,
+}
+
+/// Something sized like the surface, such as an offscreen render target, that has to follow
+/// it when the window is resized.
+pub trait ResizeListener {
+    /// Called with the new, non-zero surface size once per [WindowedContext::resize].
+    fn resized(&mut self, width: u32, height: u32);
+}
+
+/// Properties of the default framebuffer the driver actually created, which may differ from
+/// what was requested.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SurfaceInfo {
+    /// Samples per pixel, 0 without MSAA.
+    pub samples: u8,
+    /// The sample count the config was selected for: the requested one, lowered to the most
+    /// samples any config of the display offers.
+    pub requested_samples: u8,
+    /// Whether frames are drawn to a back buffer and shown by [WindowedContext::swap_buffers].
+    /// `false` only if [WindowedContextBuilder::single_buffer] was requested and granted.
+    pub double_buffered: bool,
+    /// Bits per depth value, 0 without a depth buffer. 32 usually means floating point
+    /// depth, see [WindowedContextBuilder::prefer_depth_bits].
+    pub depth_bits: u8,
+    /// Bits per stencil value, 0 without a stencil buffer.
+    pub stencil_bits: u8,
+}
+
+/// The driver's identification strings, for bug reports and an "About" dialog.
+///
+/// Drivers may leave any of them empty, which is kept as an empty string.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RendererInfo {
+    pub vendor: String,
+    pub renderer: String,
+    pub version: String,
+    pub glsl_version: String,
+}
+
+impl RendererInfo {
+    fn query(context: &Context) -> Self {
+        Self {
+            vendor: gl_string(context, three_d::context::VENDOR),
+            renderer: gl_string(context, three_d::context::RENDERER),
+            version: gl_string(context, three_d::context::VERSION),
+            glsl_version: gl_string(context, three_d::context::SHADING_LANGUAGE_VERSION),
+        }
+    }
+}
+
+/// An OpenGL or OpenGL ES version, as reported in `GL_VERSION`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GlVersion {
+    /// Whether this is an OpenGL ES version.
+    pub es: bool,
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl GlVersion {
+    /// The oldest versions `three_d` renders with: OpenGL 3.3 and OpenGL ES 3.0.
+    pub fn required(es: bool) -> Self {
+        let (major, minor) = if es { (3, 0) } else { (3, 3) };
+        Self { es, major, minor }
+    }
+
+    /// Parses a `GL_VERSION` string. Desktop GL starts with the version, as in
+    /// `"4.6.0 NVIDIA 535.54"` or `"3.3 (Core Profile) Mesa 23.1"`, while GLES prefixes it, as
+    /// in `"OpenGL ES 3.2 Mesa 23.1"` or `"OpenGL ES 3.0 (ANGLE 2.1)"`.
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.trim();
+        let (es, rest) = match version.strip_prefix("OpenGL ES") {
+            // ES 1.x drivers report a profile, as in "OpenGL ES-CM 1.1".
+            Some(rest) => (
+                true,
+                rest.trim_start_matches(|c: char| c != ' ').trim_start(),
+            ),
+            None => (false, version),
+        };
+        let number = rest.split(|c: char| c.is_whitespace()).next()?;
+        let mut components = number.split('.');
+        let major = components.next()?.parse().ok()?;
+        let minor = components.next()?.parse().ok()?;
+        Some(Self { es, major, minor })
+    }
+
+    /// Whether `three_d` can render with this version, see [GlVersion::required].
+    pub fn is_supported(&self) -> bool {
+        let required = Self::required(self.es);
+        (self.major, self.minor) >= (required.major, required.minor)
+    }
+}
+
+impl std::fmt::Display for GlVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let api = if self.es { "OpenGL ES" } else { "OpenGL" };
+        write!(f, "{api} {}.{}", self.major, self.minor)
+    }
+}
+
+/// Reads a `glGetString` value, falling back to an empty string where the driver returns
+/// something that isn't valid UTF-8.
+fn gl_string(context: &Context, name: u32) -> String {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| unsafe {
+        context.get_parameter_string(name)
+    }))
+    .unwrap_or_default()
+}
+
+/// `logical_size` in physical pixels at `scale_factor`, see
+/// [WindowedContext::resize_from_logical].
+fn physical_size(logical_size: LogicalSize<f64>, scale_factor: f64) -> PhysicalSize<u32> {
+    logical_size.to_physical(scale_factor)
+}
+
+/// The largest viewport the driver supports, `GL_MAX_VIEWPORT_DIMS`.
+fn max_viewport_size(context: &Context) -> (u32, u32) {
+    let mut dimensions = [0; 2];
+    unsafe {
+        context.get_parameter_i32_slice(three_d::context::MAX_VIEWPORT_DIMS, &mut dimensions)
+    };
+    // GL requires at least the size of the largest renderbuffer, never less than 1024 here.
+    let [width, height] = dimensions.map(|dimension| (dimension.max(0) as u32).max(1024));
+    (width, height)
+}
+
+/// Whether `size` has an area to render into. Minimized windows report zero, where a frame
+/// should be skipped rather than rendered at a clamped size.
+pub fn is_renderable_size(size: PhysicalSize<u32>) -> bool {
+    size.width > 0 && size.height > 0
+}
+
+/// `size` clamped to at least one pixel and at most `max` in each dimension, so viewport and
+/// projection math never sees zero or more than the driver can render.
+pub fn sanitize_size(size: PhysicalSize<u32>, max: (u32, u32)) -> (u32, u32) {
+    (
+        size.width.clamp(1, max.0.max(1)),
+        size.height.clamp(1, max.1.max(1)),
+    )
+}
+
+/// Whether the driver behind `context` offers the GL extension `name`, for code that only has
+/// the `three_d` context.
+///
+/// The list is read once when the context is created, with `glGetStringi` on core profiles,
+/// where the single `GL_EXTENSIONS` string of compatibility profiles is an error. Core
+/// profiles don't have to list extensions that were promoted into the core version, so check
+/// the version as well for those, as the timer queries of GL 3.3 need.
+pub fn supports_extension(context: &Context, name: &str) -> bool {
+    context.supported_extensions().contains(name)
+}
+
+/// `requested` samples per pixel, or if the display offers at most `max`, the highest
+/// [MsaaSamples] setting within that.
+fn clamp_samples(requested: u8, max: u8) -> u8 {
+    if requested <= max {
+        requested
+    } else {
+        MsaaSamples::at_most(max as u32).count()
+    }
+}
+
+/// Whether a `GL_RENDERER` string names one of the common software rasterizers.
+fn is_software_renderer(renderer: &str) -> bool {
+    const SOFTWARE_RENDERERS: [&str; 7] = [
+        "llvmpipe",
+        "softpipe",
+        "swrast",
+        "software rasterizer",
+        "swiftshader",
+        "microsoft basic render",
+        "gdi generic",
+    ];
+    let renderer = renderer.to_lowercase();
+    SOFTWARE_RENDERERS
+        .iter()
+        .any(|name| renderer.contains(name))
+}
+
+/// Whether a context on `renderer`, from a config that reports `config_accelerated`, runs on
+/// the GPU, or the error if `acceleration` requires that and it doesn't.
+fn check_hardware_acceleration(
+    acceleration: three_d::HardwareAcceleration,
+    config_accelerated: bool,
+    renderer: &str,
+) -> Result<bool, ContextError> {
+    let hardware_accelerated = config_accelerated && !is_software_renderer(renderer);
+    if !hardware_accelerated {
+        if matches!(acceleration, three_d::HardwareAcceleration::Required) {
+            log_warn!(
+                renderer = %renderer,
+                "hardware acceleration required but only software rendering is available"
+            );
+            return Err(ContextError::HardwareAccelerationUnavailable {
+                renderer: renderer.to_string(),
+            });
+        }
+        log_warn!(renderer = %renderer, "using a software renderer");
+    }
+    Ok(hardware_accelerated)
 }
 
 impl std::ops::Deref for WindowedContext {
@@ -19,49 +250,739 @@ impl std::ops::Deref for WindowedContext {
     }
 }
 
-impl WindowedContext {
-    /// Creates a new windowed context from a [winit](https://crates.io/crates/winit) window.
+/// How buffer swaps wait for the display's vertical blank, see
+/// [WindowedContext::set_vsync_mode].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VsyncMode {
+    /// Swaps right away, tearing but with the lowest latency.
+    Off,
+    /// Swaps wait for the vertical blank, never tearing.
+    #[default]
+    On,
+    /// Swaps wait for the vertical blank unless the frame is already late, then tear instead
+    /// of waiting for the next one, which avoids halving the frame rate when slightly below
+    /// the refresh rate. Needs `EXT_swap_control_tear` on GLX or WGL, elsewhere
+    /// [WindowedContext::set_vsync_mode] falls back to [VsyncMode::On].
+    Adaptive,
+}
+
+impl VsyncMode {
+    /// [VsyncMode::On] or [VsyncMode::Off].
+    pub fn from_enabled(enabled: bool) -> Self {
+        if enabled {
+            VsyncMode::On
+        } else {
+            VsyncMode::Off
+        }
+    }
+
+    pub fn is_enabled(self) -> bool {
+        self != VsyncMode::Off
+    }
+
+    /// The swap interval for the mode, `None` for [VsyncMode::Adaptive]: it needs a negative
+    /// interval (`EXT_swap_control_tear`), which glutin's [SwapInterval] can't express, see
+    /// [WindowedContext::set_adaptive_swap_interval].
+    fn swap_interval(self) -> Option<SwapInterval> {
+        match self {
+            VsyncMode::Off => Some(SwapInterval::DontWait),
+            VsyncMode::On => Some(SwapInterval::Wait(std::num::NonZeroU32::new(1).unwrap())),
+            VsyncMode::Adaptive => None,
+        }
+    }
+}
+
+/// Number of samples per pixel for multisample anti-aliasing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MsaaSamples {
+    #[default]
+    Off,
+    X2,
+    X4,
+    X8,
+    X16,
+}
+
+impl MsaaSamples {
+    /// The sample count as passed to the config template, `0` meaning no multisampling.
+    pub fn count(self) -> u8 {
+        match self {
+            MsaaSamples::Off => 0,
+            MsaaSamples::X2 => 2,
+            MsaaSamples::X4 => 4,
+            MsaaSamples::X8 => 8,
+            MsaaSamples::X16 => 16,
+        }
+    }
+
+    /// The highest setting using at most `count` samples.
+    pub fn at_most(count: u32) -> Self {
+        match count {
+            0..=1 => MsaaSamples::Off,
+            2..=3 => MsaaSamples::X2,
+            4..=7 => MsaaSamples::X4,
+            8..=15 => MsaaSamples::X8,
+            _ => MsaaSamples::X16,
+        }
+    }
+}
+
+/// Which GL display API to create the context with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BackendPreference {
+    /// EGL where available, falling back to the platform API (WGL on Windows, GLX on linux).
+    #[default]
+    PlatformDefault,
+    /// EGL only.
+    Egl,
+    /// The platform API only: WGL on Windows, GLX on linux, CGL on macOS.
+    Native,
+}
+
+/// The user-facing graphics options, in a form that can be saved and restored, with serde
+/// behind the `serde` feature.
+///
+/// Restore with [WindowedContextBuilder::graphics_settings]. The driver may not grant
+/// everything, e.g. after a GPU change; [WindowedContext::graphics_settings] then reports what
+/// was applied instead, and saving that keeps the next launch from asking again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct GraphicsSettings {
+    pub vsync: bool,
+    pub msaa: MsaaSamples,
+    pub depth_bits: u8,
+    pub stencil_bits: u8,
+    pub backend_preference: BackendPreference,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        let settings = SurfaceSettings::default();
+        Self {
+            vsync: settings.vsync,
+            msaa: MsaaSamples::at_most(settings.multisamples as u32),
+            depth_bits: settings.depth_buffer,
+            stencil_bits: settings.stencil_buffer,
+            backend_preference: BackendPreference::default(),
+        }
+    }
+}
+
+/// How the window the GL surface draws into is shared with the Dioxus webview.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WindowMode {
+    /// The webview is a child window over the GL surface, as with Dioxus'
+    /// `Config::with_as_child_window`, and the 3D content shows through wherever the page is
+    /// transparent. The surface's config gets an alpha channel, which some platforms need to
+    /// composite child windows.
+    #[default]
+    Child,
+    /// The 3D content is the whole window, there is no webview over it. An alpha channel is
+    /// only requested together with [WindowedContextBuilder::transparent].
+    Standalone,
+}
+
+/// Settings read from environment variables, for forcing a backend or option without
+/// recompiling, e.g. to reproduce a bug report or on CI.
+///
+/// `DNG_BACKEND` is `default`, `egl` or `native`, `DNG_VSYNC` is `1` or `0` (also `true` or
+/// `false`, `on` or `off`) and `DNG_MSAA` is a sample count of `0`, `2`, `4`, `8` or `16`.
+/// Unset variables leave their setting alone, malformed ones are ignored with a warning.
+/// [WindowedContext::from_tao_window] reads them, builders only through
+/// [WindowedContextBuilder::env_overrides].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EnvOverrides {
+    pub backend_preference: Option<BackendPreference>,
+    pub vsync: Option<bool>,
+    pub msaa: Option<MsaaSamples>,
+}
+
+impl EnvOverrides {
+    pub const BACKEND: &'static str = "DNG_BACKEND";
+    pub const VSYNC: &'static str = "DNG_VSYNC";
+    pub const MSAA: &'static str = "DNG_MSAA";
+
+    /// Reads the variables of the current process.
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    /// Reads the variables through `lookup`, which returns the value of a variable if it is
+    /// set.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        Self {
+            backend_preference: parse_env(&lookup, Self::BACKEND, parse_backend),
+            vsync: parse_env(&lookup, Self::VSYNC, parse_switch),
+            msaa: parse_env(&lookup, Self::MSAA, parse_msaa),
+        }
+    }
+}
+
+/// Which of the options [EnvOverrides] covers the caller set on a [WindowedContextBuilder],
+/// so the environment leaves them alone.
+#[derive(Clone, Copy, Debug, Default)]
+struct SetByCaller {
+    backend_preference: bool,
+    vsync: bool,
+    msaa: bool,
+}
+
+fn parse_env<T>(
+    lookup: &impl Fn(&str) -> Option<String>,
+    name: &str,
+    parse: fn(&str) -> Option<T>,
+) -> Option<T> {
+    let value = lookup(name)?;
+    let parsed = parse(&value.trim().to_ascii_lowercase());
+    if parsed.is_none() {
+        log_warn!(variable = name, value = %value, "ignoring malformed environment variable");
+    }
+    parsed
+}
+
+fn parse_backend(value: &str) -> Option<BackendPreference> {
+    match value {
+        "default" => Some(BackendPreference::PlatformDefault),
+        "egl" => Some(BackendPreference::Egl),
+        "native" => Some(BackendPreference::Native),
+        _ => None,
+    }
+}
+
+fn parse_switch(value: &str) -> Option<bool> {
+    match value {
+        "1" | "true" | "on" => Some(true),
+        "0" | "false" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_msaa(value: &str) -> Option<MsaaSamples> {
+    let count = value.parse::<u32>().ok()?;
+    let samples = MsaaSamples::at_most(count);
+    (samples.count() as u32 == count).then_some(samples)
+}
+
+/// Configures and creates a [WindowedContext].
+///
+/// ```ignore
+/// let context = WindowedContextBuilder::new()
+///     .vsync(true)
+///     .msaa(MsaaSamples::X4)
+///     .transparent(true)
+///     .build_from_tao_window(window)?;
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct WindowedContextBuilder {
+    settings: SurfaceSettings,
+    transparent: bool,
+    backend_preference: BackendPreference,
+    single_buffer: bool,
+    window_mode: WindowMode,
+    software_fallback: bool,
+    preferred_depth_bits: u8,
+    set_by_caller: SetByCaller,
+}
+
+impl Default for WindowedContextBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WindowedContextBuilder {
+    pub fn new() -> Self {
+        Self {
+            set_by_caller: SetByCaller::default(),
+            ..Self::from_settings(SurfaceSettings::default())
+        }
+    }
+
+    /// Starts from existing [SurfaceSettings], which [WindowedContextBuilder::env_overrides]
+    /// doesn't override.
+    pub fn from_settings(settings: SurfaceSettings) -> Self {
+        Self {
+            settings,
+            transparent: false,
+            backend_preference: BackendPreference::default(),
+            single_buffer: false,
+            window_mode: WindowMode::default(),
+            software_fallback: false,
+            preferred_depth_bits: 0,
+            set_by_caller: SetByCaller {
+                backend_preference: false,
+                vsync: true,
+                msaa: true,
+            },
+        }
+    }
+
+    pub fn vsync(mut self, vsync: bool) -> Self {
+        self.settings.vsync = vsync;
+        self.set_by_caller.vsync = true;
+        self
+    }
+
+    pub fn msaa(mut self, samples: MsaaSamples) -> Self {
+        self.settings.multisamples = samples.count();
+        self.set_by_caller.msaa = true;
+        self
+    }
+
+    pub fn depth_bits(mut self, bits: u8) -> Self {
+        self.settings.depth_buffer = bits;
+        self
+    }
+
+    pub fn stencil_bits(mut self, bits: u8) -> Self {
+        self.settings.stencil_buffer = bits;
+        self
+    }
+
+    /// Prefers a config with `bits` of depth among those with at least
+    /// [WindowedContextBuilder::depth_bits], such as 32 for the floating point depth that
+    /// makes the most of [crate::depth::DepthConfig::reverse_z]. Without one, the deepest
+    /// available is used; check [SurfaceInfo::depth_bits].
+    ///
+    /// The stencil bits stay a requirement, and many drivers only pair 32-bit depth with no
+    /// stencil, so asking for both usually falls back to 24-bit depth with 8-bit stencil.
+    pub fn prefer_depth_bits(mut self, bits: u8) -> Self {
+        self.preferred_depth_bits = bits;
+        self
+    }
+
+    /// Requests a config with an alpha channel that the window compositor honours.
+    pub fn transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    pub fn hardware_acceleration(
+        mut self,
+        hardware_acceleration: three_d::HardwareAcceleration,
+    ) -> Self {
+        self.settings.hardware_acceleration = hardware_acceleration;
+        self
+    }
+
+    pub fn backend_preference(mut self, backend_preference: BackendPreference) -> Self {
+        self.backend_preference = backend_preference;
+        self.set_by_caller.backend_preference = true;
+        self
+    }
+
+    /// Applies the options `overrides` has, except those set explicitly, before or after: with
+    /// the setters or, for vsync and MSAA, [WindowedContextBuilder::from_settings].
+    pub fn env_overrides(mut self, overrides: EnvOverrides) -> Self {
+        let set = self.set_by_caller;
+        let overrides = EnvOverrides {
+            backend_preference: overrides
+                .backend_preference
+                .filter(|_| !set.backend_preference),
+            vsync: overrides.vsync.filter(|_| !set.vsync),
+            msaa: overrides.msaa.filter(|_| !set.msaa),
+        };
+        if overrides != EnvOverrides::default() {
+            log_info!(?overrides, "applying settings from the environment");
+        }
+        if let Some(backend_preference) = overrides.backend_preference {
+            self.backend_preference = backend_preference;
+        }
+        if let Some(vsync) = overrides.vsync {
+            self.settings.vsync = vsync;
+        }
+        if let Some(msaa) = overrides.msaa {
+            self.settings.multisamples = msaa.count();
+        }
+        self
+    }
+
+    /// Applies saved [GraphicsSettings], leaving the other options as they are.
+    pub fn graphics_settings(self, settings: GraphicsSettings) -> Self {
+        self.vsync(settings.vsync)
+            .msaa(settings.msaa)
+            .depth_bits(settings.depth_bits)
+            .stencil_bits(settings.stencil_bits)
+            .backend_preference(settings.backend_preference)
+    }
+
+    /// Whether the surface is composed with a webview, see [WindowMode].
+    pub fn window_mode(mut self, window_mode: WindowMode) -> Self {
+        self.window_mode = window_mode;
+        self
+    }
+
+    /// Prefers a single-buffered surface, which draws straight to the visible buffer for the
+    /// lowest latency at the cost of showing frames while they are drawn.
+    ///
+    /// Few platforms offer it: mostly EGL on some embedded and linux drivers, and compositors
+    /// may still buffer the window. Where the surface can't be created single-buffered, a
+    /// double-buffered one is used instead; check [WindowedContext::surface_info].
+    pub fn single_buffer(mut self, single_buffer: bool) -> Self {
+        self.single_buffer = single_buffer;
+        self
+    }
+
+    /// With [three_d::HardwareAcceleration::Required], retries with
+    /// [three_d::HardwareAcceleration::Off] if no hardware accelerated context can be created,
+    /// so rendering still works on headless CI and machines without a GPU driver.
+    ///
+    /// Whether that happened is [WindowedContext::fell_back_to_software]. If the software
+    /// attempt fails too, the error is [ContextError::SoftwareFallbackFailed] with both causes.
+    pub fn software_fallback(mut self, software_fallback: bool) -> Self {
+        self.software_fallback = software_fallback;
+        self
+    }
+
+    /// The surface settings this builder will create the context with.
+    pub fn settings(&self) -> &SurfaceSettings {
+        &self.settings
+    }
+
+    /// Creates a new windowed context from a [tao](https://crates.io/crates/tao) window.
+    pub fn build_from_tao_window(self, window: &Window) -> Result<WindowedContext, ContextError> {
+        let error = match self.build(window) {
+            Err(error) if self.falls_back_on(&error) => error,
+            result => return result,
+        };
+        log_warn!(error = %error, "no hardware accelerated context, falling back to software");
+        self.hardware_acceleration(three_d::HardwareAcceleration::Off)
+            .build(window)
+            .map(|mut context| {
+                context.fell_back_to_software = true;
+                context.builder = self;
+                context
+            })
+            .map_err(|software| ContextError::SoftwareFallbackFailed {
+                hardware: Box::new(error),
+                software: Box::new(software),
+            })
+    }
+
+    /// Whether `error` from a first attempt is worth retrying in software, see
+    /// [WindowedContextBuilder::software_fallback]. Invalid settings and a missing display fail
+    /// the same way either way.
+    pub fn falls_back_on(&self, error: &ContextError) -> bool {
+        let required = matches!(
+            self.settings.hardware_acceleration,
+            three_d::HardwareAcceleration::Required
+        );
+        self.software_fallback
+            && required
+            && matches!(
+                error,
+                ContextError::HardwareAccelerationUnavailable { .. }
+                    | ContextError::NoMatchingConfig
+                    | ContextError::ConfigSelection(_)
+                    | ContextError::ContextCreation(_)
+                    | ContextError::SurfaceCreation(_)
+                    | ContextError::UnsupportedGlVersion { .. }
+            )
+    }
+
     #[allow(unsafe_code)]
-    pub fn from_tao_window(
-        window: &Window,
-        settings: SurfaceSettings,
-    ) -> Result<Self, WindowError> {
+    fn build(self, window: &Window) -> Result<WindowedContext, ContextError> {
+        let _span = log_span!("WindowedContext::build_from_tao_window");
+        let settings = self.settings;
         if settings.multisamples > 0 && !settings.multisamples.is_power_of_two() {
-            Err(WindowError::InvalidNumberOfMSAASamples)?;
+            Err(ContextError::InvalidNumberOfMsaaSamples(
+                settings.multisamples,
+            ))?;
         }
         use glutin::prelude::*;
         use raw_window_handle::*;
         let raw_display_handle = window.raw_display_handle();
         let raw_window_handle = window.raw_window_handle();
 
+        log_info!(backend_preference = ?self.backend_preference, "creating GL display");
+        let preference = self.display_api_preference(raw_window_handle);
+        let gl_display = unsafe { glutin::display::Display::new(raw_display_handle, preference) }
+            .map_err(ContextError::DisplayCreation)?;
+        log_debug!("created GL display");
+        let vsync_mode = VsyncMode::from_enabled(settings.vsync);
+        let swap_interval = vsync_mode.swap_interval().unwrap();
+
+        // Enumerating the configs without multisampling first finds the most samples the
+        // display offers, so a request beyond that is clamped instead of matching nothing.
+        let any_samples = self.config_template(raw_window_handle, 0);
+        let max_samples = unsafe { gl_display.find_configs(any_samples) }
+            .map_err(ContextError::ConfigSelection)?
+            .map(|config| config.num_samples())
+            .max()
+            .unwrap_or(0);
+        let samples = clamp_samples(settings.multisamples, max_samples);
+        if samples != settings.multisamples {
+            log_warn!(
+                requested_samples = settings.multisamples,
+                max_samples,
+                samples,
+                "MSAA sample count clamped to the most the display supports"
+            );
+        }
+
+        let config_template = self.config_template(raw_window_handle, samples);
+        // finds all valid configurations supported by this display that match the
+        // config_template this is where we will try to get a "fallback" config if
+        // we are okay with ignoring some native options required by user like multi
+        // sampling, srgb, transparency etc..
+        let config = unsafe {
+            gl_display
+                .find_configs(config_template)
+                .map_err(ContextError::ConfigSelection)?
+                .reduce(|best, config| {
+                    if self.config_score(&config, samples) > self.config_score(&best, samples) {
+                        config
+                    } else {
+                        best
+                    }
+                })
+                .ok_or(ContextError::NoMatchingConfig)?
+        };
+        log_info!(
+            samples = config.num_samples(),
+            depth_bits = config.depth_size(),
+            stencil_bits = config.stencil_size(),
+            hardware_accelerated = config.hardware_accelerated(),
+            transparency = ?config.supports_transparency(),
+            "selected GL config"
+        );
+
+        let context_attributes =
+            glutin::context::ContextAttributesBuilder::new().build(Some(raw_window_handle));
+        // for surface creation.
+        let (width, height): (u32, u32) = window.inner_size().into();
+        let width = std::num::NonZeroU32::new(width.max(1)).unwrap();
+        let height = std::num::NonZeroU32::new(height.max(1)).unwrap();
+        let surface_attributes = |single_buffer| {
+            glutin::surface::SurfaceAttributesBuilder::<glutin::surface::WindowSurface>::new()
+                .with_single_buffer(single_buffer)
+                .build(raw_window_handle, width, height)
+        };
+        // start creating the gl objects
+        let gl_context = unsafe { gl_display.create_context(&config, &context_attributes) }
+            .map_err(ContextError::ContextCreation)?;
+        log_debug!("created GL context");
+
+        let single_buffered_surface = if self.single_buffer {
+            unsafe { gl_display.create_window_surface(&config, &surface_attributes(true)) }
+                .map_err(|_error| {
+                    log_warn!(
+                        error = %_error,
+                        "single-buffered surface unavailable, using double buffering"
+                    );
+                })
+                .ok()
+        } else {
+            None
+        };
+        let gl_surface = match single_buffered_surface {
+            Some(surface) => surface,
+            None => {
+                unsafe { gl_display.create_window_surface(&config, &surface_attributes(false)) }
+                    .map_err(ContextError::SurfaceCreation)?
+            }
+        };
+        let surface_info = SurfaceInfo {
+            samples: config.num_samples(),
+            requested_samples: samples,
+            double_buffered: !gl_surface.is_single_buffered(),
+            depth_bits: config.depth_size(),
+            stencil_bits: config.stencil_size(),
+        };
+        if surface_info.depth_bits < self.preferred_depth_bits {
+            log_warn!(
+                preferred_depth_bits = self.preferred_depth_bits,
+                depth_bits = surface_info.depth_bits,
+                stencil_bits = surface_info.stencil_bits,
+                "no config with the preferred depth bits"
+            );
+        }
+        if self.single_buffer && surface_info.double_buffered {
+            log_warn!("single buffering requested but the surface is double-buffered");
+        }
+        log_debug!(
+            width = width.get(),
+            height = height.get(),
+            "created GL surface"
+        );
+        let gl_context = gl_context
+            .make_current(&gl_surface)
+            .map_err(ContextError::MakeCurrent)?;
+        log_debug!("made GL context current");
+        gl_surface
+            .set_swap_interval(&gl_context, swap_interval)
+            .map_err(ContextError::SwapInterval)?;
+
+        // three-d wants the GL context behind an `Arc`, though it never leaves this thread.
+        #[allow(clippy::arc_with_non_send_sync)]
+        let context = Context::from_gl_context(Arc::new(unsafe {
+            three_d::context::Context::from_loader_function(|s| {
+                let s = std::ffi::CString::new(s)
+                    .expect("failed to construct C string from string for gl proc address");
+
+                gl_display.get_proc_address(&s)
+            })
+        }))?;
+        crate::raster::load_point_size(|name| gl_display.get_proc_address(name));
+
+        let renderer_info = RendererInfo::query(&context);
+        log_info!(
+            vendor = %renderer_info.vendor,
+            renderer = %renderer_info.renderer,
+            version = %renderer_info.version,
+            glsl_version = %renderer_info.glsl_version,
+            "GL driver"
+        );
+        match GlVersion::parse(&renderer_info.version) {
+            Some(found) if !found.is_supported() => {
+                let required = GlVersion::required(found.es);
+                log_warn!(%found, %required, "GL version too old");
+                return Err(ContextError::UnsupportedGlVersion { found, required });
+            }
+            Some(_) => {}
+            None => log_warn!(version = %renderer_info.version, "could not parse the GL version"),
+        }
+        let hardware_accelerated = check_hardware_acceleration(
+            settings.hardware_acceleration,
+            config.hardware_accelerated(),
+            &renderer_info.renderer,
+        )?;
+
+        let applied = SurfaceSettings {
+            multisamples: config.num_samples(),
+            depth_buffer: config.depth_size(),
+            stencil_buffer: config.stencil_size(),
+            ..settings
+        };
+        if (
+            applied.multisamples,
+            applied.depth_buffer,
+            applied.stencil_buffer,
+        ) != (
+            settings.multisamples,
+            settings.depth_buffer,
+            settings.stencil_buffer,
+        ) {
+            log_warn!(
+                requested_samples = settings.multisamples,
+                samples = applied.multisamples,
+                requested_depth_bits = settings.depth_buffer,
+                depth_bits = applied.depth_buffer,
+                requested_stencil_bits = settings.stencil_buffer,
+                stencil_bits = applied.stencil_buffer,
+                "surface settings adjusted to the available config"
+            );
+        }
+
+        let max_viewport_size = max_viewport_size(&context);
+        let size = sanitize_size(
+            PhysicalSize::new(width.get(), height.get()),
+            max_viewport_size,
+        );
+        Ok(WindowedContext {
+            context,
+            glutin_context: RefCell::new(Some(gl_context)),
+            surface: gl_surface,
+            size: Cell::new(size),
+            max_viewport_size,
+            scale_factor: Cell::new(window.scale_factor()),
+            hardware_accelerated,
+            fell_back_to_software: false,
+            finish_before_swap: Cell::new(false),
+            vsync_mode: Cell::new(vsync_mode),
+            surface_info,
+            settings: applied,
+            backend_preference: self.backend_preference,
+            window_mode: self.window_mode,
+            renderer_info,
+            resize_listeners: RefCell::new(Vec::new()),
+            context_created: RefCell::new(Vec::new()),
+            builder: self,
+        })
+    }
+
+    /// Ranks the configs matching the template. The template only filters, so without this
+    /// the driver's first pick wins, which for a transparent multisampled window is often a
+    /// config without alpha or with a different sample count.
+    ///
+    /// Transparency matters most, as without it the silhouette can't blend over the webview at
+    /// all, then the sample count closest to `samples`, the clamped request, then depth bits
+    /// up to the preferred ones.
+    fn config_score(
+        &self,
+        config: &glutin::config::Config,
+        samples: u8,
+    ) -> (bool, std::cmp::Reverse<u8>, u8) {
+        use glutin::config::GlConfig;
+        let transparency = !self.transparent || config.supports_transparency() == Some(true);
+        let samples = config.num_samples().abs_diff(samples);
+        let depth = config.depth_size().min(self.preferred_depth_bits);
+        (transparency, std::cmp::Reverse(samples), depth)
+    }
+
+    /// The display API preference for the current platform and [BackendPreference].
+    fn display_api_preference(
+        &self,
+        raw_window_handle: raw_window_handle::RawWindowHandle,
+    ) -> glutin::display::DisplayApiPreference {
+        use glutin::display::DisplayApiPreference;
         // EGL is crossplatform and the official khronos way
         // but sometimes platforms/drivers may not have it, so we use back up options
-        // where possible. TODO: check whether we can expose these options as
-        // "features", so that users can select the relevant backend they want.
+        // where possible.
 
         // try egl and fallback to windows wgl. Windows is the only platform that
         // *requires* window handle to create display.
         #[cfg(target_os = "windows")]
-        let preference =
-            glutin::display::DisplayApiPreference::WglThenEgl(Some(raw_window_handle));
+        let preference = match self.backend_preference {
+            BackendPreference::PlatformDefault => {
+                DisplayApiPreference::WglThenEgl(Some(raw_window_handle))
+            }
+            BackendPreference::Egl => DisplayApiPreference::Egl,
+            BackendPreference::Native => DisplayApiPreference::Wgl(Some(raw_window_handle)),
+        };
         // try egl and fallback to x11 glx
         #[cfg(target_os = "linux")]
-        let preference = glutin::display::DisplayApiPreference::EglThenGlx(Box::new(
-            winit::platform::x11::register_xlib_error_hook,
-        ));
+        let preference = match self.backend_preference {
+            BackendPreference::PlatformDefault => DisplayApiPreference::EglThenGlx(Box::new(
+                crate::xlib_errors::register_xlib_error_hook,
+            )),
+            BackendPreference::Egl => DisplayApiPreference::Egl,
+            BackendPreference::Native => {
+                DisplayApiPreference::Glx(Box::new(crate::xlib_errors::register_xlib_error_hook))
+            }
+        };
+        // CGL is the only option on macOS and EGL the only one on android.
         #[cfg(target_os = "macos")]
-        let preference = glutin::display::DisplayApiPreference::Cgl;
+        let preference = DisplayApiPreference::Cgl;
         #[cfg(target_os = "android")]
-        let preference = glutin::display::DisplayApiPreference::Egl;
+        let preference = DisplayApiPreference::Egl;
+        #[cfg(not(target_os = "windows"))]
+        let _ = raw_window_handle;
+        preference
+    }
 
-        let gl_display =
-            unsafe { glutin::display::Display::new(raw_display_handle, preference)? };
-        let swap_interval = if settings.vsync {
-            glutin::surface::SwapInterval::Wait(std::num::NonZeroU32::new(1).unwrap())
+    /// A child window is composited from the surface's alpha, a standalone one only needs it to
+    /// be see-through.
+    fn alpha_bits(&self) -> u8 {
+        if self.transparent || self.window_mode == WindowMode::Child {
+            8
         } else {
-            glutin::surface::SwapInterval::DontWait
-        };
+            0
+        }
+    }
 
+    /// The config template the display is queried with, for `multisamples` samples per pixel,
+    /// any number with `0`.
+    fn config_template(
+        &self,
+        raw_window_handle: raw_window_handle::RawWindowHandle,
+        multisamples: u8,
+    ) -> glutin::config::ConfigTemplate {
+        let settings = &self.settings;
         let hardware_acceleration = match settings.hardware_acceleration {
             three_d::HardwareAcceleration::Required => Some(true),
             three_d::HardwareAcceleration::Preferred => None,
@@ -69,85 +990,1363 @@ impl WindowedContext {
         };
         let config_template = glutin::config::ConfigTemplateBuilder::new()
             .prefer_hardware_accelerated(hardware_acceleration)
-            .with_depth_size(settings.depth_buffer);
+            .with_depth_size(settings.depth_buffer)
+            .with_alpha_size(self.alpha_bits())
+            .with_transparency(self.transparent);
         // we don't know if multi sampling option is set. so, check if its more than 0.
-        let config_template = if settings.multisamples > 0 {
-            config_template.with_multisampling(settings.multisamples)
+        let config_template = if multisamples > 0 {
+            config_template.with_multisampling(multisamples)
         } else {
             config_template
         };
-        let config_template = config_template
+        config_template
             .with_stencil_size(settings.stencil_buffer)
             .compatible_with_native_window(raw_window_handle)
-            .build();
-        // finds all valid configurations supported by this display that match the
-        // config_template this is where we will try to get a "fallback" config if
-        // we are okay with ignoring some native options required by user like multi
-        // sampling, srgb, transparency etc..
-        let config = unsafe {
-            gl_display
-                .find_configs(config_template)?
-                .next()
-                .ok_or(WindowError::SurfaceCreationError)?
+            .build()
+    }
+}
+
+impl WindowedContext {
+    /// Creates a new windowed context from a [tao](https://crates.io/crates/tao) window.
+    ///
+    /// Shorthand for [WindowedContextBuilder::from_settings], or [WindowedContextBuilder::new]
+    /// without `settings`, followed by [WindowedContextBuilder::env_overrides] with
+    /// [EnvOverrides::from_env] and [WindowedContextBuilder::build_from_tao_window]. Given
+    /// `settings` win over the environment, which still chooses the backend.
+    pub fn from_tao_window(
+        window: &Window,
+        settings: impl Into<Option<SurfaceSettings>>,
+    ) -> Result<Self, ContextError> {
+        let builder = match settings.into() {
+            Some(settings) => WindowedContextBuilder::from_settings(settings),
+            None => WindowedContextBuilder::new(),
         };
+        builder
+            .env_overrides(EnvOverrides::from_env())
+            .build_from_tao_window(window)
+    }
 
-        let context_attributes =
-            glutin::context::ContextAttributesBuilder::new().build(Some(raw_window_handle));
-        // for surface creation.
-        let (width, height): (u32, u32) = window.inner_size().into();
-        let width = std::num::NonZeroU32::new(width.max(1)).unwrap();
-        let height = std::num::NonZeroU32::new(height.max(1)).unwrap();
-        let surface_attributes =
-            glutin::surface::SurfaceAttributesBuilder::<glutin::surface::WindowSurface>::new()
-                .build(raw_window_handle, width, height);
-        // start creating the gl objects
-        let gl_context = unsafe { gl_display.create_context(&config, &context_attributes)? };
+    /// Resizes the context and notifies the registered [ResizeListener]s.
+    ///
+    /// Degenerate sizes, as reported while the window is minimized, are ignored and the
+    /// previous size is kept, see [is_renderable_size]. Sizes beyond what the driver can
+    /// render are clamped, see [sanitize_size].
+    pub fn resize(&self, physical_size: PhysicalSize<u32>) {
+        if !is_renderable_size(physical_size) {
+            return;
+        }
+        let (width, height) = sanitize_size(physical_size, self.max_viewport_size);
+        if (width, height) != (physical_size.width, physical_size.height) {
+            log_warn!(
+                width = physical_size.width,
+                height = physical_size.height,
+                "window larger than the driver can render, clamping"
+            );
+        }
+        let (Some(surface_width), Some(surface_height)) = (
+            std::num::NonZeroU32::new(width),
+            std::num::NonZeroU32::new(height),
+        ) else {
+            return;
+        };
+        if let Some(glutin_context) = &*self.glutin_context.borrow() {
+            self.surface
+                .resize(glutin_context, surface_width, surface_height);
+        }
+        self.size.set((width, height));
+        self.resize_listeners.borrow_mut().retain(|listener| {
+            let Some(listener) = listener.upgrade() else {
+                return false;
+            };
+            listener.borrow_mut().resized(width, height);
+            true
+        });
+    }
 
-        let gl_surface =
-            unsafe { gl_display.create_window_surface(&config, &surface_attributes)? };
-        let gl_context = gl_context.make_current(&gl_surface)?;
-        gl_surface.set_swap_interval(&gl_context, swap_interval)?;
+    /// Like [WindowedContext::resize], from a size in logical pixels and the scale factor
+    /// they are in, which is remembered as the new [WindowedContext::scale_factor].
+    ///
+    /// Each dimension is rounded to the nearest physical pixel the way tao rounds window sizes,
+    /// so e.g. 801 logical pixels at 1.25 become 1001, matching what the window reports.
+    pub fn resize_from_logical(&self, logical_size: LogicalSize<f64>, scale_factor: f64) {
+        self.scale_factor.set(scale_factor);
+        self.resize(physical_size(logical_size, scale_factor));
+    }
 
-        Ok(Self {
-            context: Context::from_gl_context(Arc::new(unsafe {
-                three_d::context::Context::from_loader_function(|s| {
-                    let s = std::ffi::CString::new(s)
-                        .expect("failed to construct C string from string for gl proc address");
+    /// The scale factor set by [WindowedContext::resize_from_logical], initially the
+    /// window's.
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor.get()
+    }
 
-                    gl_display.get_proc_address(&s)
-                })
-            }))?,
-            glutin_context: gl_context,
-            surface: gl_surface,
-        })
+    /// Registers `listener` to be resized along with the surface.
+    ///
+    /// Only a weak reference is kept, dropping the listener unregisters it.
+    pub fn add_resize_listener<L: ResizeListener + 'static>(&self, listener: &Rc<RefCell<L>>) {
+        let listener: Rc<RefCell<dyn ResizeListener>> = listener.clone();
+        self.resize_listeners
+            .borrow_mut()
+            .push(Rc::downgrade(&listener));
     }
 
-    /// Resizes the context
-    pub fn resize(&self, physical_size: PhysicalSize<u32>) {
-        let width = std::num::NonZeroU32::new(physical_size.width.max(1)).unwrap();
-        let height = std::num::NonZeroU32::new(physical_size.height.max(1)).unwrap();
-        self.surface.resize(&self.glutin_context, width, height);
+    /// Registers `callback` as the place to upload GPU state, such as models, textures and
+    /// lights: it runs right away, and again with the new context after every
+    /// [WindowedContext::reinitialize], as everything uploaded to the old one is gone. The
+    /// context is made current before each call.
+    pub fn on_context_created(
+        &self,
+        callback: impl Fn(&Context) + 'static,
+    ) -> Result<(), ContextError> {
+        self.make_current()?;
+        callback(&self.context);
+        self.context_created.borrow_mut().push(Rc::new(callback));
+        Ok(())
+    }
+
+    /// Replaces the GL context and surface of `window` with new ones, built the way this
+    /// context was, such as after a driver reset, then runs the
+    /// [WindowedContext::on_context_created] callbacks.
+    ///
+    /// The resize listeners, callbacks, scale factor, [WindowedContext::vsync_mode] and
+    /// [WindowedContext::set_finish_before_swap] carry over; GL objects of the old context
+    /// don't, they have to be dropped and created again. On error the old context is kept.
+    pub fn reinitialize(&mut self, window: &Window) -> Result<(), ContextError> {
+        log_info!("reinitializing GL context");
+        let context = self.builder.build_from_tao_window(window)?;
+        context.scale_factor.set(self.scale_factor.get());
+        context
+            .finish_before_swap
+            .set(self.finish_before_swap.get());
+        *context.resize_listeners.borrow_mut() = self.resize_listeners.take();
+        *context.context_created.borrow_mut() = self.context_created.take();
+        let vsync_mode = self.vsync_mode();
+        *self = context;
+        self.make_current()?;
+        if self.vsync_mode() != vsync_mode {
+            self.set_vsync_mode(vsync_mode)?;
+        }
+        let callbacks = self.context_created.borrow().clone();
+        for callback in callbacks {
+            callback(&self.context);
+        }
+        Ok(())
+    }
+
+    /// The size of the surface in physical pixels, as of the last resize. Never zero and
+    /// never larger than [WindowedContext::max_viewport_size], see [sanitize_size].
+    pub fn size(&self) -> (u32, u32) {
+        self.size.get()
+    }
+
+    /// The size of the drawable as the GL surface reports it, in physical pixels, which is
+    /// what a frame actually fills.
+    ///
+    /// Usually the same as [WindowedContext::size], but while the window is resized some
+    /// platforms resize the drawable on their own before or after the resize event arrives;
+    /// rendering to the size of either then stretches or clips the frame. Where the surface
+    /// reports no size or a zero one, as some do during those transitions, this is
+    /// [WindowedContext::size]. Clamped like it, see [sanitize_size].
+    pub fn surface_size(&self) -> (u32, u32) {
+        match (self.surface.width(), self.surface.height()) {
+            (Some(width), Some(height)) if width > 0 && height > 0 => {
+                sanitize_size(PhysicalSize::new(width, height), self.max_viewport_size)
+            }
+            _ => self.size(),
+        }
+    }
+
+    /// The largest viewport the driver can render, in physical pixels.
+    pub fn max_viewport_size(&self) -> (u32, u32) {
+        self.max_viewport_size
+    }
+
+    /// Whether rendering runs on the GPU, judging by both the selected config and the
+    /// `GL_RENDERER` string, which catches drivers that advertise accelerated configs but
+    /// rasterize in software (llvmpipe, SwiftShader, ...).
+    pub fn is_hardware_accelerated(&self) -> bool {
+        self.hardware_accelerated
+    }
+
+    /// Whether hardware acceleration was required but unavailable, and the context was
+    /// created in software instead, see [WindowedContextBuilder::software_fallback].
+    pub fn fell_back_to_software(&self) -> bool {
+        self.fell_back_to_software
+    }
+
+    /// The GL vendor, renderer and version strings read when the context was created.
+    pub fn renderer_info(&self) -> &RendererInfo {
+        &self.renderer_info
+    }
+
+    /// Whether the driver offers the GL extension `name`, like `GL_ARB_timer_query`, see
+    /// [supports_extension].
+    pub fn supports_extension(&self, name: &str) -> bool {
+        supports_extension(&self.context, name)
+    }
+
+    /// The names of all GL extensions the driver offers, sorted.
+    pub fn extensions(&self) -> Vec<String> {
+        let mut extensions: Vec<String> = self
+            .context
+            .supported_extensions()
+            .iter()
+            .cloned()
+            .collect();
+        extensions.sort();
+        extensions
+    }
+
+    /// The number of samples per pixel of the default framebuffer, 0 without MSAA.
+    pub fn samples(&self) -> u8 {
+        self.surface_info.samples
+    }
+
+    /// The settings as applied: the sample count and buffer sizes are those of the selected
+    /// config, which can differ from the requested ones.
+    pub fn settings(&self) -> &SurfaceSettings {
+        &self.settings
+    }
+
+    /// The applied settings in their persistable form, see [GraphicsSettings].
+    pub fn graphics_settings(&self) -> GraphicsSettings {
+        GraphicsSettings {
+            vsync: self.vsync_mode().is_enabled(),
+            msaa: MsaaSamples::at_most(self.settings.multisamples as u32),
+            depth_bits: self.settings.depth_buffer,
+            stencil_bits: self.settings.stencil_buffer,
+            backend_preference: self.backend_preference,
+        }
+    }
+
+    /// The mode the context was built for, see [WindowedContextBuilder::window_mode].
+    pub fn window_mode(&self) -> WindowMode {
+        self.window_mode
+    }
+
+    /// What the created default framebuffer supports.
+    pub fn surface_info(&self) -> SurfaceInfo {
+        self.surface_info
+    }
+
+    /// A viewport covering the whole surface, see [WindowedContext::surface_size].
+    pub fn viewport(&self) -> Viewport {
+        let (width, height) = self.surface_size();
+        Viewport::new_at_origo(width, height)
+    }
+
+    /// Panics, naming the failing call site, if any GL error was raised since the last check.
+    ///
+    /// Meant to be sprinkled around custom render code while debugging it: `glGetError`
+    /// reports errors of any earlier call, so bisect with more checks to find the culprit.
+    /// Without the `gl-debug` feature this does nothing and compiles away. `KHR_debug` would
+    /// give richer messages, but installing its callback needs mutable access to the `glow`
+    /// context, which `three_d` shares.
+    #[track_caller]
+    #[inline]
+    pub fn debug_check_errors(&self) {
+        #[cfg(feature = "gl-debug")]
+        {
+            let mut errors = Vec::new();
+            // Each call returns and clears one error flag. Bounded, since after a context loss
+            // some drivers report errors forever.
+            while errors.len() < 16 {
+                match unsafe { self.context.get_error() } {
+                    three_d::context::NO_ERROR => break,
+                    error => errors.push(gl_error_name(error)),
+                }
+            }
+            if !errors.is_empty() {
+                panic!(
+                    "GL errors at {}: {}",
+                    std::panic::Location::caller(),
+                    errors.join(", ")
+                );
+            }
+        }
+    }
+
+    /// The viewport covering `rect`, e.g. the bounds of a placeholder element from Dioxus
+    /// layout, to confine the scene to that element with [crate::scene::Scene::render].
+    ///
+    /// Converts from logical pixels with a top-left origin to physical ones counted from the
+    /// bottom, with the current [WindowedContext::scale_factor] and
+    /// [WindowedContext::surface_size], so it should be called each frame rather than cached:
+    /// the result moves when the window is resized or the page scrolls. Edges are rounded
+    /// outwards and the result is clipped to the surface.
+    pub fn viewport_for(&self, rect: UiRect) -> Viewport {
+        let (width, height) = self.surface_size();
+        let scale = self.scale_factor();
+        let left = ((rect.x * scale).floor() as i64).clamp(0, width as i64);
+        let top = ((rect.y * scale).floor() as i64).clamp(0, height as i64);
+        let right = (((rect.x + rect.width) * scale).ceil() as i64).clamp(left, width as i64);
+        let bottom = (((rect.y + rect.height) * scale).ceil() as i64).clamp(top, height as i64);
+        Viewport {
+            x: left as i32,
+            y: (height as i64 - bottom) as i32,
+            width: (right - left) as u32,
+            height: (bottom - top) as u32,
+        }
+    }
+
+    /// Clears only `region` of the default framebuffer, for redrawing part of a frame such as
+    /// a HUD panel, and leaves the rest as it is.
+    ///
+    /// `region` is in physical pixels with `y` measured down from the top, like window and DOM
+    /// coordinates; GL's scissor box, which counts up from the bottom, is derived from it.
+    /// Parts outside the surface are ignored.
+    pub fn clear_region(&self, region: Viewport, clear_state: ClearState) {
+        let (width, height) = self.surface_size();
+        let x = region.x.clamp(0, width as i32);
+        let top = region.y.clamp(0, height as i32);
+        let right = (region.x + region.width as i32).clamp(x, width as i32);
+        let bottom = (region.y + region.height as i32).clamp(top, height as i32);
+        let scissor_box = ScissorBox {
+            x,
+            y: height as i32 - bottom,
+            width: (right - x) as u32,
+            height: (bottom - top) as u32,
+        };
+        if scissor_box.width == 0 || scissor_box.height == 0 {
+            return;
+        }
+        RenderTarget::screen(&self.context, width, height)
+            .clear_partially(scissor_box, clear_state);
+    }
+
+    /// Makes this context current on the calling thread, so GL calls go to it.
+    ///
+    /// Needed with more than one window, before rendering into or dropping GL objects of
+    /// a context other than the one current last, see [crate::windows::WindowContexts].
+    pub fn make_current(&self) -> Result<(), ContextError> {
+        self.with_glutin_context(|context| context.make_current(&self.surface))
+            .map_err(ContextError::MakeCurrent)
+    }
+
+    /// Releases this context from the calling thread, so no context is current until one is
+    /// made current again, with [WindowedContext::make_current] for this one. For handing
+    /// the thread to another context or library, and before tearing down.
+    ///
+    /// While not current, GL calls, rendering and [WindowedContext::swap_buffers] fail or go
+    /// nowhere; make the context current again first. Releasing a context that isn't current
+    /// does nothing. If the driver fails to release it, glutin drops the context, and every
+    /// later call needing it fails with [glutin::error::ErrorKind::ContextLost].
+    pub fn make_not_current(&self) -> Result<(), ContextError> {
+        use glutin::prelude::*;
+        let mut slot = self.glutin_context.borrow_mut();
+        match slot.take() {
+            Some(context) if !context.is_current() => {
+                *slot = Some(context);
+                Ok(())
+            }
+            Some(context) => {
+                let context = context
+                    .make_not_current()
+                    .map_err(ContextError::MakeCurrent)?;
+                *slot = Some(context.treat_as_possibly_current());
+                Ok(())
+            }
+            None => Err(ContextError::MakeCurrent(context_lost())),
+        }
+    }
+
+    /// Whether this context is current on the calling thread.
+    pub fn is_current(&self) -> bool {
+        use glutin::prelude::*;
+        self.glutin_context
+            .borrow()
+            .as_ref()
+            .is_some_and(|context| context.is_current())
     }
 
-    /// Make this context current. Needed when using multiple windows (contexts) on native.
-    pub fn _make_current(&self) -> Result<(), WindowError> {
-        Ok(self.glutin_context.make_current(&self.surface)?)
+    fn with_glutin_context<T>(
+        &self,
+        f: impl FnOnce(&glutin::context::PossiblyCurrentContext) -> glutin::error::Result<T>,
+    ) -> glutin::error::Result<T> {
+        match &*self.glutin_context.borrow() {
+            Some(context) => f(context),
+            None => Err(context_lost()),
+        }
+    }
+
+    /// Runs `render` against the screen and presents the result with
+    /// [WindowedContext::swap_buffers], the whole frame in one call.
+    ///
+    /// If `render` panics, the default framebuffer is bound again and scissoring disabled
+    /// while unwinding, so a caller that catches the panic can keep rendering; nothing is
+    /// presented for that frame.
+    #[must_use = "presenting fails when the surface is lost, which should be reported"]
+    pub fn present(&self, render: impl FnOnce(&RenderTarget<'_>)) -> Result<(), ContextError> {
+        struct Reset<'a>(&'a Context);
+        impl Drop for Reset<'_> {
+            fn drop(&mut self) {
+                if std::thread::panicking() {
+                    unsafe {
+                        self.0.bind_framebuffer(three_d::context::FRAMEBUFFER, None);
+                        self.0.disable(three_d::context::SCISSOR_TEST);
+                    }
+                }
+            }
+        }
+        let (width, height) = self.surface_size();
+        let reset = Reset(&self.context);
+        render(&RenderTarget::screen(&self.context, width, height));
+        drop(reset);
+        self.swap_buffers()
+    }
+
+    /// Sends the GL commands issued so far to the driver without waiting for them, so the GPU
+    /// starts on them now rather than when its queue fills up or the frame is presented. For
+    /// lowest latency when the CPU has other work to do before [WindowedContext::swap_buffers].
+    pub fn flush(&self) {
+        unsafe { self.context.flush() };
+    }
+
+    /// Blocks until the GPU has completed every GL command issued so far.
+    ///
+    /// Before handing a frame to something outside of GL, like a screen capture or a
+    /// compositor that shows partial frames, this guarantees it is complete. It stalls the
+    /// CPU until the GPU is idle, so in the normal render loop it costs throughput and is
+    /// only done on request, see [WindowedContext::set_finish_before_swap]. The readbacks of
+    /// this crate finish first on their own.
+    pub fn finish(&self) {
+        unsafe { self.context.finish() };
+    }
+
+    pub fn finish_before_swap(&self) -> bool {
+        self.finish_before_swap.get()
+    }
+
+    /// Makes [WindowedContext::swap_buffers] [finish](WindowedContext::finish) first, for
+    /// compositors or capture tools that tear or pick up partial frames. Off by default.
+    pub fn set_finish_before_swap(&self, enabled: bool) {
+        self.finish_before_swap.set(enabled);
     }
 
     /// Swap buffers - should always be called after rendering.
-    pub fn swap_buffers(&self) -> Result<(), WindowError> {
-        Ok(self.surface.swap_buffers(&self.glutin_context)?)
+    ///
+    /// A single-buffered surface has nothing to swap, the pending commands are flushed so
+    /// they reach the screen instead.
+    pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        log_trace!("swap buffers");
+        if self.finish_before_swap.get() {
+            self.finish();
+        }
+        if !self.surface_info.double_buffered {
+            self.flush();
+            return Ok(());
+        }
+        self.with_glutin_context(|context| self.surface.swap_buffers(context))
+            .map_err(ContextError::SwapBuffers)
     }
 
-    /// Enables or disabled vsync.
-    pub fn _set_vsync(&self, enabled: bool) -> Result<(), WindowError> {
-        let swap_interval = if enabled {
-            glutin::surface::SwapInterval::Wait(std::num::NonZeroU32::new(1).unwrap())
-        } else {
-            glutin::surface::SwapInterval::DontWait
+    /// The vsync mode in effect, which is [VsyncMode::On] where [VsyncMode::Adaptive] was
+    /// requested but isn't supported.
+    pub fn vsync_mode(&self) -> VsyncMode {
+        self.vsync_mode.get()
+    }
+
+    /// Switches the vsync mode and returns the one in effect afterwards, see
+    /// [WindowedContext::vsync_mode].
+    ///
+    /// An unsupported [VsyncMode::Adaptive] isn't an error, it falls back to [VsyncMode::On]
+    /// and is logged; compare the result to tell. On error the previous mode stays.
+    pub fn set_vsync_mode(&self, mode: VsyncMode) -> Result<VsyncMode, ContextError> {
+        if mode == VsyncMode::Adaptive {
+            let supported = self
+                .with_glutin_context(|_| Ok(self.set_adaptive_swap_interval()))
+                .map_err(ContextError::SwapInterval)?;
+            if supported {
+                self.vsync_mode.set(mode);
+                return Ok(mode);
+            }
+        }
+        let (applied, swap_interval) = match mode.swap_interval() {
+            Some(swap_interval) => (mode, swap_interval),
+            None => {
+                log_warn!(requested = ?mode, "adaptive vsync unsupported, using vsync");
+                let applied = VsyncMode::On;
+                (applied, applied.swap_interval().unwrap())
+            }
+        };
+        self.with_glutin_context(|context| self.surface.set_swap_interval(context, swap_interval))
+            .map_err(ContextError::SwapInterval)?;
+        self.vsync_mode.set(applied);
+        Ok(applied)
+    }
+
+    /// Sets the swap interval to -1 through `GLX_EXT_swap_control_tear` or
+    /// `WGL_EXT_swap_control_tear`, calling the platform directly as glutin can't. Returns
+    /// whether the display offers it, which EGL and CGL never do.
+    fn set_adaptive_swap_interval(&self) -> bool {
+        use glutin::display::{Display, GetDisplayExtensions, GetGlDisplay};
+        use glutin::prelude::GlDisplay;
+
+        let display = self.surface.display();
+        match &display {
+            #[cfg(target_os = "linux")]
+            Display::Glx(glx) if glx.extensions().contains("GLX_EXT_swap_control_tear") => {
+                use glutin::display::{AsRawDisplay, RawDisplay};
+
+                let (RawDisplay::Glx(raw_display), RawSurface::Glx(drawable)) =
+                    (glx.raw_display(), self.surface.raw_surface())
+                else {
+                    return false;
+                };
+                let pointer = display.get_proc_address(c"glXSwapIntervalEXT");
+                if pointer.is_null() {
+                    return false;
+                }
+                type SwapIntervalExt = unsafe extern "C" fn(*const std::ffi::c_void, u64, i32);
+                // SAFETY: a non-null address for glXSwapIntervalEXT is that function,
+                // `void(Display *, GLXDrawable, int)`, and both handles belong to this surface.
+                unsafe {
+                    let swap_interval =
+                        std::mem::transmute::<*const std::ffi::c_void, SwapIntervalExt>(pointer);
+                    swap_interval(raw_display, drawable, -1);
+                }
+                true
+            }
+            #[cfg(target_os = "windows")]
+            Display::Wgl(wgl) if wgl.extensions().contains("WGL_EXT_swap_control_tear") => {
+                let pointer = display.get_proc_address(c"wglSwapIntervalEXT");
+                if pointer.is_null() {
+                    return false;
+                }
+                type SwapIntervalExt = unsafe extern "system" fn(i32) -> i32;
+                // SAFETY: a non-null address for wglSwapIntervalEXT is that function,
+                // `BOOL(int)`, and it applies to the context made current by the caller.
+                unsafe {
+                    let swap_interval =
+                        std::mem::transmute::<*const std::ffi::c_void, SwapIntervalExt>(pointer);
+                    swap_interval(-1) != 0
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Enables or disables vsync, see [WindowedContext::set_vsync_mode].
+    pub fn set_vsync(&self, enabled: bool) -> Result<(), ContextError> {
+        self.set_vsync_mode(VsyncMode::from_enabled(enabled))
+            .map(|_| ())
+    }
+}
+
+fn context_lost() -> glutin::error::Error {
+    glutin::error::ErrorKind::ContextLost.into()
+}
+
+#[cfg(feature = "gl-debug")]
+fn gl_error_name(error: u32) -> String {
+    use three_d::context as gl;
+    match error {
+        gl::INVALID_ENUM => "GL_INVALID_ENUM".to_string(),
+        gl::INVALID_VALUE => "GL_INVALID_VALUE".to_string(),
+        gl::INVALID_OPERATION => "GL_INVALID_OPERATION".to_string(),
+        gl::INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION".to_string(),
+        gl::OUT_OF_MEMORY => "GL_OUT_OF_MEMORY".to_string(),
+        gl::STACK_UNDERFLOW => "GL_STACK_UNDERFLOW".to_string(),
+        gl::STACK_OVERFLOW => "GL_STACK_OVERFLOW".to_string(),
+        unknown => format!("unknown error {unknown:#06x}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The debug output of the config template the builder queries the display with, the only
+    /// way to look at its fields from outside `glutin`.
+    fn config_template(builder: &WindowedContextBuilder, multisamples: u8) -> String {
+        let handle = raw_window_handle::XlibWindowHandle::empty();
+        let template = builder.config_template(
+            raw_window_handle::RawWindowHandle::Xlib(handle),
+            multisamples,
+        );
+        format!("{template:?}")
+    }
+
+    #[test]
+    fn sample_counts_round_down_to_a_setting() {
+        assert_eq!(MsaaSamples::at_most(0), MsaaSamples::Off);
+        assert_eq!(MsaaSamples::at_most(3), MsaaSamples::X2);
+        assert_eq!(MsaaSamples::at_most(4), MsaaSamples::X4);
+        assert_eq!(MsaaSamples::at_most(12), MsaaSamples::X8);
+        assert_eq!(MsaaSamples::at_most(32), MsaaSamples::X16);
+    }
+
+    #[test]
+    fn logical_sizes_round_to_the_nearest_physical_pixel() {
+        let physical = |width, height, scale_factor| {
+            let size = physical_size(LogicalSize::new(width, height), scale_factor);
+            (size.width, size.height)
+        };
+        assert_eq!(physical(800.0, 600.0, 1.0), (800, 600));
+        assert_eq!(physical(801.0, 601.0, 1.25), (1001, 751));
+        assert_eq!(physical(333.0, 101.0, 1.5), (500, 152));
+        assert_eq!(physical(640.5, 480.25, 2.0), (1281, 961));
+    }
+
+    #[test]
+    fn desktop_and_es_version_strings_parse() {
+        let version = |es, major, minor| Some(GlVersion { es, major, minor });
+        assert_eq!(
+            GlVersion::parse("4.6.0 NVIDIA 535.54"),
+            version(false, 4, 6)
+        );
+        assert_eq!(
+            GlVersion::parse("3.3 (Core Profile) Mesa 23.1"),
+            version(false, 3, 3)
+        );
+        assert_eq!(GlVersion::parse("2.1 Mesa 20.0"), version(false, 2, 1));
+        assert_eq!(
+            GlVersion::parse("OpenGL ES 3.2 Mesa 23.1"),
+            version(true, 3, 2)
+        );
+        assert_eq!(
+            GlVersion::parse("OpenGL ES 3.0 (ANGLE 2.1)"),
+            version(true, 3, 0)
+        );
+        assert_eq!(GlVersion::parse("OpenGL ES-CM 1.1"), version(true, 1, 1));
+        assert_eq!(GlVersion::parse(""), None);
+        assert_eq!(GlVersion::parse("OpenGL ES"), None);
+        assert_eq!(GlVersion::parse("unknown"), None);
+    }
+
+    #[test]
+    fn versions_below_the_requirement_are_unsupported() {
+        let supported = |version: &str| GlVersion::parse(version).unwrap().is_supported();
+        assert!(supported("3.3.0"));
+        assert!(!supported("3.2.0"));
+        assert!(supported("OpenGL ES 3.0"));
+        assert!(!supported("OpenGL ES 2.0"));
+        assert_eq!(GlVersion::required(true).to_string(), "OpenGL ES 3.0");
+        assert_eq!(GlVersion::required(false).to_string(), "OpenGL 3.3");
+    }
+
+    #[test]
+    fn builder_settings_reach_the_config_template() {
+        let builder = WindowedContextBuilder::from_settings(SurfaceSettings::default())
+            .vsync(false)
+            .msaa(MsaaSamples::X4)
+            .depth_bits(32)
+            .stencil_bits(0)
+            .transparent(true)
+            .hardware_acceleration(three_d::HardwareAcceleration::Required);
+        let settings = builder.settings();
+        assert!(!settings.vsync);
+        assert_eq!(settings.multisamples, 4);
+        assert_eq!(settings.depth_buffer, 32);
+        assert_eq!(settings.stencil_buffer, 0);
+
+        let template = config_template(&builder, settings.multisamples);
+        assert!(template.contains("depth_size: 32"), "{template}");
+        assert!(template.contains("stencil_size: 0"), "{template}");
+        assert!(template.contains("alpha_size: 8"), "{template}");
+        assert!(template.contains("num_samples: Some(4)"), "{template}");
+        assert!(template.contains("transparency: true"), "{template}");
+        assert!(
+            template.contains("hardware_accelerated: Some(true)"),
+            "{template}"
+        );
+    }
+
+    #[test]
+    fn only_opaque_standalone_windows_go_without_alpha() {
+        for (window_mode, transparent, alpha_bits) in [
+            (WindowMode::Child, false, 8),
+            (WindowMode::Child, true, 8),
+            (WindowMode::Standalone, false, 0),
+            (WindowMode::Standalone, true, 8),
+        ] {
+            let builder = WindowedContextBuilder::new()
+                .window_mode(window_mode)
+                .transparent(transparent);
+            assert_eq!(
+                builder.alpha_bits(),
+                alpha_bits,
+                "{window_mode:?}, {transparent}"
+            );
+        }
+    }
+
+    #[test]
+    fn msaa_maps_to_sample_counts() {
+        for (samples, count) in [
+            (MsaaSamples::Off, 0),
+            (MsaaSamples::X2, 2),
+            (MsaaSamples::X4, 4),
+            (MsaaSamples::X8, 8),
+            (MsaaSamples::X16, 16),
+        ] {
+            let builder =
+                WindowedContextBuilder::from_settings(SurfaceSettings::default()).msaa(samples);
+            assert_eq!(builder.settings().multisamples, count);
+        }
+    }
+
+    #[test]
+    fn software_renderers_are_recognized() {
+        assert!(is_software_renderer("llvmpipe (LLVM 15.0.7, 256 bits)"));
+        assert!(is_software_renderer("Google SwiftShader"));
+        assert!(is_software_renderer("Microsoft Basic Render Driver"));
+        assert!(!is_software_renderer("NVIDIA GeForce RTX 3070/PCIe/SSE2"));
+        assert!(!is_software_renderer(
+            "Mesa Intel(R) UHD Graphics 620 (KBL GT2)"
+        ));
+    }
+
+    #[test]
+    fn required_acceleration_on_a_software_renderer_is_an_error() {
+        use three_d::HardwareAcceleration;
+
+        let result = check_hardware_acceleration(HardwareAcceleration::Required, true, "llvmpipe");
+        assert!(matches!(
+            result,
+            Err(ContextError::HardwareAccelerationUnavailable { ref renderer })
+                if renderer == "llvmpipe"
+        ));
+        let result = check_hardware_acceleration(HardwareAcceleration::Required, false, "Radeon");
+        assert!(matches!(
+            result,
+            Err(ContextError::HardwareAccelerationUnavailable { .. })
+        ));
+        let result = check_hardware_acceleration(HardwareAcceleration::Preferred, true, "llvmpipe");
+        assert!(matches!(result, Ok(false)));
+        let result = check_hardware_acceleration(HardwareAcceleration::Required, true, "Radeon");
+        assert!(matches!(result, Ok(true)));
+    }
+
+    #[test]
+    fn only_required_acceleration_falls_back_to_software() {
+        use three_d::HardwareAcceleration;
+
+        let error = ContextError::HardwareAccelerationUnavailable {
+            renderer: "llvmpipe".to_string(),
+        };
+        let builder = WindowedContextBuilder::from_settings(SurfaceSettings::default())
+            .hardware_acceleration(HardwareAcceleration::Required)
+            .software_fallback(true);
+        assert!(builder.falls_back_on(&error));
+        assert!(!builder.falls_back_on(&ContextError::InvalidNumberOfMsaaSamples(3)));
+        let builder = builder.hardware_acceleration(HardwareAcceleration::Preferred);
+        assert!(!builder.falls_back_on(&error));
+        let builder = builder
+            .hardware_acceleration(HardwareAcceleration::Required)
+            .software_fallback(false);
+        assert!(!builder.falls_back_on(&error));
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn hardware_acceleration_matches_the_renderer() {
+        let harness = crate::testing::TestHarness::new(8, 8).unwrap();
+        let context = harness.context();
+        let renderer = unsafe { context.get_parameter_string(three_d::context::RENDERER) };
+        if is_software_renderer(&renderer) {
+            assert!(!context.is_hardware_accelerated());
+        }
+    }
+
+    /// Records the sizes it is notified of.
+    #[derive(Default)]
+    struct SizeRecorder(Vec<(u32, u32)>);
+
+    impl ResizeListener for SizeRecorder {
+        fn resized(&mut self, width: u32, height: u32) {
+            self.0.push((width, height));
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn listeners_receive_each_new_size_once() {
+        let harness = crate::testing::TestHarness::new(64, 48).unwrap();
+        let context = harness.context();
+        let listener = Rc::new(RefCell::new(SizeRecorder::default()));
+        context.add_resize_listener(&listener);
+        context.resize(PhysicalSize::new(32, 16));
+        context.resize(PhysicalSize::new(0, 0));
+        context.resize(PhysicalSize::new(40, 0));
+        context.resize(PhysicalSize::new(48, 24));
+        assert_eq!(listener.borrow().0, [(32, 16), (48, 24)]);
+
+        drop(listener);
+        context.resize(PhysicalSize::new(64, 48));
+        assert!(context.resize_listeners.borrow().is_empty());
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn size_follows_resize() {
+        let harness = crate::testing::TestHarness::new(64, 48).unwrap();
+        let context = harness.context();
+        assert_eq!(context.size(), (64, 48));
+        context.resize(PhysicalSize::new(32, 16));
+        assert_eq!(context.size(), (32, 16));
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn surface_size_matches_the_requested_size_after_resize() {
+        let harness = crate::testing::TestHarness::new(64, 48).unwrap();
+        let context = harness.context();
+        assert_eq!(context.surface_size(), (64, 48));
+        for (width, height) in [(32, 16), (1, 40), (80, 80)] {
+            context.resize(PhysicalSize::new(width, height));
+            assert_eq!(context.surface_size(), (width, height));
+            assert_eq!(context.viewport(), Viewport::new_at_origo(width, height));
+            let image = harness.render(|_| {});
+            assert_eq!((image.width, image.height), (width, height));
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn renderer_info_is_read_after_creation() {
+        let harness = crate::testing::TestHarness::new(8, 8).unwrap();
+        let info = harness.context().renderer_info();
+        assert!(!info.renderer.is_empty());
+        let version = GlVersion::parse(&info.version);
+        assert!(
+            version.is_some_and(|version| version.is_supported()),
+            "{info:?}"
+        );
+        assert_eq!(info, &RendererInfo::query(harness.context()));
+    }
+
+    #[test]
+    fn vsync_modes_map_to_swap_intervals() {
+        assert_eq!(VsyncMode::from_enabled(false), VsyncMode::Off);
+        assert_eq!(VsyncMode::from_enabled(true), VsyncMode::On);
+        assert!(VsyncMode::Adaptive.is_enabled() && !VsyncMode::Off.is_enabled());
+        assert_eq!(VsyncMode::Off.swap_interval(), Some(SwapInterval::DontWait));
+        assert!(matches!(
+            VsyncMode::On.swap_interval(),
+            Some(SwapInterval::Wait(interval)) if interval.get() == 1
+        ));
+        // Set through the tear extension instead.
+        assert_eq!(VsyncMode::Adaptive.swap_interval(), None);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn vsync_mode_tracks_what_was_applied() {
+        let harness = crate::testing::TestHarness::new(8, 8).unwrap();
+        let context = harness.context();
+        for mode in [VsyncMode::Off, VsyncMode::On] {
+            assert_eq!(context.set_vsync_mode(mode).unwrap(), mode);
+            assert_eq!(context.vsync_mode(), mode);
+        }
+        // Without the tear extension, adaptive falls back to plain vsync.
+        let applied = context.set_vsync_mode(VsyncMode::Adaptive).unwrap();
+        assert!(matches!(applied, VsyncMode::Adaptive | VsyncMode::On));
+        assert_eq!(context.vsync_mode(), applied);
+        context.set_vsync(false).unwrap();
+        assert_eq!(context.vsync_mode(), VsyncMode::Off);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn extension_queries_answer_for_listed_and_bogus_names() {
+        let harness = crate::testing::TestHarness::new(8, 8).unwrap();
+        let context = harness.context();
+        let extensions = context.extensions();
+        assert!(extensions.windows(2).all(|pair| pair[0] <= pair[1]));
+        for extension in &extensions {
+            assert!(context.supports_extension(extension), "{extension}");
+        }
+        assert!(!context.supports_extension("GL_BOGUS_not_an_extension"));
+        assert!(!context.supports_extension(""));
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn finished_readbacks_return_the_whole_frame_every_time() {
+        use crate::lights::ShadowSettings;
+        use crate::scene::tests::shadow_scene;
+        use crate::testing::{diff, ImageDiff};
+
+        let harness = crate::testing::TestHarness::new(64, 64).unwrap();
+        let context = harness.context();
+        let mut scene = shadow_scene(context);
+        assert!(scene
+            .lights_mut()
+            .enable_shadows(1, ShadowSettings::default()));
+        let first = harness.render_scene(&mut scene, 0.0);
+        for _ in 0..3 {
+            let again = harness.render_scene(&mut scene, 0.0);
+            assert_eq!(diff(&again, &first, 0), ImageDiff::default());
+        }
+        // Two units right of the cube lies in its shadow, two units left doesn't.
+        assert_ne!(first.pixel(48, 32), first.pixel(16, 32));
+
+        assert!(!context.finish_before_swap());
+        context.set_finish_before_swap(true);
+        context.flush();
+        context.swap_buffers().unwrap();
+        context.set_finish_before_swap(false);
+    }
+
+    #[cfg(feature = "gl-debug")]
+    #[test]
+    fn gl_errors_have_readable_names() {
+        use three_d::context as gl;
+
+        assert_eq!(gl_error_name(gl::INVALID_ENUM), "GL_INVALID_ENUM");
+        assert_eq!(gl_error_name(gl::OUT_OF_MEMORY), "GL_OUT_OF_MEMORY");
+        assert_eq!(gl_error_name(0x1234), "unknown error 0x1234");
+    }
+
+    #[cfg(feature = "gl-debug")]
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn invalid_enum_is_reported_with_the_call_site() {
+        let harness = crate::testing::TestHarness::new(8, 8).unwrap();
+        let context = harness.context();
+        context.debug_check_errors();
+
+        unsafe { context.enable(0xffff) };
+        let check = std::panic::AssertUnwindSafe(|| context.debug_check_errors());
+        let panic = std::panic::catch_unwind(check).unwrap_err();
+        let message = panic.downcast_ref::<String>().unwrap();
+        assert!(message.contains("GL_INVALID_ENUM"), "{message}");
+        assert!(message.contains(file!()), "{message}");
+        // Reading the error cleared it.
+        context.debug_check_errors();
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn logical_resize_keeps_the_scale_factor_in_sync() {
+        let harness = crate::testing::TestHarness::new(64, 48).unwrap();
+        let context = harness.context();
+        context.resize_from_logical(LogicalSize::new(41.0, 31.0), 1.5);
+        assert_eq!(context.scale_factor(), 1.5);
+        assert_eq!(context.size(), (62, 47));
+        let whole = UiRect {
+            x: 0.0,
+            y: 0.0,
+            width: 41.0,
+            height: 31.0,
+        };
+        let viewport = context.viewport_for(whole);
+        assert_eq!((viewport.width, viewport.height), (62, 47));
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn surface_reports_the_buffering_it_got() {
+        use dioxus::desktop::tao::window::WindowBuilder;
+
+        let event_loop = crate::testing::new_event_loop();
+        let build = |single_buffer| {
+            let window = WindowBuilder::new()
+                .with_visible(false)
+                .with_inner_size(PhysicalSize::new(8, 8))
+                .build(&event_loop)
+                .unwrap();
+            let context = WindowedContextBuilder::new()
+                .hardware_acceleration(three_d::HardwareAcceleration::Preferred)
+                .single_buffer(single_buffer)
+                .build_from_tao_window(&window);
+            (context.unwrap(), window)
+        };
+        let (double, _window) = build(false);
+        assert!(double.surface_info().double_buffered);
+        assert_eq!(double.surface_info().samples, double.samples());
+        drop(double);
+
+        // Granted or not, presenting works, by flushing or by swapping.
+        let (single, _window) = build(true);
+        single.swap_buffers().unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn clear_region_counts_from_the_top() {
+        use crate::testing::assert_pixel_near;
+
+        let harness = crate::testing::TestHarness::new(32, 32).unwrap();
+        let context = harness.context();
+        let region = Viewport {
+            x: 8,
+            y: 4,
+            width: 16,
+            height: 8,
+        };
+        let image = harness.render(|screen| {
+            screen.clear(ClearState::color(0.0, 1.0, 0.0, 1.0));
+            context.clear_region(region, ClearState::color(1.0, 0.0, 0.0, 1.0));
+        });
+        let (red, green) = ([255, 0, 0, 255], [0, 255, 0, 255]);
+        for (x, y) in [(8, 4), (23, 4), (8, 11), (23, 11)] {
+            assert_pixel_near(&image, x, y, red, 0);
+        }
+        for (x, y) in [(7, 4), (8, 3), (24, 11), (23, 12), (16, 20)] {
+            assert_pixel_near(&image, x, y, green, 0);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn graphics_settings_survive_a_serde_round_trip() {
+        let settings = GraphicsSettings {
+            vsync: false,
+            msaa: MsaaSamples::X8,
+            depth_bits: 32,
+            stencil_bits: 0,
+            backend_preference: BackendPreference::Egl,
         };
-        Ok(self
-            .surface
-            .set_swap_interval(&self.glutin_context, swap_interval)?)
+        let saved = serde_json::to_string(&settings).unwrap();
+        let restored: GraphicsSettings = serde_json::from_str(&saved).unwrap();
+        assert_eq!(restored, settings);
+        // Settings saved by an older version lack the newer fields.
+        let restored: GraphicsSettings = serde_json::from_str(r#"{"vsync":false}"#).unwrap();
+        assert_eq!(
+            restored,
+            GraphicsSettings {
+                vsync: false,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn graphics_settings_report_the_applied_config() {
+        let harness = crate::testing::TestHarness::new(16, 16).unwrap();
+        let context = harness.context();
+        let settings = context.graphics_settings();
+        assert_eq!(settings.vsync, context.vsync_mode().is_enabled());
+        let samples = context.settings().multisamples as u32;
+        assert_eq!(settings.msaa, MsaaSamples::at_most(samples));
+        assert_eq!(settings.depth_bits, context.settings().depth_buffer);
+        assert_eq!(settings.stencil_bits, context.settings().stencil_buffer);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn present_runs_the_frame_and_recovers_from_a_panic() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let harness = crate::testing::TestHarness::new(16, 16).unwrap();
+        let context = harness.context();
+        let mut called = false;
+        let presented = context.present(|screen| {
+            screen.clear(ClearState::color(0.0, 0.0, 1.0, 1.0));
+            called = true;
+        });
+        assert!(presented.is_ok());
+        assert!(called);
+
+        let panicked = catch_unwind(AssertUnwindSafe(|| {
+            context.present(|_| {
+                unsafe { context.enable(three_d::context::SCISSOR_TEST) };
+                panic!("render failed");
+            })
+        }));
+        assert!(panicked.is_err());
+        assert!(!unsafe { context.is_enabled(three_d::context::SCISSOR_TEST) });
+        assert!(context.present(|_| {}).is_ok());
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn required_acceleration_falls_back_to_software_where_there_is_no_gpu() {
+        use dioxus::desktop::tao::window::WindowBuilder;
+        use three_d::HardwareAcceleration;
+
+        let event_loop = crate::testing::new_event_loop();
+        let window = WindowBuilder::new()
+            .with_visible(false)
+            .with_inner_size(PhysicalSize::new(8, 8))
+            .build(&event_loop)
+            .unwrap();
+        let builder = WindowedContextBuilder::new()
+            .hardware_acceleration(HardwareAcceleration::Required)
+            .software_fallback(true);
+        let context = builder.build_from_tao_window(&window).unwrap();
+        assert_eq!(
+            context.fell_back_to_software(),
+            !context.is_hardware_accelerated()
+        );
+        if context.fell_back_to_software() {
+            drop(context);
+            let required = WindowedContextBuilder::new()
+                .hardware_acceleration(HardwareAcceleration::Required)
+                .build_from_tao_window(&window);
+            assert!(matches!(
+                required,
+                Err(ContextError::HardwareAccelerationUnavailable { .. })
+            ));
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn reported_depth_and_stencil_bits_meet_the_requested_minimum() {
+        use dioxus::desktop::tao::window::WindowBuilder;
+
+        let event_loop = crate::testing::new_event_loop();
+        let window = WindowBuilder::new()
+            .with_visible(false)
+            .with_inner_size(PhysicalSize::new(8, 8))
+            .build(&event_loop)
+            .unwrap();
+        for (depth, stencil, preferred) in [(16, 0, 0), (24, 0, 32), (24, 8, 32)] {
+            let builder = WindowedContextBuilder::new()
+                .depth_bits(depth)
+                .stencil_bits(stencil)
+                .prefer_depth_bits(preferred);
+            // Not every driver has a config for each combination.
+            let Ok(context) = builder.build_from_tao_window(&window) else {
+                continue;
+            };
+            let info = context.surface_info();
+            assert!(info.depth_bits >= depth, "{info:?}");
+            assert!(info.stencil_bits >= stencil, "{info:?}");
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn context_created_callbacks_run_on_creation_and_after_reinitializing() {
+        use dioxus::desktop::tao::window::WindowBuilder;
+
+        let event_loop = crate::testing::new_event_loop();
+        let window = WindowBuilder::new()
+            .with_visible(false)
+            .with_inner_size(PhysicalSize::new(8, 8))
+            .build(&event_loop)
+            .unwrap();
+        let mut context = WindowedContextBuilder::new()
+            .build_from_tao_window(&window)
+            .unwrap();
+        let versions = Rc::new(RefCell::new(Vec::new()));
+        let recorded = versions.clone();
+        context
+            .on_context_created(move |context| {
+                // Only answers with the context current.
+                let version = unsafe { context.get_parameter_string(three_d::context::VERSION) };
+                recorded.borrow_mut().push(version);
+            })
+            .unwrap();
+        assert_eq!(versions.borrow().len(), 1);
+
+        context.reinitialize(&window).unwrap();
+        assert_eq!(versions.borrow().len(), 2);
+        assert!(versions.borrow().iter().all(|version| !version.is_empty()));
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn contexts_take_turns_being_current() {
+        use dioxus::desktop::tao::window::WindowBuilder;
+
+        let event_loop = crate::testing::new_event_loop();
+        let window = || {
+            WindowBuilder::new()
+                .with_visible(false)
+                .with_inner_size(PhysicalSize::new(8, 8))
+                .build(&event_loop)
+                .unwrap()
+        };
+        let (first_window, second_window) = (window(), window());
+        let build = |window| WindowedContextBuilder::new().build_from_tao_window(window);
+        let (first, second) = (
+            build(&first_window).unwrap(),
+            build(&second_window).unwrap(),
+        );
+        for _ in 0..3 {
+            first.make_current().unwrap();
+            assert!(first.is_current() && !second.is_current());
+            first.make_not_current().unwrap();
+            assert!(!first.is_current() && !second.is_current());
+            // Releasing again does nothing.
+            first.make_not_current().unwrap();
+            second.make_not_current().unwrap();
+
+            second.make_current().unwrap();
+            assert!(second.is_current() && !first.is_current());
+            second.make_not_current().unwrap();
+        }
+        first.make_current().unwrap();
+        first.swap_buffers().unwrap();
+    }
+
+    #[test]
+    fn degenerate_sizes_are_filtered() {
+        assert!(is_renderable_size(PhysicalSize::new(1, 1)));
+        assert!(!is_renderable_size(PhysicalSize::new(0, 600)));
+        assert!(!is_renderable_size(PhysicalSize::new(800, 0)));
+        assert_eq!(sanitize_size(PhysicalSize::new(0, 0), (4096, 4096)), (1, 1));
+        assert_eq!(
+            sanitize_size(PhysicalSize::new(10000, 300), (4096, 4096)),
+            (4096, 300)
+        );
+    }
+
+    #[test]
+    fn one_pixel_wide_sizes_are_renderable_as_they_are() {
+        assert!(is_renderable_size(PhysicalSize::new(1, 900)));
+        assert_eq!(
+            sanitize_size(PhysicalSize::new(1, 900), (4096, 4096)),
+            (1, 900)
+        );
+        assert_eq!(
+            sanitize_size(PhysicalSize::new(900, 1), (4096, 4096)),
+            (900, 1)
+        );
+        assert_eq!(
+            sanitize_size(PhysicalSize::new(u32::MAX, u32::MAX), (8192, 4096)),
+            (8192, 4096)
+        );
+        // A broken limit still leaves a pixel to render into.
+        assert_eq!(sanitize_size(PhysicalSize::new(5, 5), (0, 0)), (1, 1));
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn degenerate_resizes_keep_the_size_and_large_ones_are_clamped() {
+        let harness = crate::testing::TestHarness::new(16, 16).unwrap();
+        let context = harness.context();
+        context.resize(PhysicalSize::new(0, 0));
+        context.resize(PhysicalSize::new(0, 24));
+        assert_eq!(context.surface_size(), (16, 16));
+        assert_eq!(context.viewport(), Viewport::new_at_origo(16, 16));
+
+        context.resize(PhysicalSize::new(u32::MAX, 8));
+        let (max_width, _) = context.max_viewport_size();
+        assert_eq!(context.surface_size(), (max_width, 8));
+        assert!(max_width >= 1024);
+    }
+
+    #[test]
+    fn sample_requests_beyond_the_maximum_are_clamped_to_it() {
+        assert_eq!(clamp_samples(16, 8), 8);
+        assert_eq!(clamp_samples(16, 6), 4);
+        assert_eq!(clamp_samples(4, 1), 0);
+        assert_eq!(clamp_samples(4, 0), 0);
+        assert_eq!(clamp_samples(4, 16), 4);
+        assert_eq!(clamp_samples(0, 0), 0);
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn over_large_msaa_requests_are_clamped_instead_of_failing() {
+        use dioxus::desktop::tao::window::WindowBuilder;
+
+        let event_loop = crate::testing::new_event_loop();
+        let window = WindowBuilder::new()
+            .with_visible(false)
+            .with_inner_size(PhysicalSize::new(8, 8))
+            .build(&event_loop)
+            .unwrap();
+        let context = WindowedContextBuilder::new()
+            .msaa(MsaaSamples::X16)
+            .build_from_tao_window(&window)
+            .unwrap();
+        let info = context.surface_info();
+        let max = crate::render_texture::max_samples(&context);
+        assert!(info.samples as u32 <= max, "{info:?}, at most {max}");
+        assert!(info.requested_samples <= 16, "{info:?}");
+        if max < 16 {
+            assert_eq!(
+                info.requested_samples,
+                MsaaSamples::at_most(max).count(),
+                "{info:?}"
+            );
+        }
+        assert_eq!(context.settings().multisamples, info.samples);
+    }
+
+    /// Looks variables up in `vars` instead of the process environment.
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        |name| {
+            vars.iter()
+                .find(|(variable, _)| *variable == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn env_overrides_change_the_settings_unless_set_explicitly() {
+        let vars = [
+            ("DNG_BACKEND", "EGL"),
+            ("DNG_VSYNC", "off"),
+            ("DNG_MSAA", " 8 "),
+        ];
+        let overrides = EnvOverrides::from_lookup(env(&vars));
+        assert_eq!(
+            overrides,
+            EnvOverrides {
+                backend_preference: Some(BackendPreference::Egl),
+                vsync: Some(false),
+                msaa: Some(MsaaSamples::X8),
+            }
+        );
+        assert_eq!(EnvOverrides::from_lookup(env(&[])), EnvOverrides::default());
+
+        let builder = WindowedContextBuilder::new().env_overrides(overrides);
+        assert_eq!(builder.backend_preference, BackendPreference::Egl);
+        assert!(!builder.settings().vsync);
+        assert_eq!(builder.settings().multisamples, 8);
+        // Options set in code afterwards take precedence.
+        let builder = builder.vsync(true).msaa(MsaaSamples::X2);
+        assert!(builder.settings().vsync);
+        assert_eq!(builder.settings().multisamples, 2);
+        // And so do those set before, even to their default values.
+        let builder = WindowedContextBuilder::new()
+            .vsync(true)
+            .backend_preference(BackendPreference::PlatformDefault)
+            .env_overrides(overrides);
+        assert_eq!(
+            builder.backend_preference,
+            BackendPreference::PlatformDefault
+        );
+        assert!(builder.settings().vsync);
+        assert_eq!(builder.settings().multisamples, 8);
+
+        // As in `from_tao_window`: given settings were chosen, but not the backend.
+        let settings = SurfaceSettings::default();
+        let builder = WindowedContextBuilder::from_settings(settings).env_overrides(overrides);
+        assert_eq!(builder.backend_preference, BackendPreference::Egl);
+        assert_eq!(builder.settings().vsync, settings.vsync);
+        assert_eq!(builder.settings().multisamples, settings.multisamples);
+    }
+
+    #[test]
+    fn malformed_env_overrides_are_ignored() {
+        for (backend, vsync, msaa) in [
+            ("vulkan", "maybe", "3"),
+            ("", "2", "sixteen"),
+            ("egl!", "", "-4"),
+        ] {
+            let vars = [
+                ("DNG_BACKEND", backend),
+                ("DNG_VSYNC", vsync),
+                ("DNG_MSAA", msaa),
+            ];
+            assert_eq!(
+                EnvOverrides::from_lookup(env(&vars)),
+                EnvOverrides::default()
+            );
+        }
+        // Only the malformed one is dropped.
+        let vars = [("DNG_VSYNC", "yes"), ("DNG_MSAA", "16")];
+        let overrides = EnvOverrides::from_lookup(env(&vars));
+        assert_eq!(overrides.vsync, None);
+        assert_eq!(overrides.msaa, Some(MsaaSamples::X16));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn malformed_env_overrides_are_warned_about() {
+        let vars = [("DNG_MSAA", "3"), ("DNG_VSYNC", "0")];
+        let messages = crate::trace::tests::recorded(|| {
+            EnvOverrides::from_lookup(env(&vars));
+        });
+        assert_eq!(messages, ["ignoring malformed environment variable"]);
     }
 }
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use dioxus::desktop::tao::event::{Event, WindowEvent};
+use dioxus::desktop::tao::window::{Window, WindowId};
+
+use crate::error::ContextError;
+use crate::windowed_context::{WindowedContext, WindowedContextBuilder};
+
+/// A 3D view of one window: its context, and whatever renders into it, such as a
+/// [crate::scene::Scene].
+pub struct WindowView<T> {
+    pub context: WindowedContext,
+    pub state: T,
+}
+
+/// One [WindowedContext] per tao window, for apps that open more than one Dioxus window with
+/// a 3D view.
+///
+/// [WindowContexts::handle_event] routes window events to the view of the window they are
+/// for, by the id in the event. Every GL context has its own objects, so each view's state
+/// has to be created against its own context, and that context has to be current while the
+/// state is used; [WindowContexts::make_current] does so.
+pub struct WindowContexts<T> {
+    views: HashMap<WindowId, WindowView<T>>,
+}
+
+impl<T> Default for WindowContexts<T> {
+    fn default() -> Self {
+        Self {
+            views: HashMap::new(),
+        }
+    }
+}
+
+impl<T> WindowContexts<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a context for `window` with `builder` and `state` with `create_state`, while
+    /// the new context is current.
+    ///
+    /// A view the window already had is dropped first.
+    pub fn create(
+        &mut self,
+        window: &Window,
+        builder: WindowedContextBuilder,
+        create_state: impl FnOnce(&WindowedContext) -> T,
+    ) -> Result<&mut WindowView<T>, ContextError> {
+        let id = window.id();
+        self.remove(id);
+        let context = builder.build_from_tao_window(window)?;
+        let state = create_state(&context);
+        log_debug!(window = ?id, "created window context");
+        Ok(self
+            .views
+            .entry(id)
+            .or_insert(WindowView { context, state }))
+    }
+
+    /// Adds a view created elsewhere, replacing and dropping the one `id` had.
+    pub fn insert(&mut self, id: WindowId, view: WindowView<T>) {
+        self.remove(id);
+        self.views.insert(id, view);
+    }
+
+    pub fn get(&self, id: WindowId) -> Option<&WindowView<T>> {
+        self.views.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: WindowId) -> Option<&mut WindowView<T>> {
+        self.views.get_mut(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.views.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.views.is_empty()
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = WindowId> + '_ {
+        self.views.keys().copied()
+    }
+
+    /// Makes the context of `id` current and returns its view, `None` if `id` has none or
+    /// the context could not be made current.
+    pub fn make_current(&mut self, id: WindowId) -> Option<&mut WindowView<T>> {
+        let view = self.views.get_mut(&id)?;
+        match view.context.make_current() {
+            Ok(()) => Some(view),
+            Err(_error) => {
+                log_warn!(window = ?id, error = %_error, "failed to make window context current");
+                None
+            }
+        }
+    }
+
+    /// Drops the view of `id`, state first, with its context current so the state's GL
+    /// objects are deleted from the context they belong to. Returns whether there was one.
+    pub fn remove(&mut self, id: WindowId) -> bool {
+        let Some(view) = self.views.remove(&id) else {
+            return false;
+        };
+        if let Err(_error) = view.context.make_current() {
+            log_warn!(window = ?id, error = %_error, "dropping window state without its context");
+        }
+        let WindowView { context, state } = view;
+        drop(state);
+        drop(context);
+        log_debug!(window = ?id, "dropped window context");
+        true
+    }
+
+    /// Routes `event` to the view of the window it is for.
+    ///
+    /// Resizes resize that window's context, a destroyed window's view is dropped, see
+    /// [WindowContexts::remove], and a redraw request calls `render` with the window's view,
+    /// after making its context current. Returns the id of the window `event` was for, if it
+    /// has a view, so the caller can forward the event, e.g. to that window's
+    /// [crate::events::EventTranslator].
+    pub fn handle_event<E>(
+        &mut self,
+        event: &Event<'_, E>,
+        render: impl FnOnce(&mut WindowView<T>),
+    ) -> Option<WindowId> {
+        match event {
+            Event::RedrawRequested(id) => {
+                render(self.make_current(*id)?);
+                Some(*id)
+            }
+            Event::WindowEvent {
+                window_id, event, ..
+            } => self.handle_window_event(*window_id, event),
+            _ => None,
+        }
+    }
+
+    /// The window event part of [WindowContexts::handle_event].
+    fn handle_window_event(&mut self, id: WindowId, event: &WindowEvent<'_>) -> Option<WindowId> {
+        let view = self.views.get_mut(&id)?;
+        match event {
+            WindowEvent::Resized(size) => view.context.resize(*size),
+            WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                new_inner_size,
+            } => {
+                let logical_size = new_inner_size.to_logical(*scale_factor);
+                view.context
+                    .resize_from_logical(logical_size, *scale_factor);
+            }
+            WindowEvent::Destroyed => {
+                self.remove(id);
+            }
+            _ => {}
+        }
+        Some(id)
+    }
+}
+
+impl<T> Drop for WindowContexts<T> {
+    fn drop(&mut self) {
+        let ids: Vec<WindowId> = self.ids().collect();
+        for id in ids {
+            self.remove(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use dioxus::desktop::tao::dpi::PhysicalSize;
+    use dioxus::desktop::tao::window::WindowBuilder;
+
+    use super::*;
+
+    /// Counts how many views were dropped.
+    struct Dropped(Rc<Cell<usize>>);
+
+    impl Drop for Dropped {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GL context"]
+    fn windows_are_resized_and_closed_independently() {
+        let event_loop = crate::testing::new_event_loop();
+        let window = || {
+            WindowBuilder::new()
+                .with_visible(false)
+                .with_inner_size(PhysicalSize::new(16, 16))
+                .build(&event_loop)
+                .unwrap()
+        };
+        let (first, second) = (window(), window());
+        let dropped = Rc::new(Cell::new(0));
+        let mut contexts = WindowContexts::new();
+        for window in [&first, &second] {
+            let builder = WindowedContextBuilder::new()
+                .hardware_acceleration(three_d::HardwareAcceleration::Preferred);
+            contexts
+                .create(window, builder, |_| Dropped(dropped.clone()))
+                .unwrap();
+        }
+        let size = |contexts: &WindowContexts<Dropped>, window: &Window| {
+            contexts.get(window.id()).unwrap().context.surface_size()
+        };
+
+        let resized = WindowEvent::Resized(PhysicalSize::new(40, 20));
+        assert_eq!(
+            contexts.handle_window_event(first.id(), &resized),
+            Some(first.id())
+        );
+        assert_eq!(size(&contexts, &first), (40, 20));
+        assert_eq!(size(&contexts, &second), (16, 16));
+
+        contexts.handle_window_event(second.id(), &WindowEvent::Destroyed);
+        assert_eq!(dropped.get(), 1);
+        assert!(contexts.get(second.id()).is_none());
+        assert!(contexts.make_current(first.id()).is_some());
+        drop(contexts);
+        assert_eq!(dropped.get(), 2);
+    }
+}
@@ -0,0 +1,45 @@
+//! The Xlib error hook GLX needs to turn X errors into [glutin] errors.
+//!
+//! Xlib reports protocol errors to a single handler per process, whose default prints the
+//! error and exits. GLX registers a hook through [register_xlib_error_hook] that claims the
+//! errors of its own requests, so a failing config or context creation comes back as an error
+//! instead.
+
+use std::ffi::{c_int, c_void};
+use std::sync::{Mutex, OnceLock};
+
+use x11_dl::xlib::{Display, XErrorEvent, Xlib};
+
+type XlibErrorHook = Box<dyn Fn(*mut c_void, *mut c_void) -> bool + Send + Sync>;
+type XErrorHandler = unsafe extern "C" fn(*mut Display, *mut XErrorEvent) -> c_int;
+
+static HOOKS: Mutex<Vec<XlibErrorHook>> = Mutex::new(Vec::new());
+
+/// The handler installed before [handle_error], which gets the errors no hook claims. `None`
+/// inside if there was none, or Xlib couldn't be loaded to install [handle_error] at all.
+static PREVIOUS_HANDLER: OnceLock<Option<XErrorHandler>> = OnceLock::new();
+
+/// Adds `hook` to the hooks asked about every Xlib error, installing the process's error
+/// handler on first use. Passed to [glutin::display::DisplayApiPreference] for GLX.
+pub(crate) fn register_xlib_error_hook(hook: XlibErrorHook) {
+    HOOKS.lock().unwrap().push(hook);
+    PREVIOUS_HANDLER.get_or_init(|| {
+        let xlib = Xlib::open().ok()?;
+        // The handler stays installed for good, so Xlib stays loaded with it.
+        let xlib = Box::leak(Box::new(xlib));
+        unsafe { (xlib.XSetErrorHandler)(Some(handle_error)) }
+    });
+}
+
+unsafe extern "C" fn handle_error(display: *mut Display, event: *mut XErrorEvent) -> c_int {
+    let claimed = HOOKS
+        .lock()
+        .is_ok_and(|hooks| hooks.iter().any(|hook| hook(display.cast(), event.cast())));
+    if claimed {
+        return 0;
+    }
+    match PREVIOUS_HANDLER.get().copied().flatten() {
+        Some(previous) => previous(display, event),
+        None => 0,
+    }
+}